@@ -0,0 +1,98 @@
+# Activation script for ESP-IDF {{idf_version}}, source it from fish:
+#   source activate_idf_{{idf_version}}.fish
+
+# Deactivate a previously activated ESP-IDF environment, so versions do not stack up
+if functions -q idf_deactivate
+    idf_deactivate
+end
+
+# Save the environment replaced by the activation, it is restored by idf_deactivate
+set -g _IDF_OLD_PATH $PATH
+set -g _IDF_KEYS ESP_IDF_VERSION{% for pair in env_vars %} {{ pair.0 }}{% endfor %}
+for key in $_IDF_KEYS
+    if set -q $key
+        set -g _IDF_OLD_$key $$key
+    end
+end
+
+function idf_deactivate --description "Restore the environment from before the ESP-IDF activation"
+    if functions -q _idf_old_fish_prompt
+        functions -e fish_prompt
+        functions -c _idf_old_fish_prompt fish_prompt
+        functions -e _idf_old_fish_prompt
+    end
+    if set -q VIRTUAL_ENV; and functions -q deactivate
+        deactivate
+    end
+    set -gx PATH $_IDF_OLD_PATH
+    for key in $_IDF_KEYS
+        set -l old _IDF_OLD_$key
+        if set -q $old
+            set -gx $key $$old
+            set -e $old
+        else
+            set -e $key
+        end
+    end
+    functions -e idf.py esptool.py espefuse.py espsecure.py otatool.py parttool.py
+    set -e _IDF_OLD_PATH _IDF_KEYS
+    functions -e idf_deactivate
+    echo "ESP-IDF environment deactivated."
+end
+
+set -gx ESP_IDF_VERSION "{{idf_version}}"
+echo "Added environment variable ESP_IDF_VERSION = $ESP_IDF_VERSION"
+{% for pair in env_vars %}set -gx {{ pair.0 }} "{{ pair.1 }}"
+echo "Added environment variable {{ pair.0 }} = ${{ pair.0 }}"
+{% endfor %}
+set -gx PATH $PATH (string split ":" "{{addition_to_path}}")
+echo "Added proper directory to PATH"
+
+function idf.py
+    "{{idf_tools_path}}/python/bin/python3" "{{idf_path}}/tools/idf.py" $argv
+end
+
+function esptool.py
+    "{{idf_tools_path}}/python/bin/python3" "{{idf_path}}/components/esptool_py/esptool/esptool.py" $argv
+end
+
+function espefuse.py
+    "{{idf_tools_path}}/python/bin/python3" "{{idf_path}}/components/esptool_py/esptool/espefuse.py" $argv
+end
+
+function espsecure.py
+    "{{idf_tools_path}}/python/bin/python3" "{{idf_path}}/components/esptool_py/esptool/espsecure.py" $argv
+end
+
+function otatool.py
+    "{{idf_tools_path}}/python/bin/python3" "{{idf_path}}/components/app_update/otatool.py" $argv
+end
+
+function parttool.py
+    "{{idf_tools_path}}/python/bin/python3" "{{idf_path}}/components/partition_table/parttool.py" $argv
+end
+
+if set -q IDF_PYTHON_ENV_PATH; and test -f "$IDF_PYTHON_ENV_PATH/bin/activate.fish"
+    source "$IDF_PYTHON_ENV_PATH/bin/activate.fish"
+    echo "Activated virtual environment at $IDF_PYTHON_ENV_PATH"
+else
+    echo "Virtual environment not found at $IDF_PYTHON_ENV_PATH"
+end
+
+{% if shell_integration %}
+# Show the active ESP-IDF version in the prompt
+if functions -q fish_prompt
+    functions -c fish_prompt _idf_old_fish_prompt
+    function fish_prompt
+        echo -n "(ESP-IDF {{idf_version}}) "
+        _idf_old_fish_prompt
+    end
+end
+
+# The idf.py completion calls idf.py by name, so the ESP-IDF tools have to be in PATH
+set -gx PATH $PATH "{{idf_path}}/tools"
+env _IDF.PY_COMPLETE=fish_source "{{idf_tools_path}}/python/bin/python3" "{{idf_path}}/tools/idf.py" 2>/dev/null | source
+{% endif %}
+echo "Environment setup complete for the current shell session."
+echo "These changes will be lost when you close this terminal, or run idf_deactivate to revert them."
+echo "You are now using IDF version {{idf_version}}."
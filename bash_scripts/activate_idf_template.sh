@@ -58,6 +58,12 @@ else
     fi
 fi
 
+# Deactivate a previously activated ESP-IDF environment, so versions do not stack up.
+# It removes the aliases, so it has to run before they are defined
+if typeset -f idf_deactivate >/dev/null 2>&1; then
+    idf_deactivate
+fi
+
 alias idf.py="{{idf_tools_path_escaped}}/python/bin/python3 {{idf_path_escaped}}/tools/idf.py"
 
 alias esptool.py="{{idf_tools_path_escaped}}/python/bin/python3 {{idf_path_escaped}}/components/esptool_py/esptool/esptool.py"
@@ -70,6 +76,41 @@ alias otatool.py="{{idf_tools_path_escaped}}/python/bin/python3 {{idf_path_escap
 
 alias parttool.py="{{idf_tools_path_escaped}}/python/bin/python3 {{idf_path_escaped}}/components/partition_table/parttool.py"
 
+# Save the environment replaced by the activation, it is restored by idf_deactivate
+_IDF_OLD_PATH="$PATH"
+_IDF_KEYS=("ESP_IDF_VERSION")
+for pair in "${env_var_pairs[@]}"; do
+    _IDF_KEYS+=("${pair%%:*}")
+done
+for key in "${_IDF_KEYS[@]}"; do
+    if eval "[ -n \"\${${key}+x}\" ]"; then
+        eval "_IDF_OLD_${key}=\"\$${key}\""
+    fi
+done
+
+# Function to restore the environment from before the activation
+idf_deactivate() {
+    if [ -n "${_IDF_OLD_PS1+x}" ]; then
+        PS1="$_IDF_OLD_PS1"
+        unset _IDF_OLD_PS1
+    fi
+    if [ -n "${VIRTUAL_ENV:-}" ] && typeset -f deactivate >/dev/null 2>&1; then
+        deactivate
+    fi
+    export PATH="$_IDF_OLD_PATH"
+    for key in "${_IDF_KEYS[@]}"; do
+        if eval "[ -n \"\${_IDF_OLD_${key}+x}\" ]"; then
+            eval "export ${key}=\"\$_IDF_OLD_${key}\""
+            unset "_IDF_OLD_${key}"
+        else
+            unset "${key}"
+        fi
+    done
+    unalias idf.py esptool.py espefuse.py espsecure.py otatool.py parttool.py 2>/dev/null
+    unset _IDF_OLD_PATH _IDF_KEYS
+    unset -f idf_deactivate
+    echo "ESP-IDF environment deactivated."
+}
 
 # Main execution
 add_env_variable
@@ -77,7 +118,15 @@ add_to_path
 
 # Activate virtual environment (uncomment and provide the correct path)
 activate_venv "${IDF_PYTHON_ENV_PATH}"
-
+{% if shell_integration %}
+# Show the active ESP-IDF version in the prompt
+_IDF_OLD_PS1="${PS1:-}"
+PS1="(ESP-IDF {{idf_version}}) ${PS1:-}"
+
+# The idf.py completion calls idf.py by name, so the ESP-IDF tools have to be in PATH
+export PATH="$PATH:{{idf_path_escaped}}/tools"
+eval "$(env _IDF.PY_COMPLETE=bash_source "{{idf_tools_path_escaped}}/python/bin/python3" "{{idf_path_escaped}}/tools/idf.py" 2>/dev/null)"
+{% endif %}
 echo "Environment setup complete for the current shell session."
-echo "These changes will be lost when you close this terminal."
+echo "These changes will be lost when you close this terminal, or run idf_deactivate to revert them."
 echo "You are now using IDF version {{idf_version}}."
\ No newline at end of file
@@ -0,0 +1,69 @@
+# Activation script for ESP-IDF {{idf_version}} on Linux and macOS, dot-source it from pwsh:
+#   . ./activate_idf_{{idf_version}}.ps1
+
+# Deactivate a previously activated ESP-IDF environment, so versions do not stack up
+if (Get-Command idf_deactivate -CommandType Function -ErrorAction SilentlyContinue) {
+    idf_deactivate
+}
+
+# Save the environment replaced by the activation, it is restored by idf_deactivate
+$global:_IDF_OLD_PATH = $env:PATH
+$global:_IDF_OLD_VARS = @{}
+foreach ($key in @('ESP_IDF_VERSION'{% for pair in env_vars %}, '{{ pair.0 }}'{% endfor %})) {
+    $global:_IDF_OLD_VARS[$key] = [Environment]::GetEnvironmentVariable($key)
+}
+
+function global:idf_deactivate {
+    if ($global:_IDF_OLD_PROMPT) {
+        $function:global:prompt = $global:_IDF_OLD_PROMPT
+        Remove-Variable -Name _IDF_OLD_PROMPT -Scope Global
+    }
+    if (Get-Command deactivate -CommandType Function -ErrorAction SilentlyContinue) {
+        deactivate
+    }
+    $env:PATH = $global:_IDF_OLD_PATH
+    foreach ($entry in $global:_IDF_OLD_VARS.GetEnumerator()) {
+        if ($null -eq $entry.Value) {
+            Remove-Item -Path "env:$($entry.Key)" -ErrorAction SilentlyContinue
+        } else {
+            Set-Item -Path "env:$($entry.Key)" -Value $entry.Value
+        }
+    }
+    foreach ($name in @('idf.py', 'esptool.py', 'espefuse.py', 'espsecure.py', 'otatool.py', 'parttool.py')) {
+        Remove-Item -Path "function:$name" -ErrorAction SilentlyContinue
+    }
+    Remove-Variable -Name _IDF_OLD_PATH, _IDF_OLD_VARS -Scope Global
+    Remove-Item -Path function:idf_deactivate
+    Write-Host "ESP-IDF environment deactivated."
+}
+
+$env:ESP_IDF_VERSION = "{{idf_version}}"
+Write-Host "Added environment variable ESP_IDF_VERSION = $env:ESP_IDF_VERSION"
+{% for pair in env_vars %}$env:{{ pair.0 }} = "{{ pair.1 }}"
+Write-Host "Added environment variable {{ pair.0 }} = $env:{{ pair.0 }}"
+{% endfor %}
+$env:PATH = "$($env:PATH):{{addition_to_path}}"
+Write-Host "Added proper directory to PATH"
+
+function global:idf.py { & "{{idf_tools_path}}/python/bin/python3" "{{idf_path}}/tools/idf.py" @args }
+function global:esptool.py { & "{{idf_tools_path}}/python/bin/python3" "{{idf_path}}/components/esptool_py/esptool/esptool.py" @args }
+function global:espefuse.py { & "{{idf_tools_path}}/python/bin/python3" "{{idf_path}}/components/esptool_py/esptool/espefuse.py" @args }
+function global:espsecure.py { & "{{idf_tools_path}}/python/bin/python3" "{{idf_path}}/components/esptool_py/esptool/espsecure.py" @args }
+function global:otatool.py { & "{{idf_tools_path}}/python/bin/python3" "{{idf_path}}/components/app_update/otatool.py" @args }
+function global:parttool.py { & "{{idf_tools_path}}/python/bin/python3" "{{idf_path}}/components/partition_table/parttool.py" @args }
+
+if ($env:IDF_PYTHON_ENV_PATH -and (Test-Path "$env:IDF_PYTHON_ENV_PATH/bin/Activate.ps1")) {
+    . "$env:IDF_PYTHON_ENV_PATH/bin/Activate.ps1"
+    Write-Host "Activated virtual environment at $env:IDF_PYTHON_ENV_PATH"
+} else {
+    Write-Host "Virtual environment not found at $env:IDF_PYTHON_ENV_PATH"
+}
+
+{% if shell_integration %}
+# Show the active ESP-IDF version in the prompt, there is no idf.py completion for PowerShell
+$global:_IDF_OLD_PROMPT = $function:prompt
+function global:prompt { "(ESP-IDF {{idf_version}}) " + (& $global:_IDF_OLD_PROMPT) }
+{% endif %}
+Write-Host "Environment setup complete for the current shell session."
+Write-Host "These changes will be lost when you close this terminal, or run idf_deactivate to revert them."
+Write-Host "You are now using IDF version {{idf_version}}."
@@ -0,0 +1,12 @@
+$iconPath = "$env:USERPROFILE\Icons\eim.ico"
+$folder = Join-Path ([Environment]::GetFolderPath('Programs')) "{{folder_name}}"
+New-Item -ItemType Directory -Force -Path $folder | Out-Null
+$WshShell = New-Object -comObject WScript.Shell
+$Shortcut = $WshShell.CreateShortcut("$folder\ESP-IDF {{name}} PowerShell.lnk")
+$Shortcut.TargetPath = "powershell.exe"
+$Shortcut.Arguments = "-NoExit -ExecutionPolicy Bypass -NoProfile -Command `"& {. '{{custom_profile_filename}}'}`""
+$Shortcut.WorkingDirectory = "$env:USERPROFILE"
+$Shortcut.IconLocation = $iconPath
+$Shortcut.Save()
+
+Write-Host "Shortcut created in the Start Menu: {{folder_name}}" -ForegroundColor Green
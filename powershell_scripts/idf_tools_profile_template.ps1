@@ -21,6 +21,43 @@ if ($e) {
     return
 }
 
+# Deactivate a previously activated ESP-IDF environment, so versions do not stack up
+if (Get-Command idf_deactivate -CommandType Function -ErrorAction SilentlyContinue) {
+    idf_deactivate
+}
+
+# Save the environment replaced by the activation, it is restored by idf_deactivate
+$global:_IDF_OLD_PATH = $env:PATH
+$global:_IDF_OLD_VARS = @{}
+foreach ($key in @('ESP_IDF_VERSION') + @($env_var_pairs.Keys)) {
+    $global:_IDF_OLD_VARS[$key] = [Environment]::GetEnvironmentVariable($key)
+}
+
+function global:idf_deactivate {
+    if ($global:_IDF_OLD_PROMPT) {
+        $function:global:prompt = $global:_IDF_OLD_PROMPT
+        Remove-Variable -Name _IDF_OLD_PROMPT -Scope Global
+    }
+    if (Get-Command deactivate -CommandType Function -ErrorAction SilentlyContinue) {
+        deactivate
+    }
+    $env:PATH = $global:_IDF_OLD_PATH
+    foreach ($entry in $global:_IDF_OLD_VARS.GetEnumerator()) {
+        if ($null -eq $entry.Value) {
+            Remove-Item -Path "env:$($entry.Key)" -ErrorAction SilentlyContinue
+        } else {
+            Set-Item -Path "env:$($entry.Key)" -Value $entry.Value
+        }
+    }
+    foreach ($name in @('Invoke-idfpy', 'esptool.py', 'espefuse.py', 'espsecure.py', 'otatool.py', 'parttool.py')) {
+        Remove-Item -Path "function:$name" -ErrorAction SilentlyContinue
+    }
+    Remove-Item -Path alias:idf.py -ErrorAction SilentlyContinue
+    Remove-Variable -Name _IDF_OLD_PATH, _IDF_OLD_VARS -Scope Global
+    Remove-Item -Path function:idf_deactivate
+    Write-Host "ESP-IDF environment deactivated."
+}
+
 # Set environment variables
 $env:ESP_IDF_VERSION = "{{idf_version}}"
 $env_var_pairs.GetEnumerator() | ForEach-Object {
@@ -61,6 +98,12 @@ New-Alias -Name idf.py -Value Invoke-idfpy -Force -Scope Global
 # Activate your Python environment
 . "{{idf_tools_path}}\python\Scripts\Activate.ps1"
 
+{% if shell_integration %}
+# Show the active ESP-IDF version in the prompt, there is no idf.py completion for PowerShell
+$global:_IDF_OLD_PROMPT = $function:prompt
+function global:prompt { "(ESP-IDF {{idf_version}}) " + (& $global:_IDF_OLD_PROMPT) }
+{% endif %}
+
 # Display setup information
 Write-Host 'IDF PowerShell Environment' -ForegroundColor Green
 Write-Host '-------------------------' -ForegroundColor Green
@@ -78,4 +121,5 @@ Write-Host 'otatool.py' -ForegroundColor Yellow
 Write-Host 'parttool.py' -ForegroundColor Yellow
 Write-Host ''
 Write-Host 'Python environment activated.' -ForegroundColor Cyan
-Write-Host 'You can now use IDF commands and Python tools.' -ForegroundColor Cyan
\ No newline at end of file
+Write-Host 'You can now use IDF commands and Python tools.' -ForegroundColor Cyan
+Write-Host 'Run idf_deactivate to restore the previous environment.' -ForegroundColor Cyan
\ No newline at end of file
@@ -0,0 +1,221 @@
+use log::{debug, warn};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+use std::time::{Duration, SystemTime};
+
+/// Touched whenever a cache entry is used, so `prune_older_than` keeps the archives still in use.
+const LAST_USED_FILE_NAME: &str = ".last_used";
+
+static CACHE_ENABLED: AtomicBool = AtomicBool::new(true);
+static CACHE_DIR: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// Enables or disables the download cache, it is enabled by default.
+pub fn set_cache_enabled(enabled: bool) {
+    CACHE_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Sets the folder of the download cache, `None` restores the default in the user's cache folder.
+pub fn set_cache_dir(dir: Option<PathBuf>) {
+    *CACHE_DIR.write().unwrap() = dir;
+}
+
+/// Returns the folder of the download cache, `None` if it is disabled or no cache folder is known.
+pub fn cache_dir() -> Option<PathBuf> {
+    if !CACHE_ENABLED.load(Ordering::Relaxed) {
+        return None;
+    }
+    CACHE_DIR
+        .read()
+        .unwrap()
+        .clone()
+        .or_else(|| dirs::cache_dir().map(|dir| dir.join("eim").join("downloads")))
+}
+
+/// Returns the folder of an entry: the archives are stored by their sha256, so the archives of
+/// different ESP-IDF versions which are the same are only stored once.
+fn entry_dir(cache_dir: &Path, sha256: &str) -> PathBuf {
+    cache_dir.join(sha256.to_lowercase())
+}
+
+/// Marks an entry as used now.
+fn touch(entry: &Path) {
+    if let Err(e) = fs::write(entry.join(LAST_USED_FILE_NAME), b"") {
+        debug!("Failed to mark {} as used: {}", entry.display(), e);
+    }
+}
+
+/// Returns the cached archive with the checksum, verifying it first.
+///
+/// A corrupted archive is removed from the cache.
+pub fn lookup(sha256: &str) -> Option<PathBuf> {
+    let entry = entry_dir(&cache_dir()?, sha256);
+    let archive = fs::read_dir(&entry)
+        .ok()?
+        .flatten()
+        .map(|file| file.path())
+        .find(|path| {
+            path.file_name()
+                .is_some_and(|name| name != LAST_USED_FILE_NAME)
+        })?;
+    match crate::verify_file_checksum(sha256, archive.to_str()?) {
+        Ok(true) => {
+            touch(&entry);
+            Some(archive)
+        }
+        _ => {
+            warn!("Removing corrupted cache entry {}", entry.display());
+            let _ = fs::remove_dir_all(&entry);
+            None
+        }
+    }
+}
+
+/// Copies a cached archive into `destination_dir` under `file_name`.
+///
+/// # Returns
+///
+/// * `io::Result<Option<PathBuf>>` - The path of the copy, `None` if the archive is not cached.
+pub fn restore(
+    sha256: &str,
+    destination_dir: &Path,
+    file_name: &str,
+) -> io::Result<Option<PathBuf>> {
+    let Some(archive) = lookup(sha256) else {
+        return Ok(None);
+    };
+    fs::create_dir_all(destination_dir)?;
+    let destination = destination_dir.join(file_name);
+    // a copy and not a hard link, which would share the file with the download folder, so changing
+    // or truncating one corrupts the other; on Linux the copy is a reflink where supported
+    fs::copy(&archive, &destination)?;
+    debug!("Restored {} from the download cache", destination.display());
+    Ok(Some(destination))
+}
+
+/// Adds a verified archive to the cache, nothing is done when the cache is disabled.
+///
+/// # Parameters
+///
+/// * `sha256` - The checksum the archive was verified against.
+/// * `archive` - The downloaded archive.
+pub fn store(sha256: &str, archive: &Path) -> io::Result<()> {
+    let Some(cache_dir) = cache_dir() else {
+        return Ok(());
+    };
+    let entry = entry_dir(&cache_dir, sha256);
+    let file_name = archive
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "archive has no file name"))?;
+    let cached = entry.join(file_name);
+    if cached.exists() {
+        touch(&entry);
+        return Ok(());
+    }
+    fs::create_dir_all(&entry)?;
+    fs::copy(archive, &cached)?;
+    touch(&entry);
+    debug!("Stored {} in the download cache", cached.display());
+    Ok(())
+}
+
+/// Returns the size of the cache in bytes.
+pub fn size() -> u64 {
+    cache_dir()
+        .map(|dir| crate::utils::get_directory_size(&dir))
+        .unwrap_or(0)
+}
+
+/// Removes every cached archive.
+pub fn clear() -> io::Result<()> {
+    match cache_dir() {
+        Some(dir) if dir.exists() => fs::remove_dir_all(dir),
+        _ => Ok(()),
+    }
+}
+
+/// Removes the cached archives not used for longer than `age`.
+///
+/// Entries which can not be inspected or removed are skipped, so one of them does not keep the
+/// others from being pruned.
+///
+/// # Returns
+///
+/// * `io::Result<u64>` - The number of bytes freed.
+pub fn prune_older_than(age: Duration) -> io::Result<u64> {
+    let Some(dir) = cache_dir() else {
+        return Ok(0);
+    };
+    if !dir.exists() {
+        return Ok(0);
+    }
+    let now = SystemTime::now();
+    let mut freed = 0;
+    for entry in fs::read_dir(&dir)?.flatten() {
+        let entry = entry.path();
+        if !entry.is_dir() {
+            continue;
+        }
+        let last_used = match fs::metadata(entry.join(LAST_USED_FILE_NAME))
+            .or_else(|_| fs::metadata(&entry))
+            .and_then(|metadata| metadata.modified())
+        {
+            Ok(last_used) => last_used,
+            Err(e) => {
+                warn!(
+                    "Skipping {} while pruning the cache: {}",
+                    entry.display(),
+                    e
+                );
+                continue;
+            }
+        };
+        if now.duration_since(last_used).unwrap_or_default() > age {
+            let size = crate::utils::get_directory_size(&entry);
+            match fs::remove_dir_all(&entry) {
+                Ok(()) => {
+                    freed += size;
+                    debug!("Pruned {} from the download cache", entry.display());
+                }
+                Err(e) => warn!("Failed to prune {}: {}", entry.display(), e),
+            }
+        }
+    }
+    Ok(freed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store_and_restore() {
+        let cache = tempfile::tempdir().unwrap();
+        let work = tempfile::tempdir().unwrap();
+        set_cache_dir(Some(cache.path().to_path_buf()));
+
+        let archive = work.path().join("cmake.tar.gz");
+        fs::write(&archive, "cmake").unwrap();
+        let sha256 = crate::compute_file_checksum(archive.to_str().unwrap()).unwrap();
+        store(&sha256, &archive).unwrap();
+        assert!(size() > 0);
+
+        let restored = restore(&sha256, &work.path().join("dist"), "cmake.tar.gz")
+            .unwrap()
+            .unwrap();
+        assert_eq!(fs::read_to_string(&restored).unwrap(), "cmake");
+        // the restored archive is a copy, changing it leaves the cached one intact
+        fs::write(&restored, "changed").unwrap();
+        assert!(lookup(&sha256).is_some());
+        assert!(restore("0000", work.path(), "missing.tar.gz")
+            .unwrap()
+            .is_none());
+
+        assert_eq!(prune_older_than(Duration::from_secs(3600)).unwrap(), 0);
+        clear().unwrap();
+        assert!(lookup(&sha256).is_none());
+        set_cache_dir(None);
+    }
+}
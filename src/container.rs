@@ -0,0 +1,326 @@
+use anyhow::{anyhow, Result};
+use log::{debug, info};
+use std::fs;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::command_executor::{execute_checked, ExecOptions};
+use crate::dry_run::{self, ActionKind};
+use crate::idf_config::{IdfConfig, IdfInstallation};
+use crate::utils::get_git_path;
+
+/// The official ESP-IDF image, tagged with the ESP-IDF versions.
+pub const IDF_CONTAINER_IMAGE: &str = "espressif/idf";
+
+/// The directory the project is mounted to inside the container.
+const CONTAINER_PROJECT_DIR: &str = "/project";
+
+/// The serial ports of the boards passed to the container, as shell globs.
+const SERIAL_DEVICE_PATTERNS: [&str; 2] = ["/dev/ttyUSB*", "/dev/ttyACM*"];
+
+/// A container engine able to run the ESP-IDF image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContainerEngine {
+    Docker,
+    Podman,
+}
+
+impl ContainerEngine {
+    /// Parses the name of an engine, `docker` or `podman`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "docker" => Some(ContainerEngine::Docker),
+            "podman" => Some(ContainerEngine::Podman),
+            _ => None,
+        }
+    }
+
+    /// The executable of the engine.
+    pub fn binary(&self) -> &'static str {
+        match self {
+            ContainerEngine::Docker => "docker",
+            ContainerEngine::Podman => "podman",
+        }
+    }
+
+    /// Returns the first engine installed on the system, Docker is preferred.
+    pub fn detect() -> Option<Self> {
+        [ContainerEngine::Docker, ContainerEngine::Podman]
+            .into_iter()
+            .find(|engine| {
+                execute_checked(engine.binary(), &["--version"], vec![], &ExecOptions::new())
+                    .is_ok()
+            })
+    }
+
+    /// The arguments running a command of the image with the current directory mounted as the
+    /// project, which has to be followed by the command.
+    ///
+    /// # Parameters
+    ///
+    /// * `image` - The image reference, e.g. `espressif/idf:v5.3`.
+    /// * `current_dir` - How the script refers to the current directory, e.g. `"$PWD"`.
+    /// * `extra_args` - Arguments of `run` added before the mount, e.g. `-t` or `--device`.
+    pub fn run_args(&self, image: &str, current_dir: &str, extra_args: &[&str]) -> Vec<String> {
+        let mut args: Vec<String> = ["run", "--rm", "-i"]
+            .iter()
+            .chain(extra_args)
+            .map(|arg| arg.to_string())
+            .collect();
+        if *self == ContainerEngine::Podman {
+            // rootless podman would otherwise create the build files as a foreign user
+            args.push("--userns=keep-id".to_string());
+        }
+        args.extend([
+            "-v".to_string(),
+            format!("{}:{}", current_dir, CONTAINER_PROJECT_DIR),
+            "-w".to_string(),
+            CONTAINER_PROJECT_DIR.to_string(),
+            image.to_string(),
+        ]);
+        args
+    }
+}
+
+/// Returns the reference of the image of an ESP-IDF version, e.g. `espressif/idf:v5.3`.
+pub fn container_image(idf_version: &str) -> String {
+    format!("{}:{}", IDF_CONTAINER_IMAGE, idf_version)
+}
+
+/// Pulls an image, so it is available when idf.py is run.
+///
+/// # Parameters
+///
+/// * `engine` - The container engine.
+/// * `image` - The image reference, as returned by `container_image`.
+pub fn pull_image(engine: ContainerEngine, image: &str) -> Result<(), String> {
+    if dry_run::plan(
+        ActionKind::Download,
+        &format!("Pull {} with {}", image, engine.binary()),
+        None,
+    ) {
+        return Ok(());
+    }
+    info!("Pulling {} with {}", image, engine.binary());
+    execute_checked(
+        engine.binary(),
+        &["pull", image],
+        vec![],
+        &ExecOptions::new(),
+    )
+    .map(|_| ())
+    .map_err(|e| e.to_string())
+}
+
+/// Renders a wrapper which runs a program of the image, e.g. `idf.py`, on the current directory.
+///
+/// The POSIX script only allocates a terminal when it is run from one, so it works in pipes and
+/// CI as well, and passes the serial ports of the boards through, so `idf.py flash` and
+/// `idf.py monitor` reach them. The batch file allocates a terminal for `idf.py` only, as it can
+/// not tell whether it runs in one, and Docker Desktop can not pass serial ports to its Linux VM.
+///
+/// # Parameters
+///
+/// * `engine` - The container engine.
+/// * `image` - The image reference.
+/// * `program` - The program run in the container, e.g. `idf.py` or `python`.
+/// * `windows` - Whether to render a batch file instead of a POSIX shell script.
+pub fn render_container_wrapper(
+    engine: ContainerEngine,
+    image: &str,
+    program: &str,
+    windows: bool,
+) -> String {
+    if windows {
+        let tty: &[&str] = if program == "idf.py" { &["-t"] } else { &[] };
+        let run_args = engine.run_args(image, "\"%cd%\"", tty);
+        return format!(
+            "@echo off\r\n{} {} {} %*\r\n",
+            engine.binary(),
+            run_args.join(" "),
+            program
+        );
+    }
+    let run_args = engine.run_args(image, "\"$PWD\"", &["$TTY_ARG", "$DEVICE_ARGS"]);
+    format!(
+        "#!/usr/bin/env sh\n\
+         # Runs {program} of {image} on the project in the current directory\n\
+         TTY_ARG=\n\
+         [ -t 0 ] && TTY_ARG=-t\n\
+         DEVICE_ARGS=\n\
+         for device in {devices}; do\n\
+         \x20   [ -e \"$device\" ] && DEVICE_ARGS=\"$DEVICE_ARGS --device=$device\"\n\
+         done\n\
+         exec {binary} {run_args} {program} \"$@\"\n",
+        program = program,
+        image = image,
+        devices = SERIAL_DEVICE_PATTERNS.join(" "),
+        binary = engine.binary(),
+        run_args = run_args.join(" "),
+    )
+}
+
+/// Renders the activation script of a container installation, which puts the `idf.py` wrapper
+/// first on the PATH.
+///
+/// # Parameters
+///
+/// * `wrapper_dir` - The directory of the wrapper.
+/// * `image` - The image reference.
+/// * `windows` - Whether to render a PowerShell script instead of a POSIX shell script.
+pub fn render_container_activation(wrapper_dir: &Path, image: &str, windows: bool) -> String {
+    let dir = wrapper_dir.to_string_lossy();
+    if windows {
+        format!(
+            "$env:PATH = \"{};\" + $env:PATH\r\n$env:IDF_CONTAINER_IMAGE = \"{}\"\r\nWrite-Host \"idf.py runs in the {} container\"\r\n",
+            dir, image, image
+        )
+    } else {
+        format!(
+            "export PATH=\"{}:$PATH\"\nexport IDF_CONTAINER_IMAGE=\"{}\"\necho \"idf.py runs in the {} container\"\n",
+            dir, image, image
+        )
+    }
+}
+
+/// Writes a file, making it executable on POSIX systems.
+fn write_script(path: &Path, content: &str) -> Result<()> {
+    if dry_run::plan(
+        ActionKind::WriteFile,
+        &format!("Write {}", path.display()),
+        None,
+    ) {
+        return Ok(());
+    }
+    crate::ensure_path(path.parent().unwrap().to_str().unwrap())?;
+    fs::write(path, content)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(())
+}
+
+/// Sets up an ESP-IDF version which runs inside the official container image instead of natively,
+/// for machines where the tools can not be installed.
+///
+/// The image is pulled, an `idf.py` wrapper running it on the current directory and an activation
+/// script putting the wrapper on the PATH are written, and the installation is registered in
+/// `eim_idf.json`.
+///
+/// # Parameters
+///
+/// * `engine` - The container engine.
+/// * `idf_version` - The ESP-IDF version, which is also the tag of the image.
+/// * `install_path` - The installation directory, `path` of the `Settings`.
+/// * `config_path` - The path of `eim_idf.json`.
+///
+/// # Returns
+///
+/// * `Result<IdfInstallation, anyhow::Error>` - The registered installation.
+pub fn install_container_version(
+    engine: ContainerEngine,
+    idf_version: &str,
+    install_path: &Path,
+    config_path: &Path,
+) -> Result<IdfInstallation> {
+    let image = container_image(idf_version);
+    pull_image(engine, &image).map_err(|e| anyhow!("Failed to pull {}: {}", image, e))?;
+
+    let windows = std::env::consts::OS == "windows";
+    let version_dir = install_path.join(format!("{}-container", idf_version));
+    let wrapper_dir = version_dir.join("bin");
+    // python runs in the container as well, so the installation works with the tools using it
+    let mut wrappers = vec![];
+    for program in ["idf.py", "python"] {
+        let wrapper = match windows {
+            true => wrapper_dir.join(format!("{}.bat", program)),
+            false => wrapper_dir.join(program),
+        };
+        write_script(
+            &wrapper,
+            &render_container_wrapper(engine, &image, program, windows),
+        )?;
+        wrappers.push(wrapper);
+    }
+    let activation_script: PathBuf = if windows {
+        version_dir.join("Microsoft.PowerShell_profile.ps1")
+    } else {
+        install_path.join(format!("activate_idf_{}-container.sh", idf_version))
+    };
+    write_script(
+        &activation_script,
+        &render_container_activation(&wrapper_dir, &image, windows),
+    )?;
+    debug!("Wrote the container wrapper to {}", wrapper_dir.display());
+
+    let installation = IdfInstallation {
+        activation_script: activation_script.to_string_lossy().into_owned(),
+        id: format!("esp-idf-{}", Uuid::new_v4().to_string().replace("-", "")),
+        idf_tools_path: wrapper_dir.to_string_lossy().into_owned(),
+        name: format!("{}-container", idf_version),
+        path: version_dir.to_string_lossy().into_owned(),
+        python: wrappers[1].to_string_lossy().into_owned(),
+        env_variables: vec![],
+        activation_scripts: vec![],
+        container_image: Some(image.clone()),
+    };
+    // keep the user's selection if there already is one
+    let idf_selected_id = IdfConfig::from_file(config_path)
+        .ok()
+        .map(|config| config.idf_selected_id)
+        .filter(|id| !id.is_empty())
+        .unwrap_or_else(|| installation.id.clone());
+    let mut config = IdfConfig {
+        git_path: get_git_path().unwrap_or_default(),
+        idf_installed: vec![installation.clone()],
+        idf_selected_id,
+    };
+    config.to_file(config_path, true)?;
+    info!(
+        "ESP-IDF {} registered to run in the {} container",
+        idf_version, image
+    );
+    Ok(installation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_container_wrapper() {
+        let posix = render_container_wrapper(
+            ContainerEngine::Docker,
+            "espressif/idf:v5.3",
+            "idf.py",
+            false,
+        );
+        assert!(posix.contains("[ -t 0 ] && TTY_ARG=-t\n"));
+        assert!(posix.contains("for device in /dev/ttyUSB* /dev/ttyACM*; do\n"));
+        assert!(posix.contains(
+            "exec docker run --rm -i $TTY_ARG $DEVICE_ARGS -v \"$PWD\":/project -w /project espressif/idf:v5.3 idf.py \"$@\""
+        ));
+
+        let batch = render_container_wrapper(
+            ContainerEngine::Podman,
+            "espressif/idf:v5.3",
+            "idf.py",
+            true,
+        );
+        assert!(batch.starts_with("@echo off"));
+        assert!(batch.contains("podman run --rm -i -t --userns=keep-id -v \"%cd%\":/project"));
+        assert!(batch.contains("idf.py %*"));
+    }
+
+    #[test]
+    fn test_container_engine_from_name() {
+        assert_eq!(
+            ContainerEngine::from_name("Podman"),
+            Some(ContainerEngine::Podman)
+        );
+        assert_eq!(ContainerEngine::from_name("lxc"), None);
+        assert_eq!(container_image("v5.3"), "espressif/idf:v5.3");
+    }
+}
@@ -0,0 +1,766 @@
+use log::{debug, warn};
+use reqwest::header::{CONTENT_RANGE, RANGE};
+use reqwest::StatusCode;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::events::{self, InstallPhase};
+use crate::idf_tools::Download;
+use crate::messages::{message, MessageId};
+use crate::progress::{self, ProgressDetail, ProgressReporter, ProgressUpdate};
+use crate::CancellationToken;
+
+/// How many files are downloaded at the same time by default.
+pub const DEFAULT_PARALLEL_DOWNLOADS: usize = 4;
+
+/// Files are not split into segments smaller than this, the extra requests would cost more
+/// than they save.
+const MIN_SEGMENT_SIZE: u64 = 4 * 1024 * 1024;
+
+/// How often the progress of a segmented download is reported.
+const SEGMENT_PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+/// How a `DownloadManager` fetches a single file.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum DownloadBackend {
+    /// One request per file, to its URL.
+    #[default]
+    SingleStream,
+    /// Large files are split into up to `segments` ranges, fetched in parallel from the URL and
+    /// the same file on the `mirrors` (see `mirror_urls`) and merged into the output file. Servers
+    /// without range support are downloaded from in a single stream.
+    Segmented {
+        segments: usize,
+        mirrors: Vec<String>,
+    },
+}
+
+impl DownloadBackend {
+    /// Segmented downloads from the tools mirrors of `get_idf_tools_mirrors_list`.
+    pub fn segmented(segments: usize) -> Self {
+        DownloadBackend::Segmented {
+            segments,
+            mirrors: crate::get_idf_tools_mirrors_list()
+                .iter()
+                .map(|mirror| mirror.to_string())
+                .collect(),
+        }
+    }
+}
+
+/// Progress of the downloads of a `DownloadManager`, for all files through a single channel.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DownloadManagerProgress {
+    /// The download of a file started, `total` is its size in bytes.
+    Started { name: String, total: u64 },
+    /// A file progressed, in bytes.
+    Progress {
+        name: String,
+        downloaded: u64,
+        total: u64,
+    },
+    /// The file is downloaded and verified.
+    Completed { name: String, path: PathBuf },
+    /// The file could not be downloaded or did not match its checksum.
+    Failed { name: String, error: String },
+    /// All files together progressed, in bytes.
+    Total { downloaded: u64, total: u64 },
+    /// All files were processed.
+    Finished,
+}
+
+/// How often the current speed of a transfer is measured, shorter intervals make it jumpy.
+const SPEED_SAMPLE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The speed of a download, sent as `DownloadProgress::Transfer` next to the plain progress.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransferStats {
+    /// The name of the file being downloaded.
+    pub file_name: String,
+    pub downloaded: u64,
+    pub total: u64,
+    /// The speed over the last sample interval, in bytes per second.
+    pub speed: f64,
+    /// The speed since the download started, in bytes per second.
+    pub average_speed: f64,
+    /// The estimated time until the download completes, `None` until there is a speed.
+    pub eta: Option<Duration>,
+}
+
+/// Measures the speed of a single download from the bytes downloaded over time.
+#[derive(Debug, Clone)]
+pub struct TransferMeter {
+    file_name: String,
+    total: u64,
+    started: Instant,
+    last_sample: (Instant, u64),
+    speed: Option<f64>,
+}
+
+impl TransferMeter {
+    pub fn new(file_name: &str, total: u64) -> Self {
+        Self::started_at(file_name, total, Instant::now())
+    }
+
+    fn started_at(file_name: &str, total: u64, started: Instant) -> Self {
+        TransferMeter {
+            file_name: file_name.to_string(),
+            total,
+            started,
+            last_sample: (started, 0),
+            speed: None,
+        }
+    }
+
+    /// Records the bytes downloaded so far.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<TransferStats>` - The stats once per sample interval, `None` in between so the
+    ///   frontends are not flooded with a message per chunk.
+    pub fn update(&mut self, downloaded: u64) -> Option<TransferStats> {
+        self.update_at(downloaded, Instant::now())
+    }
+
+    fn update_at(&mut self, downloaded: u64, now: Instant) -> Option<TransferStats> {
+        let (sampled_at, sampled_bytes) = self.last_sample;
+        let elapsed = now.duration_since(sampled_at);
+        if elapsed < SPEED_SAMPLE_INTERVAL {
+            return None;
+        }
+        self.speed = Some(downloaded.saturating_sub(sampled_bytes) as f64 / elapsed.as_secs_f64());
+        self.last_sample = (now, downloaded);
+        Some(self.stats_at(downloaded, now))
+    }
+
+    /// Returns the stats of the download regardless of the sample interval.
+    pub fn stats(&self, downloaded: u64) -> TransferStats {
+        self.stats_at(downloaded, Instant::now())
+    }
+
+    fn stats_at(&self, downloaded: u64, now: Instant) -> TransferStats {
+        let seconds = now.duration_since(self.started).as_secs_f64();
+        let average_speed = if seconds > 0.0 {
+            downloaded as f64 / seconds
+        } else {
+            0.0
+        };
+        // the average stands in until the first sample
+        let speed = self.speed.unwrap_or(average_speed);
+        let eta = (speed > 0.0)
+            .then(|| Duration::from_secs_f64(self.total.saturating_sub(downloaded) as f64 / speed));
+        TransferStats {
+            file_name: self.file_name.clone(),
+            downloaded,
+            total: self.total,
+            speed,
+            average_speed,
+            eta,
+        }
+    }
+}
+
+/// The bytes downloaded so far per file, to compute the total progress.
+type DownloadedBytes = Arc<Mutex<HashMap<String, u64>>>;
+
+/// Downloads several files concurrently, e.g. the tools from `get_list_of_tools_to_download`.
+///
+/// ```rust,ignore
+/// let manager = DownloadManager::new(dist_dir).parallelism(8);
+/// let results = manager.download(downloads, sender).await;
+/// ```
+#[derive(Debug, Clone)]
+pub struct DownloadManager {
+    destination: PathBuf,
+    parallelism: usize,
+    cancellation: CancellationToken,
+    backend: DownloadBackend,
+}
+
+impl DownloadManager {
+    /// Creates a manager downloading to `destination`, which is created if needed.
+    pub fn new<P: Into<PathBuf>>(destination: P) -> Self {
+        DownloadManager {
+            destination: destination.into(),
+            parallelism: DEFAULT_PARALLEL_DOWNLOADS,
+            cancellation: CancellationToken::new(),
+            backend: DownloadBackend::default(),
+        }
+    }
+
+    /// Sets how many files are downloaded at the same time, at least one.
+    pub fn parallelism(mut self, parallelism: usize) -> Self {
+        self.parallelism = parallelism.max(1);
+        self
+    }
+
+    /// Stop all downloads once the token is cancelled.
+    pub fn cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = token;
+        self
+    }
+
+    /// Sets how every file is fetched, a single stream by default.
+    pub fn backend(mut self, backend: DownloadBackend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Downloads the files and verifies them against their checksums.
+    ///
+    /// Files already present with the right checksum or found in the download cache are not
+    /// downloaded again. A failed file does not stop the others.
+    ///
+    /// # Parameters
+    ///
+    /// * `downloads` - The files by name, as returned by `get_list_of_tools_to_download`.
+    /// * `sender` - A channel sender receiving the progress of all files.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<(String, Result<PathBuf, String>)>` - The path of every file or the error, sorted by name.
+    pub async fn download(
+        &self,
+        downloads: HashMap<String, Download>,
+        sender: Sender<DownloadManagerProgress>,
+    ) -> Vec<(String, Result<PathBuf, String>)> {
+        let mut results = vec![];
+        if let Err(e) = crate::ensure_path(self.destination.to_str().unwrap()) {
+            results.extend(downloads.into_keys().map(|name| {
+                (
+                    name,
+                    Err(message(MessageId::FolderCreationFailed, &[&e.to_string()])),
+                )
+            }));
+            let _ = sender.send(DownloadManagerProgress::Finished);
+            return results;
+        }
+        let total: u64 = downloads.values().map(|download| download.size).sum();
+        let downloaded: DownloadedBytes = Arc::new(Mutex::new(HashMap::new()));
+        let semaphore = Arc::new(Semaphore::new(self.parallelism));
+        let mut tasks = JoinSet::new();
+
+        for (name, download) in downloads {
+            let semaphore = semaphore.clone();
+            let destination = self.destination.clone();
+            let cancellation = self.cancellation.clone();
+            let sender = sender.clone();
+            let downloaded = downloaded.clone();
+            let backend = self.backend.clone();
+            tasks.spawn(async move {
+                let _permit = semaphore.acquire_owned().await.unwrap();
+                let result = download_one(
+                    &name,
+                    &download,
+                    &destination,
+                    &backend,
+                    cancellation,
+                    sender.clone(),
+                    downloaded,
+                    total,
+                )
+                .await;
+                let _ = sender.send(match &result {
+                    Ok(path) => DownloadManagerProgress::Completed {
+                        name: name.clone(),
+                        path: path.clone(),
+                    },
+                    Err(e) => DownloadManagerProgress::Failed {
+                        name: name.clone(),
+                        error: e.clone(),
+                    },
+                });
+                (name, result)
+            });
+        }
+
+        while let Some(joined) = tasks.join_next().await {
+            match joined {
+                Ok(result) => results.push(result),
+                Err(e) => warn!("A download task failed: {}", e),
+            }
+        }
+        results.sort_by(|a, b| a.0.cmp(&b.0));
+        let _ = sender.send(DownloadManagerProgress::Finished);
+        results
+    }
+}
+
+/// Records the bytes downloaded of a file and reports the total progress.
+fn report_total(
+    downloaded: &DownloadedBytes,
+    name: &str,
+    bytes: u64,
+    total: u64,
+    sender: &Sender<DownloadManagerProgress>,
+) {
+    let sum = {
+        let mut downloaded = downloaded.lock().unwrap();
+        downloaded.insert(name.to_string(), bytes);
+        downloaded.values().sum()
+    };
+    let _ = sender.send(DownloadManagerProgress::Total {
+        downloaded: sum,
+        total,
+    });
+}
+
+/// Downloads and verifies a single file, forwarding its progress to the shared channel.
+async fn download_one(
+    name: &str,
+    download: &Download,
+    destination: &Path,
+    backend: &DownloadBackend,
+    cancellation: CancellationToken,
+    sender: Sender<DownloadManagerProgress>,
+    downloaded: DownloadedBytes,
+    total: u64,
+) -> Result<PathBuf, String> {
+    let filename = Path::new(&download.url)
+        .file_name()
+        .and_then(|filename| filename.to_str())
+        .ok_or_else(|| format!("Invalid download url {}", download.url))?;
+    let path = destination.join(filename);
+    let verify = |path: &Path| {
+        crate::verify_file_checksum(&download.sha256, path.to_str().unwrap())
+            .map_err(|e| format!("Failed to verify {}: {}", path.display(), e))
+    };
+    if verify(&path)? {
+        debug!("Using already downloaded {}", path.display());
+        report_total(&downloaded, name, download.size, total, &sender);
+        return Ok(path);
+    }
+    match crate::cache::restore(&download.sha256, destination, filename) {
+        Ok(Some(path)) => {
+            report_total(&downloaded, name, download.size, total, &sender);
+            return Ok(path);
+        }
+        Ok(None) => {}
+        Err(e) => warn!("Failed to restore {} from the download cache: {}", name, e),
+    }
+
+    let _ = sender.send(DownloadManagerProgress::Started {
+        name: name.to_string(),
+        total: download.size,
+    });
+    let reporter = {
+        let name = name.to_string();
+        let sender = sender.clone();
+        move |update: &ProgressUpdate| {
+            if let Some(ProgressDetail::Bytes {
+                downloaded: bytes,
+                total: file_total,
+            }) = update.detail
+            {
+                let _ = sender.send(DownloadManagerProgress::Progress {
+                    name: name.clone(),
+                    downloaded: bytes,
+                    total: file_total,
+                });
+                report_total(&downloaded, &name, bytes, total, &sender);
+            }
+        }
+    };
+    let result = match backend {
+        DownloadBackend::SingleStream => crate::download_file_cancellable(
+            &download.url,
+            destination.to_str().unwrap(),
+            reporter,
+            cancellation,
+        )
+        .await
+        .map_err(|e| e.to_string()),
+        DownloadBackend::Segmented { segments, mirrors } => download_segmented(
+            &mirror_urls(&download.url, mirrors),
+            destination,
+            *segments,
+            &reporter,
+            &cancellation,
+        )
+        .await
+        .map(|_| ()),
+    };
+    result.map_err(|e| message(MessageId::DownloadFailed, &[name, &e]))?;
+
+    if crate::dry_run::is_dry_run() {
+        Ok(path)
+    } else if verify(&path)? {
+        if let Err(e) = crate::cache::store(&download.sha256, &path) {
+            warn!("Failed to add {} to the download cache: {}", name, e);
+        }
+        Ok(path)
+    } else {
+        let _ = std::fs::remove_file(&path);
+        Err(message(MessageId::ChecksumMismatch, &[name]))
+    }
+}
+
+/// Returns the URL of a file on every mirror, the given URL first.
+///
+/// The mirrors serve the same paths, e.g. `https://github.com` and
+/// `https://dl.espressif.com/github_assets`. A URL on none of the mirrors has no alternatives.
+pub fn mirror_urls(url: &str, mirrors: &[String]) -> Vec<String> {
+    let mut urls = vec![url.to_string()];
+    let base = mirrors
+        .iter()
+        .map(|mirror| mirror.trim_end_matches('/'))
+        .filter(|mirror| {
+            url.strip_prefix(mirror)
+                .is_some_and(|path| path.starts_with('/'))
+        })
+        .max_by_key(|mirror| mirror.len());
+    if let Some(base) = base {
+        let path = &url[base.len()..];
+        for mirror in mirrors {
+            let mirrored = format!("{}{}", mirror.trim_end_matches('/'), path);
+            if !urls.contains(&mirrored) {
+                urls.push(mirrored);
+            }
+        }
+    }
+    urls
+}
+
+/// Returns the size of the file of `Content-Range: bytes 0-0/1234`.
+fn parse_content_range_total(content_range: &str) -> Option<u64> {
+    content_range.rsplit('/').next()?.trim().parse().ok()
+}
+
+/// Requests the first byte of a file, which tells whether the server supports range requests.
+///
+/// # Returns
+///
+/// * `Option<u64>` - The size of the file, `None` without range support.
+async fn probe_range_support(client: &reqwest::Client, url: &str) -> Option<u64> {
+    let response = crate::http_client::authorize(client.get(url), url)
+        .header(RANGE, "bytes=0-0")
+        .send()
+        .await
+        .ok()?;
+    if response.status() != StatusCode::PARTIAL_CONTENT {
+        return None;
+    }
+    parse_content_range_total(response.headers().get(CONTENT_RANGE)?.to_str().ok()?)
+}
+
+/// Splits `total` bytes into at most `segments` inclusive ranges of the same size.
+fn split_ranges(total: u64, segments: usize) -> Vec<(u64, u64)> {
+    let segments = segments.max(1) as u64;
+    let size = total.div_ceil(segments).max(1);
+    (0..segments)
+        .map(|segment| segment * size)
+        .take_while(|start| *start < total)
+        .map(|start| (start, (start + size).min(total) - 1))
+        .collect()
+}
+
+/// Fetches the inclusive range of a file into its place in `path`.
+///
+/// The bytes are added to `downloaded` as they arrive, and taken back when the range fails, so
+/// it can be retried from another URL.
+async fn download_range(
+    client: &reqwest::Client,
+    url: &str,
+    path: &Path,
+    (start, end): (u64, u64),
+    downloaded: &AtomicU64,
+    cancellation: &CancellationToken,
+) -> Result<(), String> {
+    let mut written = 0;
+    let result = async {
+        let mut response = crate::http_client::authorize(client.get(url), url)
+            .header(RANGE, format!("bytes={}-{}", start, end))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| e.to_string())?;
+        if response.status() != StatusCode::PARTIAL_CONTENT {
+            return Err(format!("{} ignored the range request", url));
+        }
+        let mut file = OpenOptions::new()
+            .write(true)
+            .open(path)
+            .map_err(|e| e.to_string())?;
+        file.seek(SeekFrom::Start(start))
+            .map_err(|e| e.to_string())?;
+        while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+            if cancellation.is_cancelled() {
+                return Err(message(MessageId::DownloadCancelled, &[url]));
+            }
+            file.write_all(&chunk).map_err(|e| e.to_string())?;
+            written += chunk.len() as u64;
+            downloaded.fetch_add(chunk.len() as u64, Ordering::Relaxed);
+        }
+        if written != end - start + 1 {
+            return Err(format!(
+                "{} sent {} of {} bytes",
+                url,
+                written,
+                end - start + 1
+            ));
+        }
+        Ok(())
+    }
+    .await;
+    if result.is_err() {
+        downloaded.fetch_sub(written, Ordering::Relaxed);
+    }
+    result
+}
+
+/// Downloads a file in parallel ranges from several URLs serving it, see
+/// `DownloadBackend::Segmented`.
+///
+/// The file is downloaded in a single stream with `download_file_cancellable` from the first URL
+/// when it is too small to split or the server does not support range requests. The other URLs
+/// take segments only if they support range requests too, a segment failing on one of them is
+/// retried from the first URL.
+///
+/// # Parameters
+///
+/// * `urls` - The URLs of the file, see `mirror_urls`.
+/// * `destination` - The folder the file is saved to, under the name of the first URL.
+/// * `segments` - Into how many ranges the file is split at most.
+/// * `reporter` - The reporter receiving the progress of the whole file.
+/// * `cancellation` - Stops all segments once cancelled.
+///
+/// # Returns
+///
+/// * `Result<PathBuf, String>` - The downloaded file, which is removed when the download fails.
+pub async fn download_segmented(
+    urls: &[String],
+    destination: &Path,
+    segments: usize,
+    reporter: &dyn ProgressReporter,
+    cancellation: &CancellationToken,
+) -> Result<PathBuf, String> {
+    let url = urls.first().ok_or("No URL to download from")?;
+    let file_name = Path::new(url)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| format!("Invalid download url {}", url))?;
+    let path = destination.join(file_name);
+    let client = crate::http_client::client().map_err(|e| e.to_string())?;
+    // dry runs only plan the download, which the single stream does
+    let total = if crate::dry_run::is_dry_run() {
+        None
+    } else {
+        probe_range_support(&client, url).await
+    };
+    let ranges = total
+        .map(|total| split_ranges(total, segments.min((total / MIN_SEGMENT_SIZE) as usize)))
+        .unwrap_or_default();
+    let (Some(total), true) = (total, ranges.len() > 1) else {
+        debug!("Downloading {} in a single stream", url);
+        crate::download_file_cancellable(
+            url,
+            destination.to_str().unwrap(),
+            reporter,
+            cancellation.clone(),
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+        return Ok(path);
+    };
+    let mut sources = vec![url.clone()];
+    for mirror in &urls[1..] {
+        if probe_range_support(&client, mirror).await == Some(total) {
+            sources.push(mirror.clone());
+        }
+    }
+    debug!(
+        "Downloading {} in {} segments from {} sources",
+        file_name,
+        ranges.len(),
+        sources.len()
+    );
+
+    File::create(&path)
+        .and_then(|file| file.set_len(total))
+        .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    let update = || ProgressUpdate::new(InstallPhase::Download, url);
+    progress::report(reporter, &update().detail(ProgressDetail::Started));
+    let downloaded = Arc::new(AtomicU64::new(0));
+    let mut tasks = JoinSet::new();
+    for (index, range) in ranges.into_iter().enumerate() {
+        let mut candidates = vec![sources[index % sources.len()].clone()];
+        if candidates[0] != *url {
+            candidates.push(url.clone());
+        }
+        let client = client.clone();
+        let path = path.clone();
+        let downloaded = downloaded.clone();
+        let cancellation = cancellation.clone();
+        tasks.spawn(async move {
+            let mut error = String::new();
+            for candidate in candidates {
+                match download_range(
+                    &client,
+                    &candidate,
+                    &path,
+                    range,
+                    &downloaded,
+                    &cancellation,
+                )
+                .await
+                {
+                    Ok(()) => return Ok(()),
+                    Err(e) => {
+                        warn!("Segment {:?} from {} failed: {}", range, candidate, e);
+                        error = e;
+                    }
+                }
+                if cancellation.is_cancelled() {
+                    break;
+                }
+            }
+            Err(error)
+        });
+    }
+
+    let mut meter = TransferMeter::new(file_name, total);
+    let mut result = Ok(());
+    loop {
+        tokio::select! {
+            joined = tasks.join_next() => match joined {
+                None => break,
+                Some(Ok(Ok(()))) => {}
+                Some(Ok(Err(e))) => {
+                    // the other segments are of no use anymore
+                    tasks.abort_all();
+                    result = result.and(Err(e));
+                }
+                Some(Err(e)) if !e.is_cancelled() => result = result.and(Err(e.to_string())),
+                Some(Err(_)) => {}
+            },
+            _ = tokio::time::sleep(SEGMENT_PROGRESS_INTERVAL) => {}
+        }
+        let bytes = downloaded.load(Ordering::Relaxed);
+        progress::report(
+            reporter,
+            &update()
+                .percent(events::percent(bytes, total))
+                .detail(ProgressDetail::Bytes {
+                    downloaded: bytes,
+                    total,
+                }),
+        );
+        if let Some(stats) = meter.update(bytes) {
+            progress::report(reporter, &update().detail(ProgressDetail::Transfer(stats)));
+        }
+    }
+
+    match result {
+        Ok(()) => {
+            progress::report(
+                reporter,
+                &update().detail(ProgressDetail::Transfer(meter.stats(total))),
+            );
+            progress::report(
+                reporter,
+                &update().percent(100).detail(ProgressDetail::Finished),
+            );
+            Ok(path)
+        }
+        Err(error) => {
+            let _ = std::fs::remove_file(&path);
+            progress::report(
+                reporter,
+                &update().detail(ProgressDetail::Failed(error.clone())),
+            );
+            Err(error)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::channel;
+
+    #[test]
+    fn test_report_total() {
+        let downloaded: DownloadedBytes = Arc::new(Mutex::new(HashMap::new()));
+        let (sender, receiver) = channel();
+        report_total(&downloaded, "cmake", 100, 300, &sender);
+        report_total(&downloaded, "ninja", 50, 300, &sender);
+        report_total(&downloaded, "cmake", 200, 300, &sender);
+
+        let totals: Vec<DownloadManagerProgress> = receiver.try_iter().collect();
+        assert_eq!(
+            totals.last(),
+            Some(&DownloadManagerProgress::Total {
+                downloaded: 250,
+                total: 300
+            })
+        );
+    }
+
+    #[test]
+    fn test_transfer_meter() {
+        let start = Instant::now();
+        let mut meter = TransferMeter::started_at("cmake.tar.gz", 3000, start);
+        assert_eq!(
+            meter.update_at(100, start + Duration::from_millis(100)),
+            None
+        );
+
+        let stats = meter
+            .update_at(1000, start + Duration::from_secs(1))
+            .unwrap();
+        assert_eq!(stats.file_name, "cmake.tar.gz");
+        assert_eq!(stats.speed, 1000.0);
+        assert_eq!(stats.eta, Some(Duration::from_secs(2)));
+
+        let stats = meter
+            .update_at(1500, start + Duration::from_secs(2))
+            .unwrap();
+        assert_eq!(stats.speed, 500.0);
+        assert_eq!(stats.average_speed, 750.0);
+        assert_eq!(stats.eta, Some(Duration::from_secs(3)));
+    }
+
+    #[test]
+    fn test_split_ranges() {
+        assert_eq!(split_ranges(10, 3), vec![(0, 3), (4, 7), (8, 9)]);
+        assert_eq!(split_ranges(2, 4), vec![(0, 0), (1, 1)]);
+        assert_eq!(split_ranges(10, 0), vec![(0, 9)]);
+    }
+
+    #[test]
+    fn test_mirror_urls() {
+        let mirrors = vec![
+            "https://github.com".to_string(),
+            "https://dl.espressif.com/github_assets".to_string(),
+        ];
+        assert_eq!(
+            mirror_urls(
+                "https://dl.espressif.com/github_assets/espressif/ninja.zip",
+                &mirrors
+            ),
+            vec![
+                "https://dl.espressif.com/github_assets/espressif/ninja.zip",
+                "https://github.com/espressif/ninja.zip",
+            ]
+        );
+        assert_eq!(
+            mirror_urls("https://example.com/ninja.zip", &mirrors),
+            vec!["https://example.com/ninja.zip"]
+        );
+        assert_eq!(parse_content_range_total("bytes 0-0/1234"), Some(1234));
+    }
+
+    #[test]
+    fn test_parallelism_is_at_least_one() {
+        let manager = DownloadManager::new("dist").parallelism(0);
+        assert_eq!(manager.parallelism, 1);
+    }
+}
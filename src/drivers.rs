@@ -0,0 +1,2095 @@
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use ed25519_dalek::{Signature, VerifyingKey};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::command_executor;
+use crate::events::InstallPhase;
+use crate::messages::{message, MessageId};
+use crate::progress::{self, ProgressDetail, ProgressReporter, ProgressUpdate};
+
+/// A USB-serial/JTAG driver needed by ESP development boards on Windows.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DriverInfo {
+    /// Short identifier of the driver, e.g. `cp210x`.
+    pub name: String,
+    pub description: String,
+    /// File name of the INF file, as shown in the `Original Name` column of `pnputil`.
+    /// A path relative to the root of the package is used as is instead of searching for the file.
+    pub inf_name: String,
+    /// `(vendor id, product id)` pairs of the devices served by the driver, lowercase hex.
+    pub devices: Vec<(String, String)>,
+    /// Where the archive with the driver package is downloaded from.
+    pub url: String,
+    /// The expected sha256 of the archive, not verified when `None`.
+    #[serde(default)]
+    pub sha256: Option<String>,
+    /// The version of the driver in the package, as reported by `pnputil`. When `None`,
+    /// any installed version is considered good enough.
+    #[serde(default)]
+    pub version: Option<String>,
+    /// A copy of the package tried when `url` fails, e.g. on the Espressif download server.
+    #[serde(default)]
+    pub mirror_url: Option<String>,
+    /// The common name (`CN=...`) or the thumbprint of the certificate the catalog of the package
+    /// has to be signed with, `WHQL_SIGNER` when `None`.
+    #[serde(default)]
+    pub signer: Option<String>,
+}
+
+/// The common name of the certificate Microsoft signs the catalogs of WHQL certified drivers with.
+pub const WHQL_SIGNER: &str = "CN=Microsoft Windows Hardware Compatibility Publisher";
+
+/// Where Espressif hosts copies of the driver packages of the built-in list.
+pub const ESPRESSIF_DRIVERS_MIRROR: &str = "https://dl.espressif.com/dl/eim/drivers/";
+
+/// The official location of the drivers manifest. Its detached signature is expected at the same URL with `.sig` appended.
+pub const DEFAULT_DRIVERS_MANIFEST_URL: &str = "https://dl.espressif.com/dl/eim/drivers.json";
+
+/// The hex encoded ed25519 public key the published drivers manifest is signed with.
+pub const DRIVERS_MANIFEST_PUBLIC_KEY: &str =
+    "401ac7a37473679ca001206f86c976311a1e74f154df53c7d9fd218c7e1452ed";
+
+static DRIVERS_MANIFEST_URL: RwLock<Option<String>> = RwLock::new(None);
+static DRIVERS_MANIFEST_PUBLIC_KEY_OVERRIDE: RwLock<Option<String>> = RwLock::new(None);
+static EXTRA_DRIVERS: RwLock<Vec<DriverInfo>> = RwLock::new(vec![]);
+
+/// Overrides the location of the drivers manifest. Passing `None` restores the default.
+pub fn set_drivers_manifest_url(url: Option<String>) {
+    *DRIVERS_MANIFEST_URL.write().unwrap() = url;
+}
+
+/// Returns the URL the drivers manifest is downloaded from.
+pub fn get_drivers_manifest_url() -> String {
+    DRIVERS_MANIFEST_URL
+        .read()
+        .unwrap()
+        .clone()
+        .unwrap_or_else(|| DEFAULT_DRIVERS_MANIFEST_URL.to_string())
+}
+
+/// Sets the hex encoded ed25519 public key the drivers manifest has to be signed with, e.g. for
+/// a manifest published by a board vendor. Passing `None` restores `DRIVERS_MANIFEST_PUBLIC_KEY`.
+///
+/// Manifests without a valid signature are always rejected and the built-in driver list is
+/// used instead.
+pub fn set_drivers_manifest_public_key(key: Option<String>) {
+    *DRIVERS_MANIFEST_PUBLIC_KEY_OVERRIDE.write().unwrap() = key;
+}
+
+/// Returns the key the drivers manifest is verified with.
+fn get_drivers_manifest_public_key() -> String {
+    DRIVERS_MANIFEST_PUBLIC_KEY_OVERRIDE
+        .read()
+        .unwrap()
+        .clone()
+        .unwrap_or_else(|| DRIVERS_MANIFEST_PUBLIC_KEY.to_string())
+}
+
+/// Sets the drivers installed alongside the published ones, e.g. for custom USB bridges of board vendors.
+///
+/// An extra driver with the same name as a published one replaces it. Like the published drivers,
+/// extra drivers are only installed when their catalog is signed by their `signer`.
+pub fn set_extra_drivers(drivers: Option<Vec<DriverInfo>>) {
+    *EXTRA_DRIVERS.write().unwrap() = drivers.unwrap_or_default();
+}
+
+/// Adds the extra drivers to the list, replacing the drivers with the same name.
+fn merge_extra_drivers(mut drivers: Vec<DriverInfo>, extra: &[DriverInfo]) -> Vec<DriverInfo> {
+    drivers.retain(|d| !extra.iter().any(|e| e.name == d.name));
+    drivers.extend(extra.iter().cloned());
+    drivers
+}
+
+/// The list of drivers published by Espressif, so new driver versions do not need a new release.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DriversManifest {
+    pub drivers: Vec<DriverInfo>,
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    let hex = hex.trim();
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Verifies the detached ed25519 signature of the manifest.
+///
+/// # Parameters
+///
+/// * `content` - The manifest exactly as downloaded.
+/// * `signature` - The hex encoded signature.
+/// * `public_key` - The hex encoded public key.
+///
+/// # Returns
+///
+/// * `Ok(())` if the signature is valid, otherwise an error describing the problem.
+pub fn verify_manifest_signature(
+    content: &[u8],
+    signature: &str,
+    public_key: &str,
+) -> Result<(), String> {
+    let key: [u8; 32] = decode_hex(public_key)
+        .and_then(|k| k.try_into().ok())
+        .ok_or("Malformed drivers manifest public key")?;
+    let key = VerifyingKey::from_bytes(&key).map_err(|e| format!("Invalid public key: {}", e))?;
+    let signature: [u8; 64] = decode_hex(signature)
+        .and_then(|s| s.try_into().ok())
+        .ok_or("Malformed drivers manifest signature")?;
+    // the strict verification rejects weak keys, which validate forged signatures
+    key.verify_strict(content, &Signature::from_bytes(&signature))
+        .map_err(|_| "The drivers manifest signature is not valid".to_string())
+}
+
+fn get_drivers_manifest_cache_path() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|d| d.join("eim").join("cache").join("drivers.json"))
+}
+
+/// Returns the path the signature of the cached manifest is stored at, next to the manifest.
+fn signature_path(manifest_path: &Path) -> PathBuf {
+    manifest_path.with_extension("json.sig")
+}
+
+/// Parses the manifest after verifying its signature.
+fn parse_signed_manifest(content: &str, signature: &str) -> Result<DriversManifest, String> {
+    verify_manifest_signature(
+        content.as_bytes(),
+        signature,
+        &get_drivers_manifest_public_key(),
+    )?;
+    serde_json::from_str(content).map_err(|e| format!("Malformed drivers manifest: {}", e))
+}
+
+/// Reads the cached manifest, verifying its signature again as the cache may have been
+/// modified since it was downloaded.
+fn read_cached_manifest(cache_path: &Path) -> Result<DriversManifest, String> {
+    let content = std::fs::read_to_string(cache_path).map_err(|e| e.to_string())?;
+    let signature =
+        std::fs::read_to_string(signature_path(cache_path)).map_err(|e| e.to_string())?;
+    parse_signed_manifest(&content, &signature)
+}
+
+/// Downloads the drivers manifest and its signature, verifying the signature.
+///
+/// # Returns
+///
+/// * `Ok((String, String))` - The content of the manifest and its hex encoded signature.
+/// * `Err(String)` - If the manifest or its signature could not be downloaded, or the signature is invalid.
+pub async fn fetch_drivers_manifest() -> Result<(String, String), String> {
+    let url = get_drivers_manifest_url();
+    let client = crate::http_client::client().map_err(|e| e.to_string())?;
+    let fetch = |url: String| {
+        let client = client.clone();
+        async move {
+            client
+                .get(&url)
+                .send()
+                .await
+                .and_then(|r| r.error_for_status())
+                .map_err(|e| format!("Failed to download {}: {}", url, e))?
+                .text()
+                .await
+                .map_err(|e| format!("Failed to download {}: {}", url, e))
+        }
+    };
+    let content = fetch(url.clone()).await?;
+    let signature = fetch(format!("{}.sig", url)).await?;
+    verify_manifest_signature(
+        content.as_bytes(),
+        &signature,
+        &get_drivers_manifest_public_key(),
+    )?;
+    Ok((content, signature))
+}
+
+/// Returns the drivers to work with: from the remote manifest, from its last downloaded copy,
+/// or the built-in list as the last resort (and always in offline mode).
+pub async fn get_drivers() -> Vec<DriverInfo> {
+    let drivers = get_published_drivers().await;
+    merge_extra_drivers(drivers, &EXTRA_DRIVERS.read().unwrap())
+}
+
+async fn get_published_drivers() -> Vec<DriverInfo> {
+    let cache_path = get_drivers_manifest_cache_path();
+    if !crate::system_dependencies::is_offline_mode() {
+        let fetched = fetch_drivers_manifest()
+            .await
+            .and_then(|(content, signature)| {
+                parse_signed_manifest(&content, &signature)
+                    .map(|manifest| (manifest, content, signature))
+            });
+        match fetched {
+            Ok((manifest, content, signature)) => {
+                if let Some(cache_path) = &cache_path {
+                    let written = std::fs::create_dir_all(cache_path.parent().unwrap())
+                        .and_then(|_| std::fs::write(cache_path, &content))
+                        .and_then(|_| std::fs::write(signature_path(cache_path), &signature));
+                    if let Err(e) = written {
+                        warn!("Failed to cache the drivers manifest: {}", e);
+                    }
+                }
+                return manifest.drivers;
+            }
+            Err(e) => warn!("{}", e),
+        }
+    }
+    match cache_path.as_deref().map(read_cached_manifest) {
+        Some(Ok(manifest)) => {
+            debug!("Using the cached drivers manifest");
+            manifest.drivers
+        }
+        Some(Err(e)) => {
+            debug!("Ignoring the cached drivers manifest: {}", e);
+            get_known_drivers()
+        }
+        None => {
+            debug!("Using the built-in drivers list");
+            get_known_drivers()
+        }
+    }
+}
+
+/// A third party driver package present in the Windows driver store.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InstalledDriver {
+    /// Name of the package in the driver store, e.g. `oem12.inf`.
+    pub published_name: String,
+    /// Name of the INF file the package was installed from.
+    pub original_name: String,
+    pub provider: String,
+    /// Driver version without the date.
+    pub version: String,
+}
+
+/// Which drivers should be installed and which were skipped, and why.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DriverPlan {
+    pub install: Vec<DriverInfo>,
+    pub skipped: Vec<(DriverInfo, String)>,
+}
+
+/// What happened to a single driver.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum DriverStatus {
+    Installed,
+    /// The driver was not needed, with the reason.
+    Skipped(String),
+    DownloadFailed(String),
+    InstallFailed(String),
+    /// Administrator rights were needed but not granted, with the reason.
+    ElevationDenied(String),
+    /// The files of the package are not validly signed by the expected signer, with the reason.
+    SignatureInvalid(String),
+}
+
+/// Why a driver could not be installed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DriverError {
+    /// The driver package could not be downloaded or failed the checksum.
+    DownloadFailed(String),
+    /// The user declined the UAC prompt, or prompting was not allowed in silent mode.
+    ElevationDenied(String),
+    /// The driver package could not be extracted or `pnputil` failed.
+    InstallFailed(String),
+    /// The files of the package carry no valid Authenticode signature of the expected signer.
+    SignatureInvalid(String),
+}
+
+impl std::fmt::Display for DriverError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DriverError::DownloadFailed(msg) => write!(f, "Driver download failed: {}", msg),
+            DriverError::ElevationDenied(msg) => {
+                write!(f, "Administrator rights are required: {}", msg)
+            }
+            DriverError::InstallFailed(msg) => write!(f, "Driver installation failed: {}", msg),
+            DriverError::SignatureInvalid(msg) => {
+                write!(f, "Driver package is not trusted: {}", msg)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DriverError {}
+
+/// How `install_drivers` installs the drivers.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DriverInstallOptions {
+    /// Never show a UAC prompt. Without administrator rights, the drivers are reported as `ElevationDenied`.
+    pub silent: bool,
+    /// Install packages without valid Authenticode signatures of the expected signer.
+    /// Only meant for testing drivers which are not yet signed.
+    pub allow_unsigned: bool,
+}
+
+/// The result of processing a single driver by `install_drivers`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DriverResult {
+    pub name: String,
+    /// Whether an already downloaded package was reused instead of downloading it again.
+    pub cached: bool,
+    pub status: DriverStatus,
+}
+
+impl DriverResult {
+    /// Returns the error of a failed driver, `None` when it was installed or skipped.
+    pub fn error(&self) -> Option<DriverError> {
+        match &self.status {
+            DriverStatus::Installed | DriverStatus::Skipped(_) => None,
+            DriverStatus::DownloadFailed(e) => Some(DriverError::DownloadFailed(e.clone())),
+            DriverStatus::InstallFailed(e) => Some(DriverError::InstallFailed(e.clone())),
+            DriverStatus::ElevationDenied(e) => Some(DriverError::ElevationDenied(e.clone())),
+            DriverStatus::SignatureInvalid(e) => Some(DriverError::SignatureInvalid(e.clone())),
+        }
+    }
+}
+
+/// The outcome of a run of `install_drivers`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DriverReport {
+    /// The result of every driver, in the order they were processed.
+    pub results: Vec<DriverResult>,
+}
+
+impl DriverReport {
+    /// Names of the drivers which were installed.
+    pub fn installed(&self) -> Vec<&str> {
+        self.results
+            .iter()
+            .filter(|r| r.status == DriverStatus::Installed)
+            .map(|r| r.name.as_str())
+            .collect()
+    }
+
+    /// The drivers which failed, with their errors.
+    pub fn failures(&self) -> Vec<(&str, DriverError)> {
+        self.results
+            .iter()
+            .filter_map(|r| r.error().map(|e| (r.name.as_str(), e)))
+            .collect()
+    }
+
+    /// Checks if every driver was either installed or skipped.
+    pub fn is_success(&self) -> bool {
+        self.results.iter().all(|r| r.error().is_none())
+    }
+
+    /// Turns the report into an error when drivers failed and none was installed.
+    ///
+    /// The error of the first failed driver is returned, so a declined elevation prompt is
+    /// reported as `DriverError::ElevationDenied` rather than as a generic failure.
+    fn into_result(self) -> Result<DriverReport, DriverError> {
+        if self.installed().is_empty() {
+            if let Some((_, error)) = self.failures().into_iter().next() {
+                return Err(error);
+            }
+        }
+        Ok(self)
+    }
+}
+
+fn driver(
+    name: &str,
+    description: &str,
+    inf_name: &str,
+    devices: &[(&str, &str)],
+    url: &str,
+) -> DriverInfo {
+    DriverInfo {
+        name: name.to_string(),
+        description: description.to_string(),
+        inf_name: inf_name.to_string(),
+        devices: devices
+            .iter()
+            .map(|(vid, pid)| (vid.to_string(), pid.to_string()))
+            .collect(),
+        url: url.to_string(),
+        sha256: None,
+        version: None,
+        mirror_url: Some(format!("{}{}.zip", ESPRESSIF_DRIVERS_MIRROR, name)),
+        signer: None,
+    }
+}
+
+/// Returns the drivers for the USB bridges used on ESP development boards.
+///
+/// The vendor packages are not pinned by a checksum, as the vendors update them in place. Like
+/// every package they are only installed when their catalog is signed by `WHQL_SIGNER`.
+pub fn get_known_drivers() -> Vec<DriverInfo> {
+    vec![
+        driver(
+            "espressif_usb",
+            "Espressif USB-JTAG/serial debug unit",
+            "usb_jtag_debug_unit.inf",
+            &[("303a", "1001"), ("303a", "1002")],
+            "https://dl.espressif.com/dl/idf-driver/idf-driver-esp32-usb-jtag-2021-07-15.zip",
+        ),
+        driver(
+            "cp210x",
+            "Silicon Labs CP210x USB to UART bridge",
+            "silabser.inf",
+            &[("10c4", "ea60")],
+            "https://www.silabs.com/documents/public/software/CP210x_Universal_Windows_Driver.zip",
+        ),
+        driver(
+            "ftdi",
+            "FTDI USB to UART/JTAG bridge",
+            "ftdibus.inf",
+            &[("0403", "6001"), ("0403", "6010"), ("0403", "6014")],
+            "https://www.ftdichip.com/Drivers/CDM/CDM%20v2.12.28%20WHQL%20Certified.zip",
+        ),
+        driver(
+            "ch34x",
+            "WCH CH340/CH341 USB to UART bridge",
+            "ch341ser.inf",
+            &[("1a86", "7523")],
+            "https://www.wch.cn/downloads/file/65.html",
+        ),
+        driver(
+            "ch343",
+            "WCH CH343/CH9102 USB to UART bridge",
+            "ch343ser.inf",
+            &[("1a86", "55d4")],
+            "https://www.wch.cn/downloads/CH343SER_ZIP.html",
+        ),
+    ]
+}
+
+/// Parses the output of `pnputil /enum-drivers`.
+///
+/// The output consists of blocks of `Key: Value` lines separated by empty lines.
+/// The `Driver Version` value contains the date followed by the version.
+pub fn parse_pnputil_drivers(output: &str) -> Vec<InstalledDriver> {
+    let mut drivers = vec![];
+    for block in output.replace("\r\n", "\n").split("\n\n") {
+        let mut published_name = None;
+        let mut original_name = None;
+        let mut provider = String::new();
+        let mut version = String::new();
+        for line in block.lines() {
+            let (key, value) = match line.split_once(':') {
+                Some((k, v)) => (k.trim(), v.trim()),
+                None => continue,
+            };
+            match key {
+                "Published Name" => published_name = Some(value.to_string()),
+                "Original Name" => original_name = Some(value.to_lowercase()),
+                "Provider Name" => provider = value.to_string(),
+                "Driver Version" => {
+                    version = value.split_whitespace().last().unwrap_or("").to_string()
+                }
+                _ => {}
+            }
+        }
+        if let (Some(published_name), Some(original_name)) = (published_name, original_name) {
+            drivers.push(InstalledDriver {
+                published_name,
+                original_name,
+                provider,
+                version,
+            });
+        }
+    }
+    drivers
+}
+
+/// Lists the third party driver packages in the Windows driver store.
+///
+/// # Returns
+///
+/// * `Ok(Vec<InstalledDriver>)` - The installed driver packages, always empty on other operating systems.
+/// * `Err(String)` - If `pnputil` could not be executed.
+pub fn get_installed_drivers() -> Result<Vec<InstalledDriver>, String> {
+    if std::env::consts::OS != "windows" {
+        return Ok(vec![]);
+    }
+    let output = command_executor::execute_checked(
+        "pnputil",
+        &["/enum-drivers"],
+        vec![],
+        &command_executor::ExecOptions::new(),
+    )
+    .map_err(|e| format!("Failed to enumerate drivers: {}", e))?;
+    Ok(parse_pnputil_drivers(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+/// Extracts `(vendor id, product id)` pairs from Windows device instance ids
+/// like `USB\VID_10C4&PID_EA60\0001`, one per line.
+pub fn parse_usb_instance_ids(output: &str) -> Vec<(String, String)> {
+    let mut devices = vec![];
+    for line in output.lines() {
+        let line = line.trim().to_lowercase();
+        let vid = line.find("vid_").and_then(|i| line.get(i + 4..i + 8));
+        let pid = line.find("pid_").and_then(|i| line.get(i + 4..i + 8));
+        if let (Some(vid), Some(pid)) = (vid, pid) {
+            let device = (vid.to_string(), pid.to_string());
+            if !devices.contains(&device) {
+                devices.push(device);
+            }
+        }
+    }
+    devices
+}
+
+/// Extracts `(vendor id, product id)` pairs from the output of `ioreg -p IOUSB -l`,
+/// where the ids are printed in decimal.
+pub fn parse_ioreg_usb(output: &str) -> Vec<(String, String)> {
+    let property = |block: &str, name: &str| -> Option<u32> {
+        block
+            .lines()
+            .find(|line| line.contains(&format!("\"{}\"", name)))?
+            .split('=')
+            .nth(1)?
+            .trim()
+            .parse()
+            .ok()
+    };
+    let mut devices = vec![];
+    // every device starts with a `+-o Name@location` line
+    for block in output.split("+-o ") {
+        if let (Some(vid), Some(pid)) = (property(block, "idVendor"), property(block, "idProduct"))
+        {
+            let device = (format!("{:04x}", vid), format!("{:04x}", pid));
+            if !devices.contains(&device) {
+                devices.push(device);
+            }
+        }
+    }
+    devices
+}
+
+/// Lists the `(vendor id, product id)` pairs of the connected USB devices, lowercase hex.
+///
+/// Failures to enumerate the devices are logged and result in an empty list.
+pub fn get_connected_usb_devices() -> Vec<(String, String)> {
+    match std::env::consts::OS {
+        "windows" => {
+            let output = command_executor::execute_command(
+                "powershell",
+                &[
+                    "-NoProfile",
+                    "-NonInteractive",
+                    "-Command",
+                    "Get-PnpDevice -PresentOnly | Where-Object { $_.InstanceId -like 'USB\\VID_*' } | ForEach-Object { $_.InstanceId }",
+                ],
+            );
+            match output {
+                Ok(o) => parse_usb_instance_ids(&String::from_utf8_lossy(&o.stdout)),
+                Err(e) => {
+                    warn!("Failed to enumerate USB devices: {}", e);
+                    vec![]
+                }
+            }
+        }
+        "macos" => match command_executor::execute_command("ioreg", &["-p", "IOUSB", "-l"]) {
+            Ok(o) => parse_ioreg_usb(&String::from_utf8_lossy(&o.stdout)),
+            Err(e) => {
+                warn!("Failed to enumerate USB devices: {}", e);
+                vec![]
+            }
+        },
+        _ => {
+            let mut devices = vec![];
+            let entries = match std::fs::read_dir("/sys/bus/usb/devices") {
+                Ok(entries) => entries,
+                Err(e) => {
+                    warn!("Failed to enumerate USB devices: {}", e);
+                    return devices;
+                }
+            };
+            for entry in entries.flatten() {
+                let read = |file: &str| {
+                    std::fs::read_to_string(entry.path().join(file))
+                        .ok()
+                        .map(|v| v.trim().to_lowercase())
+                };
+                if let (Some(vid), Some(pid)) = (read("idVendor"), read("idProduct")) {
+                    if !devices.contains(&(vid.clone(), pid.clone())) {
+                        devices.push((vid, pid));
+                    }
+                }
+            }
+            devices
+        }
+    }
+}
+
+/// Plans the installation of only the drivers needed by the connected devices.
+///
+/// Unlike `plan_driver_installation` with no devices, nothing is installed when no supported device is connected.
+///
+/// # Parameters
+///
+/// * `drivers` - The available drivers, see `get_drivers`.
+/// * `installed` - A slice of the driver packages present in the system, see `get_installed_drivers`.
+///
+/// # Returns
+///
+/// * `DriverPlan` - The drivers to install and the skipped ones with the reason.
+pub fn plan_for_connected_devices(
+    drivers: &[DriverInfo],
+    installed: &[InstalledDriver],
+) -> DriverPlan {
+    let connected = get_connected_usb_devices();
+    debug!("Connected USB devices: {:?}", connected);
+    if connected.is_empty() {
+        return DriverPlan {
+            install: vec![],
+            skipped: drivers
+                .iter()
+                .map(|d| (d.clone(), "no matching device".to_string()))
+                .collect(),
+        };
+    }
+    plan_driver_installation(drivers, &connected, installed)
+}
+
+/// Checks if installing drivers makes sense in the current environment.
+///
+/// Drivers are only installed on Windows, and never in containers, CI, or WSL (where the drivers
+/// belong to the Windows host).
+pub fn should_install_drivers() -> bool {
+    let info = crate::platform::platform_info();
+    if info.os != "windows" || info.is_wsl {
+        return false;
+    }
+    if info.is_unattended() {
+        debug!("Skipping driver installation in container/CI environment");
+        return false;
+    }
+    true
+}
+
+/// Compares two dotted driver versions (`11.3.0.198`) numerically. Missing parts count as zero.
+pub fn compare_driver_versions(a: &str, b: &str) -> std::cmp::Ordering {
+    let parse = |v: &str| -> Vec<u64> {
+        v.trim()
+            .split('.')
+            .map(|part| part.trim().parse().unwrap_or(0))
+            .collect()
+    };
+    let (a, b) = (parse(a), parse(b));
+    for i in 0..a.len().max(b.len()) {
+        let ordering = a.get(i).unwrap_or(&0).cmp(b.get(i).unwrap_or(&0));
+        if ordering != std::cmp::Ordering::Equal {
+            return ordering;
+        }
+    }
+    std::cmp::Ordering::Equal
+}
+
+/// Decides which of the drivers need to be installed.
+///
+/// Drivers which do not serve any of the needed devices, and drivers of which an equal or newer
+/// version is already present in the driver store, are skipped, so an installed driver is never
+/// downgraded or duplicated. Drivers without a known version are skipped if any version is present.
+/// When `needed_devices` is empty, every driver is considered needed.
+///
+/// # Parameters
+///
+/// * `drivers` - The available drivers, see `get_drivers`.
+/// * `needed_devices` - A slice of `(vendor id, product id)` pairs of the devices to support.
+/// * `installed` - A slice of the driver packages present in the system, see `get_installed_drivers`.
+///
+/// # Returns
+///
+/// * `DriverPlan` - The drivers to install and the skipped ones with the reason.
+pub fn plan_driver_installation(
+    drivers: &[DriverInfo],
+    needed_devices: &[(String, String)],
+    installed: &[InstalledDriver],
+) -> DriverPlan {
+    let mut plan = DriverPlan {
+        install: vec![],
+        skipped: vec![],
+    };
+    for driver in drivers.iter().cloned() {
+        let needed = needed_devices.is_empty()
+            || driver.devices.iter().any(|(vid, pid)| {
+                needed_devices
+                    .iter()
+                    .any(|(v, p)| v.eq_ignore_ascii_case(vid) && p.eq_ignore_ascii_case(pid))
+            });
+        if !needed {
+            plan.skipped
+                .push((driver, "no matching device".to_string()));
+            continue;
+        }
+        // the driver store may contain several versions of the same package
+        let newest = installed
+            .iter()
+            .filter(|i| i.original_name.eq_ignore_ascii_case(&driver.inf_name))
+            .max_by(|a, b| compare_driver_versions(&a.version, &b.version));
+        let up_to_date = match (newest, &driver.version) {
+            (Some(present), Some(version)) => {
+                compare_driver_versions(&present.version, version) != std::cmp::Ordering::Less
+            }
+            (Some(_), None) => true,
+            (None, _) => false,
+        };
+        match newest {
+            Some(present) if up_to_date => {
+                debug!(
+                    "Driver {} already installed as {}",
+                    driver.name, present.published_name
+                );
+                let reason = message(
+                    MessageId::DriverAlreadyInstalled,
+                    &[&present.published_name, &present.version],
+                );
+                plan.skipped.push((driver, reason));
+            }
+            Some(present) => {
+                debug!(
+                    "Driver {} {} is older than {}, updating",
+                    driver.name,
+                    present.version,
+                    driver.version.as_deref().unwrap_or_default()
+                );
+                plan.install.push(driver);
+            }
+            None => plan.install.push(driver),
+        }
+    }
+    plan
+}
+
+/// Exit codes of `pnputil /add-driver` which mean the driver is in place: success, no matching
+/// device was updated (`ERROR_NO_MORE_ITEMS`), and reboot required (`ERROR_SUCCESS_REBOOT_REQUIRED`).
+const PNPUTIL_SUCCESS_CODES: [i32; 3] = [0, 259, 3010];
+
+/// Extensions of the archives a driver package can be downloaded as.
+const ARCHIVE_EXTENSIONS: [&str; 4] = [".zip", ".7z", ".tar.gz", ".tgz"];
+
+fn is_archive_url(url: &str) -> bool {
+    let path = url.split(['?', '#']).next().unwrap_or(url).to_lowercase();
+    ARCHIVE_EXTENSIONS.iter().any(|ext| path.ends_with(ext))
+}
+
+/// The file name the package is stored under, `<name>.zip` when the URL points to a web page.
+fn archive_file_name(driver: &DriverInfo) -> String {
+    match Path::new(&driver.url).file_name() {
+        Some(name) if is_archive_url(&driver.url) => name.to_string_lossy().to_string(),
+        _ => format!("{}.zip", driver.name),
+    }
+}
+
+/// Checks the magic bytes of the file, so a saved error or landing page is not mistaken for a package.
+pub fn is_archive(path: &Path) -> bool {
+    let mut magic = [0u8; 6];
+    let read = std::fs::File::open(path)
+        .and_then(|mut file| std::io::Read::read(&mut file, &mut magic))
+        .unwrap_or(0);
+    let magic = &magic[..read];
+    magic.starts_with(b"PK\x03\x04")
+        || magic.starts_with(b"7z\xbc\xaf\x27\x1c")
+        || magic.starts_with(&[0x1f, 0x8b])
+}
+
+/// Finds the link to the driver package on a vendor download page, like the ones of WCH.
+///
+/// # Parameters
+///
+/// * `html` - The content of the page.
+/// * `page_url` - The URL of the page, relative links are resolved against it.
+///
+/// # Returns
+///
+/// * `Option<String>` - The absolute URL of the first linked archive.
+pub fn find_archive_link(html: &str, page_url: &str) -> Option<String> {
+    let base = reqwest::Url::parse(page_url).ok()?;
+    let link = regex::Regex::new(r#"(?i)href\s*=\s*["']([^"']+)["']"#).unwrap();
+    link.captures_iter(html)
+        .map(|c| c[1].replace("&amp;", "&"))
+        .find(|href| is_archive_url(href))
+        .and_then(|href| base.join(&href).ok())
+        .map(|url| url.to_string())
+}
+
+/// Follows redirects and vendor landing pages to the URL the package is really served from.
+async fn resolve_download_url(client: &reqwest::Client, url: &str) -> Result<String, String> {
+    let response = crate::http_client::authorize(client.get(url), url)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("Failed to download {}: {}", url, e))?;
+    let final_url = response.url().to_string();
+    if final_url != url {
+        debug!("{} redirects to {}", url, final_url);
+    }
+    let is_html = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|ct| ct.to_str().ok())
+        .map(|ct| ct.to_lowercase().contains("text/html"))
+        .unwrap_or(false);
+    if !is_html {
+        return Ok(final_url);
+    }
+    let html = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to download {}: {}", url, e))?;
+    let link = find_archive_link(&html, &final_url)
+        .ok_or_else(|| format!("{} is a web page without a link to a driver package", url))?;
+    debug!("Resolved {} to {}", url, link);
+    Ok(link)
+}
+
+/// Searches the directory recursively for a file with the given name, ignoring case.
+fn find_file(dir: &Path, name: &str) -> Option<PathBuf> {
+    for entry in std::fs::read_dir(dir).ok()?.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            if let Some(found) = find_file(&path, name) {
+                return Some(found);
+            }
+        } else if entry
+            .file_name()
+            .to_string_lossy()
+            .eq_ignore_ascii_case(name)
+        {
+            return Some(path);
+        }
+    }
+    None
+}
+
+/// Downloads the package of a driver, reusing an already downloaded copy when it matches the checksum.
+///
+/// # Parameters
+///
+/// * `driver` - The driver whose package is downloaded.
+/// * `download_dir` - The directory the package is downloaded to, it must exist.
+/// * `reporter` - The reporter receiving the download progress, as updates of the `Drivers` phase
+///   with the name of the driver as item.
+///
+/// # Returns
+///
+/// * `Result<(PathBuf, bool), String>` - The path of the package and whether it was already downloaded.
+pub async fn download_driver(
+    driver: &DriverInfo,
+    download_dir: &Path,
+    reporter: &dyn ProgressReporter,
+) -> Result<(PathBuf, bool), String> {
+    let archive = download_dir.join(archive_file_name(driver));
+    let verify = |archive: &Path| match &driver.sha256 {
+        Some(sha256) => crate::verify_file_checksum(sha256, archive.to_str().unwrap())
+            .map_err(|e| format!("Failed to verify {}: {}", archive.display(), e)),
+        None => Ok(is_archive(archive)),
+    };
+    if verify(&archive)? {
+        debug!("Using already downloaded {}", archive.display());
+        return Ok((archive, true));
+    }
+
+    progress::report(
+        reporter,
+        &ProgressUpdate::new(InstallPhase::Drivers, &driver.name)
+            .message(&message(MessageId::Downloading, &[&driver.name])),
+    );
+    let client = crate::http_client::client().map_err(|e| e.to_string())?;
+    let mut errors = vec![];
+    for url in std::iter::once(&driver.url).chain(driver.mirror_url.as_ref()) {
+        match download_package(driver, url, &client, &archive, download_dir, reporter).await {
+            Ok(()) if verify(&archive)? => return Ok((archive, false)),
+            Ok(()) => errors.push(format!("Checksum of {} does not match", url)),
+            Err(e) => errors.push(e),
+        }
+        let _ = std::fs::remove_file(&archive);
+        warn!("{}", errors.last().unwrap());
+    }
+    Err(errors.join("; "))
+}
+
+/// Downloads the package from one location and stores it as `archive`.
+async fn download_package(
+    driver: &DriverInfo,
+    url: &str,
+    client: &reqwest::Client,
+    archive: &Path,
+    download_dir: &Path,
+    reporter: &dyn ProgressReporter,
+) -> Result<(), String> {
+    let url = resolve_download_url(client, url).await?;
+    // download_file publishes the download itself, the reporter gets it as progress of the driver
+    let driver_progress = |update: &ProgressUpdate| {
+        if let Some(bytes @ ProgressDetail::Bytes { .. }) = &update.detail {
+            reporter.report(&ProgressUpdate {
+                phase: InstallPhase::Drivers,
+                item: driver.name.clone(),
+                detail: Some(bytes.clone()),
+                ..update.clone()
+            });
+        }
+    };
+    let result = crate::download_file(&url, download_dir.to_str().unwrap(), driver_progress).await;
+    // download_file names the file after the last segment of the URL
+    let downloaded = download_dir.join(Path::new(&url).file_name().unwrap());
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&downloaded);
+        return Err(message(MessageId::DownloadFailed, &[&url, &e.to_string()]));
+    }
+    if downloaded != archive {
+        std::fs::rename(&downloaded, archive)
+            .map_err(|e| format!("Failed to move {}: {}", downloaded.display(), e))?;
+    }
+    if !is_archive(archive) {
+        return Err(message(MessageId::NotADriverPackage, &[&url]));
+    }
+    Ok(())
+}
+
+/// Checks if the process runs with administrator rights (as root on other systems).
+pub fn is_elevated() -> bool {
+    match std::env::consts::OS {
+        "windows" => command_executor::execute_command(
+            "powershell",
+            &[
+                "-NoProfile",
+                "-NonInteractive",
+                "-Command",
+                "([Security.Principal.WindowsPrincipal][Security.Principal.WindowsIdentity]::GetCurrent()).IsInRole([Security.Principal.WindowsBuiltInRole]::Administrator)",
+            ],
+        )
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().eq_ignore_ascii_case("true"))
+        .unwrap_or(false),
+        _ => crate::system_dependencies::is_root(),
+    }
+}
+
+/// Extensions of the driver files which must carry an Authenticode signature.
+const SIGNED_DRIVER_EXTENSIONS: [&str; 3] = ["sys", "cat", "dll"];
+
+/// Collects the files of an extracted driver package which must be signed.
+fn collect_signed_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_signed_files(&path, files);
+        } else if path
+            .extension()
+            .map(|ext| {
+                SIGNED_DRIVER_EXTENSIONS
+                    .iter()
+                    .any(|e| ext.eq_ignore_ascii_case(e))
+            })
+            .unwrap_or(false)
+        {
+            files.push(path);
+        }
+    }
+}
+
+/// Returns the common name (`CN`) of a certificate subject like `CN=Name, O=Organization, C=US`.
+/// Values may be quoted, as when they contain a comma.
+fn subject_common_name(subject: &str) -> Option<String> {
+    let mut attributes = vec![];
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in subject.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => attributes.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    attributes.push(current);
+    attributes.iter().find_map(|attribute| {
+        let (name, value) = attribute.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case("CN")
+            .then(|| value.trim().to_string())
+    })
+}
+
+/// Checks if a certificate is the expected signer, given by its common name or its thumbprint.
+/// The common name has to match exactly, other attributes of the subject are not considered.
+fn is_expected_signer(subject: &str, thumbprint: &str, signer: &str) -> bool {
+    let signer = signer.trim();
+    if signer.is_empty() {
+        return false;
+    }
+    let expected_name = match signer.split_once('=') {
+        Some((name, value)) if name.trim().eq_ignore_ascii_case("CN") => value.trim(),
+        _ => signer,
+    };
+    thumbprint.eq_ignore_ascii_case(signer)
+        || subject_common_name(subject).is_some_and(|name| name.eq_ignore_ascii_case(expected_name))
+}
+
+/// Parses the `<status>|<subject>|<thumbprint>|<path>` lines printed for every file by
+/// `Get-AuthenticodeSignature`.
+///
+/// The catalogs, which Windows checks the driver files against, have to be signed by `signer`.
+///
+/// # Returns
+///
+/// * `Vec<(String, String)>` - The path and status of every file whose status is not `Valid`,
+///   and of every catalog signed by another certificate than `signer`.
+pub fn parse_authenticode_status(output: &str, signer: &str) -> Vec<(String, String)> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.trim().splitn(4, '|');
+            Some((
+                fields.next()?,
+                fields.next()?,
+                fields.next()?,
+                fields.next()?,
+            ))
+        })
+        .filter_map(|(status, subject, thumbprint, path)| {
+            let is_catalog = path.to_lowercase().ends_with(".cat");
+            if status != "Valid" {
+                Some((path.to_string(), status.to_string()))
+            } else if is_catalog && !is_expected_signer(subject, thumbprint, signer) {
+                Some((path.to_string(), format!("signed by {}", subject)))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Verifies the Authenticode signatures of the driver binaries and catalogs in an extracted package.
+///
+/// # Parameters
+///
+/// * `dir` - The directory the driver package was extracted to.
+/// * `signer` - The common name or the thumbprint of the certificate the catalogs have to be signed with.
+///
+/// # Returns
+///
+/// * `Result<(), String>` - An error listing the files which are not validly signed, or the
+///   catalogs signed by another certificate, or if the package contains no catalog at all.
+pub fn verify_driver_signatures(dir: &Path, signer: &str) -> Result<(), String> {
+    let mut files = vec![];
+    collect_signed_files(dir, &mut files);
+    if !files.iter().any(|f| {
+        f.extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("cat"))
+    }) {
+        return Err(message(
+            MessageId::NoSignedCatalog,
+            &[&dir.display().to_string()],
+        ));
+    }
+    let paths = files
+        .iter()
+        .map(|f| command_executor::quote_powershell(&f.to_string_lossy()))
+        .collect::<Vec<_>>()
+        .join(",");
+    let script = format!(
+        "Get-AuthenticodeSignature -LiteralPath {} | ForEach-Object {{ \"$($_.Status)|$($_.SignerCertificate.Subject)|$($_.SignerCertificate.Thumbprint)|$($_.Path)\" }}",
+        paths
+    );
+    let output = command_executor::execute_command(
+        "powershell",
+        &["-NoProfile", "-NonInteractive", "-Command", &script],
+    )
+    .map_err(|e| format!("Failed to verify driver signatures: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Failed to verify driver signatures: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if stdout.lines().filter(|l| l.contains('|')).count() < files.len() {
+        return Err("Not all driver files were checked for a signature".to_string());
+    }
+    let invalid = parse_authenticode_status(&stdout, signer);
+    if invalid.is_empty() {
+        Ok(())
+    } else {
+        Err(invalid
+            .iter()
+            .map(|(path, status)| format!("{} ({})", path, status))
+            .collect::<Vec<_>>()
+            .join(", "))
+    }
+}
+
+/// Extracts a downloaded driver package into a fresh folder named after the driver.
+///
+/// # Returns
+///
+/// * `Result<PathBuf, String>` - The path of the INF file of the driver inside the extracted package.
+pub fn extract_driver_package(
+    driver: &DriverInfo,
+    archive: &Path,
+    download_dir: &Path,
+) -> Result<PathBuf, String> {
+    let extract_dir = download_dir.join(&driver.name);
+    if extract_dir.exists() {
+        std::fs::remove_dir_all(&extract_dir)
+            .map_err(|e| format!("Failed to remove {}: {}", extract_dir.display(), e))?;
+    }
+    crate::decompress_archive(archive.to_str().unwrap(), extract_dir.to_str().unwrap())
+        .map_err(|e| format!("Failed to extract {}: {}", archive.display(), e))?;
+    let inf_path = Path::new(&driver.inf_name);
+    if inf_path.components().count() > 1 {
+        let inf = extract_dir.join(inf_path);
+        return if inf.is_file() {
+            Ok(inf)
+        } else {
+            Err(format!(
+                "{} not found in {}",
+                driver.inf_name,
+                archive.display()
+            ))
+        };
+    }
+    find_file(&extract_dir, &driver.inf_name)
+        .ok_or_else(|| format!("{} not found in {}", driver.inf_name, archive.display()))
+}
+
+/// Extracts the driver package and adds the driver to the Windows driver store.
+fn install_driver(
+    driver: &DriverInfo,
+    archive: &Path,
+    download_dir: &Path,
+    elevated: bool,
+    options: &DriverInstallOptions,
+) -> Result<(), DriverError> {
+    let inf = extract_driver_package(driver, archive, download_dir)
+        .map_err(DriverError::InstallFailed)?;
+    let extract_dir = download_dir.join(&driver.name);
+    let signer = driver.signer.as_deref().unwrap_or(WHQL_SIGNER);
+    if let Err(e) = verify_driver_signatures(&extract_dir, signer) {
+        if !options.allow_unsigned {
+            return Err(DriverError::SignatureInvalid(e));
+        }
+        warn!(
+            "Installing {} despite invalid signatures: {}",
+            driver.name, e
+        );
+    }
+    let args = ["/add-driver", inf.to_str().unwrap(), "/install"];
+    let output = if elevated {
+        command_executor::execute_command("pnputil", &args)
+    } else if options.silent {
+        return Err(DriverError::ElevationDenied(
+            "not running as administrator and prompting is disabled".to_string(),
+        ));
+    } else {
+        command_executor::execute_command_elevated(
+            "pnputil",
+            &args,
+            &command_executor::ExecOptions::new(),
+        )
+    };
+    let output = output.map_err(|e| match e.kind() {
+        std::io::ErrorKind::PermissionDenied => DriverError::ElevationDenied(e.to_string()),
+        _ => DriverError::InstallFailed(format!("Failed to run pnputil: {}", e)),
+    })?;
+    match output.status.code() {
+        Some(code) if PNPUTIL_SUCCESS_CODES.contains(&code) => Ok(()),
+        code => Err(DriverError::InstallFailed(format!(
+            "pnputil failed with exit code {:?}: {}",
+            code,
+            String::from_utf8_lossy(&output.stdout).trim()
+        ))),
+    }
+}
+
+/// Records the download of a driver package in dry-run mode, without resolving its URL.
+fn plan_driver_download(driver: &DriverInfo, download_dir: &Path) {
+    crate::dry_run::plan(
+        crate::dry_run::ActionKind::Download,
+        &format!(
+            "Download the {} driver from {} to {}",
+            driver.name,
+            driver.url,
+            download_dir.display()
+        ),
+        None,
+    );
+}
+
+/// Downloads and installs the drivers, reporting the progress of every driver as updates of the
+/// `Drivers` phase.
+///
+/// A failure of one driver does not stop the others, every driver gets its own result in the report.
+/// Downloaded packages are kept in `download_dir` and reused by the next run.
+/// Without administrator rights, every driver asks for elevation through UAC, unless
+/// `options.silent` is set. Once the user declines, the remaining drivers are not prompted for.
+/// Packages are verified against their checksum when it is known, and refused when their files
+/// are not validly signed or their catalog is not signed by the `signer` of the driver, unless
+/// `options.allow_unsigned` is set.
+///
+/// In dry-run mode the downloads and installations are only planned and every driver is reported
+/// as skipped, nothing is downloaded or written.
+///
+/// # Parameters
+///
+/// * `drivers` - The drivers to install, usually the `install` part of a `DriverPlan`.
+/// * `download_dir` - The directory the packages are downloaded and extracted to.
+/// * `options` - How the drivers are installed.
+/// * `reporter` - The reporter receiving the progress, the item of the updates is the driver name.
+///
+/// # Returns
+///
+/// * `Result<DriverReport, DriverError>` - The outcome for every driver, in the order of `drivers`.
+///   When drivers failed and none of them was installed, the error of the first failed driver.
+pub async fn install_drivers(
+    drivers: &[DriverInfo],
+    download_dir: &Path,
+    options: &DriverInstallOptions,
+    reporter: impl ProgressReporter,
+) -> Result<DriverReport, DriverError> {
+    if crate::dry_run::is_dry_run() {
+        let results = drivers
+            .iter()
+            .map(|driver| {
+                plan_driver_download(driver, download_dir);
+                crate::dry_run::plan(
+                    crate::dry_run::ActionKind::InstallDriver,
+                    &format!("Install the {} driver", driver.name),
+                    None,
+                );
+                DriverResult {
+                    name: driver.name.clone(),
+                    cached: false,
+                    status: DriverStatus::Skipped("Dry run".to_string()),
+                }
+            })
+            .collect();
+        return Ok(DriverReport { results });
+    }
+    let mut results = vec![];
+    let elevated = is_elevated();
+    let mut elevation_denied: Option<String> = None;
+    std::fs::create_dir_all(download_dir).map_err(|e| {
+        DriverError::DownloadFailed(format!(
+            "Failed to create {}: {}",
+            download_dir.display(),
+            e
+        ))
+    })?;
+    for driver in drivers {
+        let update = || ProgressUpdate::new(InstallPhase::Drivers, &driver.name);
+        progress::report(&reporter, &update().detail(ProgressDetail::Started));
+        let (archive, cached) = match download_driver(driver, download_dir, &reporter).await {
+            Ok(fetched) => fetched,
+            Err(e) => {
+                warn!("{}", e);
+                progress::report(
+                    &reporter,
+                    &update().detail(ProgressDetail::Failed(e.clone())),
+                );
+                results.push(DriverResult {
+                    name: driver.name.clone(),
+                    cached: false,
+                    status: DriverStatus::DownloadFailed(e),
+                });
+                continue;
+            }
+        };
+        progress::report(
+            &reporter,
+            &update().message(&message(MessageId::Installing, &[&driver.name])),
+        );
+        let result = match &elevation_denied {
+            Some(reason) => Err(DriverError::ElevationDenied(reason.clone())),
+            None => install_driver(driver, &archive, download_dir, elevated, options),
+        };
+        let status = match result {
+            Ok(()) => {
+                progress::report(&reporter, &update().detail(ProgressDetail::Finished));
+                DriverStatus::Installed
+            }
+            Err(e) => {
+                warn!("Failed to install driver {}: {}", driver.name, e);
+                progress::report(
+                    &reporter,
+                    &update().detail(ProgressDetail::Failed(e.to_string())),
+                );
+                match e {
+                    DriverError::DownloadFailed(reason) => DriverStatus::DownloadFailed(reason),
+                    DriverError::ElevationDenied(reason) => {
+                        elevation_denied = Some(reason.clone());
+                        DriverStatus::ElevationDenied(reason)
+                    }
+                    DriverError::InstallFailed(reason) => DriverStatus::InstallFailed(reason),
+                    DriverError::SignatureInvalid(reason) => DriverStatus::SignatureInvalid(reason),
+                }
+            }
+        };
+        results.push(DriverResult {
+            name: driver.name.clone(),
+            cached,
+            status,
+        });
+    }
+    DriverReport { results }.into_result()
+}
+
+/// Downloads the driver packages without installing them, e.g. to prepare an offline installation.
+///
+/// # Parameters
+///
+/// * `drivers` - The drivers whose packages are downloaded.
+/// * `download_dir` - The directory the packages are downloaded to, created if missing.
+/// * `reporter` - The reporter receiving the progress, the item of the updates is the driver name.
+///
+/// # Returns
+///
+/// * `Result<Vec<(String, Result<PathBuf, DriverError>)>, DriverError>` - The driver name and the path
+///   of its package, in the order of `drivers`. An error if the download directory cannot be created.
+///   In dry-run mode the downloads are only planned and the paths do not exist.
+pub async fn download_drivers(
+    drivers: &[DriverInfo],
+    download_dir: &Path,
+    reporter: impl ProgressReporter,
+) -> Result<Vec<(String, Result<PathBuf, DriverError>)>, DriverError> {
+    if crate::dry_run::is_dry_run() {
+        return Ok(drivers
+            .iter()
+            .map(|driver| {
+                plan_driver_download(driver, download_dir);
+                (
+                    driver.name.clone(),
+                    Ok(download_dir.join(archive_file_name(driver))),
+                )
+            })
+            .collect());
+    }
+    let mut results = vec![];
+    std::fs::create_dir_all(download_dir).map_err(|e| {
+        DriverError::DownloadFailed(format!(
+            "Failed to create {}: {}",
+            download_dir.display(),
+            e
+        ))
+    })?;
+    for driver in drivers {
+        let update = || ProgressUpdate::new(InstallPhase::Drivers, &driver.name);
+        progress::report(&reporter, &update().detail(ProgressDetail::Started));
+        let result = download_driver(driver, download_dir, &reporter)
+            .await
+            .map(|(archive, _)| archive)
+            .map_err(DriverError::DownloadFailed);
+        match &result {
+            Ok(_) => progress::report(&reporter, &update().detail(ProgressDetail::Finished)),
+            Err(e) => {
+                warn!("{}", e);
+                progress::report(
+                    &reporter,
+                    &update().detail(ProgressDetail::Failed(e.to_string())),
+                );
+            }
+        }
+        results.push((driver.name.clone(), result));
+    }
+    Ok(results)
+}
+
+/// Turns the skipped drivers of a plan into results, so they can be reported together with the installed ones.
+pub fn skipped_results(plan: &DriverPlan, reporter: impl ProgressReporter) -> Vec<DriverResult> {
+    plan.skipped
+        .iter()
+        .map(|(driver, reason)| {
+            progress::report(
+                &reporter,
+                &ProgressUpdate::new(InstallPhase::Drivers, &driver.name)
+                    .message(reason)
+                    .detail(ProgressDetail::Finished),
+            );
+            DriverResult {
+                name: driver.name.clone(),
+                cached: false,
+                status: DriverStatus::Skipped(reason.clone()),
+            }
+        })
+        .collect()
+}
+
+/// Where the udev rules for Espressif devices are installed on Linux.
+pub const UDEV_RULES_PATH: &str = "/etc/udev/rules.d/60-espressif.rules";
+
+/// Where the udev rules shipped with OpenOCD are installed on Linux.
+pub const OPENOCD_UDEV_RULES_PATH: &str = "/etc/udev/rules.d/60-openocd.rules";
+
+/// USB vendor and product ids of the ESP built-in USB-JTAG/serial and of the common USB-serial bridges.
+pub const ESP_USB_DEVICES: &[(&str, &str, &str)] = &[
+    ("303a", "1001", "Espressif USB-JTAG/serial debug unit"),
+    ("303a", "1002", "Espressif USB bridge"),
+    ("10c4", "ea60", "Silicon Labs CP210x USB to UART bridge"),
+    ("0403", "6001", "FTDI FT232 USB to UART bridge"),
+    (
+        "0403",
+        "6010",
+        "FTDI FT2232 USB to JTAG/UART bridge (ESP-Prog)",
+    ),
+    ("0403", "6014", "FTDI FT232H USB to JTAG bridge"),
+    ("1a86", "7523", "WCH CH340 USB to UART bridge"),
+    ("1a86", "55d4", "WCH CH9102 USB to UART bridge"),
+];
+
+/// What `setup_serial_permissions` should do.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SerialSetupOptions {
+    /// Only return the commands which would be executed.
+    pub dry_run: bool,
+    /// Add the current user to the group owning the serial devices.
+    pub add_to_serial_group: bool,
+    /// The `60-openocd.rules` file shipped with the installed OpenOCD, installed together with the Espressif rules.
+    pub openocd_rules: Option<PathBuf>,
+}
+
+/// Result of setting up the serial permissions.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UdevSetupResult {
+    /// The commands which were executed, or would be executed in dry-run mode.
+    pub commands: Vec<String>,
+    /// The group owning the serial devices (`dialout` or `uucp`).
+    pub serial_group: String,
+    /// Whether the current user is a member of the serial group.
+    pub in_serial_group: bool,
+    /// Whether the user was added to the serial group, which takes effect after logging in again.
+    pub needs_relogin: bool,
+}
+
+/// Generates the content of the udev rules file for all `ESP_USB_DEVICES`.
+///
+/// # Parameters
+///
+/// * `group` - The group given access to the devices, the serial group of `get_serial_group`.
+pub fn get_udev_rules(group: &str) -> String {
+    let mut rules = String::from("# Espressif development boards, generated by eim\n");
+    for (vendor, product, description) in ESP_USB_DEVICES {
+        rules.push_str(&format!(
+            "# {}\nATTRS{{idVendor}}==\"{}\", ATTRS{{idProduct}}==\"{}\", MODE=\"660\", GROUP=\"{}\", TAG+=\"uaccess\"\n",
+            description, vendor, product, group
+        ));
+    }
+    rules
+}
+
+/// Returns the group owning the serial devices, `dialout` on most distributions and `uucp` on Arch.
+pub fn get_serial_group() -> &'static str {
+    match command_executor::execute_command("getent", &["group", "dialout"]) {
+        Ok(o) if o.status.success() => "dialout",
+        _ => "uucp",
+    }
+}
+
+/// Checks if the current user is a member of the given group.
+pub fn is_user_in_group(group: &str) -> bool {
+    match command_executor::execute_command("id", &["-nG"]) {
+        Ok(o) => String::from_utf8_lossy(&o.stdout)
+            .split_whitespace()
+            .any(|g| g == group),
+        Err(_) => false,
+    }
+}
+
+fn current_user() -> Option<String> {
+    std::env::var("USER")
+        .ok()
+        .filter(|u| !u.is_empty())
+        .or_else(|| {
+            command_executor::execute_command("id", &["-un"])
+                .ok()
+                .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+        })
+}
+
+/// Gives the current user access to the ESP serial and JTAG devices on Linux, so flashing works without sudo.
+///
+/// The Espressif udev rules (and optionally the OpenOCD ones) are installed to `/etc/udev/rules.d`
+/// and udev is reloaded. Optionally the user is added to the serial group, which takes effect
+/// after logging in again. The commands are run with elevated privileges.
+///
+/// # Parameters
+///
+/// * `options` - What should be set up, see `SerialSetupOptions`.
+///
+/// # Returns
+///
+/// * `Ok(UdevSetupResult)` - The executed (or planned) commands and the state of the group membership.
+/// * `Err(String)` - If not running on Linux or any of the commands failed.
+///
+/// In the dry-run mode of `dry_run::set_dry_run`, the commands are planned as with `options.dry_run`.
+pub fn setup_serial_permissions(options: &SerialSetupOptions) -> Result<UdevSetupResult, String> {
+    if std::env::consts::OS != "linux" {
+        return Err(message(MessageId::UnsupportedOs, &[std::env::consts::OS]));
+    }
+    let dry_run = options.dry_run || crate::dry_run::is_dry_run();
+    // the rules are staged in files only the user can write, with unpredictable names, as the
+    // elevated commands must not follow a link planted by another user
+    let mut staged_rules = vec![];
+    let mut commands: Vec<Vec<String>> = vec![];
+    let mut stage = |content: Option<String>, destination: &str| -> Result<(), String> {
+        let mut file = tempfile::Builder::new()
+            .prefix("eim-udev-")
+            .suffix(".rules")
+            .tempfile()
+            .map_err(|e| format!("Failed to create a temporary file: {}", e))?;
+        if let Some(content) = content {
+            std::io::Write::write_all(&mut file, content.as_bytes()).map_err(|e| e.to_string())?;
+        }
+        commands.push(vec![
+            "install".to_string(),
+            "-m".to_string(),
+            "644".to_string(),
+            file.path().to_string_lossy().to_string(),
+            destination.to_string(),
+        ]);
+        staged_rules.push(file);
+        Ok(())
+    };
+    // the devices belong to the group the user is added to
+    let serial_group = get_serial_group();
+    let write = !dry_run;
+    stage(write.then(|| get_udev_rules(serial_group)), UDEV_RULES_PATH)?;
+    if let Some(openocd_rules) = &options.openocd_rules {
+        // read as the user, so only rules the user can read are installed
+        let content = if write {
+            Some(
+                std::fs::read_to_string(openocd_rules)
+                    .map_err(|e| format!("Failed to read {}: {}", openocd_rules.display(), e))?,
+            )
+        } else {
+            None
+        };
+        stage(content, OPENOCD_UDEV_RULES_PATH)?;
+    }
+    commands.push(vec![
+        "udevadm".to_string(),
+        "control".to_string(),
+        "--reload-rules".to_string(),
+    ]);
+    commands.push(vec!["udevadm".to_string(), "trigger".to_string()]);
+
+    let mut in_serial_group = is_user_in_group(serial_group);
+    let add_user = options.add_to_serial_group && !in_serial_group;
+    if add_user {
+        let user = current_user().ok_or("Could not determine the current user")?;
+        commands.push(vec![
+            "usermod".to_string(),
+            "-aG".to_string(),
+            serial_group.to_string(),
+            user,
+        ]);
+    }
+
+    if dry_run {
+        for command in &commands {
+            crate::dry_run::plan(
+                crate::dry_run::ActionKind::RunCommand,
+                &command.join(" "),
+                None,
+            );
+        }
+    } else {
+        for command in &commands {
+            let args: Vec<&str> = command[1..].iter().map(|a| a.as_str()).collect();
+            match command_executor::execute_command_elevated(
+                &command[0],
+                &args,
+                &command_executor::ExecOptions::new(),
+            ) {
+                Ok(o) if o.status.success() => debug!("{} succeeded", command.join(" ")),
+                Ok(o) => {
+                    return Err(format!(
+                        "{} failed: {}",
+                        command.join(" "),
+                        String::from_utf8_lossy(&o.stderr)
+                    ))
+                }
+                Err(e) => return Err(format!("{} failed: {}", command.join(" "), e)),
+            }
+        }
+        in_serial_group = in_serial_group || add_user;
+    }
+
+    if !in_serial_group && !options.add_to_serial_group {
+        warn!(
+            "The current user is not a member of the {} group, add it with: sudo usermod -aG {} $USER",
+            serial_group, serial_group
+        );
+    }
+    let sudo = if crate::system_dependencies::is_root() {
+        ""
+    } else {
+        "sudo "
+    };
+    Ok(UdevSetupResult {
+        commands: commands
+            .iter()
+            .map(|c| format!("{}{}", sudo, c.join(" ")))
+            .collect(),
+        serial_group: serial_group.to_string(),
+        in_serial_group,
+        needs_relogin: add_user,
+    })
+}
+
+/// Installs the Espressif udev rules, see `setup_serial_permissions`.
+///
+/// # Parameters
+///
+/// * `dry_run` - If `true`, nothing is executed and only the planned commands are returned.
+pub fn setup_udev_rules(dry_run: bool) -> Result<UdevSetupResult, String> {
+    setup_serial_permissions(&SerialSetupOptions {
+        dry_run,
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::{Digest, Sha256};
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::Mutex;
+
+    /// Serves `body` to the given number of requests on a local port and returns the URL of `file_name`.
+    fn serve(
+        body: Vec<u8>,
+        content_type: &'static str,
+        requests: usize,
+        file_name: &str,
+    ) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!(
+            "http://{}/drivers/{}",
+            listener.local_addr().unwrap(),
+            file_name
+        );
+        std::thread::spawn(move || {
+            for stream in listener.incoming().take(requests) {
+                let mut stream = stream.unwrap();
+                let mut request = [0; 4096];
+                let _ = stream.read(&mut request);
+                let header = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    content_type,
+                    body.len()
+                );
+                let _ = stream.write_all(header.as_bytes());
+                let _ = stream.write_all(&body);
+            }
+        });
+        url
+    }
+
+    const ZIP: &str = "application/zip";
+    const HTML: &str = "text/html; charset=utf-8";
+
+    fn zip_with_inf(inf_name: &str) -> Vec<u8> {
+        let mut buffer = std::io::Cursor::new(vec![]);
+        let mut zip = zip::ZipWriter::new(&mut buffer);
+        zip.start_file(
+            format!("x64/{}", inf_name),
+            zip::write::SimpleFileOptions::default(),
+        )
+        .unwrap();
+        zip.write_all(b"[Version]\r\nSignature=\"$Windows NT$\"\r\n")
+            .unwrap();
+        zip.finish().unwrap();
+        buffer.into_inner()
+    }
+
+    fn test_driver(url: String, body: &[u8]) -> DriverInfo {
+        let mut driver = driver("esp-test", "Test", "esp.inf", &[("303a", "1001")], &url);
+        driver.mirror_url = None;
+        driver.sha256 = Some(format!("{:x}", Sha256::digest(body)));
+        driver
+    }
+
+    #[tokio::test]
+    async fn test_download_and_extract_driver() {
+        let dir = tempfile::tempdir().unwrap();
+        let body = zip_with_inf("esp.inf");
+        let driver = test_driver(serve(body.clone(), ZIP, 2, "esp.zip"), &body);
+        let updates = Mutex::new(vec![]);
+        let reporter = |update: &ProgressUpdate| updates.lock().unwrap().push(update.clone());
+
+        let (archive, cached) = download_driver(&driver, dir.path(), &reporter)
+            .await
+            .unwrap();
+        assert!(!cached);
+        assert_eq!(archive, dir.path().join("esp.zip"));
+        assert!(updates
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|update| update.item == "esp-test"
+                && matches!(update.detail, Some(ProgressDetail::Bytes { .. }))));
+
+        // the server is gone, the second download has to reuse the package
+        let (_, cached) = download_driver(&driver, dir.path(), &()).await.unwrap();
+        assert!(cached);
+
+        let inf = extract_driver_package(&driver, &archive, dir.path()).unwrap();
+        assert_eq!(inf, dir.path().join("esp-test").join("x64").join("esp.inf"));
+    }
+
+    #[tokio::test]
+    async fn test_download_driver_checksum_mismatch() {
+        let dir = tempfile::tempdir().unwrap();
+        let body = zip_with_inf("esp.inf");
+        let driver = test_driver(serve(body, ZIP, 2, "esp.zip"), b"other");
+        let result = download_driver(&driver, dir.path(), &()).await;
+        assert!(result.unwrap_err().contains("does not match"));
+    }
+
+    #[tokio::test]
+    async fn test_download_driver_from_landing_page() {
+        let dir = tempfile::tempdir().unwrap();
+        let body = zip_with_inf("ch343ser.inf");
+        let package = serve(body.clone(), ZIP, 2, "CH343SER.ZIP");
+        let page = format!(
+            r#"<html><a href="/downloads/CH343SER_EXE.html">EXE</a><a class="btn" href="{}">Download</a></html>"#,
+            package
+        );
+        let driver = test_driver(
+            serve(page.into_bytes(), HTML, 1, "CH343SER_ZIP.html"),
+            &body,
+        );
+
+        let (archive, _) = download_driver(&driver, dir.path(), &()).await.unwrap();
+        assert_eq!(archive, dir.path().join("esp-test.zip"));
+        assert!(is_archive(&archive));
+    }
+
+    #[tokio::test]
+    async fn test_download_driver_falls_back_to_mirror() {
+        let dir = tempfile::tempdir().unwrap();
+        let body = zip_with_inf("esp.inf");
+        let landing = serve(b"<html>Not here</html>".to_vec(), HTML, 1, "esp.zip");
+        let mut driver = test_driver(landing, &body);
+        driver.mirror_url = Some(serve(body.clone(), ZIP, 2, "esp.zip"));
+
+        let (archive, cached) = download_driver(&driver, dir.path(), &()).await.unwrap();
+        assert!(!cached);
+        assert!(is_archive(&archive));
+    }
+
+    #[test]
+    fn test_find_archive_link() {
+        let html = r#"<a href="/products/CH343.html">CH343</a>
+            <a href='/downloads/file/CH343SER.ZIP?t=1&amp;s=2'>CH343SER.ZIP</a>"#;
+        assert_eq!(
+            find_archive_link(html, "https://www.wch.cn/downloads/CH343SER_ZIP.html"),
+            Some("https://www.wch.cn/downloads/file/CH343SER.ZIP?t=1&s=2".to_string())
+        );
+        assert_eq!(
+            find_archive_link("<html></html>", "https://www.wch.cn/"),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_download_drivers_reports_failures() {
+        let dir = tempfile::tempdir().unwrap();
+        let body = zip_with_inf("esp.inf");
+        let good = test_driver(serve(body.clone(), ZIP, 2, "esp.zip"), &body);
+        let mut missing = test_driver("http://127.0.0.1:1/drivers/missing.zip".to_string(), &body);
+        missing.name = "missing".to_string();
+        let updates = Mutex::new(vec![]);
+        let reporter = |update: &ProgressUpdate| updates.lock().unwrap().push(update.clone());
+
+        let results = download_drivers(&[good, missing], dir.path(), &reporter)
+            .await
+            .unwrap();
+        assert!(results[0].1.is_ok());
+        assert!(results[1].1.is_err());
+        let updates = updates.into_inner().unwrap();
+        assert!(updates
+            .iter()
+            .any(|update| update.item == "esp-test"
+                && update.detail == Some(ProgressDetail::Finished)));
+        assert!(matches!(
+            updates.last().and_then(|update| update.detail.as_ref()),
+            Some(ProgressDetail::Failed(_))
+        ));
+    }
+
+    const PNPUTIL_OUTPUT: &str = "Microsoft PnP Utility\r\n\r\nPublished Name:     oem3.inf\r\nOriginal Name:      silabser.inf\r\nProvider Name:      Silicon Labs\r\nClass Name:         Ports (COM & LPT)\r\nClass GUID:         {4d36e978-e325-11ce-bfc1-08002be10318}\r\nDriver Version:     10/17/2023 11.3.0.198\r\nSigner Name:        Microsoft Windows Hardware Compatibility Publisher\r\n\r\nPublished Name:     oem7.inf\r\nOriginal Name:      nvhda.inf\r\nProvider Name:      NVIDIA Corporation\r\nDriver Version:     03/18/2022 1.3.39.14\r\n";
+
+    #[test]
+    fn test_parse_pnputil_drivers() {
+        let drivers = parse_pnputil_drivers(PNPUTIL_OUTPUT);
+        assert_eq!(drivers.len(), 2);
+        assert_eq!(drivers[0].published_name, "oem3.inf");
+        assert_eq!(drivers[0].original_name, "silabser.inf");
+        assert_eq!(drivers[0].provider, "Silicon Labs");
+        assert_eq!(drivers[0].version, "11.3.0.198");
+    }
+
+    #[test]
+    fn test_plan_driver_installation() {
+        let installed = parse_pnputil_drivers(PNPUTIL_OUTPUT);
+        let needed = vec![
+            ("10C4".to_string(), "EA60".to_string()),
+            ("1a86".to_string(), "7523".to_string()),
+        ];
+        let plan = plan_driver_installation(&get_known_drivers(), &needed, &installed);
+        let install: Vec<&str> = plan.install.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(install, vec!["ch34x"]);
+        assert!(plan
+            .skipped
+            .iter()
+            .any(|(d, reason)| d.name == "cp210x" && reason.starts_with("already installed")));
+        assert_eq!(plan.skipped.len(), 4);
+
+        let plan = plan_driver_installation(&get_known_drivers(), &[], &[]);
+        assert_eq!(plan.install.len(), get_known_drivers().len());
+    }
+
+    #[test]
+    fn test_plan_driver_versions() {
+        let installed = parse_pnputil_drivers(PNPUTIL_OUTPUT);
+        let needed = vec![("10c4".to_string(), "ea60".to_string())];
+        let mut drivers = get_known_drivers();
+        let cp210x = drivers.iter_mut().find(|d| d.name == "cp210x").unwrap();
+
+        cp210x.version = Some("11.3.0.198".to_string());
+        let plan = plan_driver_installation(&drivers, &needed, &installed);
+        assert!(plan.install.is_empty());
+
+        let cp210x = drivers.iter_mut().find(|d| d.name == "cp210x").unwrap();
+        cp210x.version = Some("11.4.0.393".to_string());
+        let plan = plan_driver_installation(&drivers, &needed, &installed);
+        assert_eq!(plan.install.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_usb_instance_ids() {
+        let output = "USB\\VID_10C4&PID_EA60\\0001\r\nUSB\\VID_303A&PID_1001&MI_00\\6&1234\r\nUSB\\ROOT_HUB30\\4&abc\r\nUSB\\VID_10C4&PID_EA60\\0002\r\n";
+        assert_eq!(
+            parse_usb_instance_ids(output),
+            vec![
+                ("10c4".to_string(), "ea60".to_string()),
+                ("303a".to_string(), "1001".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_ioreg_usb() {
+        let output = "+-o Root  <class IORegistryEntry>\n  +-o CP2102N USB to UART Bridge Controller@01100000\n    |   \"idProduct\" = 60000\n    |   \"idVendor\" = 4292\n  +-o USB Hub@01200000\n    |   \"bDeviceClass\" = 9\n";
+        assert_eq!(
+            parse_ioreg_usb(output),
+            vec![("10c4".to_string(), "ea60".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_compare_driver_versions() {
+        use std::cmp::Ordering;
+        assert_eq!(
+            compare_driver_versions("11.3.0.198", "11.3.0.198"),
+            Ordering::Equal
+        );
+        assert_eq!(
+            compare_driver_versions("2.12.28", "2.12.36.4"),
+            Ordering::Less
+        );
+        assert_eq!(compare_driver_versions("10.1", "9.9.9"), Ordering::Greater);
+        assert_eq!(compare_driver_versions("3.8", "3.8.0.0"), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_verify_manifest_signature() {
+        // test vector 1 of RFC 8032: the empty message
+        let public_key = "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a";
+        let signature = "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b";
+        assert!(verify_manifest_signature(b"", signature, public_key).is_ok());
+        assert!(verify_manifest_signature(b"tampered", signature, public_key).is_err());
+        assert!(verify_manifest_signature(b"", "abcd", public_key).is_err());
+    }
+
+    #[test]
+    fn test_cached_manifest_is_verified() {
+        // test vector 2 of RFC 8032, a single byte message
+        set_drivers_manifest_public_key(Some(
+            "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c".to_string(),
+        ));
+        let dir = tempfile::tempdir().unwrap();
+        let cache_path = dir.path().join("drivers.json");
+        std::fs::write(&cache_path, [0x72]).unwrap();
+        assert!(read_cached_manifest(&cache_path).is_err());
+        std::fs::write(
+            signature_path(&cache_path),
+            "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00",
+        )
+        .unwrap();
+        // the signature is valid, but the content is no manifest
+        assert!(read_cached_manifest(&cache_path)
+            .unwrap_err()
+            .starts_with("Malformed"));
+        std::fs::write(&cache_path, [0x73]).unwrap();
+        assert!(read_cached_manifest(&cache_path)
+            .unwrap_err()
+            .contains("not valid"));
+        set_drivers_manifest_public_key(None);
+    }
+
+    #[test]
+    fn test_parse_drivers_manifest() {
+        let manifest: DriversManifest = serde_json::from_str(
+            r#"{"drivers": [{"name": "cp210x", "description": "CP210x", "inf_name": "silabser.inf",
+                "devices": [["10c4", "ea60"]], "url": "https://example.com/cp210x.zip"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            manifest.drivers[0].devices,
+            vec![("10c4".to_string(), "ea60".to_string())]
+        );
+        assert_eq!(manifest.drivers[0].sha256, None);
+    }
+
+    #[test]
+    fn test_merge_extra_drivers() {
+        let mut custom = driver(
+            "cp210x",
+            "Vendor CP210x",
+            "x64/vendor.inf",
+            &[("10c4", "ea60")],
+            "https://example.com/vendor.zip",
+        );
+        custom.sha256 = Some("abc".to_string());
+        let bridge = driver(
+            "bridge",
+            "Vendor bridge",
+            "bridge.inf",
+            &[("1234", "5678")],
+            "https://example.com/bridge.zip",
+        );
+        let known = get_known_drivers();
+        let merged = merge_extra_drivers(known.clone(), &[custom.clone(), bridge.clone()]);
+        assert_eq!(merged.len(), known.len() + 1);
+        assert_eq!(merged.iter().filter(|d| d.name == "cp210x").count(), 1);
+        assert!(merged.contains(&custom));
+        assert!(merged.contains(&bridge));
+    }
+
+    #[test]
+    fn test_driver_report() {
+        let result = |name: &str, status: DriverStatus| DriverResult {
+            name: name.to_string(),
+            cached: false,
+            status,
+        };
+        let denied = DriverReport {
+            results: vec![
+                result("ftdi", DriverStatus::Skipped("not needed".to_string())),
+                result(
+                    "cp210x",
+                    DriverStatus::ElevationDenied("declined".to_string()),
+                ),
+                result("ch343", DriverStatus::InstallFailed("pnputil".to_string())),
+            ],
+        };
+        assert!(!denied.is_success());
+        assert_eq!(
+            denied.clone().into_result(),
+            Err(DriverError::ElevationDenied("declined".to_string()))
+        );
+
+        let mut partial = denied;
+        partial.results[1].status = DriverStatus::Installed;
+        assert_eq!(partial.installed(), vec!["cp210x"]);
+        assert_eq!(partial.failures().len(), 1);
+        assert_eq!(partial.clone().into_result(), Ok(partial));
+    }
+
+    #[test]
+    fn test_udev_rules() {
+        let rules = get_udev_rules("uucp");
+        assert!(rules.contains(
+            "ATTRS{idVendor}==\"303a\", ATTRS{idProduct}==\"1001\", MODE=\"660\", GROUP=\"uucp\""
+        ));
+        assert_eq!(
+            rules.lines().filter(|l| l.starts_with("ATTRS")).count(),
+            ESP_USB_DEVICES.len()
+        );
+    }
+
+    #[test]
+    fn test_parse_authenticode_status() {
+        let whql = "CN=Microsoft Windows Hardware Compatibility Publisher, O=Microsoft Corporation, L=Redmond, S=Washington, C=US|A1B2|";
+        let output = format!(
+            "Valid|{}C:\\drivers\\esp\\esp.cat\r\nNotSigned|||C:\\drivers\\esp\\esp.sys\r\nHashMismatch|CN=Espressif|C3D4|C:\\drivers\\esp\\esp.dll\r\n",
+            whql
+        );
+        assert_eq!(parse_authenticode_status(&output, "a1b2").len(), 2);
+        assert_eq!(
+            parse_authenticode_status("Valid|CN=Espressif|C3D4|C:\\esp\\esp.cat", WHQL_SIGNER),
+            vec![(
+                "C:\\esp\\esp.cat".to_string(),
+                "signed by CN=Espressif".to_string()
+            )]
+        );
+        assert_eq!(
+            parse_authenticode_status(&output, WHQL_SIGNER),
+            vec![
+                (
+                    "C:\\drivers\\esp\\esp.sys".to_string(),
+                    "NotSigned".to_string()
+                ),
+                (
+                    "C:\\drivers\\esp\\esp.dll".to_string(),
+                    "HashMismatch".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_is_expected_signer() {
+        let whql = "CN=Microsoft Windows Hardware Compatibility Publisher, O=Microsoft Corporation, L=Redmond, S=Washington, C=US";
+        assert!(is_expected_signer(whql, "A1B2", WHQL_SIGNER));
+        assert!(is_expected_signer(whql, "A1B2", "a1b2"));
+        assert!(is_expected_signer(
+            whql,
+            "A1B2",
+            "Microsoft Windows Hardware Compatibility Publisher"
+        ));
+        // the expected name only appears in another attribute of the subject
+        let impostor = "CN=Impostor, OU=Microsoft Windows Hardware Compatibility Publisher";
+        assert!(!is_expected_signer(impostor, "C3D4", WHQL_SIGNER));
+        assert!(!is_expected_signer(
+            "CN=Microsoft Windows Hardware Compatibility Publisher Test",
+            "C3D4",
+            WHQL_SIGNER
+        ));
+        assert_eq!(
+            subject_common_name("O=\"Espressif, Inc.\", CN=\"Espressif, Inc.\"").as_deref(),
+            Some("Espressif, Inc.")
+        );
+        assert_eq!(subject_common_name("O=Espressif"), None);
+    }
+
+    #[test]
+    fn test_find_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("x64").join("release");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(nested.join("SilabSer.inf"), "").unwrap();
+        assert_eq!(
+            find_file(dir.path(), "silabser.inf"),
+            Some(nested.join("SilabSer.inf"))
+        );
+        assert_eq!(find_file(dir.path(), "ftdibus.inf"), None);
+    }
+}
@@ -0,0 +1,168 @@
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use crate::idf_tools::{filter_tools_by_target, get_download_for_platform, ToolsFile};
+
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
+static PLANNED_ACTIONS: Mutex<Vec<PlannedAction>> = Mutex::new(Vec::new());
+
+#[cfg(test)]
+thread_local! {
+    // enables the dry-run mode for a single test, without affecting the tests running in parallel
+    static TEST_DRY_RUN: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+}
+
+/// What a planned action would change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ActionKind {
+    Download,
+    Clone,
+    Extract,
+    CreateDirectory,
+    WriteFile,
+    RunCommand,
+    InstallPackage,
+    InstallDriver,
+    Environment,
+    Registry,
+}
+
+/// A change the installation would make to the system, recorded instead of made in dry-run mode.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlannedAction {
+    pub kind: ActionKind,
+    pub description: String,
+    /// The download size in bytes, when known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+}
+
+/// Enables the dry-run mode: downloads, clones, extractions, package and driver installations,
+/// file writes and environment or registry changes are only recorded, see `take_planned_actions`.
+pub fn set_dry_run(enabled: bool) {
+    DRY_RUN.store(enabled, Ordering::Relaxed);
+}
+
+/// Returns `true` if the dry-run mode is enabled, see `set_dry_run`.
+pub fn is_dry_run() -> bool {
+    #[cfg(test)]
+    if TEST_DRY_RUN.with(|dry_run| dry_run.get()) {
+        return true;
+    }
+    DRY_RUN.load(Ordering::Relaxed)
+}
+
+/// Records an action in dry-run mode.
+///
+/// # Returns
+///
+/// * `bool` - `true` if the dry-run mode is enabled and the caller must skip the action.
+pub fn plan(kind: ActionKind, description: &str, size: Option<u64>) -> bool {
+    if !is_dry_run() {
+        return false;
+    }
+    info!("[dry-run] {}", description);
+    PLANNED_ACTIONS.lock().unwrap().push(PlannedAction {
+        kind,
+        description: description.to_string(),
+        size,
+    });
+    true
+}
+
+/// Returns the actions recorded since the last call, in order.
+pub fn take_planned_actions() -> Vec<PlannedAction> {
+    std::mem::take(&mut *PLANNED_ACTIONS.lock().unwrap())
+}
+
+/// Lists the tool archives an installation would download for the selected targets, with their
+/// sizes from `tools.json`, so a dry run can report the total download size without fetching them.
+///
+/// # Parameters
+///
+/// * `tools_file` - The parsed `tools.json` of the ESP-IDF version.
+/// * `targets` - The selected targets.
+/// * `platform` - The platform, as returned by `get_platform_identification`.
+pub fn plan_tool_downloads(
+    tools_file: ToolsFile,
+    targets: &[String],
+    platform: &str,
+) -> Vec<PlannedAction> {
+    let mut actions = vec![];
+    for tool in filter_tools_by_target(tools_file.tools, targets) {
+        for version in tool.versions.iter().filter(|v| v.status == "recommended") {
+            if let Some(download) = get_download_for_platform(&version.downloads, platform) {
+                actions.push(PlannedAction {
+                    kind: ActionKind::Download,
+                    description: format!(
+                        "Download {} {} from {}",
+                        tool.name, version.name, download.url
+                    ),
+                    size: Some(download.size),
+                });
+            }
+        }
+    }
+    actions
+}
+
+/// Sums the known download sizes of the actions.
+pub fn total_download_size(actions: &[PlannedAction]) -> u64 {
+    actions
+        .iter()
+        .filter(|action| action.kind == ActionKind::Download)
+        .filter_map(|action| action.size)
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_total_download_size() {
+        let actions = vec![
+            PlannedAction {
+                kind: ActionKind::Download,
+                description: "Download cmake".to_string(),
+                size: Some(100),
+            },
+            PlannedAction {
+                kind: ActionKind::Download,
+                description: "Download ninja".to_string(),
+                size: None,
+            },
+            PlannedAction {
+                kind: ActionKind::Extract,
+                description: "Extract cmake".to_string(),
+                size: Some(300),
+            },
+        ];
+
+        assert_eq!(total_download_size(&actions), 100);
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_leaves_files_untouched() {
+        let dir = tempfile::tempdir().unwrap();
+        let report_path = dir.path().join("report").join("eim_install_report.json");
+        TEST_DRY_RUN.with(|dry_run| dry_run.set(true));
+        crate::install_report::InstallReport::new()
+            .to_file(&report_path)
+            .unwrap();
+        // nothing listens on the discard port, the download fails if it is attempted
+        crate::download_file(
+            "http://127.0.0.1:9/cmake.zip",
+            dir.path().to_str().unwrap(),
+            (),
+        )
+        .await
+        .unwrap();
+        TEST_DRY_RUN.with(|dry_run| dry_run.set(false));
+
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+}
@@ -0,0 +1,415 @@
+use anyhow::{anyhow, Result};
+use log::debug;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::command_executor::quote_posix;
+use crate::idf_config::IdfInstallation;
+use crate::idf_tools::{get_tools_export_paths, read_and_parse_tools_file};
+use crate::messages::{message, MessageId};
+use crate::version_manager::get_esp_ide_config;
+use crate::ActivationShell;
+
+/// First line of the `.envrc` files written by `write_envrc`, used to recognize them.
+const ENVRC_HEADER: &str = "# Generated by eim";
+
+/// The environment the activation script of an installation sets up.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActivationEnvironment {
+    /// The variables set by the activation, `ESP_IDF_VERSION` first.
+    pub variables: Vec<(String, String)>,
+    /// The directories added to `PATH`, in order of precedence.
+    pub path_additions: Vec<String>,
+}
+
+/// Finds an installation by its id or name in the ESP-IDF configuration file.
+pub(crate) fn find_installation(identifier: &str) -> Result<IdfInstallation> {
+    get_esp_ide_config()?
+        .idf_installed
+        .into_iter()
+        .find(|install| install.id == identifier || install.name == identifier)
+        .ok_or_else(|| anyhow!(message(MessageId::VersionNotInstalled, &[identifier])))
+}
+
+/// Computes the activation environment of an installation.
+///
+/// Besides the tool directories from `tools.json`, `PATH` gets the python environment and the
+/// ESP-IDF `tools` directory, so `idf.py` resolves without the aliases of the activation scripts.
+///
+/// # Parameters
+///
+/// * `installation` - The installation to compute the environment for.
+///
+/// # Returns
+///
+/// * `Result<ActivationEnvironment, anyhow::Error>` - The environment, or an error if `tools.json`
+///   of the installation can not be read.
+pub fn get_activation_environment(installation: &IdfInstallation) -> Result<ActivationEnvironment> {
+    let idf_path = PathBuf::from(&installation.path);
+    let tools_path = PathBuf::from(&installation.idf_tools_path);
+    let mut variables = vec![("ESP_IDF_VERSION".to_string(), installation.name.clone())];
+    variables.extend(
+        crate::setup_environment_variables(&tools_path, &idf_path)
+            .map_err(|e| anyhow!("Failed to setup environment variables: {}", e))?,
+    );
+
+    let tools_file =
+        read_and_parse_tools_file(idf_path.join("tools").join("tools.json").to_str().unwrap())
+            .map_err(|e| anyhow!("Failed to read tools.json: {}", e))?;
+    let python_bin = Path::new(&installation.python)
+        .parent()
+        .map(|bin| bin.to_string_lossy().into_owned());
+    let mut path_additions: Vec<String> = python_bin.into_iter().collect();
+    path_additions.push(idf_path.join("tools").to_string_lossy().into_owned());
+    path_additions.extend(get_tools_export_paths(
+        tools_file,
+        vec!["all".to_string()],
+        tools_path.join("tools").to_str().unwrap(),
+    ));
+    Ok(ActivationEnvironment {
+        variables,
+        path_additions,
+    })
+}
+
+/// Renders the content of a direnv `.envrc` file exporting the activation environment.
+pub fn render_envrc(installation_name: &str, environment: &ActivationEnvironment) -> String {
+    let mut envrc = format!(
+        "{} for ESP-IDF {}, run `direnv allow` to enable it.\n\n",
+        ENVRC_HEADER, installation_name
+    );
+    for (key, value) in &environment.variables {
+        envrc.push_str(&format!("export {}={}\n", key, quote_posix(value)));
+    }
+    envrc.push('\n');
+    // PATH_add prepends, so the directory with the highest precedence goes last
+    for path in environment.path_additions.iter().rev() {
+        envrc.push_str(&format!("PATH_add {}\n", quote_posix(path)));
+    }
+    envrc
+}
+
+/// A machine-readable format of the activation environment, see `export_environment`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// A JSON object mapping the variable names to their values.
+    Json,
+    /// `KEY="value"` lines, as read by dotenv libraries and `docker --env-file`.
+    Dotenv,
+    /// Lines to append to the file in `$GITHUB_ENV` in a GitHub Actions step.
+    GithubEnv,
+}
+
+impl ExportFormat {
+    /// Parses the name of a format: `json`, `dotenv` or `github-env`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "json" => Some(ExportFormat::Json),
+            "dotenv" | "env" => Some(ExportFormat::Dotenv),
+            "github-env" | "github_env" | "github" => Some(ExportFormat::GithubEnv),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the variables of the environment with `PATH` set to the additions followed by `current_path`.
+pub(crate) fn variables_with_path(
+    environment: &ActivationEnvironment,
+    current_path: Option<std::ffi::OsString>,
+) -> Vec<(String, String)> {
+    let mut paths: Vec<PathBuf> = environment
+        .path_additions
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+    if let Some(current_path) = current_path {
+        paths.extend(std::env::split_paths(&current_path));
+    }
+    let path = std::env::join_paths(paths)
+        .map(|path| path.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut variables = environment.variables.clone();
+    variables.push(("PATH".to_string(), path));
+    variables
+}
+
+/// Quotes a value for a dotenv file.
+fn quote_dotenv(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    format!("\"{}\"", escaped)
+}
+
+/// Renders the activation environment in the given format.
+///
+/// # Parameters
+///
+/// * `environment` - The activation environment.
+/// * `format` - The format to render.
+/// * `current_path` - The `PATH` the additions are prepended to, usually the one of the current process.
+pub fn render_environment(
+    environment: &ActivationEnvironment,
+    format: ExportFormat,
+    current_path: Option<std::ffi::OsString>,
+) -> String {
+    let variables = variables_with_path(environment, current_path);
+    match format {
+        ExportFormat::Json => {
+            let map: serde_json::Map<String, serde_json::Value> = variables
+                .into_iter()
+                .map(|(key, value)| (key, serde_json::Value::String(value)))
+                .collect();
+            serde_json::to_string_pretty(&map).unwrap_or_default()
+        }
+        ExportFormat::Dotenv => variables
+            .iter()
+            .map(|(key, value)| format!("{}={}\n", key, quote_dotenv(value)))
+            .collect(),
+        ExportFormat::GithubEnv => variables
+            .iter()
+            .map(|(key, value)| {
+                if value.contains('\n') {
+                    // multiline values need the heredoc syntax
+                    format!("{}<<EIM_EOF\n{}\nEIM_EOF\n", key, value)
+                } else {
+                    format!("{}={}\n", key, value)
+                }
+            })
+            .collect(),
+    }
+}
+
+/// Exports the activation environment of an installation, so CI pipelines and IDEs can use it
+/// without parsing the activation scripts.
+///
+/// `PATH` holds the tool directories followed by the `PATH` of the current process.
+///
+/// # Parameters
+///
+/// * `identifier` - The id or name of the installation.
+/// * `format` - The format to export.
+///
+/// # Returns
+///
+/// * `Result<String, anyhow::Error>` - The rendered environment.
+pub fn export_environment(identifier: &str, format: ExportFormat) -> Result<String> {
+    let installation = find_installation(identifier)?;
+    let environment = get_activation_environment(&installation)?;
+    Ok(render_environment(
+        &environment,
+        format,
+        std::env::var_os("PATH"),
+    ))
+}
+
+/// Quotes a value as a Nushell raw string, with one `#` more than the longest run of `#`
+/// following a `'` in the value, so the value can not end the string early.
+fn quote_nu(value: &str) -> String {
+    let hashes = value
+        .split('\'')
+        .skip(1)
+        .map(|part| part.len() - part.trim_start_matches('#').len())
+        .max()
+        .unwrap_or(0)
+        + 1;
+    let hashes = "#".repeat(hashes);
+    format!("r{}'{}'{}", hashes, value, hashes)
+}
+
+/// Renders the activation environment as commands in the syntax of a shell, to be evaluated in
+/// the current session (e.g. `eval "$(eim export)"` or `eim export --shell fish | source`).
+///
+/// Every command ends with `;` so the output still works when `eval` gets it on a single line, as
+/// with the backticks of csh.
+pub fn render_shell_exports(environment: &ActivationEnvironment, shell: ActivationShell) -> String {
+    let mut commands: Vec<String> = environment
+        .variables
+        .iter()
+        .map(|(key, value)| match shell {
+            ActivationShell::Bash | ActivationShell::Zsh => {
+                format!("export {}={};", key, quote_posix(value))
+            }
+            ActivationShell::Fish => format!(
+                "set -gx {} '{}';",
+                key,
+                value.replace('\\', "\\\\").replace('\'', "\\'")
+            ),
+            ActivationShell::Tcsh => format!("setenv {} {};", key, quote_posix(value)),
+            ActivationShell::PowerShell => {
+                format!("$env:{} = '{}';", key, value.replace('\'', "''"))
+            }
+            ActivationShell::Nushell => format!("$env.{} = {};", key, quote_nu(value)),
+        })
+        .collect();
+    if !environment.path_additions.is_empty() {
+        let joined = std::env::join_paths(&environment.path_additions)
+            .map(|path| path.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        commands.push(match shell {
+            ActivationShell::Bash | ActivationShell::Zsh => {
+                format!("export PATH={}:\"$PATH\";", quote_posix(&joined))
+            }
+            ActivationShell::Fish => format!(
+                "set -gx PATH {} $PATH;",
+                environment
+                    .path_additions
+                    .iter()
+                    .map(|path| format!("'{}'", path.replace('\\', "\\\\").replace('\'', "\\'")))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ),
+            ActivationShell::Tcsh => format!("setenv PATH {}:\"$PATH\";", quote_posix(&joined)),
+            ActivationShell::PowerShell => format!(
+                "$env:PATH = '{}' + [IO.Path]::PathSeparator + $env:PATH;",
+                joined.replace('\'', "''")
+            ),
+            ActivationShell::Nushell => format!(
+                "$env.PATH = ($env.PATH | prepend [{}]);",
+                environment
+                    .path_additions
+                    .iter()
+                    .map(|path| quote_nu(path))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ),
+        });
+    }
+    commands.join("\n") + "\n"
+}
+
+/// Returns the commands activating an installation in the current session of a shell, without
+/// writing any script file.
+///
+/// # Parameters
+///
+/// * `identifier` - The id or name of the installation.
+/// * `shell` - The shell whose syntax is used.
+///
+/// # Returns
+///
+/// * `Result<String, anyhow::Error>` - The commands, one per line, to be printed for `eval`.
+pub fn export_shell_environment(identifier: &str, shell: ActivationShell) -> Result<String> {
+    let installation = find_installation(identifier)?;
+    let environment = get_activation_environment(&installation)?;
+    Ok(render_shell_exports(&environment, shell))
+}
+
+/// Writes a direnv `.envrc` file activating an installation whenever `directory` is entered.
+///
+/// An existing `.envrc` is only replaced when it was generated by eim.
+///
+/// # Parameters
+///
+/// * `identifier` - The id or name of the installation.
+/// * `directory` - The project directory, or `None` for the folder of the installation itself.
+///
+/// # Returns
+///
+/// * `Result<PathBuf, anyhow::Error>` - The path of the written `.envrc` file.
+pub fn write_envrc(identifier: &str, directory: Option<&Path>) -> Result<PathBuf> {
+    let installation = find_installation(identifier)?;
+    let directory = match directory {
+        Some(directory) => directory.to_path_buf(),
+        None => Path::new(&installation.path)
+            .parent()
+            .ok_or_else(|| anyhow!("Invalid ESP-IDF path {}", installation.path))?
+            .to_path_buf(),
+    };
+    let envrc_path = directory.join(".envrc");
+    if let Ok(existing) = fs::read_to_string(&envrc_path) {
+        if !existing.starts_with(ENVRC_HEADER) {
+            return Err(anyhow!(
+                "{} already exists and was not generated by eim",
+                envrc_path.display()
+            ));
+        }
+    }
+    let environment = get_activation_environment(&installation)?;
+    fs::write(&envrc_path, render_envrc(&installation.name, &environment))?;
+    debug!("Wrote {} for {}", envrc_path.display(), installation.name);
+    Ok(envrc_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_envrc() {
+        let environment = ActivationEnvironment {
+            variables: vec![
+                ("ESP_IDF_VERSION".to_string(), "v5.3".to_string()),
+                ("IDF_PATH".to_string(), "/home/o'neil/esp-idf".to_string()),
+            ],
+            path_additions: vec!["/opt/python/bin".to_string(), "/opt/tools/bin".to_string()],
+        };
+
+        let envrc = render_envrc("v5.3", &environment);
+
+        assert!(envrc.starts_with(ENVRC_HEADER));
+        assert!(envrc.contains("export ESP_IDF_VERSION=v5.3\n"));
+        assert!(envrc.contains(r"export IDF_PATH='/home/o'\''neil/esp-idf'"));
+        assert!(envrc.ends_with("PATH_add /opt/tools/bin\nPATH_add /opt/python/bin\n"));
+    }
+
+    #[test]
+    fn test_render_environment_formats() {
+        let environment = ActivationEnvironment {
+            variables: vec![("IDF_PATH".to_string(), "/opt/esp \"idf\"".to_string())],
+            path_additions: vec!["/opt/tools/bin".to_string()],
+        };
+        let current_path = Some(std::ffi::OsString::from("/usr/bin"));
+        let expected_path = std::env::join_paths(["/opt/tools/bin", "/usr/bin"])
+            .unwrap()
+            .into_string()
+            .unwrap();
+
+        let json = render_environment(&environment, ExportFormat::Json, current_path.clone());
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed["IDF_PATH"], "/opt/esp \"idf\"");
+        assert_eq!(parsed["PATH"], expected_path.as_str());
+
+        let dotenv = render_environment(&environment, ExportFormat::Dotenv, current_path.clone());
+        assert!(dotenv.starts_with("IDF_PATH=\"/opt/esp \\\"idf\\\"\"\n"));
+
+        let github = render_environment(&environment, ExportFormat::GithubEnv, current_path);
+        assert_eq!(
+            github,
+            format!("IDF_PATH=/opt/esp \"idf\"\nPATH={}\n", expected_path)
+        );
+    }
+
+    #[test]
+    fn test_render_shell_exports() {
+        let environment = ActivationEnvironment {
+            variables: vec![("IDF_PATH".to_string(), "/opt/o'neil".to_string())],
+            path_additions: vec!["/opt/tools/bin".to_string()],
+        };
+
+        assert_eq!(
+            render_shell_exports(&environment, ActivationShell::Bash),
+            "export IDF_PATH='/opt/o'\\''neil';\nexport PATH=/opt/tools/bin:\"$PATH\";\n"
+        );
+        assert_eq!(
+            render_shell_exports(&environment, ActivationShell::Fish),
+            "set -gx IDF_PATH '/opt/o\\'neil';\nset -gx PATH '/opt/tools/bin' $PATH;\n"
+        );
+        assert!(
+            render_shell_exports(&environment, ActivationShell::PowerShell)
+                .starts_with("$env:IDF_PATH = '/opt/o''neil';\n")
+        );
+        assert_eq!(
+            render_shell_exports(&environment, ActivationShell::Nushell),
+            "$env.IDF_PATH = r#'/opt/o'neil'#;\n$env.PATH = ($env.PATH | prepend [r#'/opt/tools/bin'#]);\n"
+        );
+    }
+
+    #[test]
+    fn test_quote_nu() {
+        assert_eq!(quote_nu("/opt/esp"), "r#'/opt/esp'#");
+        assert_eq!(quote_nu("/opt/a'#b"), "r##'/opt/a'#b'##");
+        assert_eq!(quote_nu("x'###y'#"), "r####'x'###y'#'####");
+    }
+}
@@ -0,0 +1,177 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+/// A phase of the installation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InstallPhase {
+    Prerequisites,
+    Download,
+    Extract,
+    Clone,
+    Submodules,
+    Tools,
+    PythonEnvironment,
+    PostInstall,
+    Drivers,
+}
+
+/// How important a message is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+/// A progress event of the installation, delivered to every subscriber.
+///
+/// The events of all modules share this type, so a frontend can drive a multi-phase progress UI
+/// from a single subscription instead of one channel per operation. They are not sent on their
+/// own: every event is the `ProgressUpdate` an operation reports, see `progress::report`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InstallEvent {
+    /// Work on an item of a phase started, e.g. the download of an archive.
+    Started { phase: InstallPhase, item: String },
+    /// The item progressed, `percent` is between 0 and 100.
+    Progress {
+        phase: InstallPhase,
+        item: String,
+        percent: u8,
+    },
+    /// The item is done.
+    Completed { phase: InstallPhase, item: String },
+    /// Work on the item failed.
+    Failed {
+        phase: InstallPhase,
+        item: String,
+        error: String,
+    },
+    /// A message to show to the user.
+    Message {
+        phase: InstallPhase,
+        severity: Severity,
+        message: String,
+    },
+}
+
+impl InstallEvent {
+    /// The phase the event belongs to.
+    pub fn phase(&self) -> InstallPhase {
+        match self {
+            InstallEvent::Started { phase, .. }
+            | InstallEvent::Progress { phase, .. }
+            | InstallEvent::Completed { phase, .. }
+            | InstallEvent::Failed { phase, .. }
+            | InstallEvent::Message { phase, .. } => *phase,
+        }
+    }
+
+    /// The severity of the event, failures are errors and all other events but messages are infos.
+    pub fn severity(&self) -> Severity {
+        match self {
+            InstallEvent::Failed { .. } => Severity::Error,
+            InstallEvent::Message { severity, .. } => *severity,
+            _ => Severity::Info,
+        }
+    }
+}
+
+/// Receives the progress events of the installation.
+///
+/// `on_event` is called synchronously from the installation code, possibly from several threads,
+/// so it should only hand the event off, e.g. to a channel or an UI event loop.
+pub trait InstallEventSubscriber: Send + Sync {
+    fn on_event(&self, event: &InstallEvent);
+}
+
+impl<F: Fn(&InstallEvent) + Send + Sync> InstallEventSubscriber for F {
+    fn on_event(&self, event: &InstallEvent) {
+        self(event)
+    }
+}
+
+/// Identifies a subscription, to end it with `unsubscribe`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubscriptionId(u64);
+
+static NEXT_SUBSCRIPTION_ID: AtomicU64 = AtomicU64::new(0);
+static SUBSCRIBERS: RwLock<Vec<(SubscriptionId, Arc<dyn InstallEventSubscriber>)>> =
+    RwLock::new(Vec::new());
+
+/// Registers a subscriber for all following events.
+pub fn subscribe(subscriber: Arc<dyn InstallEventSubscriber>) -> SubscriptionId {
+    let id = SubscriptionId(NEXT_SUBSCRIPTION_ID.fetch_add(1, Ordering::Relaxed));
+    SUBSCRIBERS.write().unwrap().push((id, subscriber));
+    id
+}
+
+/// Removes a subscriber registered with `subscribe`.
+pub fn unsubscribe(id: SubscriptionId) {
+    SUBSCRIBERS
+        .write()
+        .unwrap()
+        .retain(|(subscribed, _)| *subscribed != id);
+}
+
+/// Delivers an event to all subscribers, called by `progress::report` for every update.
+pub(crate) fn publish(event: InstallEvent) {
+    let subscribers: Vec<Arc<dyn InstallEventSubscriber>> = SUBSCRIBERS
+        .read()
+        .unwrap()
+        .iter()
+        .map(|(_, subscriber)| subscriber.clone())
+        .collect();
+    for subscriber in subscribers {
+        subscriber.on_event(&event);
+    }
+}
+
+/// Computes the percentage of `done` out of `total`, 0 when the total is unknown.
+pub fn percent(done: u64, total: u64) -> u8 {
+    if total == 0 {
+        return 0;
+    }
+    (done.min(total) * 100 / total) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_publish_reaches_subscribers() {
+        let received = Arc::new(Mutex::new(vec![]));
+        let sink = received.clone();
+        let id = subscribe(Arc::new(move |event: &InstallEvent| {
+            if let InstallEvent::Failed { item, .. } = event {
+                if item == "test-item" {
+                    sink.lock().unwrap().push(event.clone());
+                }
+            }
+        }));
+        publish(InstallEvent::Failed {
+            phase: InstallPhase::Download,
+            item: "test-item".to_string(),
+            error: "offline".to_string(),
+        });
+        unsubscribe(id);
+        publish(InstallEvent::Failed {
+            phase: InstallPhase::Download,
+            item: "test-item".to_string(),
+            error: "offline".to_string(),
+        });
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].phase(), InstallPhase::Download);
+        assert_eq!(received[0].severity(), Severity::Error);
+    }
+
+    #[test]
+    fn test_percent() {
+        assert_eq!(percent(50, 200), 25);
+        assert_eq!(percent(300, 200), 100);
+        assert_eq!(percent(10, 0), 0);
+    }
+}
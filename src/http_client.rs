@@ -0,0 +1,211 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::RwLock;
+use std::time::Duration;
+
+/// The user agent of all requests, GitHub rejects API requests without one.
+pub const USER_AGENT: &str = "esp-idf-installer";
+
+/// How long an idle connection is kept open for the next request to the same host.
+const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+
+static CLIENT: RwLock<Option<reqwest::Client>> = RwLock::new(None);
+static PROXY: RwLock<Option<String>> = RwLock::new(None);
+static CA_CERTIFICATE: RwLock<Option<Vec<u8>>> = RwLock::new(None);
+static MIRROR_CREDENTIALS: RwLock<Vec<MirrorCredentials>> = RwLock::new(Vec::new());
+
+/// The credentials of a mirror behind authentication, attached to the tool downloads and the
+/// git operations whose URL starts with `url`.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct MirrorCredentials {
+    /// The mirror, e.g. `https://mirror.example.com/github`.
+    pub url: String,
+    /// The user for basic authentication, or the git user sent with the `token`.
+    #[serde(default)]
+    pub username: Option<String>,
+    /// The password for basic authentication.
+    #[serde(default)]
+    pub password: Option<String>,
+    /// A token sent as `Authorization: Bearer`, git sends it as the password instead.
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+/// Hides the secrets, the settings are logged.
+impl std::fmt::Debug for MirrorCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MirrorCredentials")
+            .field("url", &self.url)
+            .field("username", &self.username)
+            .field("password", &self.password.as_ref().map(|_| "***"))
+            .field("token", &self.token.as_ref().map(|_| "***"))
+            .finish()
+    }
+}
+
+impl MirrorCredentials {
+    /// Whether `url` is on this mirror.
+    fn matches(&self, url: &str) -> bool {
+        let mirror = self.url.trim_end_matches('/');
+        url == mirror
+            || url
+                .strip_prefix(mirror)
+                .is_some_and(|rest| rest.starts_with('/'))
+    }
+
+    /// The user and password git authenticates with, a token is sent as the password.
+    pub fn git_user_password(&self) -> Option<(&str, &str)> {
+        match (&self.token, &self.password) {
+            // the user is ignored by most git hosts when a token is sent
+            (Some(token), _) => Some((self.username.as_deref().unwrap_or("oauth2"), token)),
+            (None, Some(password)) => Some((self.username.as_deref()?, password)),
+            (None, None) => None,
+        }
+    }
+}
+
+/// Sets the credentials of the mirrors behind authentication.
+pub fn set_mirror_credentials(credentials: Vec<MirrorCredentials>) {
+    *MIRROR_CREDENTIALS.write().unwrap() = credentials;
+}
+
+/// Returns the credentials of the mirror `url` is on, the most specific one if several match.
+pub fn credentials_for(url: &str) -> Option<MirrorCredentials> {
+    MIRROR_CREDENTIALS
+        .read()
+        .unwrap()
+        .iter()
+        .filter(|credentials| credentials.matches(url))
+        .max_by_key(|credentials| credentials.url.len())
+        .cloned()
+}
+
+/// Attaches the credentials of the mirror `url` is on to a request, if any.
+pub fn authorize(request: reqwest::RequestBuilder, url: &str) -> reqwest::RequestBuilder {
+    match credentials_for(url) {
+        Some(MirrorCredentials {
+            token: Some(token), ..
+        }) => request.bearer_auth(token),
+        Some(MirrorCredentials {
+            username: Some(username),
+            password,
+            ..
+        }) => request.basic_auth(username, password),
+        _ => request,
+    }
+}
+
+/// Sets the proxy all requests go through, `None` uses the proxy of the `HTTPS_PROXY`,
+/// `HTTP_PROXY` and `ALL_PROXY` environment variables, if any.
+pub fn set_proxy(proxy: Option<String>) {
+    *PROXY.write().unwrap() = proxy;
+    reset_client();
+}
+
+/// Trusts the certificate authorities of a PEM file in addition to the system ones, e.g. for
+/// networks intercepting TLS. `None` trusts only the system ones.
+pub fn set_ca_certificate(path: Option<&Path>) -> io::Result<()> {
+    let pem = match path {
+        Some(path) => Some(fs::read(path)?),
+        None => None,
+    };
+    *CA_CERTIFICATE.write().unwrap() = pem;
+    reset_client();
+    Ok(())
+}
+
+/// Drops the shared client, the next call of `client` builds a new one with the current settings.
+fn reset_client() {
+    *CLIENT.write().unwrap() = None;
+}
+
+fn build_client() -> Result<reqwest::Client, reqwest::Error> {
+    let mut builder = reqwest::Client::builder()
+        .user_agent(USER_AGENT)
+        .pool_idle_timeout(POOL_IDLE_TIMEOUT);
+    if let Some(proxy) = PROXY.read().unwrap().as_deref() {
+        builder = builder.proxy(reqwest::Proxy::all(proxy)?);
+    }
+    if let Some(pem) = CA_CERTIFICATE.read().unwrap().as_deref() {
+        for certificate in reqwest::Certificate::from_pem_bundle(pem)? {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+    builder.build()
+}
+
+/// Returns the client shared by all downloads, built on first use.
+///
+/// Sharing the client keeps its connections open between the requests, which are reused for
+/// the following requests to the same host, over HTTP/2 when the server supports it. Cloning the
+/// returned client is cheap, the clones share the connections.
+///
+/// # Returns
+///
+/// * `Result<reqwest::Client, reqwest::Error>` - The client, or an error if the proxy or the
+///   certificate authorities set are invalid.
+pub fn client() -> Result<reqwest::Client, reqwest::Error> {
+    if let Some(client) = CLIENT.read().unwrap().as_ref() {
+        return Ok(client.clone());
+    }
+    let mut shared = CLIENT.write().unwrap();
+    // another thread may have built it meanwhile
+    if let Some(client) = shared.as_ref() {
+        return Ok(client.clone());
+    }
+    let client = build_client()?;
+    *shared = Some(client.clone());
+    Ok(client)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalid_proxy_is_reported() {
+        set_proxy(Some("not a proxy url".to_string()));
+        assert!(client().is_err());
+        set_proxy(None);
+        assert!(client().is_ok());
+    }
+
+    #[test]
+    fn test_credentials_for() {
+        let credentials = |url: &str, token: &str| MirrorCredentials {
+            url: url.to_string(),
+            username: None,
+            password: None,
+            token: Some(token.to_string()),
+        };
+        set_mirror_credentials(vec![
+            credentials("https://mirror.example.com/", "outer"),
+            credentials("https://mirror.example.com/github", "inner"),
+        ]);
+        let token = |url: &str| credentials_for(url).and_then(|credentials| credentials.token);
+        assert_eq!(
+            token("https://mirror.example.com/github/espressif/esp-idf.git"),
+            Some("inner".to_string())
+        );
+        assert_eq!(
+            token("https://mirror.example.com/gitlab/tool.zip"),
+            Some("outer".to_string())
+        );
+        assert_eq!(token("https://mirror.example.com.evil/tool.zip"), None);
+        set_mirror_credentials(vec![]);
+    }
+
+    #[test]
+    fn test_debug_hides_secrets() {
+        let credentials = MirrorCredentials {
+            url: "https://mirror.example.com".to_string(),
+            username: Some("ci".to_string()),
+            password: Some("hunter2".to_string()),
+            token: None,
+        };
+        assert!(!format!("{:?}", credentials).contains("hunter2"));
+        assert_eq!(credentials.git_user_password(), Some(("ci", "hunter2")));
+    }
+}
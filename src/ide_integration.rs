@@ -0,0 +1,297 @@
+use anyhow::{anyhow, Result};
+use log::debug;
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::environment::{
+    find_installation, get_activation_environment, render_shell_exports, ActivationEnvironment,
+};
+use crate::idf_config::IdfInstallation;
+use crate::version_manager::get_esp_ide_config;
+use crate::ActivationShell;
+
+/// Returns the key of a path setting of the ESP-IDF VS Code extension, which reads the Windows
+/// variant of these settings from the keys with the `Win` suffix.
+fn vscode_path_key(name: &str) -> String {
+    if cfg!(windows) {
+        format!("idf.{}Win", name)
+    } else {
+        format!("idf.{}", name)
+    }
+}
+
+/// Computes the settings pointing the ESP-IDF VS Code extension to an installation.
+///
+/// # Parameters
+///
+/// * `installation` - The installation the extension should use.
+/// * `git_path` - The git executable recorded in the ESP-IDF configuration file, skipped when empty.
+///
+/// # Returns
+///
+/// * `Result<Map<String, Value>, anyhow::Error>` - The settings, or an error if the activation
+///   environment of the installation can not be computed.
+pub fn vscode_settings(
+    installation: &IdfInstallation,
+    git_path: &str,
+) -> Result<Map<String, Value>> {
+    let environment = get_activation_environment(installation)?;
+    let mut settings = Map::new();
+    settings.insert(
+        vscode_path_key("espIdfPath"),
+        Value::String(installation.path.clone()),
+    );
+    settings.insert(
+        vscode_path_key("toolsPath"),
+        Value::String(installation.idf_tools_path.clone()),
+    );
+    settings.insert(
+        vscode_path_key("pythonBinPath"),
+        Value::String(installation.python.clone()),
+    );
+    if !git_path.is_empty() {
+        settings.insert(
+            vscode_path_key("gitPath"),
+            Value::String(git_path.to_string()),
+        );
+    }
+    let extra_paths = std::env::join_paths(&environment.path_additions)
+        .map(|path| path.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    settings.insert(
+        "idf.customExtraPaths".to_string(),
+        Value::String(extra_paths),
+    );
+    let extra_vars: Map<String, Value> = environment
+        .variables
+        .into_iter()
+        .map(|(key, value)| (key, Value::String(value)))
+        .collect();
+    settings.insert("idf.customExtraVars".to_string(), Value::Object(extra_vars));
+    Ok(settings)
+}
+
+/// Merges settings into the content of an existing `settings.json`, keeping all other settings.
+///
+/// # Parameters
+///
+/// * `existing` - The current content of `settings.json`, `None` if it does not exist yet.
+/// * `settings` - The settings to add or replace.
+///
+/// # Returns
+///
+/// * `Result<String, anyhow::Error>` - The new content, or an error if the existing content is
+///   not a JSON object. Comments are not supported, the file is left for the user to edit then.
+pub fn merge_vscode_settings(
+    existing: Option<&str>,
+    settings: Map<String, Value>,
+) -> Result<String> {
+    let mut merged = match existing.map(str::trim) {
+        None | Some("") => Map::new(),
+        Some(content) => match serde_json::from_str::<Value>(content) {
+            Ok(Value::Object(object)) => object,
+            Ok(_) => return Err(anyhow!("settings.json does not contain a JSON object")),
+            Err(e) => {
+                return Err(anyhow!(
+                    "Failed to parse settings.json, it may contain comments: {}",
+                    e
+                ))
+            }
+        },
+    };
+    merged.extend(settings);
+    Ok(serde_json::to_string_pretty(&Value::Object(merged))? + "\n")
+}
+
+/// Writes or updates `.vscode/settings.json` of a project so the ESP-IDF VS Code extension uses
+/// an installation managed by eim.
+///
+/// # Parameters
+///
+/// * `identifier` - The id or name of the installation.
+/// * `project_dir` - The project directory, `.vscode` is created in it when missing.
+///
+/// # Returns
+///
+/// * `Result<PathBuf, anyhow::Error>` - The path of the written `settings.json`.
+pub fn write_vscode_settings(identifier: &str, project_dir: &Path) -> Result<PathBuf> {
+    let installation = find_installation(identifier)?;
+    let settings = vscode_settings(&installation, &get_esp_ide_config()?.git_path)?;
+
+    let vscode_dir = project_dir.join(".vscode");
+    fs::create_dir_all(&vscode_dir)?;
+    let settings_path = vscode_dir.join("settings.json");
+    let existing = fs::read_to_string(&settings_path).ok();
+    let content = merge_vscode_settings(existing.as_deref(), settings)?;
+    fs::write(&settings_path, content)?;
+    debug!(
+        "Wrote {} for {}",
+        settings_path.display(),
+        installation.name
+    );
+    Ok(settings_path)
+}
+
+/// Escapes a key or value of a Java properties file, the format of Eclipse preference files.
+fn escape_properties(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(':', "\\:")
+        .replace('=', "\\=")
+        .replace('\n', "\\n")
+}
+
+/// Renders an Eclipse preferences export (`.epf`) setting the activation environment as the
+/// workspace build environment of CDT, which Espressif-IDE builds its projects with.
+///
+/// `PATH` is prepended to the one Eclipse was started with, all other variables are replaced.
+pub fn render_eclipse_preferences(environment: &ActivationEnvironment) -> String {
+    let delimiter = if cfg!(windows) { ";" } else { ":" };
+    let mut entries: Vec<(String, String, &str)> = environment
+        .variables
+        .iter()
+        .map(|(key, value)| (key.clone(), value.clone(), "replace"))
+        .collect();
+    if !environment.path_additions.is_empty() {
+        entries.push((
+            "PATH".to_string(),
+            environment.path_additions.join(delimiter),
+            "prepend",
+        ));
+    }
+
+    let mut preferences = "#Eclipse preferences export\nfile_export_version=3.0\n".to_string();
+    for (key, value, operation) in entries {
+        let prefix = format!(
+            "/instance/org.eclipse.cdt.core/environment/workspace/{}",
+            escape_properties(&key)
+        );
+        preferences.push_str(&format!(
+            "{}/delimiter={}\n",
+            prefix,
+            escape_properties(delimiter)
+        ));
+        preferences.push_str(&format!("{}/operation={}\n", prefix, operation));
+        preferences.push_str(&format!("{}/value={}\n", prefix, escape_properties(&value)));
+    }
+    preferences
+}
+
+/// Writes an Eclipse preferences export with the activation environment of an installation, to
+/// be imported in Espressif-IDE with `File > Import > General > Preferences`.
+///
+/// # Parameters
+///
+/// * `identifier` - The id or name of the installation.
+/// * `directory` - The directory to write `espressif-ide-<name>.epf` to.
+///
+/// # Returns
+///
+/// * `Result<PathBuf, anyhow::Error>` - The path of the written file.
+pub fn write_eclipse_preferences(identifier: &str, directory: &Path) -> Result<PathBuf> {
+    let installation = find_installation(identifier)?;
+    let environment = get_activation_environment(&installation)?;
+    fs::create_dir_all(directory)?;
+    let path = directory.join(format!("espressif-ide-{}.epf", installation.name));
+    fs::write(&path, render_eclipse_preferences(&environment))?;
+    debug!("Wrote {} for {}", path.display(), installation.name);
+    Ok(path)
+}
+
+/// Renders an environment file for a CLion toolchain, a batch file on Windows and a POSIX shell
+/// script elsewhere, so CMake runs with the ESP-IDF tools of the installation.
+pub fn render_clion_environment(environment: &ActivationEnvironment, windows: bool) -> String {
+    if !windows {
+        return render_shell_exports(environment, ActivationShell::Bash);
+    }
+    let mut script = "@echo off\r\n".to_string();
+    for (key, value) in &environment.variables {
+        script.push_str(&format!("set \"{}={}\"\r\n", key, value));
+    }
+    if !environment.path_additions.is_empty() {
+        script.push_str(&format!(
+            "set \"PATH={};%PATH%\"\r\n",
+            environment.path_additions.join(";")
+        ));
+    }
+    script
+}
+
+/// Writes the environment file of an installation for CLion, to be selected as the
+/// `Environment file` of a toolchain in `Settings > Build, Execution, Deployment > Toolchains`.
+///
+/// # Parameters
+///
+/// * `identifier` - The id or name of the installation.
+/// * `directory` - The directory to write `clion-env-<name>.sh` (`.bat` on Windows) to.
+///
+/// # Returns
+///
+/// * `Result<PathBuf, anyhow::Error>` - The path of the written file.
+pub fn write_clion_environment(identifier: &str, directory: &Path) -> Result<PathBuf> {
+    let installation = find_installation(identifier)?;
+    let environment = get_activation_environment(&installation)?;
+    fs::create_dir_all(directory)?;
+    let windows = cfg!(windows);
+    let extension = if windows { "bat" } else { "sh" };
+    let path = directory.join(format!("clion-env-{}.{}", installation.name, extension));
+    fs::write(&path, render_clion_environment(&environment, windows))?;
+    debug!("Wrote {} for {}", path.display(), installation.name);
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_vscode_settings() {
+        let mut settings = Map::new();
+        settings.insert(
+            "idf.espIdfPath".to_string(),
+            Value::String("/opt/esp/v5.3/esp-idf".to_string()),
+        );
+        let existing = r#"{ "editor.tabSize": 4, "idf.espIdfPath": "/old/esp-idf" }"#;
+
+        let merged = merge_vscode_settings(Some(existing), settings.clone()).unwrap();
+        let parsed: Value = serde_json::from_str(&merged).unwrap();
+        assert_eq!(parsed["editor.tabSize"], 4);
+        assert_eq!(parsed["idf.espIdfPath"], "/opt/esp/v5.3/esp-idf");
+
+        assert!(merge_vscode_settings(None, settings.clone()).is_ok());
+        assert!(merge_vscode_settings(Some("// comment\n{}"), settings.clone()).is_err());
+        assert!(merge_vscode_settings(Some("[]"), settings).is_err());
+    }
+
+    #[test]
+    fn test_render_eclipse_preferences() {
+        let environment = ActivationEnvironment {
+            variables: vec![("IDF_PATH".to_string(), "C:\\esp\\esp-idf".to_string())],
+            path_additions: vec!["/opt/tools/bin".to_string()],
+        };
+
+        let preferences = render_eclipse_preferences(&environment);
+
+        assert!(preferences.starts_with("#Eclipse preferences export\n"));
+        assert!(preferences.contains(
+            "/instance/org.eclipse.cdt.core/environment/workspace/IDF_PATH/value=C\\:\\\\esp\\\\esp-idf\n"
+        ));
+        assert!(preferences.contains(
+            "/instance/org.eclipse.cdt.core/environment/workspace/PATH/operation=prepend\n"
+        ));
+    }
+
+    #[test]
+    fn test_render_clion_environment_windows() {
+        let environment = ActivationEnvironment {
+            variables: vec![("IDF_PATH".to_string(), "C:\\esp\\esp-idf".to_string())],
+            path_additions: vec!["C:\\esp\\tools".to_string()],
+        };
+
+        assert_eq!(
+            render_clion_environment(&environment, true),
+            "@echo off\r\nset \"IDF_PATH=C:\\esp\\esp-idf\"\r\nset \"PATH=C:\\esp\\tools;%PATH%\"\r\n"
+        );
+    }
+}
@@ -1,6 +1,7 @@
 use anyhow::{anyhow, Context, Result};
 use log::debug;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs::{self, OpenOptions};
 use std::io::{self, Write};
 use std::path::Path;
@@ -17,6 +18,28 @@ pub struct IdfInstallation {
     pub name: String,
     pub path: String,
     pub python: String,
+    /// Names of the persistent environment variables eim set for this installation, reverted on removal.
+    #[serde(
+        rename = "envVariables",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub env_variables: Vec<String>,
+    /// All activation scripts generated for this installation, one per shell.
+    #[serde(
+        rename = "activationScripts",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub activation_scripts: Vec<String>,
+    /// The container image of an installation set up by `container::install_container_version`,
+    /// whose idf.py runs inside that image instead of natively.
+    #[serde(
+        rename = "containerImage",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub container_image: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -48,6 +71,13 @@ impl IdfConfig {
     /// config.to_file("eim_idf.json", true)?;
     /// ```
     pub fn to_file<P: AsRef<Path>>(&mut self, path: P, pretty: bool) -> Result<()> {
+        if crate::dry_run::plan(
+            crate::dry_run::ActionKind::WriteFile,
+            &format!("Write {}", path.as_ref().display()),
+            None,
+        ) {
+            return Ok(());
+        }
         // Create parent directories if they don't exist
         ensure_path(path.as_ref().parent().unwrap().to_str().unwrap())?;
 
@@ -60,6 +90,31 @@ impl IdfConfig {
             debug!("Creating new ide config file");
         }
 
+        self.write(path, pretty)
+    }
+
+    /// Saves the configuration to a file, replacing the installations recorded there.
+    ///
+    /// Unlike `to_file`, the installations of an existing file are not added, so this is the one
+    /// to use for a configuration read with `from_file` and modified.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path where to save the configuration file
+    /// * `pretty` - If true, the JSON will be pretty-printed
+    pub fn replace_file<P: AsRef<Path>>(&self, path: P, pretty: bool) -> Result<()> {
+        if crate::dry_run::plan(
+            crate::dry_run::ActionKind::WriteFile,
+            &format!("Write {}", path.as_ref().display()),
+            None,
+        ) {
+            return Ok(());
+        }
+        ensure_path(path.as_ref().parent().unwrap().to_str().unwrap())?;
+        self.write(path, pretty)
+    }
+
+    fn write<P: AsRef<Path>>(&self, path: P, pretty: bool) -> Result<()> {
         // Convert to JSON string
         let json_string = if pretty {
             serde_json::to_string_pretty(self)
@@ -202,3 +257,144 @@ impl IdfConfig {
 pub fn parse_idf_config<P: AsRef<Path>>(path: P) -> Result<IdfConfig> {
     IdfConfig::from_file(path)
 }
+
+/// An installation in the `esp_idf.json` of idf-env and the ESP-IDF VS Code extension.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct IdfEnvInstallation {
+    pub version: String,
+    pub python: String,
+    pub path: String,
+}
+
+/// The `esp_idf.json` layout used by idf-env and the ESP-IDF VS Code extension, where the
+/// installations are keyed by their id and share a single tools directory.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct IdfEnvConfig {
+    #[serde(rename = "gitPath", default)]
+    pub git_path: String,
+    #[serde(rename = "idfToolsPath", default)]
+    pub idf_tools_path: String,
+    #[serde(rename = "idfSelectedId", default)]
+    pub idf_selected_id: String,
+    #[serde(rename = "idfInstalled", default)]
+    pub idf_installed: BTreeMap<String, IdfEnvInstallation>,
+    /// Other keys of the file (`$schema`, `_comment`, ...), kept when it is written back.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl IdfEnvConfig {
+    /// Reads an `esp_idf.json` file written by idf-env or the ESP-IDF VS Code extension.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let content = fs::read_to_string(path)?;
+        let config: IdfEnvConfig = serde_json::from_str(&content)?;
+        Ok(config)
+    }
+
+    /// Writes the configuration to `path`, replacing the file if it exists. In dry-run mode the
+    /// write is only planned.
+    pub fn to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        if crate::dry_run::plan(
+            crate::dry_run::ActionKind::WriteFile,
+            &format!("Write {}", path.as_ref().display()),
+            None,
+        ) {
+            return Ok(());
+        }
+        ensure_path(path.as_ref().parent().unwrap().to_str().unwrap())?;
+        let json_string = serde_json::to_string_pretty(self)?;
+        fs::write(path, json_string).with_context(|| anyhow!("writing to file esp_idf.json failed"))
+    }
+}
+
+impl From<&IdfEnvConfig> for IdfConfig {
+    /// Converts an idf-env configuration, the version of each installation becomes its name.
+    ///
+    /// The activation scripts are left empty as idf-env does not generate any.
+    fn from(config: &IdfEnvConfig) -> Self {
+        IdfConfig {
+            git_path: config.git_path.clone(),
+            idf_installed: config
+                .idf_installed
+                .iter()
+                .map(|(id, install)| IdfInstallation {
+                    activation_script: String::new(),
+                    id: id.clone(),
+                    idf_tools_path: config.idf_tools_path.clone(),
+                    name: install.version.clone(),
+                    path: install.path.clone(),
+                    python: install.python.clone(),
+                    env_variables: vec![],
+                    activation_scripts: vec![],
+                    container_image: None,
+                })
+                .collect(),
+            idf_selected_id: config.idf_selected_id.clone(),
+        }
+    }
+}
+
+impl From<&IdfConfig> for IdfEnvConfig {
+    /// Converts to the idf-env layout, which has one tools directory for all installations: the
+    /// one of the selected installation, or of the first one when none is selected.
+    fn from(config: &IdfConfig) -> Self {
+        let idf_tools_path = config
+            .get_selected_installation()
+            .or(config.idf_installed.first())
+            .map(|install| install.idf_tools_path.clone())
+            .unwrap_or_default();
+        IdfEnvConfig {
+            git_path: config.git_path.clone(),
+            idf_tools_path,
+            idf_selected_id: config.idf_selected_id.clone(),
+            idf_installed: config
+                .idf_installed
+                .iter()
+                .map(|install| {
+                    (
+                        install.id.clone(),
+                        IdfEnvInstallation {
+                            version: install.name.clone(),
+                            python: install.python.clone(),
+                            path: install.path.clone(),
+                        },
+                    )
+                })
+                .collect(),
+            extra: serde_json::Map::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_idf_env_config_round_trip() {
+        let content = r#"{
+            "$schema": "http://json-schema.org/schema#",
+            "gitPath": "C:/Espressif/tools/idf-git/2.30.1/cmd/git.exe",
+            "idfToolsPath": "C:/Espressif",
+            "idfSelectedId": "esp-idf-1234",
+            "idfInstalled": {
+                "esp-idf-1234": {
+                    "version": "4.4",
+                    "python": "C:/Espressif/python_env/idf4.4_py3.8_env/Scripts/python.exe",
+                    "path": "C:/Espressif/frameworks/esp-idf-v4.4"
+                }
+            }
+        }"#;
+        let idf_env: IdfEnvConfig = serde_json::from_str(content).unwrap();
+
+        let config = IdfConfig::from(&idf_env);
+        let installation = config.get_selected_installation().unwrap();
+        assert_eq!(installation.name, "4.4");
+        assert_eq!(installation.idf_tools_path, "C:/Espressif");
+
+        let back = IdfEnvConfig::from(&config);
+        assert_eq!(back.idf_installed, idf_env.idf_installed);
+        assert_eq!(back.idf_tools_path, idf_env.idf_tools_path);
+        assert!(idf_env.extra.contains_key("$schema"));
+    }
+}
@@ -4,6 +4,7 @@ use std::fs::File;
 use std::io::prelude::*;
 use std::path::{Path, PathBuf};
 
+use crate::messages::{message, MessageId};
 use crate::python_utils::get_python_platform_definition;
 use crate::system_dependencies;
 use crate::utils::find_directories_by_name;
@@ -146,7 +147,8 @@ pub fn get_platform_identification(python: Option<&str>) -> Result<String, Strin
     platform_from_name.insert("Windows-x86_64", "win64");
     platform_from_name.insert("Windows-AMD64", "win64");
     platform_from_name.insert("x86_64-w64-mingw32", "win64");
-    platform_from_name.insert("Windows-ARM64", "win64");
+    platform_from_name.insert("Windows-ARM64", "win-arm64");
+    platform_from_name.insert("aarch64-w64-mingw32", "win-arm64");
 
     // macOS
     platform_from_name.insert("macos", "macos");
@@ -190,11 +192,47 @@ pub fn get_platform_identification(python: Option<&str>) -> Result<String, Strin
 
     let platform = match platform_from_name.get(&python_platform_string.as_str()) {
         Some(platform) => platform,
-        None => return Err(format!("Unsupported platform: {}", python_platform_string)),
+        None => {
+            return Err(message(
+                MessageId::UnsupportedPlatform,
+                &[&python_platform_string],
+            ))
+        }
     };
+    // an emulated x64 or x86 python on Windows on ARM reports the emulated architecture
+    if platform.starts_with("win") && crate::platform::native_arch() == "aarch64" {
+        return Ok("win-arm64".to_string());
+    }
     Ok(platform.to_string())
 }
 
+/// Returns the platforms whose tool builds can run on the given platform, the native one first.
+///
+/// Windows on ARM runs x64 builds through emulation and Apple Silicon runs x86_64 builds through
+/// Rosetta, so those are used when a tool has no native build.
+pub fn platform_fallbacks(platform: &str) -> Vec<&str> {
+    match platform {
+        "win-arm64" => vec!["win-arm64", "win64"],
+        "macos-arm64" => vec!["macos-arm64", "macos"],
+        _ => vec![platform],
+    }
+}
+
+/// Selects the download of a tool version for the platform, falling back to emulated builds.
+///
+/// # Parameters
+///
+/// * `downloads` - The downloads of a tool version, keyed by platform.
+/// * `platform` - The platform, as returned by `get_platform_identification`.
+pub fn get_download_for_platform<'a>(
+    downloads: &'a HashMap<String, Download>,
+    platform: &str,
+) -> Option<&'a Download> {
+    platform_fallbacks(platform)
+        .into_iter()
+        .find_map(|p| downloads.get(p))
+}
+
 /// Retrieves a HashMap of tool names and their corresponding Download instances based on the given platform.
 ///
 /// # Arguments
@@ -214,7 +252,7 @@ pub fn get_download_link_by_platform(
     let mut tool_links = HashMap::new();
     for tool in tools {
         tool.versions.iter().for_each(|version| {
-            match version.downloads.get(platform) {
+            match get_download_for_platform(&version.downloads, platform) {
                 Some(download) => tool_links.insert(tool.name.clone(), download.clone()),
                 None => None,
             };
@@ -533,4 +571,26 @@ mod tests {
 
         assert_eq!(updated_tools.get("tool1").unwrap().url, "");
     }
+
+    #[test]
+    fn test_get_download_for_platform_falls_back_to_emulated_build() {
+        let mut downloads = HashMap::new();
+        downloads.insert(
+            "win64".to_string(),
+            Download {
+                sha256: "abc123".to_string(),
+                size: 1024,
+                url: "https://github.com/example/tool-win64.zip".to_string(),
+                rename_dist: None,
+            },
+        );
+
+        assert_eq!(
+            get_download_for_platform(&downloads, "win-arm64")
+                .unwrap()
+                .url,
+            "https://github.com/example/tool-win64.zip"
+        );
+        assert!(get_download_for_platform(&downloads, "linux-arm64").is_none());
+    }
 }
@@ -1,6 +1,93 @@
-use log::error;
-use serde_derive::Deserialize;
+use log::{debug, error, warn};
+use reqwest::header::{
+    HeaderMap, HeaderName, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+};
+use reqwest::StatusCode;
+use serde_derive::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use std::time::{Duration, SystemTime};
+
+/// How long the cached `idf_versions.json` is considered fresh, unless specified otherwise.
+pub const DEFAULT_VERSIONS_CACHE_TTL: Duration = Duration::from_secs(60 * 60 * 24);
+
+/// The official location of the versions manifest.
+pub const DEFAULT_VERSIONS_URL: &str = "https://dl.espressif.com/dl/esp-idf/idf_versions.json";
+
+/// Environment variable which overrides the location of the versions manifest.
+pub const VERSIONS_URL_ENV_VAR: &str = "EIM_IDF_VERSIONS_URL";
+
+static VERSIONS_URL: RwLock<Option<String>> = RwLock::new(None);
+
+/// Overrides the location of the versions manifest for the whole process.
+/// Passing `None` restores the default. The `EIM_IDF_VERSIONS_URL` environment variable
+/// still takes precedence over the value set here.
+pub fn set_versions_url(url: Option<String>) {
+    *VERSIONS_URL.write().unwrap() = url;
+}
+
+/// Returns the URL the versions manifest is downloaded from.
+///
+/// The `EIM_IDF_VERSIONS_URL` environment variable has the highest priority, followed by the
+/// value set by `set_versions_url`, falling back to `DEFAULT_VERSIONS_URL`.
+pub fn get_versions_url() -> String {
+    if let Ok(url) = std::env::var(VERSIONS_URL_ENV_VAR) {
+        if !url.is_empty() {
+            return url;
+        }
+    }
+    VERSIONS_URL
+        .read()
+        .unwrap()
+        .clone()
+        .unwrap_or_else(|| DEFAULT_VERSIONS_URL.to_string())
+}
+
+/// Errors which can occur while obtaining or processing the ESP-IDF versions.
+#[derive(Debug)]
+pub enum IdfVersionsError {
+    /// The versions could not be downloaded (offline, DNS failure, HTTP error status, ...).
+    Network(reqwest::Error),
+    /// The downloaded or cached data is not a valid versions manifest.
+    Parse(serde_json::Error),
+    /// The request can not be satisfied by the manifest (e.g. an unknown version).
+    Unsupported(String),
+}
+
+impl std::fmt::Display for IdfVersionsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IdfVersionsError::Network(e) => write!(f, "Failed to download IDF versions: {}", e),
+            IdfVersionsError::Parse(e) => write!(f, "Malformed IDF versions manifest: {}", e),
+            IdfVersionsError::Unsupported(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for IdfVersionsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            IdfVersionsError::Network(e) => Some(e),
+            IdfVersionsError::Parse(e) => Some(e),
+            IdfVersionsError::Unsupported(_) => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for IdfVersionsError {
+    fn from(e: reqwest::Error) -> Self {
+        IdfVersionsError::Network(e)
+    }
+}
+
+impl From<serde_json::Error> for IdfVersionsError {
+    fn from(e: serde_json::Error) -> Self {
+        IdfVersionsError::Parse(e)
+    }
+}
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Version {
@@ -36,9 +123,263 @@ pub struct Releases {
     pub RELEASES: std::collections::HashMap<String, Release>,
 }
 
+/// Where the list of versions was obtained from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VersionsSource {
+    /// The versions manifest (`idf_versions.json`), downloaded or cached.
+    Manifest,
+    /// The GitHub releases of espressif/esp-idf. This list contains no targets and no release dates.
+    GitHubReleases,
+}
+
+/// Releases together with the information whether they were served from an outdated cache.
+#[derive(Debug, Clone)]
+pub struct CachedReleases {
+    pub releases: Releases,
+    /// `true` when the versions could not be downloaded and an expired cache was used instead.
+    pub stale: bool,
+    pub source: VersionsSource,
+}
+
+/// A release as returned by the GitHub releases API.
+#[derive(Debug, Deserialize, Clone)]
+pub struct GitHubRelease {
+    pub tag_name: String,
+    #[serde(default)]
+    pub prerelease: bool,
+    #[serde(default)]
+    pub draft: bool,
+    /// The release notes in markdown.
+    #[serde(default)]
+    pub body: Option<String>,
+}
+
+const GITHUB_RELEASES_URL: &str =
+    "https://api.github.com/repos/espressif/esp-idf/releases?per_page=100";
+
+impl Version {
+    /// Returns the URL of the release notes of this version, see `get_release_notes_url`.
+    pub fn release_notes_url(&self) -> Option<String> {
+        get_release_notes_url(&self.name)
+    }
+}
+
+/// Returns the URL of the GitHub release page with the release notes of the given version.
+///
+/// # Returns
+///
+/// * `Some(String)` with the URL for tagged versions (`v5.4`, `v5.4-beta1`), `None` for branches
+///   like `master`, `latest` or `release/v5.4`, which have no release notes.
+pub fn get_release_notes_url(version: &str) -> Option<String> {
+    if !version.starts_with('v') || parse_version_numbers(version).is_none() {
+        return None;
+    }
+    Some(format!(
+        "https://github.com/espressif/esp-idf/releases/tag/{}",
+        version
+    ))
+}
+
+/// Downloads the release notes (in markdown) of the given version from the GitHub releases API.
+///
+/// # Returns
+///
+/// * A `Result` containing the markdown of the release notes. `IdfVersionsError::Unsupported` is returned
+///   for versions without release notes, other variants when the download or parsing fails.
+pub async fn fetch_release_notes(version: &str) -> Result<String, IdfVersionsError> {
+    if get_release_notes_url(version).is_none() {
+        return Err(IdfVersionsError::Unsupported(format!(
+            "There are no release notes for {}",
+            version
+        )));
+    }
+    let url = format!(
+        "https://api.github.com/repos/espressif/esp-idf/releases/tags/{}",
+        version
+    );
+    let client = crate::http_client::client()?;
+    let response = client
+        .get(&url)
+        .header("Accept", "application/vnd.github+json")
+        .send()
+        .await?
+        .error_for_status()?;
+    let release: GitHubRelease = serde_json::from_str(&response.text().await?)?;
+    Ok(release.body.unwrap_or_default())
+}
+
+/// Returns the path of the cached `idf_versions.json` in the eim data directory.
+///
+/// Manifests downloaded from a custom URL are cached separately, so switching
+/// between mirrors never serves the data of the other one.
+pub fn get_versions_cache_path() -> Option<PathBuf> {
+    let url = get_versions_url();
+    let file_name = if url == DEFAULT_VERSIONS_URL {
+        "idf_versions.json".to_string()
+    } else {
+        let hash = format!("{:x}", Sha256::digest(url.as_bytes()));
+        format!("idf_versions_{}.json", &hash[..16])
+    };
+    dirs::data_local_dir().map(|data_dir| data_dir.join("eim").join("cache").join(file_name))
+}
+
+/// The validators of the cached `idf_versions.json`, sent with the next download so the server
+/// can answer `304 Not Modified` instead of sending the whole manifest again.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+struct CacheValidators {
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+impl CacheValidators {
+    fn from_headers(headers: &HeaderMap) -> Self {
+        let header = |name: HeaderName| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.to_string())
+        };
+        CacheValidators {
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+        }
+    }
+}
+
+/// Returns the path of the validators stored next to the cached versions file.
+fn get_versions_validators_path() -> Option<PathBuf> {
+    get_versions_cache_path().map(|path| path.with_extension("validators.json"))
+}
+
+/// Reads the validators of the cached versions file, empty when there are none.
+fn read_cache_validators() -> CacheValidators {
+    get_versions_validators_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Reads the cached versions file, if any, together with its age.
+fn read_versions_cache() -> Option<(String, Duration)> {
+    let cache_path = get_versions_cache_path()?;
+    let content = fs::read_to_string(&cache_path).ok()?;
+    let modified = fs::metadata(&cache_path).ok()?.modified().ok()?;
+    let age = SystemTime::now()
+        .duration_since(modified)
+        .unwrap_or(Duration::ZERO);
+    Some((content, age))
+}
+
+/// Stores the downloaded versions file and its validators in the cache. Failures are only logged.
+fn write_versions_cache(content: &str, validators: &CacheValidators) {
+    if let Some(cache_path) = get_versions_cache_path() {
+        let _ = crate::ensure_path(cache_path.parent().unwrap().to_str().unwrap());
+        if let Err(e) = fs::write(&cache_path, content) {
+            warn!("Failed to write versions cache: {}", e);
+        }
+    }
+    if let Some(validators_path) = get_versions_validators_path() {
+        let content = serde_json::to_string(validators).unwrap_or_default();
+        if let Err(e) = fs::write(&validators_path, content) {
+            warn!("Failed to write versions cache validators: {}", e);
+        }
+    }
+}
+
+/// Retrieves the IDF versions, using the local cache when it is younger than `ttl`.
+///
+/// When the cache is expired, the versions are downloaded and the cache is updated.
+/// If the download fails (e.g. when offline), the expired cache is served with the `stale` flag set.
+/// Without any cache, the versions are built from the GitHub releases of ESP-IDF as a last resort,
+/// which is reported by `CachedReleases::source`.
+///
+/// # Arguments
+///
+/// * `ttl` - How long the cached versions are considered fresh.
+///
+/// # Returns
+///
+/// * A `Result` containing `CachedReleases` if the versions could be obtained from the network or the cache.
+///   If there is no cache and neither the manifest nor the GitHub releases can be downloaded,
+///   the `IdfVersionsError` of the manifest download is returned.
+pub async fn get_idf_versions_with_ttl(ttl: Duration) -> Result<CachedReleases, IdfVersionsError> {
+    let cached = read_versions_cache();
+    if let Some((content, age)) = &cached {
+        if *age < ttl {
+            if let Ok(releases) = serde_json::from_str::<Releases>(content) {
+                debug!("Using cached IDF versions ({}s old)", age.as_secs());
+                return Ok(CachedReleases {
+                    releases,
+                    stale: false,
+                    source: VersionsSource::Manifest,
+                });
+            }
+        }
+    }
+    match refresh_idf_versions().await {
+        Ok(releases) => Ok(CachedReleases {
+            releases,
+            stale: false,
+            source: VersionsSource::Manifest,
+        }),
+        Err(err) => match cached {
+            Some((content, _)) => {
+                warn!(
+                    "Failed to download IDF versions, using cached data: {}",
+                    err
+                );
+                let releases: Releases = serde_json::from_str(&content)?;
+                Ok(CachedReleases {
+                    releases,
+                    stale: true,
+                    source: VersionsSource::Manifest,
+                })
+            }
+            None => {
+                warn!(
+                    "Failed to download IDF versions, falling back to GitHub releases: {}",
+                    err
+                );
+                match download_idf_versions_from_github().await {
+                    Ok(releases) => Ok(CachedReleases {
+                        releases,
+                        stale: false,
+                        source: VersionsSource::GitHubReleases,
+                    }),
+                    Err(e) => {
+                        error!("GitHub releases fallback failed: {}", e);
+                        Err(err)
+                    }
+                }
+            }
+        },
+    }
+}
+
+/// Downloads the IDF versions regardless of the cache age and updates the cache.
+///
+/// # Returns
+///
+/// * A `Result` containing the freshly downloaded `Releases`, or an `IdfVersionsError`.
+pub async fn refresh_idf_versions() -> Result<Releases, IdfVersionsError> {
+    let cached = read_versions_cache().map(|(content, _)| content);
+    let (json_versions_file, validators) = download_idf_versions_json(
+        cached
+            .as_deref()
+            .map(|content| (content, read_cache_validators())),
+    )
+    .await?;
+    let versions: Releases = serde_json::from_str(&json_versions_file)?;
+    // rewritten even when not modified, which restarts the cache ttl
+    write_versions_cache(&json_versions_file, &validators);
+    Ok(versions)
+}
+
 // TODO: handle the possibility of multiple downloads
-pub async fn get_idf_versions() -> Result<Releases, String> {
-    Ok(download_idf_versions().await.unwrap())
+pub async fn get_idf_versions() -> Result<Releases, IdfVersionsError> {
+    get_idf_versions_with_ttl(DEFAULT_VERSIONS_CACHE_TTL)
+        .await
+        .map(|cached| cached.releases)
 }
 
 /// Retrieves the available IDF targets from the official website.
@@ -49,13 +390,13 @@ pub async fn get_idf_versions() -> Result<Releases, String> {
 /// # Returns
 ///
 /// * A `Result` containing a vector of strings representing the available IDF targets if successful.
-///   If there is an error fetching the IDF versions or processing them, a `String` containing the error message is returned.
+///   If there is an error fetching the IDF versions or processing them, an `IdfVersionsError` is returned.
 ///
 /// # Errors
 ///
-/// * If there is an error fetching the IDF versions or processing them, a `String` containing the error message is returned.
+/// * If there is an error fetching the IDF versions or processing them, an `IdfVersionsError` is returned.
 ///
-pub async fn get_avalible_targets() -> Result<Vec<String>, String> {
+pub async fn get_avalible_targets() -> Result<Vec<String>, IdfVersionsError> {
     let versions = get_idf_versions().await;
     match versions {
         Ok(releases) => {
@@ -70,28 +411,108 @@ pub async fn get_avalible_targets() -> Result<Vec<String>, String> {
     }
 }
 
-/// This function downloads the IDF versions from the official website.
+/// This function downloads the IDF versions from the official website
+/// (or from the location configured by `set_versions_url` / `EIM_IDF_VERSIONS_URL`).
+///
+/// The download is conditional on the ETag and Last-Modified of the cached copy, which is used
+/// when the manifest did not change, and also when it can not be downloaded (e.g. when offline).
 ///
 /// # Returns
 ///
 /// * A Result containing a `Releases` struct if the download and parsing are successful.
-///   If there is an error during the download or parsing, a `Box<dyn std::error::Error>` is returned.
+///   If there is an error during the download or parsing, an `IdfVersionsError` is returned.
 ///
 /// # Errors
 ///
-/// * If there is an error during the HTTP request, the error is returned as a `reqwest::Error`.
-/// * If there is an error during the JSON deserialization, the error is returned as a `serde_json::Error`.
+/// * If there is an error during the HTTP request and nothing is cached, `IdfVersionsError::Network` is returned.
+/// * If there is an error during the JSON deserialization, `IdfVersionsError::Parse` is returned.
 ///
-pub async fn download_idf_versions() -> Result<Releases, Box<dyn std::error::Error>> {
-    let url = "https://dl.espressif.com/dl/esp-idf/idf_versions.json".to_string();
-    let client = reqwest::Client::builder()
-        .user_agent("esp-idf-installer")
-        .build()?;
-    let response = client.get(&url).send().await?;
-    let json_versions_file = response.text().await?;
-    let versions: Releases = serde_json::from_str(&json_versions_file)?;
+pub async fn download_idf_versions() -> Result<Releases, IdfVersionsError> {
+    match refresh_idf_versions().await {
+        Err(IdfVersionsError::Network(err)) => {
+            let Some((content, _)) = read_versions_cache() else {
+                return Err(IdfVersionsError::Network(err));
+            };
+            warn!(
+                "Failed to download IDF versions, using cached data: {}",
+                err
+            );
+            Ok(serde_json::from_str(&content)?)
+        }
+        result => result,
+    }
+}
 
-    Ok(versions)
+/// Downloads the raw content of `idf_versions.json` from the URL returned by `get_versions_url`.
+///
+/// # Arguments
+///
+/// * `cached` - The cached content with its validators, returned as is when the server answers
+///   `304 Not Modified`.
+///
+/// # Returns
+///
+/// * A `Result` containing the content and the validators to cache it with.
+async fn download_idf_versions_json(
+    cached: Option<(&str, CacheValidators)>,
+) -> Result<(String, CacheValidators), IdfVersionsError> {
+    let url = get_versions_url();
+    let client = crate::http_client::client()?;
+    let mut request = crate::http_client::authorize(client.get(&url), &url);
+    if let Some((_, validators)) = &cached {
+        if let Some(etag) = &validators.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &validators.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+    let response = request.send().await?;
+    if response.status() == StatusCode::NOT_MODIFIED {
+        if let Some((content, validators)) = cached {
+            debug!("IDF versions not modified since they were cached");
+            return Ok((content.to_string(), validators));
+        }
+    }
+    let response = response.error_for_status()?;
+    let validators = CacheValidators::from_headers(response.headers());
+    Ok((response.text().await?, validators))
+}
+
+/// Builds the list of versions from the GitHub releases of ESP-IDF.
+///
+/// Drafts are skipped. As GitHub knows nothing about targets or support windows,
+/// `IDF_TARGETS` and `RELEASES` are left empty.
+pub fn releases_from_github(github_releases: Vec<GitHubRelease>) -> Releases {
+    Releases {
+        VERSIONS: github_releases
+            .into_iter()
+            .filter(|release| !release.draft)
+            .map(|release| Version {
+                name: release.tag_name,
+                pre_release: release.prerelease,
+                old: false,
+                end_of_life: false,
+                has_targets: false,
+                supported_targets: vec![],
+            })
+            .collect(),
+        IDF_TARGETS: vec![],
+        RELEASES: HashMap::new(),
+    }
+}
+
+/// Downloads the list of versions from the GitHub releases API of espressif/esp-idf.
+pub async fn download_idf_versions_from_github() -> Result<Releases, IdfVersionsError> {
+    let client = crate::http_client::client()?;
+    let response = client
+        .get(GITHUB_RELEASES_URL)
+        .header("Accept", "application/vnd.github+json")
+        .send()
+        .await?
+        .error_for_status()?;
+    let github_releases: Vec<GitHubRelease> = serde_json::from_str(&response.text().await?)?;
+    Ok(releases_from_github(github_releases))
 }
 
 /// This function groups the IDF versions by their supported targets.
@@ -123,6 +544,33 @@ pub fn get_idf_versions_by_target(versions: &Releases) -> HashMap<String, Vec<Ve
     versions_by_target
 }
 
+/// Controls which of the versions not recommended for new projects are listed.
+///
+/// The default filter lists only stable, supported versions.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VersionFilter {
+    /// Include versions marked as `pre_release` (betas and release candidates).
+    pub include_prereleases: bool,
+    /// Include versions marked as `old` or `end_of_life`.
+    pub include_eol: bool,
+}
+
+impl VersionFilter {
+    /// Checks whether the version passes the filter. The `latest` alias is never listed.
+    pub fn matches(&self, version: &Version) -> bool {
+        if version.name == "latest" {
+            return false;
+        }
+        if version.pre_release && !self.include_prereleases {
+            return false;
+        }
+        if (version.end_of_life || version.old) && !self.include_eol {
+            return false;
+        }
+        true
+    }
+}
+
 /// This function retrieves the IDF version names for a given target.
 ///
 /// # Arguments
@@ -131,26 +579,45 @@ pub fn get_idf_versions_by_target(versions: &Releases) -> HashMap<String, Vec<Ve
 ///
 /// # Returns
 ///
-/// * A vector of strings containing the IDF version names for the given target.
+/// * A `Result` containing a vector of strings with the IDF version names for the given target.
 ///   If the target is not found or there are no valid versions, an empty vector is returned.
 ///
 /// # Errors
 ///
-/// * If there is an error fetching the IDF versions or processing them, an error message is returned as a string.
+/// * If there is an error fetching the IDF versions or processing them, an `IdfVersionsError` is returned.
 ///
-pub async fn get_idf_name_by_target(target: &String) -> Vec<String> {
-    let versions = get_idf_versions().await;
-    let versions_by_target = get_idf_versions_by_target(&versions.unwrap());
+pub async fn get_idf_name_by_target(target: &String) -> Result<Vec<String>, IdfVersionsError> {
+    get_idf_name_by_target_filtered(target, VersionFilter::default()).await
+}
+
+/// This function retrieves the IDF version names for a given target, applying the given filter.
+///
+/// # Arguments
+///
+/// * `target` - A reference to a string representing the target for which the IDF versions are needed.
+/// * `filter` - A `VersionFilter` specifying whether pre-release and EOL versions should be included.
+///
+/// # Returns
+///
+/// * A `Result` containing a vector of strings with the IDF version names for the given target.
+///   If the target is not found or there are no matching versions, an empty vector is returned.
+///   If there is an error fetching the IDF versions, an `IdfVersionsError` is returned.
+pub async fn get_idf_name_by_target_filtered(
+    target: &String,
+    filter: VersionFilter,
+) -> Result<Vec<String>, IdfVersionsError> {
+    let versions = get_idf_versions().await?;
+    let versions_by_target = get_idf_versions_by_target(&versions);
     let mut selected_versions = vec![];
     if let Some(versions) = versions_by_target.get(target) {
         for v in versions {
-            if v.end_of_life || v.pre_release || v.old || v.name == "latest" {
+            if !filter.matches(v) {
                 continue;
             }
             selected_versions.push(v.name.clone());
         }
     }
-    selected_versions
+    Ok(selected_versions)
 }
 
 /// Retrieves the names of all valid IDF versions.
@@ -160,32 +627,500 @@ pub async fn get_idf_name_by_target(target: &String) -> Vec<String> {
 ///
 /// # Returns
 ///
-/// * A vector of strings containing the names of valid IDF versions.
-///   If there is an error fetching the IDF versions or processing them, an empty vector is returned.
+/// * A `Result` containing a vector of strings with the names of valid IDF versions.
 ///
 /// # Errors
 ///
-/// * If there is an error fetching the IDF versions or processing them, an error message is logged.
-pub async fn get_idf_names() -> Vec<String> {
-    let versions = get_idf_versions().await;
-    match versions {
-        Ok(releases) => {
-            let mut names = vec![];
-            for version in &releases.VERSIONS {
-                if version.end_of_life
-                    || version.pre_release
-                    || version.old
-                    || version.name == "latest"
-                {
-                    continue;
+/// * If there is an error fetching the IDF versions or processing them, an `IdfVersionsError` is returned.
+pub async fn get_idf_names() -> Result<Vec<String>, IdfVersionsError> {
+    get_idf_names_filtered(VersionFilter::default()).await
+}
+
+/// Retrieves the names of the IDF versions passing the given filter.
+///
+/// # Arguments
+///
+/// * `filter` - A `VersionFilter` specifying whether pre-release and EOL versions should be included.
+///
+/// # Returns
+///
+/// * A `Result` containing a vector of strings with the names of the matching IDF versions.
+///   If there is an error fetching the IDF versions or processing them, an `IdfVersionsError` is returned.
+pub async fn get_idf_names_filtered(
+    filter: VersionFilter,
+) -> Result<Vec<String>, IdfVersionsError> {
+    let releases = get_idf_versions().await?;
+    Ok(filter_version_names(&releases, filter))
+}
+
+/// Returns the names of the versions in `releases` passing the given filter, in manifest order.
+pub fn filter_version_names(releases: &Releases, filter: VersionFilter) -> Vec<String> {
+    releases
+        .VERSIONS
+        .iter()
+        .filter(|version| filter.matches(version))
+        .map(|version| version.name.clone())
+        .collect()
+}
+
+/// A version together with the support window of the release line it belongs to.
+#[derive(Debug, Clone)]
+pub struct VersionSupportWindow {
+    pub version: Version,
+    /// `None` if the manifest contains no dates for the release line (e.g. `master`).
+    pub release: Option<Release>,
+}
+
+/// Converts a civil date to the number of days since 1970-01-01.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = (if year >= 0 { year } else { year - 399 }) / 400;
+    let yoe = year - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// Parses a `YYYY-MM-DD` date into the number of days since 1970-01-01.
+pub fn parse_iso_date(date: &str) -> Option<i64> {
+    let mut parts = date.trim().splitn(3, '-');
+    let year = parts.next()?.parse::<i64>().ok()?;
+    let month = parts.next()?.parse::<i64>().ok()?;
+    let day = parts.next()?.get(..2)?.parse::<i64>().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    Some(days_from_civil(year, month, day))
+}
+
+/// Returns today's date (UTC) as the number of days since 1970-01-01.
+pub fn today() -> i64 {
+    let secs = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_secs();
+    (secs / 86400) as i64
+}
+
+/// Returns the name of the release line (`v5.1`) a version (`v5.1.2`, `release/v5.1`) belongs to.
+pub fn get_release_line(version: &str) -> String {
+    let version = version.trim_start_matches("release/");
+    let parts: Vec<&str> = version.split('.').collect();
+    if parts.len() >= 2 {
+        let minor = parts[1].split('-').next().unwrap_or(parts[1]);
+        format!("{}.{}", parts[0], minor)
+    } else {
+        version.to_string()
+    }
+}
+
+/// Looks up the support window of the release line the given version belongs to.
+///
+/// # Arguments
+///
+/// * `releases` - A reference to a `Releases` struct containing the IDF versions and release dates.
+/// * `version` - The version name, for example `v5.1.2`.
+///
+/// # Returns
+///
+/// * `Some(&Release)` with the start and end date of the release line, `None` if the manifest has no dates for it.
+pub fn get_release_for_version<'a>(releases: &'a Releases, version: &str) -> Option<&'a Release> {
+    releases.RELEASES.get(&get_release_line(version))
+}
+
+/// Returns the number of days from `date` until the end of the support of the given release.
+///
+/// The result is negative if the release was already out of support at `date`.
+pub fn days_until_end_of_support(release: &Release, date: i64) -> Option<i64> {
+    parse_iso_date(&release.end_date).map(|end| end - date)
+}
+
+/// Returns every version listed in the manifest together with its support window.
+pub fn get_versions_support_windows(releases: &Releases) -> Vec<VersionSupportWindow> {
+    releases
+        .VERSIONS
+        .iter()
+        .map(|version| VersionSupportWindow {
+            version: version.clone(),
+            release: get_release_for_version(releases, &version.name).cloned(),
+        })
+        .collect()
+}
+
+/// Returns the versions whose release line is supported on the given date.
+///
+/// # Arguments
+///
+/// * `releases` - A reference to a `Releases` struct containing the IDF versions and release dates.
+/// * `date` - The date as the number of days since 1970-01-01, see `parse_iso_date` and `today`.
+///
+/// # Returns
+///
+/// * A vector of `Version` structs whose release started on or before `date` and ends on or after it.
+///   Versions without dates in the manifest are not included.
+pub fn get_supported_versions_on(releases: &Releases, date: i64) -> Vec<Version> {
+    get_versions_support_windows(releases)
+        .into_iter()
+        .filter(|window| match &window.release {
+            Some(release) => {
+                let start = parse_iso_date(&release.start_date);
+                let end = parse_iso_date(&release.end_date);
+                matches!((start, end), (Some(start), Some(end)) if start <= date && date <= end)
+            }
+            None => false,
+        })
+        .map(|window| window.version)
+        .collect()
+}
+
+/// Returns the names of the versions which reach the end of their support within `days` from `date`.
+///
+/// Frontends can use this to warn the user when a near-EOL release is selected.
+pub fn get_near_eol_versions(releases: &Releases, date: i64, days: i64) -> Vec<String> {
+    get_versions_support_windows(releases)
+        .into_iter()
+        .filter(|window| {
+            window
+                .release
+                .as_ref()
+                .and_then(|release| days_until_end_of_support(release, date))
+                .map(|left| (0..=days).contains(&left))
+                .unwrap_or(false)
+        })
+        .map(|window| window.version.name)
+        .collect()
+}
+
+/// A target which is not supported by the selected ESP-IDF version.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TargetValidationError {
+    pub target: String,
+    pub version: String,
+    pub supported_targets: Vec<String>,
+}
+
+impl std::fmt::Display for TargetValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Target {} is not supported by ESP-IDF {} (supported targets: {})",
+            self.target,
+            self.version,
+            self.supported_targets.join(", ")
+        )
+    }
+}
+
+/// Validates the selected targets against the support matrix of the given version.
+///
+/// The `all` target is always valid. Versions which are not in the manifest, or which do not
+/// list their supported targets, are not validated.
+///
+/// # Arguments
+///
+/// * `releases` - A reference to a `Releases` struct containing the IDF versions.
+/// * `version` - The version name or specifier (see `resolve_version_specifier`).
+/// * `targets` - The selected targets.
+///
+/// # Returns
+///
+/// * `Ok(())` if every target is supported, otherwise one `TargetValidationError` for each unsupported target.
+pub fn validate_targets_for_version(
+    releases: &Releases,
+    version: &str,
+    targets: &[String],
+) -> Result<(), Vec<TargetValidationError>> {
+    let resolved = resolve_version_specifier(releases, version).unwrap_or(version.to_string());
+    let version = match releases.VERSIONS.iter().find(|v| v.name == resolved) {
+        Some(version) if !version.supported_targets.is_empty() => version,
+        _ => return Ok(()),
+    };
+    let errors: Vec<TargetValidationError> = targets
+        .iter()
+        .filter(|target| *target != "all" && !version.supported_targets.contains(target))
+        .map(|target| TargetValidationError {
+            target: target.clone(),
+            version: version.name.clone(),
+            supported_targets: version.supported_targets.clone(),
+        })
+        .collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Parses a version name (`v5.3.1`, `5.3`, `v5.4-beta1`) into its numeric components.
+///
+/// Missing components are returned as `None`, anything after a `-` is ignored.
+pub fn parse_version_numbers(name: &str) -> Option<(u32, Option<u32>, Option<u32>)> {
+    let name = name.trim().trim_start_matches("release/");
+    let name = name.strip_prefix('v').unwrap_or(name);
+    let name = name.split('-').next()?;
+    let mut parts = name.split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = match parts.next() {
+        Some(part) => Some(part.parse().ok()?),
+        None => None,
+    };
+    let patch = match parts.next() {
+        Some(part) => Some(part.parse().ok()?),
+        None => None,
+    };
+    Some((major, minor, patch))
+}
+
+/// Resolves a partial or symbolic version specifier to a concrete version from the manifest.
+///
+/// Supported specifiers are:
+/// - an exact version name present in the manifest (`v5.3.1`, `v5.4-beta1`, `master`),
+/// - `latest` for the newest stable version,
+/// - a partial version (`5`, `5.x`, `5.3`, `v5.3`, `release/v5.3`) for the newest stable version
+///   of that major or minor line.
+///
+/// Pre-release, old and EOL versions are only returned when requested by their exact name.
+///
+/// # Arguments
+///
+/// * `releases` - A reference to a `Releases` struct containing the IDF versions.
+/// * `spec` - The version specifier to resolve.
+///
+/// # Returns
+///
+/// * `Some(String)` with the name of the resolved version, `None` if no version matches.
+pub fn resolve_version_specifier(releases: &Releases, spec: &str) -> Option<String> {
+    let spec = spec.trim();
+    if spec != "latest" && releases.VERSIONS.iter().any(|v| v.name == spec) {
+        return Some(spec.to_string());
+    }
+    let wanted = if spec == "latest" {
+        None
+    } else {
+        Some(parse_version_numbers(
+            spec.trim_end_matches(".x").trim_end_matches(".*"),
+        )?)
+    };
+    releases
+        .VERSIONS
+        .iter()
+        .filter(|v| VersionFilter::default().matches(v))
+        .filter_map(|v| {
+            let (major, minor, patch) = parse_version_numbers(&v.name)?;
+            let matches = match wanted {
+                None => true,
+                Some((w_major, w_minor, w_patch)) => {
+                    w_major == major
+                        && w_minor.is_none_or(|m| Some(m) == minor)
+                        && w_patch.is_none_or(|p| Some(p) == patch)
                 }
-                names.push(version.name.clone());
+            };
+            if matches {
+                Some((
+                    (major, minor.unwrap_or(0), patch.unwrap_or(0)),
+                    v.name.clone(),
+                ))
+            } else {
+                None
             }
-            names
-        }
-        Err(err) => {
-            error!("{}", err);
-            vec![]
+        })
+        .max_by(|a, b| a.0.cmp(&b.0))
+        .map(|(_, name)| name)
+}
+
+/// Resolves a version specifier against the current versions manifest.
+///
+/// See `resolve_version_specifier` for the supported specifiers.
+///
+/// # Returns
+///
+/// * A `Result` containing the name of the resolved version. If the manifest can not be obtained,
+///   the corresponding `IdfVersionsError` is returned; if nothing matches the specifier,
+///   `IdfVersionsError::Unsupported` is returned.
+pub async fn resolve_version(spec: &str) -> Result<String, IdfVersionsError> {
+    let releases = get_idf_versions().await?;
+    resolve_version_specifier(&releases, spec).ok_or_else(|| {
+        IdfVersionsError::Unsupported(format!("No ESP-IDF version matches '{}'", spec))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn releases() -> Releases {
+        let mut dates = HashMap::new();
+        dates.insert(
+            "v5.1".to_string(),
+            Release {
+                start_date: "2023-06-18".to_string(),
+                end_date: "2025-12-18".to_string(),
+            },
+        );
+        dates.insert(
+            "v4.4".to_string(),
+            Release {
+                start_date: "2022-01-26".to_string(),
+                end_date: "2024-07-26".to_string(),
+            },
+        );
+        let version = |name: &str| Version {
+            name: name.to_string(),
+            pre_release: false,
+            old: false,
+            end_of_life: false,
+            has_targets: true,
+            supported_targets: vec!["esp32".to_string()],
+        };
+        Releases {
+            VERSIONS: vec![version("v5.1.2"), version("v4.4.7"), version("latest")],
+            IDF_TARGETS: vec![],
+            RELEASES: dates,
         }
     }
+
+    #[test]
+    fn test_cache_validators_from_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(ETAG, "\"5f3c-61a2\"".parse().unwrap());
+        let validators = CacheValidators::from_headers(&headers);
+        assert_eq!(validators.etag.as_deref(), Some("\"5f3c-61a2\""));
+        assert_eq!(validators.last_modified, None);
+
+        let json = serde_json::to_string(&validators).unwrap();
+        assert_eq!(
+            serde_json::from_str::<CacheValidators>(&json).unwrap(),
+            validators
+        );
+    }
+
+    #[test]
+    fn test_filter_version_names() {
+        let mut releases = releases();
+        releases.VERSIONS[0].pre_release = true;
+        releases.VERSIONS[1].end_of_life = true;
+        assert!(filter_version_names(&releases, VersionFilter::default()).is_empty());
+        let filter = VersionFilter {
+            include_prereleases: true,
+            include_eol: false,
+        };
+        assert_eq!(filter_version_names(&releases, filter), vec!["v5.1.2"]);
+        let filter = VersionFilter {
+            include_prereleases: true,
+            include_eol: true,
+        };
+        assert_eq!(
+            filter_version_names(&releases, filter),
+            vec!["v5.1.2", "v4.4.7"]
+        );
+    }
+
+    #[test]
+    fn test_resolve_version_specifier() {
+        let mut releases = releases();
+        releases.VERSIONS.insert(
+            0,
+            Version {
+                name: "v5.1.4".to_string(),
+                ..releases.VERSIONS[0].clone()
+            },
+        );
+        releases.VERSIONS.insert(
+            0,
+            Version {
+                name: "v5.2-beta1".to_string(),
+                pre_release: true,
+                ..releases.VERSIONS[0].clone()
+            },
+        );
+        let resolve = |spec| resolve_version_specifier(&releases, spec);
+        assert_eq!(resolve("latest"), Some("v5.1.4".to_string()));
+        assert_eq!(resolve("5"), Some("v5.1.4".to_string()));
+        assert_eq!(resolve("5.x"), Some("v5.1.4".to_string()));
+        assert_eq!(resolve("v5.1"), Some("v5.1.4".to_string()));
+        assert_eq!(resolve("release/v5.1"), Some("v5.1.4".to_string()));
+        assert_eq!(resolve("v5.1.2"), Some("v5.1.2".to_string()));
+        assert_eq!(resolve("v4.4"), Some("v4.4.7".to_string()));
+        assert_eq!(resolve("v5.2-beta1"), Some("v5.2-beta1".to_string()));
+        assert_eq!(resolve("v5.2"), None);
+        assert_eq!(resolve("foo"), None);
+    }
+
+    #[test]
+    fn test_releases_from_github() {
+        let github_releases: Vec<GitHubRelease> = serde_json::from_str(
+            r#"[
+                {"tag_name": "v5.4-beta1", "prerelease": true, "draft": false},
+                {"tag_name": "v5.3.1", "prerelease": false, "draft": false},
+                {"tag_name": "v5.3.2", "prerelease": false, "draft": true}
+            ]"#,
+        )
+        .unwrap();
+        let releases = releases_from_github(github_releases);
+        assert_eq!(releases.VERSIONS.len(), 2);
+        assert!(releases.VERSIONS[0].pre_release);
+        assert_eq!(
+            resolve_version_specifier(&releases, "latest"),
+            Some("v5.3.1".to_string())
+        );
+    }
+
+    #[test]
+    fn test_validate_targets_for_version() {
+        let releases = releases();
+        let targets = vec!["esp32".to_string(), "esp32p4".to_string()];
+        let errors = validate_targets_for_version(&releases, "v5.1.2", &targets).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].target, "esp32p4");
+        assert!(validate_targets_for_version(&releases, "v5.1", &["all".to_string()]).is_ok());
+        assert!(validate_targets_for_version(&releases, "v9.9", &targets).is_ok());
+    }
+
+    #[test]
+    fn test_get_release_notes_url() {
+        assert_eq!(
+            get_release_notes_url("v5.4"),
+            Some("https://github.com/espressif/esp-idf/releases/tag/v5.4".to_string())
+        );
+        assert!(get_release_notes_url("v5.4-beta1").is_some());
+        assert_eq!(get_release_notes_url("master"), None);
+        assert_eq!(get_release_notes_url("latest"), None);
+        assert_eq!(get_release_notes_url("release/v5.4"), None);
+    }
+
+    #[test]
+    fn test_parse_iso_date() {
+        assert_eq!(parse_iso_date("1970-01-01"), Some(0));
+        assert_eq!(parse_iso_date("2000-03-01"), Some(11017));
+        assert_eq!(parse_iso_date("2024-13-01"), None);
+        assert_eq!(parse_iso_date("not a date"), None);
+    }
+
+    #[test]
+    fn test_get_release_line() {
+        assert_eq!(get_release_line("v5.1.2"), "v5.1");
+        assert_eq!(get_release_line("v5.3-beta1"), "v5.3");
+        assert_eq!(get_release_line("release/v5.2"), "v5.2");
+        assert_eq!(get_release_line("latest"), "latest");
+    }
+
+    #[test]
+    fn test_get_supported_versions_on() {
+        let releases = releases();
+        let date = parse_iso_date("2024-09-01").unwrap();
+        let supported: Vec<String> = get_supported_versions_on(&releases, date)
+            .into_iter()
+            .map(|v| v.name)
+            .collect();
+        assert_eq!(supported, vec!["v5.1.2".to_string()]);
+    }
+
+    #[test]
+    fn test_get_near_eol_versions() {
+        let releases = releases();
+        let date = parse_iso_date("2024-07-01").unwrap();
+        assert_eq!(get_near_eol_versions(&releases, date, 30), vec!["v4.4.7"]);
+        assert!(get_near_eol_versions(&releases, date, 10).is_empty());
+    }
 }
@@ -0,0 +1,345 @@
+use anyhow::{anyhow, Context, Result};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::dry_run::{self, PlannedAction};
+use crate::telemetry::{self, LifecycleEvent};
+use crate::utils::now_timestamp;
+
+/// Name of the report file written next to `eim_idf.json` by `InstallReport::write_next_to_config`.
+pub const INSTALL_REPORT_FILE_NAME: &str = "eim_install_report.json";
+
+/// An ESP-IDF version set up by the installation.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct InstalledVersion {
+    pub version: String,
+    #[serde(rename = "idfPath")]
+    pub idf_path: String,
+    #[serde(rename = "toolsPath")]
+    pub tools_path: String,
+    #[serde(rename = "activationScript", skip_serializing_if = "Option::is_none")]
+    pub activation_script: Option<String>,
+}
+
+/// The error of a failed installation, together with the report telling how far it got.
+#[derive(Debug)]
+pub struct InstallError {
+    /// The finished report, already written next to `eim_idf.json` when its folder is set.
+    pub report: InstallReport,
+    pub error: anyhow::Error,
+}
+
+impl std::fmt::Display for InstallError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+impl std::error::Error for InstallError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.error.as_ref())
+    }
+}
+
+/// A timed phase of the installation, e.g. `prerequisites`, `clone` or `tools`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PhaseReport {
+    pub name: String,
+    #[serde(rename = "durationMs")]
+    pub duration_ms: u64,
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A step the installation did not run, with the reason.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SkippedStep {
+    pub name: String,
+    pub reason: String,
+}
+
+/// The outcome of an installation, meant as a CI artifact or to be attached to support tickets.
+///
+/// The install entry points (`install_from_bundle`, `reinstall_from_manifest`) build and return it.
+/// Frontends running the installation steps themselves create it when the installation starts,
+/// record the phases and versions as they go and call `finish` at the end.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct InstallReport {
+    #[serde(rename = "eimVersion")]
+    pub eim_version: String,
+    pub platform: String,
+    #[serde(rename = "startedAt")]
+    pub started_at: String,
+    #[serde(rename = "finishedAt", skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<String>,
+    #[serde(rename = "totalDurationMs")]
+    pub total_duration_ms: u64,
+    pub success: bool,
+    pub versions: Vec<InstalledVersion>,
+    pub phases: Vec<PhaseReport>,
+    #[serde(rename = "mirrorsUsed")]
+    pub mirrors_used: Vec<String>,
+    pub warnings: Vec<String>,
+    #[serde(rename = "skippedSteps")]
+    pub skipped_steps: Vec<SkippedStep>,
+    /// Whether the installation ran in dry-run mode, nothing was changed then.
+    #[serde(rename = "dryRun", default)]
+    pub dry_run: bool,
+    /// The changes a dry run would have made, see `dry_run::take_planned_actions`.
+    #[serde(
+        rename = "plannedActions",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub planned_actions: Vec<PlannedAction>,
+    #[serde(skip)]
+    started: Option<Instant>,
+}
+
+impl Default for InstallReport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InstallReport {
+    /// Starts a report, the total duration is measured from now.
+    pub fn new() -> Self {
+        let dry_run = dry_run::is_dry_run();
+        if !dry_run {
+            telemetry::emit(LifecycleEvent::InstallStarted);
+        }
+        InstallReport {
+            eim_version: env!("CARGO_PKG_VERSION").to_string(),
+            platform: format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH),
+            started_at: now_timestamp(),
+            finished_at: None,
+            total_duration_ms: 0,
+            success: false,
+            versions: vec![],
+            phases: vec![],
+            mirrors_used: vec![],
+            warnings: vec![],
+            skipped_steps: vec![],
+            dry_run,
+            planned_actions: vec![],
+            started: Some(Instant::now()),
+        }
+    }
+
+    /// Records a phase that took `duration`.
+    pub fn record_phase(&mut self, name: &str, duration: Duration, error: Option<String>) {
+        self.phases.push(PhaseReport {
+            name: name.to_string(),
+            duration_ms: duration.as_millis() as u64,
+            success: error.is_none(),
+            error,
+        });
+    }
+
+    /// Runs `phase`, records how long it took and whether it failed, and returns its result.
+    pub fn time_phase<T, E: std::fmt::Display>(
+        &mut self,
+        name: &str,
+        phase: impl FnOnce() -> Result<T, E>,
+    ) -> Result<T, E> {
+        let start = Instant::now();
+        let result = phase();
+        let error = result.as_ref().err().map(|e| e.to_string());
+        self.record_phase(name, start.elapsed(), error);
+        result
+    }
+
+    /// Records an installed version.
+    pub fn add_version(&mut self, version: InstalledVersion) {
+        self.versions.push(version);
+    }
+
+    /// Records a mirror something was downloaded from, each mirror is listed once.
+    pub fn add_mirror(&mut self, mirror: &str) {
+        if !self.mirrors_used.iter().any(|used| used == mirror) {
+            self.mirrors_used.push(mirror.to_string());
+        }
+    }
+
+    /// Records a warning, it is logged as well.
+    pub fn warn(&mut self, message: &str) {
+        warn!("{}", message);
+        self.warnings.push(message.to_string());
+    }
+
+    /// Records a step that was not run.
+    pub fn skip(&mut self, name: &str, reason: &str) {
+        self.skipped_steps.push(SkippedStep {
+            name: name.to_string(),
+            reason: reason.to_string(),
+        });
+    }
+
+    /// Completes the report with the end time and the overall outcome, which is reported to the
+    /// telemetry event sink. In dry-run mode the planned actions are collected instead.
+    ///
+    /// The installation only counts as successful when `success` is set and no phase failed.
+    pub fn finish(&mut self, success: bool) {
+        self.finished_at = Some(now_timestamp());
+        if let Some(started) = self.started {
+            self.total_duration_ms = started.elapsed().as_millis() as u64;
+        }
+        self.success = success && self.phases.iter().all(|phase| phase.success);
+        if self.dry_run {
+            self.planned_actions.extend(dry_run::take_planned_actions());
+            return;
+        }
+
+        let versions: Vec<String> = self.versions.iter().map(|v| v.version.clone()).collect();
+        let duration = Duration::from_millis(self.total_duration_ms);
+        telemetry::emit(if self.success {
+            LifecycleEvent::InstallSucceeded { versions, duration }
+        } else {
+            LifecycleEvent::InstallFailed {
+                versions,
+                duration,
+                failed_phase: self
+                    .phases
+                    .iter()
+                    .find(|phase| !phase.success)
+                    .map(|phase| phase.name.clone()),
+            }
+        });
+    }
+
+    /// Finishes the report with the outcome of the installation and writes it next to
+    /// `eim_idf.json` when `esp_idf_json_path` is set. A failure outside of the recorded phases is
+    /// added as a warning, so the report always tells why the installation failed.
+    ///
+    /// # Arguments
+    ///
+    /// * `outcome` - The result of the installation
+    /// * `esp_idf_json_path` - The folder of `eim_idf.json`, `esp_idf_json_path` of the `Settings`
+    ///
+    /// # Returns
+    ///
+    /// * `Result<InstallReport, InstallError>` - The finished report, or the error of the
+    ///   installation together with the report.
+    pub(crate) fn complete(
+        mut self,
+        outcome: Result<()>,
+        esp_idf_json_path: Option<&Path>,
+    ) -> Result<InstallReport, InstallError> {
+        if let Err(e) = &outcome {
+            if self.phases.iter().all(|phase| phase.success) {
+                self.warn(&e.to_string());
+            }
+        }
+        self.finish(outcome.is_ok());
+        if let Some(path) = esp_idf_json_path {
+            if let Err(e) = self.write_next_to_config(path) {
+                warn!("Failed to write the installation report: {}", e);
+            }
+        }
+        match outcome {
+            Ok(()) => Ok(self),
+            Err(error) => Err(InstallError {
+                report: self,
+                error,
+            }),
+        }
+    }
+
+    /// Renders the report as pretty-printed JSON.
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Saves the report to a file. In dry-run mode the write is only planned.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path where to save the report
+    pub fn to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        if dry_run::plan(
+            dry_run::ActionKind::WriteFile,
+            &format!(
+                "Write the installation report to {}",
+                path.as_ref().display()
+            ),
+            None,
+        ) {
+            return Ok(());
+        }
+        crate::ensure_path(path.as_ref().parent().unwrap().to_str().unwrap())?;
+        fs::write(path.as_ref(), self.to_json()?)
+            .with_context(|| anyhow!("writing report to {} failed", path.as_ref().display()))
+    }
+
+    /// Writes the report as `eim_install_report.json` in the folder holding `eim_idf.json`.
+    ///
+    /// # Arguments
+    ///
+    /// * `esp_idf_json_path` - The folder of `eim_idf.json`, `esp_idf_json_path` of the `Settings`
+    ///
+    /// # Returns
+    ///
+    /// * `Result<PathBuf>` - On success, returns the path of the written report.
+    pub fn write_next_to_config(&self, esp_idf_json_path: &Path) -> Result<PathBuf> {
+        let report_path = esp_idf_json_path.join(INSTALL_REPORT_FILE_NAME);
+        self.to_file(&report_path)?;
+        info!("Installation report written to {}", report_path.display());
+        Ok(report_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_install_report() {
+        let mut report = InstallReport::new();
+        let cloned: Result<(), String> = report.time_phase("clone", || Ok(()));
+        assert!(cloned.is_ok());
+        report.add_mirror("https://github.com");
+        report.add_mirror("https://github.com");
+        report.skip("drivers", "not on Windows");
+        report.finish(true);
+
+        assert!(report.success);
+        assert_eq!(report.mirrors_used.len(), 1);
+
+        let failed: Result<(), String> = report.time_phase("tools", || Err("offline".to_string()));
+        assert!(failed.is_err());
+        report.finish(true);
+        assert!(!report.success);
+
+        let parsed: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(parsed["phases"][1]["error"], "offline");
+        assert_eq!(parsed["skippedSteps"][0]["name"], "drivers");
+    }
+
+    #[test]
+    fn test_complete_writes_report() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut report = InstallReport::new();
+        report.record_phase("tools", Duration::from_millis(5), None);
+        let error = report
+            .complete(Err(anyhow!("no installation path")), Some(dir.path()))
+            .unwrap_err();
+        assert_eq!(error.to_string(), "no installation path");
+        assert!(!error.report.success);
+        assert_eq!(error.report.phases.len(), 1);
+
+        let written: InstallReport = serde_json::from_str(
+            &fs::read_to_string(dir.path().join(INSTALL_REPORT_FILE_NAME)).unwrap(),
+        )
+        .unwrap();
+        assert!(!written.success);
+        assert_eq!(written.warnings, vec!["no installation path".to_string()]);
+
+        let report = InstallReport::new().complete(Ok(()), None).unwrap();
+        assert!(report.success);
+    }
+}
@@ -1,29 +1,57 @@
 use decompress::{self, DecompressError, Decompression, ExtractOptsBuilder};
+use events::InstallPhase;
 use git2::{FetchOptions, ObjectType, RemoteCallbacks, Repository, SubmoduleUpdateOptions};
 use log::{error, info, trace, warn};
-use reqwest::Client;
+use messages::MessageId;
+use progress::{ProgressDetail, ProgressReporter, ProgressUpdate};
 #[cfg(feature = "userustpython")]
 use rustpython_vm::literal::char;
 use sha2::{Digest, Sha256};
 use tera::{Context, Tera};
 use utils::find_directories_by_name;
 
+pub mod cache;
 pub mod command_executor;
+pub mod container;
+pub mod download;
+pub mod drivers;
+pub mod dry_run;
+pub mod environment;
+pub mod events;
+pub mod http_client;
+pub mod ide_integration;
 pub mod idf_config;
 pub mod idf_tools;
 pub mod idf_versions;
+pub mod install_report;
+pub mod manifest;
+pub mod messages;
+pub mod migration;
+pub mod mirrors;
+pub mod offline_bundle;
+pub mod platform;
+pub mod progress;
+pub mod project;
 pub mod python_utils;
+pub mod sbom;
+pub mod self_update;
 pub mod settings;
 pub mod system_dependencies;
+pub mod telemetry;
 pub mod utils;
 pub mod version_manager;
+pub mod win_path;
+#[cfg(windows)]
+pub mod win_tools;
 use std::fs::{set_permissions, File};
 use std::{
     env,
     fs::{self},
     io::{self, Read, Write},
     path::{Path, PathBuf},
-    sync::mpsc::Sender,
+    sync::atomic::{AtomicBool, Ordering},
+    sync::Arc,
+    sync::RwLock,
 };
 
 /// Creates an executable shell script with the given content and file path.
@@ -95,39 +123,150 @@ fn format_powershell_env_pairs(pairs: &Vec<(String, String)>) -> String {
     format!("$env_var_pairs = @{{\n{}\n}}", formatted_pairs.join("\n"))
 }
 
-/// Creates an activation shell script for the ESP-IDF toolchain.
-///
-/// # Parameters
-///
-/// * `file_path`: A string representing the path where the activation script should be created.
-/// * `idf_path`: A string representing the path to the ESP-IDF installation.
-/// * `idf_tools_path`: A string representing the path to the ESP-IDF tools installation.
-/// * `idf_version`: A string representing the version of the ESP-IDF toolchain.
-/// * `export_paths`: A vector of strings representing additional paths to be added to the shell's PATH environment variable.
-///
-/// # Return
-///
-/// * `Result<(), String>`: On success, returns `Ok(())`. On error, returns `Err(String)` containing the error message.
-pub fn create_activation_shell_script(
+/// A shell an activation script can be generated for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActivationShell {
+    Bash,
+    Zsh,
+    Fish,
+    Tcsh,
+    /// PowerShell Core on Linux and macOS, Windows uses the profile created by `create_powershell_profile`.
+    PowerShell,
+    Nushell,
+}
+
+impl ActivationShell {
+    /// All the shells activation scripts can be generated for.
+    pub const ALL: [ActivationShell; 6] = [
+        ActivationShell::Bash,
+        ActivationShell::Zsh,
+        ActivationShell::Fish,
+        ActivationShell::Tcsh,
+        ActivationShell::PowerShell,
+        ActivationShell::Nushell,
+    ];
+
+    /// Shells without a POSIX login shell counterpart, their scripts are always generated.
+    const ALWAYS_GENERATED: [ActivationShell; 2] =
+        [ActivationShell::PowerShell, ActivationShell::Nushell];
+
+    /// Parses the name of a shell (or the path of its executable), e.g. `fish` or `/bin/zsh`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.rsplit('/').next().unwrap_or(name);
+        match name {
+            "bash" | "sh" => Some(ActivationShell::Bash),
+            "zsh" => Some(ActivationShell::Zsh),
+            "fish" => Some(ActivationShell::Fish),
+            "tcsh" | "csh" => Some(ActivationShell::Tcsh),
+            "pwsh" | "powershell" => Some(ActivationShell::PowerShell),
+            "nu" | "nushell" => Some(ActivationShell::Nushell),
+            _ => None,
+        }
+    }
+
+    /// The extension of the activation scripts for this shell.
+    pub fn script_extension(&self) -> &'static str {
+        match self {
+            ActivationShell::Bash => "sh",
+            ActivationShell::Zsh => "zsh",
+            ActivationShell::Fish => "fish",
+            ActivationShell::Tcsh => "csh",
+            ActivationShell::PowerShell => "ps1",
+            ActivationShell::Nushell => "nu",
+        }
+    }
+
+    fn template(&self) -> &'static str {
+        match self {
+            ActivationShell::Bash => include_str!("./../bash_scripts/activate_idf_template.sh"),
+            ActivationShell::Zsh => include_str!("./../bash_scripts/activate_idf_template.zsh"),
+            ActivationShell::Fish => include_str!("./../bash_scripts/activate_idf_template.fish"),
+            ActivationShell::Tcsh => include_str!("./../bash_scripts/activate_idf_template.csh"),
+            ActivationShell::PowerShell => {
+                include_str!("./../powershell_scripts/activate_idf_template.ps1")
+            }
+            ActivationShell::Nushell => include_str!("./../bash_scripts/activate_idf_template.nu"),
+        }
+    }
+}
+
+/// Returns the file name of the activation script of an ESP-IDF version for the given shell.
+pub fn activation_script_name(idf_version: &str, shell: ActivationShell) -> String {
+    format!("activate_idf_{}.{}", idf_version, shell.script_extension())
+}
+
+static ACTIVATION_SHELLS: RwLock<Option<Vec<String>>> = RwLock::new(None);
+
+/// Sets the shells activation scripts are generated for, in addition to the bash one.
+/// Passing `None` generates a script for the login shell, see `detect_login_shell`.
+pub fn set_activation_shells(shells: Option<Vec<String>>) {
+    *ACTIVATION_SHELLS.write().unwrap() = shells;
+}
+
+static SHELL_INTEGRATION: AtomicBool = AtomicBool::new(false);
+
+/// Enables the shell integration of the generated activation scripts: the prompt is prefixed
+/// with the active ESP-IDF version and the idf.py completions are loaded where available.
+pub fn set_shell_integration(enabled: bool) {
+    SHELL_INTEGRATION.store(enabled, Ordering::Relaxed);
+}
+
+/// Detects the login shell of the user from the `SHELL` environment variable.
+pub fn detect_login_shell() -> Option<ActivationShell> {
+    std::env::var("SHELL")
+        .ok()
+        .and_then(|shell| ActivationShell::from_name(&shell))
+}
+
+/// Returns the shells to generate activation scripts for. The bash script always comes first,
+/// as it is the one registered in the IDE configuration, the PowerShell and Nushell ones last.
+fn selected_activation_shells() -> Vec<ActivationShell> {
+    let mut shells = vec![ActivationShell::Bash];
+    let requested = match ACTIVATION_SHELLS.read().unwrap().as_ref() {
+        Some(names) => names
+            .iter()
+            .filter_map(|name| {
+                let shell = ActivationShell::from_name(name);
+                if shell.is_none() {
+                    warn!("Unsupported activation shell {}, skipping", name);
+                }
+                shell
+            })
+            .collect(),
+        None => detect_login_shell().into_iter().collect::<Vec<_>>(),
+    };
+    for shell in requested
+        .into_iter()
+        .chain(ActivationShell::ALWAYS_GENERATED)
+    {
+        if !shells.contains(&shell) {
+            shells.push(shell);
+        }
+    }
+    shells
+}
+
+/// Renders the activation script of an ESP-IDF version for one shell into `file_path`.
+fn render_activation_script(
+    shell: ActivationShell,
     file_path: &str,
     idf_path: &str,
     idf_tools_path: &str,
     idf_version: &str,
-    export_paths: Vec<String>,
-    env_var_pairs: Vec<(String, String)>,
-) -> Result<(), String> {
-    ensure_path(file_path).map_err(|e| e.to_string())?;
+    export_paths: &[String],
+    env_var_pairs: &Vec<(String, String)>,
+) -> Result<PathBuf, String> {
     let mut filename = PathBuf::from(file_path);
-    filename.push(format!("activate_idf_{}.sh", idf_version));
-    let template = include_str!("./../bash_scripts/activate_idf_template.sh");
+    filename.push(activation_script_name(idf_version, shell));
     let mut tera = Tera::default();
-    if let Err(e) = tera.add_raw_template("activate_idf_template", template) {
+    if let Err(e) = tera.add_raw_template("activate_idf_template", shell.template()) {
         error!("Failed to add template: {}", e);
         return Err(e.to_string());
     }
     let mut context = Context::new();
-    let env_var_pairs_str = format_bash_env_pairs(&env_var_pairs);
+    let env_var_pairs_str = format_bash_env_pairs(env_var_pairs);
     context.insert("env_var_pairs", &env_var_pairs_str);
+    context.insert("env_vars", env_var_pairs);
     context.insert("idf_path", &idf_path);
     context.insert(
         "idf_path_escaped",
@@ -141,6 +280,10 @@ pub fn create_activation_shell_script(
     );
     context.insert("idf_version", &idf_version);
     context.insert("addition_to_path", &export_paths.join(":"));
+    context.insert(
+        "shell_integration",
+        &SHELL_INTEGRATION.load(Ordering::Relaxed),
+    );
     let rendered = match tera.render("activate_idf_template", &context) {
         Err(e) => {
             error!("Failed to render template: {}", e);
@@ -150,9 +293,89 @@ pub fn create_activation_shell_script(
     };
 
     create_executable_shell_script(filename.to_str().unwrap(), &rendered)?;
+    Ok(filename)
+}
+
+/// Creates an activation shell script for the ESP-IDF toolchain.
+///
+/// # Parameters
+///
+/// * `file_path`: A string representing the path where the activation script should be created.
+/// * `idf_path`: A string representing the path to the ESP-IDF installation.
+/// * `idf_tools_path`: A string representing the path to the ESP-IDF tools installation.
+/// * `idf_version`: A string representing the version of the ESP-IDF toolchain.
+/// * `export_paths`: A vector of strings representing additional paths to be added to the shell's PATH environment variable.
+///
+/// # Return
+///
+/// * `Result<(), String>`: On success, returns `Ok(())`. On error, returns `Err(String)` containing the error message.
+pub fn create_activation_shell_script(
+    file_path: &str,
+    idf_path: &str,
+    idf_tools_path: &str,
+    idf_version: &str,
+    export_paths: Vec<String>,
+    env_var_pairs: Vec<(String, String)>,
+) -> Result<(), String> {
+    ensure_path(file_path).map_err(|e| e.to_string())?;
+    render_activation_script(
+        ActivationShell::Bash,
+        file_path,
+        idf_path,
+        idf_tools_path,
+        idf_version,
+        &export_paths,
+        &env_var_pairs,
+    )?;
     Ok(())
 }
 
+/// Creates the activation scripts of an ESP-IDF version for bash, the selected shells
+/// (see `set_activation_shells`), PowerShell Core and Nushell.
+///
+/// The parameters are the same as for `create_activation_shell_script`.
+///
+/// # Return
+///
+/// * `Result<Vec<PathBuf>, String>`: The generated scripts, the bash one first. Failing to generate
+///   the bash script is an error, failures for the other shells are only logged.
+pub fn create_activation_scripts(
+    file_path: &str,
+    idf_path: &str,
+    idf_tools_path: &str,
+    idf_version: &str,
+    export_paths: Vec<String>,
+    env_var_pairs: Vec<(String, String)>,
+) -> Result<Vec<PathBuf>, String> {
+    ensure_path(file_path).map_err(|e| e.to_string())?;
+    let mut scripts = vec![];
+    for shell in selected_activation_shells() {
+        match render_activation_script(
+            shell,
+            file_path,
+            idf_path,
+            idf_tools_path,
+            idf_version,
+            &export_paths,
+            &env_var_pairs,
+        ) {
+            Ok(script) => scripts.push(script),
+            Err(e) if shell == ActivationShell::Bash => return Err(e),
+            Err(e) => warn!("Failed to create the {:?} activation script: {}", shell, e),
+        }
+    }
+    Ok(scripts)
+}
+
+/// Lists the activation scripts of an ESP-IDF version present in `file_path`, for all supported shells.
+pub fn find_activation_scripts(file_path: &Path, idf_version: &str) -> Vec<PathBuf> {
+    ActivationShell::ALL
+        .iter()
+        .map(|shell| file_path.join(activation_script_name(idf_version, *shell)))
+        .filter(|script| script.exists())
+        .collect()
+}
+
 // TODO: unify the replace_unescaped_spaces functions
 pub fn replace_unescaped_spaces_posix(input: &str) -> String {
     let mut result = String::with_capacity(input.len());
@@ -258,7 +481,7 @@ fn create_powershell_profile(
     }
     ensure_path(profile_path).expect("Unable to create directory");
     let mut context = Context::new();
-    println!("idf_path: {}", replace_unescaped_spaces_win(idf_path));
+    log::debug!("idf_path: {}", replace_unescaped_spaces_win(idf_path));
     context.insert("idf_path", &replace_unescaped_spaces_win(idf_path));
     context.insert("idf_version", &idf_version);
     context.insert(
@@ -271,6 +494,10 @@ fn create_powershell_profile(
         &replace_unescaped_spaces_win(idf_tools_path),
     );
     context.insert("add_paths_extras", &export_paths.join(";"));
+    context.insert(
+        "shell_integration",
+        &SHELL_INTEGRATION.load(Ordering::Relaxed),
+    );
     let rendered = match tera.render("powershell_profile", &context) {
         Err(e) => {
             error!("Failed to render template: {}", e);
@@ -287,7 +514,9 @@ fn create_powershell_profile(
     Ok(filename.display().to_string())
 }
 
-/// Creates a desktop shortcut for the IDF tools using PowerShell on Windows.
+/// Creates a desktop shortcut for the IDF tools on Windows.
+///
+/// The shortcut is created through COM, the PowerShell script is only used when that fails.
 ///
 /// # Parameters
 ///
@@ -296,7 +525,7 @@ fn create_powershell_profile(
 ///
 /// # Return Value
 ///
-/// * `Result<String, std::io::Error>` - On success, returns a string indicating the output of the PowerShell script.
+/// * `Result<String, std::io::Error>` - On success, returns a string describing the created shortcut.
 ///   On error, returns an `std::io::Error` indicating the cause of the error.
 pub fn create_desktop_shortcut(
     profile_path: &str,
@@ -322,12 +551,30 @@ pub fn create_desktop_shortcut(
                     return Err(err);
                 }
             };
+            if platform::platform_info().is_unattended() {
+                warn!("Skipping desktop shortcut creation in container/CI environment.");
+                return Ok(filename);
+            }
             let icon = include_bytes!("../assets/eim.ico");
             let mut home = dirs::home_dir().unwrap();
             home.push("Icons");
             let _ = ensure_path(home.to_str().unwrap());
             home.push("eim.ico");
             fs::write(&home, icon).expect("Unable to write file");
+            let desktop = dirs::home_dir().unwrap().join("Desktop");
+            let link_path = desktop.join(format!("IDF_{}_Powershell.lnk", idf_version));
+            match create_native_powershell_shortcut(&link_path, &filename, &desktop, &home) {
+                Ok(()) => {
+                    return Ok(format!(
+                        "Shortcut created on the desktop: {}",
+                        link_path.display()
+                    ))
+                }
+                Err(e) => warn!(
+                    "Failed to create the desktop shortcut natively, falling back to PowerShell: {}",
+                    e
+                ),
+            }
             let powershell_script_template =
                 include_str!("./../powershell_scripts/create_desktop_shortcut_template.ps1");
             // Create a new Tera instance
@@ -373,6 +620,144 @@ pub fn create_desktop_shortcut(
     }
 }
 
+/// Returns the name of the Start Menu folder holding the shortcuts of an ESP-IDF version.
+pub fn start_menu_folder_name(idf_version: &str) -> String {
+    format!("ESP-IDF {}", idf_version.trim_start_matches('v'))
+}
+
+/// Returns the path of the Start Menu folder of an ESP-IDF version, for the current user.
+fn start_menu_folder(idf_version: &str) -> Option<PathBuf> {
+    dirs::data_dir().map(|d| {
+        d.join("Microsoft")
+            .join("Windows")
+            .join("Start Menu")
+            .join("Programs")
+            .join(start_menu_folder_name(idf_version))
+    })
+}
+
+/// Creates a shortcut starting a PowerShell activated by the given profile through COM, without running any script.
+///
+/// # Parameters
+///
+/// * `link_path` - Where the shortcut is written.
+/// * `profile_filename` - A string representing the path of the PowerShell profile activating the version.
+/// * `working_dir` - The directory the PowerShell starts in.
+/// * `icon` - The icon file displayed for the shortcut.
+#[cfg(windows)]
+fn create_native_powershell_shortcut(
+    link_path: &Path,
+    profile_filename: &str,
+    working_dir: &Path,
+    icon: &Path,
+) -> Result<(), std::io::Error> {
+    let arguments = format!(
+        "-NoExit -ExecutionPolicy Bypass -NoProfile -Command \"& {{. '{}'}}\"",
+        profile_filename
+    );
+    win_tools::create_shortcut(link_path, "powershell.exe", &arguments, working_dir, icon)
+}
+
+#[cfg(not(windows))]
+fn create_native_powershell_shortcut(
+    _link_path: &Path,
+    _profile_filename: &str,
+    _working_dir: &Path,
+    _icon: &Path,
+) -> Result<(), std::io::Error> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "Shortcuts are only supported on Windows",
+    ))
+}
+
+/// Creates a Start Menu folder "ESP-IDF x.y" with a shortcut to a PowerShell activated for the version, on Windows.
+///
+/// # Parameters
+///
+/// * `profile_filename` - A string representing the path of the PowerShell profile activating the version.
+/// * `idf_version` - A string representing the ESP-IDF version.
+///
+/// # Return Value
+///
+/// * `Result<String, std::io::Error>` - On success, returns a string indicating the output of the PowerShell script.
+///   On error, returns an `std::io::Error` indicating the cause of the error.
+pub fn create_start_menu_shortcut(
+    profile_filename: &str,
+    idf_version: &str,
+) -> Result<String, std::io::Error> {
+    if std::env::consts::OS != "windows" {
+        warn!("Creating Start Menu shortcuts is only supported on Windows.");
+        return Ok("Unimplemented on this platform.".to_string());
+    }
+    if platform::platform_info().is_unattended() {
+        warn!("Skipping Start Menu shortcut creation in container/CI environment.");
+        return Ok(String::new());
+    }
+    let icon = include_bytes!("../assets/eim.ico");
+    let mut home = dirs::home_dir().unwrap();
+    home.push("Icons");
+    let _ = ensure_path(home.to_str().unwrap());
+    home.push("eim.ico");
+    fs::write(&home, icon)?;
+    if let Some(folder) = start_menu_folder(idf_version) {
+        let link_path = folder.join(format!("ESP-IDF {} PowerShell.lnk", idf_version));
+        let created = fs::create_dir_all(&folder).and_then(|_| {
+            create_native_powershell_shortcut(
+                &link_path,
+                profile_filename,
+                &dirs::home_dir().unwrap(),
+                &home,
+            )
+        });
+        match created {
+            Ok(()) => {
+                return Ok(format!(
+                    "Shortcut created in the Start Menu: {}",
+                    link_path.display()
+                ))
+            }
+            Err(e) => warn!(
+                "Failed to create the Start Menu shortcut natively, falling back to PowerShell: {}",
+                e
+            ),
+        }
+    }
+    let template = include_str!("./../powershell_scripts/create_start_menu_shortcut_template.ps1");
+    let mut tera = Tera::default();
+    if let Err(e) = tera.add_raw_template("powershell_script", template) {
+        error!("Failed to add template: {}", e);
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            "Failed to add template",
+        ));
+    }
+    let mut context = Context::new();
+    context.insert("custom_profile_filename", &profile_filename);
+    context.insert("name", &idf_version);
+    context.insert("folder_name", &start_menu_folder_name(idf_version));
+    let rendered = tera.render("powershell_script", &context).map_err(|e| {
+        error!("Failed to render template: {}", e);
+        std::io::Error::new(std::io::ErrorKind::Other, "Failed to render template")
+    })?;
+    run_powershell_script(&rendered)
+}
+
+/// Removes the Start Menu folder of an ESP-IDF version with all its shortcuts, if it exists.
+///
+/// # Parameters
+///
+/// * `idf_version` - A string representing the ESP-IDF version.
+pub fn remove_start_menu_shortcut(idf_version: &str) -> Result<(), std::io::Error> {
+    if std::env::consts::OS != "windows" {
+        return Ok(());
+    }
+    match start_menu_folder(idf_version) {
+        Some(folder) if folder.exists() => fs::remove_dir_all(folder),
+        _ => Ok(()),
+    }
+}
+
 /// Retrieves the path to the local data directory for storing logs.
 ///
 /// This function uses the `dirs` crate to find the appropriate directory for storing logs.
@@ -414,6 +799,23 @@ pub fn verify_file_checksum(expected_checksum: &str, file_path: &str) -> Result<
         return Ok(false);
     }
 
+    let computed_checksum = compute_file_checksum(file_path)?;
+
+    // Compare the computed checksum with the expected checksum
+    Ok(computed_checksum == expected_checksum)
+}
+
+/// Computes the SHA256 checksum of a file.
+///
+/// # Arguments
+///
+/// * `file_path` - A string representing the path to the file.
+///
+/// # Returns
+///
+/// * `Ok(String)` - The checksum as a lowercase hexadecimal string.
+/// * `Err(io::Error)` if an error occurs while opening or reading the file.
+pub fn compute_file_checksum(file_path: &str) -> Result<String, io::Error> {
     let mut file = File::open(file_path)?;
 
     let mut hasher = Sha256::new();
@@ -431,10 +833,7 @@ pub fn verify_file_checksum(expected_checksum: &str, file_path: &str) -> Result<
     let result = hasher.finalize();
 
     // Convert the hash to a hexadecimal string
-    let computed_checksum = format!("{:x}", result);
-
-    // Compare the computed checksum with the expected checksum
-    Ok(computed_checksum == expected_checksum)
+    Ok(format!("{:x}", result))
 }
 
 /// Sets up the environment variables required for the ESP-IDF build system.
@@ -552,8 +951,36 @@ fn get_openocd_scripts_folder(idf_tools_path: &PathBuf) -> Result<String, std::i
     Ok(result[0].clone())
 }
 
+/// A token used to cancel long running operations: downloads, git clones and submodule updates,
+/// archive extraction, external commands and the creation of the python environment.
+///
+/// Clones share the same state, so the token can be handed to the operations while the
+/// frontend keeps a clone to cancel them. The `_cancellable` operations remove the partial
+/// files, clones and environments they created when cancelled.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests the cancellation of all operations using this token.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Progress of a single download, the same progress is published as `InstallEvent`s of the
+/// `Download` phase to the subscribers of `events`.
 pub enum DownloadProgress {
     Progress(u64, u64), // (downloaded, total)
+    /// The speed and remaining time, sent about twice a second and once more at the end.
+    Transfer(download::TransferStats),
     Complete,
     Error(String),
 }
@@ -561,23 +988,69 @@ pub enum DownloadProgress {
 pub async fn download_file(
     url: &str,
     destination_path: &str,
-    progress_sender: Sender<DownloadProgress>,
+    progress_sender: impl ProgressReporter,
 ) -> Result<(), std::io::Error> {
-    // Create a new HTTP client
-    let client = Client::new();
+    download_file_cancellable(
+        url,
+        destination_path,
+        progress_sender,
+        CancellationToken::new(),
+    )
+    .await
+}
+
+/// Downloads a file like `download_file`, stopping with an error of kind
+/// `std::io::ErrorKind::Interrupted` once the token is cancelled.
+pub async fn download_file_cancellable(
+    url: &str,
+    destination_path: &str,
+    progress_sender: impl ProgressReporter,
+    cancellation: CancellationToken,
+) -> Result<(), std::io::Error> {
+    if dry_run::plan(
+        dry_run::ActionKind::Download,
+        &format!("Download {} to {}", url, destination_path),
+        None,
+    ) {
+        progress::report(
+            &progress_sender,
+            &ProgressUpdate::new(InstallPhase::Download, url).detail(ProgressDetail::Finished),
+        );
+        return Ok(());
+    }
+    let update = || ProgressUpdate::new(InstallPhase::Download, url);
+    progress::report(&progress_sender, &update().detail(ProgressDetail::Started));
+    let result = download_to_file(url, destination_path, &progress_sender, &cancellation).await;
+    if let Err(e) = &result {
+        progress::report(
+            &progress_sender,
+            &update().detail(ProgressDetail::Failed(e.to_string())),
+        );
+    }
+    result
+}
+
+/// Does the work of `download_file_cancellable`, reporting the progress up to the completion.
+/// Failures are reported by the caller.
+async fn download_to_file(
+    url: &str,
+    destination_path: &str,
+    progress_sender: &dyn ProgressReporter,
+    cancellation: &CancellationToken,
+) -> Result<(), std::io::Error> {
+    let update = || ProgressUpdate::new(InstallPhase::Download, url);
+    // The shared client reuses the connections of the previous downloads
+    let client =
+        http_client::client().map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
 
     // Send a GET request to the specified URL
-    let mut response = client
-        .get(url)
+    let mut response = http_client::authorize(client.get(url), url)
         .send()
         .await
         .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
 
     // Get the total size of the file being downloaded
     let total_size = response.content_length().ok_or_else(|| {
-        let _ = progress_sender.send(DownloadProgress::Error(
-            "Failed to get content length".into(),
-        ));
         std::io::Error::new(std::io::ErrorKind::Other, "Failed to get content length")
     })?;
     log::debug!("Downloading {} to {}", url, destination_path);
@@ -595,6 +1068,8 @@ pub async fn download_file(
 
     // Initialize the amount downloaded
     let mut downloaded: u64 = 0;
+    let mut meter = download::TransferMeter::new(filename, total_size);
+    let mut reported_percent = 0;
 
     // Download the file in chunks
     while let Some(chunk) = response
@@ -602,21 +1077,52 @@ pub async fn download_file(
         .await
         .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?
     {
+        if cancellation.is_cancelled() {
+            // do not leave a truncated archive behind, it would be taken for a complete one
+            drop(file);
+            let _ = fs::remove_file(Path::new(&destination_path).join(Path::new(filename)));
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Interrupted,
+                messages::message(MessageId::DownloadCancelled, &[url]),
+            ));
+        }
         // Update the amount downloaded
         downloaded += chunk.len() as u64;
 
         // Write the chunk to the file
         file.write_all(&chunk)?;
 
-        // Call the progress callback function
-        if let Err(e) = progress_sender.send(DownloadProgress::Progress(downloaded, total_size)) {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                format!("Failed to send progress: {}", e),
-            ));
+        // only report whole percents, the chunks are far smaller than that
+        let percent = events::percent(downloaded, total_size);
+        if percent != reported_percent {
+            reported_percent = percent;
+            progress::report(
+                progress_sender,
+                &update().percent(percent).detail(ProgressDetail::Bytes {
+                    downloaded,
+                    total: total_size,
+                }),
+            );
+        }
+        if let Some(stats) = meter.update(downloaded) {
+            progress::report(
+                progress_sender,
+                &update()
+                    .percent(percent)
+                    .detail(ProgressDetail::Transfer(stats)),
+            );
         }
     }
-    let _ = progress_sender.send(DownloadProgress::Complete);
+    progress::report(
+        progress_sender,
+        &update()
+            .percent(100)
+            .detail(ProgressDetail::Transfer(meter.stats(downloaded))),
+    );
+    progress::report(
+        progress_sender,
+        &update().percent(100).detail(ProgressDetail::Finished),
+    );
 
     // Return Ok(()) if the download was successful
     Ok(())
@@ -654,8 +1160,58 @@ pub fn decompress_archive(
     archive_path: &str,
     destination_path: &str,
 ) -> Result<Decompression, DecompressError> {
+    if dry_run::plan(
+        dry_run::ActionKind::Extract,
+        &format!("Extract {} to {}", archive_path, destination_path),
+        None,
+    ) {
+        return Ok(Decompression {
+            id: "dry-run",
+            files: vec![],
+        });
+    }
+    let update = || ProgressUpdate::new(InstallPhase::Extract, archive_path);
+    progress::report(&(), &update().detail(ProgressDetail::Started));
     let opts = &ExtractOptsBuilder::default().strip(0).build().unwrap();
-    decompress::decompress(archive_path, destination_path, opts)
+    let result = decompress::decompress(archive_path, destination_path, opts);
+    progress::report(
+        &(),
+        &update().detail(match &result {
+            Ok(_) => ProgressDetail::Finished,
+            Err(e) => ProgressDetail::Failed(e.to_string()),
+        }),
+    );
+    result
+}
+
+/// Decompresses an archive like `decompress_archive`, unless the token is cancelled.
+///
+/// The extraction itself can not be interrupted, so when the token is cancelled meanwhile, the
+/// extracted files are removed once it finishes and an error of kind
+/// `std::io::ErrorKind::Interrupted` is returned.
+pub fn decompress_archive_cancellable(
+    archive_path: &str,
+    destination_path: &str,
+    cancellation: &CancellationToken,
+) -> Result<Decompression, DecompressError> {
+    let cancelled = || {
+        DecompressError::IO(io::Error::new(
+            io::ErrorKind::Interrupted,
+            messages::message(MessageId::ExtractionCancelled, &[archive_path]),
+        ))
+    };
+    if cancellation.is_cancelled() {
+        return Err(cancelled());
+    }
+    let existed = Path::new(destination_path).exists();
+    let result = decompress_archive(archive_path, destination_path);
+    if cancellation.is_cancelled() {
+        if !existed {
+            let _ = utils::remove_directory_all(destination_path);
+        }
+        return Err(cancelled());
+    }
+    result
 }
 
 /// Ensures that a directory exists at the specified path.
@@ -712,6 +1268,9 @@ pub fn add_path_to_path(directory_path: &str) {
 }
 
 /// Messages that can be sent to update the progress bar.
+///
+/// The same progress is published as `InstallEvent`s of the `Clone` and `Submodules` phases to the
+/// subscribers of `events`.
 pub enum ProgressMessage {
     /// Update the progress bar with the given value.
     Update(u64),
@@ -719,6 +1278,45 @@ pub enum ProgressMessage {
     Finish,
 }
 
+/// Reports the transfer progress of a clone to the progress bar and to the event subscribers.
+fn report_transfer_progress(
+    tx: &dyn ProgressReporter,
+    phase: InstallPhase,
+    url: &str,
+    stats: &git2::Progress,
+) {
+    let received = stats.received_objects() as u64;
+    let total = stats.total_objects() as u64;
+    progress::report(
+        tx,
+        &ProgressUpdate::new(phase, url)
+            .percent(events::percent(received, total))
+            .detail(ProgressDetail::Objects { received, total }),
+    );
+}
+
+/// Answers the credential requests of git with the credentials of the mirror, see
+/// `http_client::set_mirror_credentials`. Every URL is answered once, so wrong credentials fail
+/// the operation instead of being retried forever.
+fn add_mirror_credentials(callbacks: &mut RemoteCallbacks<'_>) {
+    let mut attempted = std::collections::HashSet::new();
+    callbacks.credentials(move |url, _username_from_url, _allowed_types| {
+        let credentials = http_client::credentials_for(url);
+        match credentials
+            .as_ref()
+            .and_then(|credentials| credentials.git_user_password())
+        {
+            Some((username, password)) if attempted.insert(url.to_string()) => {
+                git2::Cred::userpass_plaintext(username, password)
+            }
+            _ => Err(git2::Error::from_str(&format!(
+                "Authentication to {} failed",
+                url
+            ))),
+        }
+    });
+}
+
 /// Performs a shallow clone of a Git repository.
 ///
 /// # Arguments
@@ -739,8 +1337,9 @@ fn shallow_clone(
     path: &str,
     branch: Option<&str>,
     tag: Option<&str>,
-    tx: std::sync::mpsc::Sender<ProgressMessage>,
+    tx: &dyn ProgressReporter,
     recurse_submodules: bool,
+    cancellation: &CancellationToken,
 ) -> Result<Repository, git2::Error> {
     // Initialize fetch options with depth 1 for shallow cloning
     let mut fo = FetchOptions::new();
@@ -748,13 +1347,12 @@ fn shallow_clone(
         fo.depth(1);
     }
 
-    // Set up remote callbacks for progress reporting
+    // Set up remote callbacks for progress reporting, returning false aborts the transfer
     let mut callbacks = RemoteCallbacks::new();
+    add_mirror_credentials(&mut callbacks);
     callbacks.transfer_progress(|stats| {
-        let val =
-            ((stats.received_objects() as f64) / (stats.total_objects() as f64) * 100.0) as u64;
-        tx.send(ProgressMessage::Update(val)).unwrap();
-        true
+        report_transfer_progress(tx, InstallPhase::Clone, url, &stats);
+        !cancellation.is_cancelled()
     });
     fo.remote_callbacks(callbacks);
 
@@ -794,29 +1392,127 @@ fn shallow_clone(
     if recurse_submodules {
         let mut sfo = FetchOptions::new();
         let mut callbacks = RemoteCallbacks::new();
+        add_mirror_credentials(&mut callbacks);
         info!("Fetching submodules");
         callbacks.transfer_progress(|stats| {
-            let val =
-                ((stats.received_objects() as f64) / (stats.total_objects() as f64) * 100.0) as u64;
-            tx.send(ProgressMessage::Update(val)).unwrap();
-            true
+            report_transfer_progress(tx, InstallPhase::Submodules, url, &stats);
+            !cancellation.is_cancelled()
         });
         sfo.remote_callbacks(callbacks);
-        tx.send(ProgressMessage::Finish).unwrap();
-        update_submodules(&repo, sfo, tx.clone())?;
+        progress::report(
+            tx,
+            &ProgressUpdate::new(InstallPhase::Clone, url).detail(ProgressDetail::Finished),
+        );
+        update_submodules(&repo, sfo, tx, cancellation)?;
         info!("Finished fetching submodules");
     }
     // Return the opened repository
     Ok(repo)
 }
 
+/// Brings a repository left behind by an earlier clone, complete or not, to a tag or branch.
+///
+/// Only the requested tag or branch is fetched from `url`, without changing the remotes of the
+/// repository. Files missing from the working tree, e.g. of a clone interrupted during the
+/// checkout, are restored, while local modifications fail the checkout instead of being
+/// overwritten.
+///
+/// # Arguments
+///
+/// * `repo` - The existing repository.
+/// * `url` - A string representing the URL of the Git repository to fetch from.
+/// * `branch` - An optional string representing the branch to checkout, fetched shallow like by `shallow_clone`.
+/// * `tag` - An optional string representing the tag to checkout.
+/// * `tx` - The reporter receiving the fetch progress.
+///
+/// # Returns
+///
+/// * `Ok(Repository)` if the requested tag or branch is checked out.
+/// * `Err(git2::Error)` if an error occurs during the fetch or the checkout.
+fn update_existing_clone(
+    repo: Repository,
+    url: &str,
+    branch: Option<&str>,
+    tag: Option<&str>,
+    tx: &dyn ProgressReporter,
+    recurse_submodules: bool,
+    cancellation: &CancellationToken,
+) -> Result<Repository, git2::Error> {
+    info!(
+        "Updating the existing repository in {}",
+        repo.workdir().unwrap_or(repo.path()).display()
+    );
+    let mut fo = FetchOptions::new();
+    if tag.is_none() {
+        fo.depth(1);
+    }
+    let mut callbacks = RemoteCallbacks::new();
+    add_mirror_credentials(&mut callbacks);
+    callbacks.transfer_progress(|stats| {
+        report_transfer_progress(tx, InstallPhase::Clone, url, &stats);
+        !cancellation.is_cancelled()
+    });
+    fo.remote_callbacks(callbacks);
+
+    let refspec = match (tag, branch) {
+        (Some(tag), _) => format!("+refs/tags/{0}:refs/tags/{0}", tag),
+        (None, Some(branch)) => format!("+refs/heads/{0}:refs/remotes/origin/{0}", branch),
+        (None, None) => "+refs/heads/*:refs/remotes/origin/*".to_string(),
+    };
+    repo.remote_anonymous(url)?
+        .fetch(&[refspec.as_str()], Some(&mut fo), None)?;
+
+    let mut checkout = git2::build::CheckoutBuilder::new();
+    checkout.safe().recreate_missing(true);
+    if let Some(tag) = tag {
+        let tag_obj = repo
+            .find_reference(&format!("refs/tags/{}", tag))?
+            .peel(ObjectType::Commit)?;
+        repo.checkout_tree(&tag_obj, Some(&mut checkout))?;
+        repo.set_head_detached(tag_obj.id())?;
+    } else if let Some(branch) = branch {
+        let obj = repo
+            .revparse_single(&format!("origin/{}", branch))?
+            .peel(ObjectType::Commit)?;
+        repo.checkout_tree(&obj, Some(&mut checkout))?;
+        // moves the local branch even when it is checked out, which `Repository::branch` refuses
+        repo.reference(
+            &format!("refs/heads/{}", branch),
+            obj.id(),
+            true,
+            &format!("eim: update {} from {}", branch, url),
+        )?;
+        repo.set_head(&format!("refs/heads/{}", branch))?;
+    }
+
+    if recurse_submodules {
+        let mut sfo = FetchOptions::new();
+        let mut callbacks = RemoteCallbacks::new();
+        add_mirror_credentials(&mut callbacks);
+        info!("Fetching submodules");
+        callbacks.transfer_progress(|stats| {
+            report_transfer_progress(tx, InstallPhase::Submodules, url, &stats);
+            !cancellation.is_cancelled()
+        });
+        sfo.remote_callbacks(callbacks);
+        progress::report(
+            tx,
+            &ProgressUpdate::new(InstallPhase::Clone, url).detail(ProgressDetail::Finished),
+        );
+        update_submodules(&repo, sfo, tx, cancellation)?;
+        info!("Finished fetching submodules");
+    }
+    Ok(repo)
+}
+
 /// Updates submodules in the given repository using the provided fetch options.//+
 /////+
 /// # Parameters//+
 /////+
 /// * `repo`: A reference to the `git2::Repository` object representing the repository.//+
 /// * `fetch_options`: A `git2::FetchOptions` object containing the fetch options to be used.//+
-/// * `tx`: A `std::sync::mpsc::Sender<ProgressMessage>` object for sending progress messages.//+
+/// * `tx`: The reporter receiving the progress of the submodules.//+
+/// * `cancellation`: Stops the update before the next submodule once cancelled.
 /////+
 /// # Returns//+
 /////+
@@ -824,7 +1520,8 @@ fn shallow_clone(
 fn update_submodules(
     repo: &Repository,
     fetch_options: FetchOptions,
-    tx: std::sync::mpsc::Sender<ProgressMessage>,
+    tx: &dyn ProgressReporter,
+    cancellation: &CancellationToken,
 ) -> Result<(), git2::Error> {
     let mut submodule_update_options = git2::SubmoduleUpdateOptions::new();
     submodule_update_options.fetch(fetch_options);
@@ -833,18 +1530,28 @@ fn update_submodules(
         repo: &Repository,
         path: &Path,
         fetch_options: &mut SubmoduleUpdateOptions,
-        tx: std::sync::mpsc::Sender<ProgressMessage>,
+        tx: &dyn ProgressReporter,
+        cancellation: &CancellationToken,
     ) -> Result<(), git2::Error> {
         let submodules = repo.submodules()?;
         for mut submodule in submodules {
-            tx.send(ProgressMessage::Finish).unwrap();
+            if cancellation.is_cancelled() {
+                return Err(clone_cancelled_error());
+            }
+            let update = ProgressUpdate::new(
+                InstallPhase::Submodules,
+                submodule.name().unwrap_or_default(),
+            );
+            progress::report(tx, &update.clone().detail(ProgressDetail::Started));
             submodule.update(true, Some(fetch_options))?;
+            progress::report(tx, &update.detail(ProgressDetail::Finished));
             let sub_repo = submodule.open()?;
             update_submodules_recursive(
                 &sub_repo,
                 &path.join(submodule.path()),
                 fetch_options,
-                tx.clone(),
+                tx,
+                cancellation,
             )?;
         }
         Ok(())
@@ -854,24 +1561,84 @@ fn update_submodules(
         repo,
         repo.workdir().unwrap(),
         &mut submodule_update_options,
-        tx.clone(),
+        tx,
+        cancellation,
+    )
+}
+
+/// The error returned by the git operations once their cancellation token is cancelled.
+fn clone_cancelled_error() -> git2::Error {
+    git2::Error::new(
+        git2::ErrorCode::User,
+        git2::ErrorClass::None,
+        &messages::message(MessageId::CloneCancelled, &[]),
     )
 }
 
+/// Lists the entries of `path` before a clone into it, `None` when the directory does not
+/// exist yet and is created by the clone.
+fn entries_before_clone(path: &str) -> Option<Vec<std::ffi::OsString>> {
+    fs::read_dir(path).ok().map(|entries| {
+        entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name())
+            .collect()
+    })
+}
+
+/// Finishes a cancellable clone: once the token is cancelled, the partial clone in `path` is
+/// removed and the error is replaced by the cancellation error.
+///
+/// Only what the clone created is removed: the whole directory if it did not exist before
+/// (`existing` is `None`), otherwise the entries not listed in `existing`.
+fn finish_cancellable_clone<T>(
+    result: Result<T, git2::Error>,
+    path: &str,
+    existing: Option<Vec<std::ffi::OsString>>,
+    cancellation: &CancellationToken,
+) -> Result<T, git2::Error> {
+    if !cancellation.is_cancelled() {
+        return result;
+    }
+    let created: Vec<PathBuf> = match existing {
+        None => vec![PathBuf::from(path)],
+        Some(existing) => fs::read_dir(path)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .filter(|entry| !existing.contains(&entry.file_name()))
+                    .map(|entry| entry.path())
+                    .collect()
+            })
+            .unwrap_or_default(),
+    };
+    for created_path in created {
+        if let Err(e) = utils::remove_directory_all(&created_path) {
+            warn!(
+                "Failed to remove the partial clone in {}: {}",
+                created_path.display(),
+                e
+            );
+        }
+    }
+    Err(clone_cancelled_error())
+}
+
 // This function is not used right now  because of limited scope of the POC
 // It gets specific fork of rustpython with build in libraries needed for IDF
 #[cfg(feature = "userustpython")]
 pub fn get_rustpython_fork(
     custom_path: &str,
-    tx: std::sync::mpsc::Sender<ProgressMessage>,
+    tx: impl ProgressReporter,
 ) -> Result<String, git2::Error> {
     let output = shallow_clone(
         "https://github.com/Hahihula/RustPython.git",
         custom_path,
         Some("test-rust-build"),
         None,
-        tx,
+        &tx,
         false,
+        &CancellationToken::new(),
     );
     match output {
         Ok(repo) => Ok(repo.path().to_str().unwrap().to_string()),
@@ -883,18 +1650,24 @@ pub fn get_rustpython_fork(
 pub fn run_idf_tools_using_rustpython(custom_path: &str) -> Result<String, std::io::Error> {
     let script_path = "esp-idf/tools/idf_tools.py";
     // env::set_var("RUSTPYTHONPATH", "/tmp/test-directory/RustPython/Lib"); // this is not needed as the standart library is bakend into the binary
-    let output = std::process::Command::new("rustpython") // this works only on my machine (needs to point to the rustpython executable)
-        .current_dir(custom_path)
-        .arg(script_path)
-        .arg("--idf-path")
-        .arg(format!("{}/esp-idf", custom_path))
-        .arg("--tools-json")
-        .arg(format!("{}/esp-idf/tools/tools.json", custom_path))
-        .arg("install")
-        .arg("--targets")
-        .arg("all")
-        .arg("all")
-        .output();
+    let idf_path = format!("{}/esp-idf", custom_path);
+    let tools_json = format!("{}/esp-idf/tools/tools.json", custom_path);
+    let output = command_executor::execute_command_with_options(
+        "rustpython", // this works only on my machine (needs to point to the rustpython executable)
+        &[
+            script_path,
+            "--idf-path",
+            &idf_path,
+            "--tools-json",
+            &tools_json,
+            "install",
+            "--targets",
+            "all",
+            "all",
+        ],
+        vec![],
+        &command_executor::ExecOptions::new().current_dir(custom_path),
+    );
     match output {
         Ok(out) => {
             if out.status.success() {
@@ -910,12 +1683,15 @@ pub fn run_idf_tools_using_rustpython(custom_path: &str) -> Result<String, std::
 /// Clones the ESP-IDF repository from the specified URL, tag, or branch,
 /// using the provided progress function for reporting cloning progress.
 ///
+/// A repository already in `path`, e.g. of an earlier run interrupted during the clone, is
+/// updated instead: the version is fetched into it and checked out.
+///
 /// # Parameters
 ///
 /// * `path`: A reference to a string representing the local path where the repository should be cloned.
 /// * `version`: A reference to a string representing the version of ESP-IDF to clone.
 /// * `mirror`: An optional reference to a string representing the URL of a mirror to use for cloning the repository.
-/// * `tx`: A `std::sync::mpsc::Sender<ProgressMessage>` object for sending progress messages.
+/// * `tx`: The reporter receiving the clone progress, e.g. a `Sender<ProgressMessage>`.
 /// * `with_submodules`: A boolean indicating whether to clone the ESP-IDF repository with submodules.
 ///
 /// # Return Value
@@ -926,8 +1702,28 @@ pub fn get_esp_idf_by_version_and_mirror(
     path: &str,
     version: &str,
     mirror: Option<&str>,
-    tx: std::sync::mpsc::Sender<ProgressMessage>,
+    tx: impl ProgressReporter,
     with_submodules: bool,
+) -> Result<std::string::String, git2::Error> {
+    get_esp_idf_by_version_and_mirror_cancellable(
+        path,
+        version,
+        mirror,
+        tx,
+        with_submodules,
+        &CancellationToken::new(),
+    )
+}
+
+/// Clones ESP-IDF like `get_esp_idf_by_version_and_mirror`, aborting the transfer once the token
+/// is cancelled. The partial clone is removed then.
+pub fn get_esp_idf_by_version_and_mirror_cancellable(
+    path: &str,
+    version: &str,
+    mirror: Option<&str>,
+    tx: impl ProgressReporter,
+    with_submodules: bool,
+    cancellation: &CancellationToken,
 ) -> Result<std::string::String, git2::Error> {
     let tag = if version == "master" {
         None
@@ -944,13 +1740,14 @@ pub fn get_esp_idf_by_version_and_mirror(
             }
         })
         .flatten();
-    get_esp_idf_by_tag_name(
+    get_esp_idf_by_tag_name_cancellable(
         path,
         tag.as_deref(),
         tx,
         mirror,
         group_name,
         with_submodules,
+        cancellation,
     )
 }
 
@@ -975,10 +1772,33 @@ pub fn get_esp_idf_by_version_and_mirror(
 pub fn get_esp_idf_by_tag_name(
     custom_path: &str,
     tag: Option<&str>,
-    tx: std::sync::mpsc::Sender<ProgressMessage>,
+    tx: impl ProgressReporter,
+    mirror: Option<&str>,
+    group_name: Option<&str>,
+    with_submodules: bool,
+) -> Result<String, git2::Error> {
+    get_esp_idf_by_tag_name_cancellable(
+        custom_path,
+        tag,
+        tx,
+        mirror,
+        group_name,
+        with_submodules,
+        &CancellationToken::new(),
+    )
+}
+
+/// Clones ESP-IDF like `get_esp_idf_by_tag_name`, aborting the transfer once the token is
+/// cancelled. The partial clone is removed then, an existing repository which was being updated
+/// is kept.
+pub fn get_esp_idf_by_tag_name_cancellable(
+    custom_path: &str,
+    tag: Option<&str>,
+    tx: impl ProgressReporter,
     mirror: Option<&str>,
     group_name: Option<&str>,
     with_submodules: bool,
+    cancellation: &CancellationToken,
 ) -> Result<String, git2::Error> {
     let group = group_name.unwrap_or("espressif");
     let url = match mirror {
@@ -987,18 +1807,181 @@ pub fn get_esp_idf_by_tag_name(
         }
         None => "https://github.com/espressif/esp-idf.git".to_string(),
     };
+    if dry_run::plan(
+        dry_run::ActionKind::Clone,
+        &format!(
+            "Clone {} at {} to {}",
+            url,
+            tag.unwrap_or("master"),
+            custom_path
+        ),
+        None,
+    ) {
+        return Ok(Path::new(custom_path)
+            .join(".git")
+            .to_string_lossy()
+            .into_owned());
+    }
 
+    if let Ok(repo) = Repository::open(custom_path) {
+        let (branch, tag) = match tag {
+            Some(tag) => (None, Some(tag)),
+            None => (Some("master"), None),
+        };
+        let output =
+            update_existing_clone(repo, &url, branch, tag, &tx, with_submodules, cancellation);
+        if cancellation.is_cancelled() {
+            return Err(clone_cancelled_error());
+        }
+        return output.map(|repo| repo.path().to_str().unwrap().to_string());
+    }
+
+    let existing = entries_before_clone(custom_path);
     let _ = ensure_path(custom_path);
     let output = match tag {
-        Some(tag) => shallow_clone(&url, custom_path, None, Some(tag), tx, with_submodules),
-        None => shallow_clone(&url, custom_path, Some("master"), None, tx, with_submodules),
+        Some(tag) => shallow_clone(
+            &url,
+            custom_path,
+            None,
+            Some(tag),
+            &tx,
+            with_submodules,
+            cancellation,
+        ),
+        None => shallow_clone(
+            &url,
+            custom_path,
+            Some("master"),
+            None,
+            &tx,
+            with_submodules,
+            cancellation,
+        ),
     };
-    match output {
+    match finish_cancellable_clone(output, custom_path, existing, cancellation) {
         Ok(repo) => Ok(repo.path().to_str().unwrap().to_string()),
         Err(e) => Err(e),
     }
 }
 
+/// Clones the ESP-IDF repository and checks out the exact commit specified.
+///
+/// Unlike `get_esp_idf_by_tag_name`, the clone is not shallow, as an arbitrary commit
+/// may not be reachable from the tip of any branch.
+///
+/// # Parameters
+///
+/// * `custom_path`: A string representing the local path where the repository should be cloned.
+/// * `commit`: A string representing the SHA of the commit to checkout.
+/// * `tx`: The reporter receiving the clone progress, e.g. a `Sender<ProgressMessage>`.
+/// * `mirror`: An optional string representing the URL of a mirror to use for cloning the repository.
+/// * `with_submodules`: A boolean indicating whether to clone the ESP-IDF repository with submodules.
+///
+/// # Returns
+///
+/// * `Result<String, git2::Error>`: On success, returns a `Result` containing the path of the cloned repository as a string.
+///   On error, returns a `Result` containing a `git2::Error` indicating the cause of the error.
+pub fn get_esp_idf_by_commit(
+    custom_path: &str,
+    commit: &str,
+    tx: impl ProgressReporter,
+    mirror: Option<&str>,
+    with_submodules: bool,
+) -> Result<String, git2::Error> {
+    get_esp_idf_by_commit_cancellable(
+        custom_path,
+        commit,
+        tx,
+        mirror,
+        with_submodules,
+        &CancellationToken::new(),
+    )
+}
+
+/// Clones ESP-IDF like `get_esp_idf_by_commit`, aborting the transfer once the token is
+/// cancelled. The partial clone is removed then.
+pub fn get_esp_idf_by_commit_cancellable(
+    custom_path: &str,
+    commit: &str,
+    tx: impl ProgressReporter,
+    mirror: Option<&str>,
+    with_submodules: bool,
+    cancellation: &CancellationToken,
+) -> Result<String, git2::Error> {
+    let url = match mirror {
+        Some(url) if url.contains("https://gitee.com/") => {
+            "https://github.com/EspressifSystems/esp-idf.git".replace("https://github.com", url)
+        }
+        Some(url) => "https://github.com/espressif/esp-idf.git".replace("https://github.com", url),
+        None => "https://github.com/espressif/esp-idf.git".to_string(),
+    };
+    if dry_run::plan(
+        dry_run::ActionKind::Clone,
+        &format!("Clone {} at {} to {}", url, commit, custom_path),
+        None,
+    ) {
+        return Ok(Path::new(custom_path)
+            .join(".git")
+            .to_string_lossy()
+            .into_owned());
+    }
+    let existing = entries_before_clone(custom_path);
+    let _ = ensure_path(custom_path);
+    let result = clone_at_commit(
+        &url,
+        custom_path,
+        commit,
+        &tx,
+        with_submodules,
+        cancellation,
+    );
+    finish_cancellable_clone(result, custom_path, existing, cancellation)
+}
+
+/// Does the work of `get_esp_idf_by_commit_cancellable`, leaving the cleanup to the caller.
+fn clone_at_commit(
+    url: &str,
+    custom_path: &str,
+    commit: &str,
+    tx: &dyn ProgressReporter,
+    with_submodules: bool,
+    cancellation: &CancellationToken,
+) -> Result<String, git2::Error> {
+    let mut fo = FetchOptions::new();
+    let mut callbacks = RemoteCallbacks::new();
+    add_mirror_credentials(&mut callbacks);
+    callbacks.transfer_progress(|stats| {
+        report_transfer_progress(tx, InstallPhase::Clone, url, &stats);
+        !cancellation.is_cancelled()
+    });
+    fo.remote_callbacks(callbacks);
+    let mut builder = git2::build::RepoBuilder::new();
+    builder.fetch_options(fo);
+    let repo = builder.clone(url, Path::new(custom_path))?;
+
+    let obj = repo.revparse_single(commit)?.peel(ObjectType::Commit)?;
+    repo.checkout_tree(&obj, None)?;
+    repo.set_head_detached(obj.id())?;
+
+    if with_submodules {
+        let mut sfo = FetchOptions::new();
+        let mut callbacks = RemoteCallbacks::new();
+        add_mirror_credentials(&mut callbacks);
+        callbacks.transfer_progress(|stats| {
+            report_transfer_progress(tx, InstallPhase::Submodules, url, &stats);
+            !cancellation.is_cancelled()
+        });
+        sfo.remote_callbacks(callbacks);
+        progress::report(
+            tx,
+            &ProgressUpdate::new(InstallPhase::Clone, url).detail(ProgressDetail::Finished),
+        );
+        update_submodules(&repo, sfo, tx, cancellation)?;
+    }
+    let path = repo.path().to_str().unwrap().to_string();
+    Ok(path)
+}
+
 /// Expands a tilde (~) in a given path to the user's home directory.
 ///
 /// This function takes a reference to a `Path` and returns a `PathBuf` representing the expanded path.
@@ -1031,7 +2014,7 @@ pub fn expand_tilde(path: &Path) -> PathBuf {
 
 /// Performs post-installation tasks for a single version of ESP-IDF.
 ///
-/// This function creates a desktop shortcut on Windows systems and generates an activation shell script
+/// This function creates a desktop and a Start Menu shortcut on Windows systems and generates an activation shell script
 /// for other operating systems. The desktop shortcut is created using the `create_desktop_shortcut` function,
 /// and the activation shell script is generated using the `create_activation_shell_script` function.
 ///
@@ -1049,6 +2032,17 @@ pub fn single_version_post_install(
     tool_install_directory: &str,
     export_paths: Vec<String>,
 ) {
+    let post_install = match std::env::consts::OS {
+        "windows" => "Create the desktop and Start Menu shortcuts",
+        _ => "Write the activation scripts",
+    };
+    if dry_run::plan(
+        dry_run::ActionKind::WriteFile,
+        &format!("{} of ESP-IDF {}", post_install, idf_version),
+        None,
+    ) {
+        return;
+    }
     let env_vars = setup_environment_variables(
         &PathBuf::from(tool_install_directory),
         &PathBuf::from(idf_path),
@@ -1073,11 +2067,17 @@ pub fn single_version_post_install(
             } else {
                 info!("Desktop shortcut created successfully")
             }
+            let profile =
+                PathBuf::from(version_instalation_path).join("Microsoft.PowerShell_profile.ps1");
+            match create_start_menu_shortcut(profile.to_str().unwrap(), idf_version) {
+                Ok(_) => info!("Start Menu shortcut created successfully"),
+                Err(err) => error!("Failed to create Start Menu shortcut: {}", err),
+            }
         }
         _ => {
             let install_folder = PathBuf::from(version_instalation_path);
             let install_path = install_folder.parent().unwrap().to_str().unwrap();
-            let _ = create_activation_shell_script(
+            let _ = create_activation_scripts(
                 // todo: handle error
                 install_path,
                 idf_path,
@@ -1135,6 +2135,66 @@ mod tests {
     use std::fs;
     use std::io::Write;
 
+    #[test]
+    fn test_update_existing_clone_checks_out_tag() {
+        let source = tempfile::tempdir().unwrap();
+        let upstream = Repository::init(source.path()).unwrap();
+        fs::write(source.path().join("version.txt"), "v5.3").unwrap();
+        let mut index = upstream.index().unwrap();
+        index.add_path(Path::new("version.txt")).unwrap();
+        let tree = upstream.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("eim", "eim@example.com").unwrap();
+        let commit = upstream
+            .commit(Some("HEAD"), &signature, &signature, "v5.3", &tree, &[])
+            .unwrap();
+        upstream
+            .tag_lightweight("v5.3", &upstream.find_object(commit, None).unwrap(), false)
+            .unwrap();
+
+        // an interrupted clone leaves an empty repository behind
+        let target = tempfile::tempdir().unwrap();
+        let partial = Repository::init(target.path()).unwrap();
+        let repo = update_existing_clone(
+            partial,
+            source.path().to_str().unwrap(),
+            None,
+            Some("v5.3"),
+            &(),
+            false,
+            &CancellationToken::new(),
+        )
+        .unwrap();
+        assert_eq!(repo.head().unwrap().target(), Some(commit));
+        assert_eq!(
+            fs::read_to_string(target.path().join("version.txt")).unwrap(),
+            "v5.3"
+        );
+    }
+
+    #[test]
+    fn test_cancelled_clone_keeps_existing_directory() {
+        let cancellation = CancellationToken::new();
+        cancellation.cancel();
+
+        let target = tempfile::tempdir().unwrap();
+        fs::write(target.path().join("notes.txt"), "keep").unwrap();
+        let path = target.path().to_str().unwrap();
+        let existing = entries_before_clone(path);
+        fs::create_dir_all(target.path().join(".git")).unwrap();
+        let result = finish_cancellable_clone(Ok(()), path, existing, &cancellation);
+        assert!(result.is_err());
+        assert!(target.path().join("notes.txt").exists());
+        assert!(!target.path().join(".git").exists());
+
+        let created = target.path().join("esp-idf");
+        let path = created.to_str().unwrap();
+        let existing = entries_before_clone(path);
+        fs::create_dir_all(created.join(".git")).unwrap();
+        let result = finish_cancellable_clone(Ok(()), path, existing, &cancellation);
+        assert!(result.is_err());
+        assert!(!created.exists());
+    }
+
     #[test]
     fn test_verify_file_checksum_with_valid_file() {
         let file_path = "test_file.txt";
@@ -1247,4 +2307,42 @@ mod tests {
 
         assert_eq!(expanded_path, home_dir.join("test_directory"));
     }
+    #[test]
+    fn test_activation_shell_from_name() {
+        assert_eq!(
+            ActivationShell::from_name("/usr/bin/fish"),
+            Some(ActivationShell::Fish)
+        );
+        assert_eq!(
+            ActivationShell::from_name("csh"),
+            Some(ActivationShell::Tcsh)
+        );
+        assert_eq!(
+            ActivationShell::from_name("nu"),
+            Some(ActivationShell::Nushell)
+        );
+        assert_eq!(ActivationShell::from_name("ksh"), None);
+    }
+
+    #[test]
+    fn test_render_fish_activation_script() {
+        let dir = tempfile::tempdir().unwrap();
+        let env_vars = vec![("IDF_PYTHON_ENV_PATH".to_string(), "/opt/venv".to_string())];
+
+        let script = render_activation_script(
+            ActivationShell::Fish,
+            dir.path().to_str().unwrap(),
+            "/opt/esp-idf",
+            "/opt/tools",
+            "v5.3",
+            &["/opt/tools/bin".to_string()],
+            &env_vars,
+        )
+        .unwrap();
+        let content = fs::read_to_string(&script).unwrap();
+
+        assert_eq!(script.file_name().unwrap(), "activate_idf_v5.3.fish");
+        assert!(content.contains("set -gx IDF_PYTHON_ENV_PATH \"/opt/venv\""));
+        assert!(content.contains("\"/opt/esp-idf/tools/idf.py\" $argv"));
+    }
 }
@@ -0,0 +1,397 @@
+use anyhow::{anyhow, Context, Result};
+use git2::Repository;
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use crate::command_executor;
+use crate::dry_run::{self, ActionKind};
+use crate::idf_tools::{filter_tools_by_target, get_platform_identification, ToolsFile};
+use crate::install_report::{InstallError, InstallReport, InstalledVersion};
+use crate::progress::ProgressReporter;
+use crate::settings::Settings;
+
+/// Name of the manifest file stored in the installation folder of every version.
+pub const MANIFEST_FILE_NAME: &str = "eim_manifest.json";
+
+/// A single tool pinned by the manifest.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ManifestTool {
+    pub name: String,
+    pub version: String,
+    pub url: String,
+    pub sha256: String,
+}
+
+/// Everything needed to reproduce an ESP-IDF installation bit by bit.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct InstallationManifest {
+    #[serde(rename = "idfVersion")]
+    pub idf_version: String,
+    #[serde(rename = "idfCommit")]
+    pub idf_commit: String,
+    pub platform: String,
+    pub targets: Vec<String>,
+    pub tools: Vec<ManifestTool>,
+    #[serde(rename = "pythonPackages")]
+    pub python_packages: Vec<String>,
+    pub settings: Settings,
+}
+
+impl InstallationManifest {
+    /// Saves the manifest to a file as pretty-printed JSON. In dry-run mode the write is only
+    /// planned.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path where to save the manifest
+    pub fn to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        if crate::dry_run::plan(
+            crate::dry_run::ActionKind::WriteFile,
+            &format!(
+                "Write the installation manifest to {}",
+                path.as_ref().display()
+            ),
+            None,
+        ) {
+            return Ok(());
+        }
+        crate::ensure_path(path.as_ref().parent().unwrap().to_str().unwrap())?;
+        let json_string = serde_json::to_string_pretty(self)?;
+        fs::write(path.as_ref(), json_string)
+            .with_context(|| anyhow!("writing manifest to {} failed", path.as_ref().display()))
+    }
+
+    /// Reads and parses a manifest from a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the manifest file
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let content = fs::read_to_string(path)?;
+        let manifest: InstallationManifest = serde_json::from_str(&content)?;
+        Ok(manifest)
+    }
+}
+
+/// Retrieves the SHA of the commit currently checked out in the ESP-IDF repository.
+pub fn get_idf_commit(idf_path: &Path) -> Result<String> {
+    let repo = Repository::open(idf_path)?;
+    let commit = repo.head()?.peel_to_commit()?;
+    Ok(commit.id().to_string())
+}
+
+/// Lists the python packages installed in the given interpreter in `pip freeze` format.
+pub fn get_python_packages(python: &str) -> Result<Vec<String>> {
+    let output = command_executor::execute_checked(
+        python,
+        &["-m", "pip", "freeze"],
+        vec![],
+        &command_executor::ExecOptions::new(),
+    )?;
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect())
+}
+
+/// Collects the recommended version of every tool needed for the selected targets on the given platform.
+///
+/// # Parameters
+///
+/// * `tools_file` - A `ToolsFile` instance containing the list of tools and their versions.
+/// * `targets` - A reference to a vector of strings representing the selected targets.
+/// * `platform` - A string slice representing the platform, as returned by `get_platform_identification`.
+///
+/// # Returns
+///
+/// * `Vec<ManifestTool>` - The pinned tools, sorted by name.
+pub fn get_manifest_tools(
+    tools_file: ToolsFile,
+    targets: &[String],
+    platform: &str,
+) -> Vec<ManifestTool> {
+    let mut tools = vec![];
+    for tool in filter_tools_by_target(tools_file.tools, targets) {
+        for version in tool.versions.iter().filter(|v| v.status == "recommended") {
+            if let Some(download) =
+                crate::idf_tools::get_download_for_platform(&version.downloads, platform)
+            {
+                tools.push(ManifestTool {
+                    name: tool.name.clone(),
+                    version: version.name.clone(),
+                    url: download.url.clone(),
+                    sha256: download.sha256.clone(),
+                });
+            }
+        }
+    }
+    tools.sort_by(|a, b| a.name.cmp(&b.name));
+    tools
+}
+
+/// Creates the manifest of a freshly installed ESP-IDF version and writes it to the installation folder.
+///
+/// This should be called by the installer after the tools and the python environment were installed.
+///
+/// # Parameters
+///
+/// * `version_installation_path` - A reference to a `Path` representing the folder of the installed version.
+/// * `idf_version` - A string slice representing the installed ESP-IDF version.
+/// * `idf_path` - A reference to a `Path` representing the ESP-IDF repository.
+/// * `tools_file` - A `ToolsFile` instance parsed from the `tools.json` of the repository.
+/// * `python` - A string slice representing the python interpreter of the installed virtual environment.
+/// * `settings` - A reference to the `Settings` used for the installation.
+///
+/// # Returns
+///
+/// * `Result<PathBuf>` - On success, returns the path of the written manifest.
+pub fn write_installation_manifest(
+    version_installation_path: &Path,
+    idf_version: &str,
+    idf_path: &Path,
+    tools_file: ToolsFile,
+    python: &str,
+    settings: &Settings,
+) -> Result<PathBuf> {
+    let platform = get_platform_identification(None).map_err(|e| anyhow!(e))?;
+    let targets = settings
+        .target
+        .clone()
+        .unwrap_or_else(|| vec!["all".to_string()]);
+    let manifest = InstallationManifest {
+        idf_version: idf_version.to_string(),
+        idf_commit: get_idf_commit(idf_path)?,
+        tools: get_manifest_tools(tools_file, &targets, &platform),
+        python_packages: get_python_packages(python)?,
+        platform,
+        targets,
+        settings: settings.clone(),
+    };
+    let manifest_path = version_installation_path.join(MANIFEST_FILE_NAME);
+    manifest.to_file(&manifest_path)?;
+    info!(
+        "Installation manifest written to {}",
+        manifest_path.display()
+    );
+    Ok(manifest_path)
+}
+
+/// Reinstalls ESP-IDF exactly as described by the manifest.
+///
+/// The ESP-IDF repository is cloned at the recorded commit, every tool is downloaded from the recorded
+/// URL and verified against the recorded checksum, and the python environment is created with the
+/// recorded package versions pinned.
+///
+/// The installation report is written next to `eim_idf.json` of the recorded settings whether the
+/// reinstallation succeeded or not.
+///
+/// # Parameters
+///
+/// * `manifest` - A reference to the `InstallationManifest` to reproduce.
+/// * `install_path` - A reference to a `Path` under which the version folder will be created.
+/// * `progress_sender` - The reporter receiving the download progress, e.g. a `Sender<DownloadProgress>`.
+/// * `tx` - The reporter receiving the clone progress, e.g. a `Sender<ProgressMessage>`.
+///
+/// # Returns
+///
+/// * `Result<InstallReport, InstallError>` - On success, returns the report of the installation,
+///   listing the reinstalled version. On failure the error carries the report as well.
+pub async fn reinstall_from_manifest(
+    manifest: &InstallationManifest,
+    install_path: &Path,
+    progress_sender: impl ProgressReporter,
+    tx: impl ProgressReporter,
+) -> Result<InstallReport, InstallError> {
+    let mut report = InstallReport::new();
+    let outcome = reinstall_version(manifest, install_path, progress_sender, tx, &mut report).await;
+    report.complete(
+        outcome,
+        manifest
+            .settings
+            .esp_idf_json_path
+            .as_deref()
+            .map(Path::new),
+    )
+}
+
+async fn reinstall_version(
+    manifest: &InstallationManifest,
+    install_path: &Path,
+    progress_sender: impl ProgressReporter,
+    tx: impl ProgressReporter,
+    report: &mut InstallReport,
+) -> Result<()> {
+    let platform = get_platform_identification(None).map_err(|e| anyhow!(e))?;
+    if platform != manifest.platform {
+        return Err(anyhow!(
+            "Manifest was created for {}, this system is {}",
+            manifest.platform,
+            platform
+        ));
+    }
+    let version_path = install_path.join(&manifest.idf_version);
+    let idf_path = version_path.join("esp-idf");
+    let tools_path = version_path.join(
+        manifest
+            .settings
+            .tool_install_folder_name
+            .clone()
+            .unwrap_or("tools".to_string()),
+    );
+    let dist_path = version_path.join(
+        manifest
+            .settings
+            .tool_download_folder_name
+            .clone()
+            .unwrap_or("dist".to_string()),
+    );
+
+    if let Some(mirror) = &manifest.settings.idf_mirror {
+        report.add_mirror(mirror);
+    }
+    report.time_phase("clone", || {
+        crate::get_esp_idf_by_commit(
+            idf_path.to_str().unwrap(),
+            &manifest.idf_commit,
+            tx,
+            manifest.settings.idf_mirror.as_deref(),
+            manifest.settings.recurse_submodules.unwrap_or(false),
+        )
+    })?;
+
+    let started = Instant::now();
+    let installed = install_tools(manifest, &dist_path, &tools_path, &progress_sender).await;
+    report.record_phase(
+        "tools",
+        started.elapsed(),
+        installed.as_ref().err().map(|e| e.to_string()),
+    );
+    installed?;
+
+    report.time_phase("python", || -> Result<()> {
+        let env_vars = crate::setup_environment_variables(&tools_path, &idf_path)
+            .map_err(|e| anyhow!("Failed to setup environment variables: {}", e))?;
+        let idf_tools_py = idf_path.join("tools").join("idf_tools.py");
+        if dry_run::plan(
+            ActionKind::RunCommand,
+            &format!(
+                "Run {} install-python-env and install the pinned python packages",
+                idf_tools_py.display()
+            ),
+            None,
+        ) {
+            return Ok(());
+        }
+        crate::python_utils::run_python_script_from_file_with_options(
+            idf_tools_py.to_str().unwrap(),
+            Some("install-python-env"),
+            None,
+            Some(&env_vars),
+            &crate::command_executor::ExecOptions::new().clean_env(),
+        )
+        .map_err(|e| anyhow!("Failed to create python environment: {}", e))?;
+
+        let python = match std::env::consts::OS {
+            "windows" => tools_path.join("python").join("Scripts").join("python.exe"),
+            _ => tools_path.join("python").join("bin").join("python3"),
+        };
+        let requirements = version_path.join("requirements.lock.txt");
+        fs::write(&requirements, manifest.python_packages.join("\n"))?;
+        command_executor::execute_checked(
+            python.to_str().unwrap(),
+            &["-m", "pip", "install", "-r", requirements.to_str().unwrap()],
+            vec![],
+            &command_executor::ExecOptions::new(),
+        )
+        .map_err(|e| anyhow!("Failed to install pinned python packages: {}", e))?;
+        Ok(())
+    })?;
+    manifest.to_file(version_path.join(MANIFEST_FILE_NAME))?;
+    report.add_version(InstalledVersion {
+        version: manifest.idf_version.clone(),
+        idf_path: idf_path.to_string_lossy().into_owned(),
+        tools_path: tools_path.to_string_lossy().into_owned(),
+        activation_script: None,
+    });
+    Ok(())
+}
+
+/// Downloads, verifies and extracts the tools recorded in the manifest, reusing the archives
+/// already in `dist_path` or in the download cache.
+async fn install_tools(
+    manifest: &InstallationManifest,
+    dist_path: &Path,
+    tools_path: &Path,
+    progress_sender: &impl ProgressReporter,
+) -> Result<()> {
+    if dry_run::is_dry_run() {
+        for tool in &manifest.tools {
+            dry_run::plan(
+                ActionKind::Download,
+                &format!("Download {} {} from {}", tool.name, tool.version, tool.url),
+                None,
+            );
+        }
+        return Ok(());
+    }
+    crate::ensure_path(dist_path.to_str().unwrap())?;
+    for tool in &manifest.tools {
+        let filename = Path::new(&tool.url)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| anyhow!("Invalid download url {}", tool.url))?;
+        let archive = dist_path.join(filename);
+        if !crate::verify_file_checksum(&tool.sha256, archive.to_str().unwrap())?
+            && crate::cache::restore(&tool.sha256, dist_path, filename)?.is_none()
+        {
+            debug!("Downloading {} {}", tool.name, tool.version);
+            crate::download_file(&tool.url, dist_path.to_str().unwrap(), progress_sender).await?;
+            if !crate::verify_file_checksum(&tool.sha256, archive.to_str().unwrap())? {
+                return Err(anyhow!("Checksum mismatch for {}", tool.name));
+            }
+            crate::cache::store(&tool.sha256, &archive)?;
+        }
+        let destination = tools_path
+            .join("tools")
+            .join(&tool.name)
+            .join(&tool.version);
+        crate::decompress_archive(archive.to_str().unwrap(), destination.to_str().unwrap())
+            .map_err(|e| anyhow!("Failed to extract {}: {}", tool.name, e))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(MANIFEST_FILE_NAME);
+        let manifest = InstallationManifest {
+            idf_version: "v5.1.2".to_string(),
+            idf_commit: "482a8fb2d78e3b58eb21b26da8a5bedf90623213".to_string(),
+            platform: "linux-amd64".to_string(),
+            targets: vec!["esp32".to_string()],
+            tools: vec![ManifestTool {
+                name: "cmake".to_string(),
+                version: "3.24.0".to_string(),
+                url: "https://github.com/Kitware/CMake/releases/download/v3.24.0/cmake.tar.gz"
+                    .to_string(),
+                sha256: "abc123".to_string(),
+            }],
+            python_packages: vec!["esptool==4.7.0".to_string()],
+            settings: Settings::default(),
+        };
+        manifest.to_file(&path).unwrap();
+        let loaded = InstallationManifest::from_file(&path).unwrap();
+        assert_eq!(loaded.idf_commit, manifest.idf_commit);
+        assert_eq!(loaded.tools, manifest.tools);
+        assert_eq!(loaded.python_packages, manifest.python_packages);
+    }
+}
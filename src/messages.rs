@@ -0,0 +1,237 @@
+use std::sync::{Arc, RwLock};
+
+/// Identifies a user-facing message of the library, so frontends can translate it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MessageId {
+    VersionNotInstalled,
+    VersionSelected,
+    VersionRenamed,
+    VersionRemoved,
+    RemoveInstallationFolderFailed,
+    RemoveActivationScriptFailed,
+    DownloadCancelled,
+    ExtractionCancelled,
+    CloneCancelled,
+    ToolsInstallationCancelled,
+    UnsupportedOs,
+    UnsupportedPackageManager,
+    NoPackageManager,
+    PrerequisitesFailed,
+    PackageManagerCannotInstall,
+    PackageInstallFailed,
+    NetworkRequired,
+    HomebrewInstallFailed,
+    XcodeToolsTimeout,
+    Downloading,
+    Installing,
+    DriverAlreadyInstalled,
+    FolderCreationFailed,
+    ChecksumMismatch,
+    DownloadFailed,
+    NotADriverPackage,
+    NoSignedCatalog,
+    UnsupportedPlatform,
+}
+
+impl MessageId {
+    /// All messages, e.g. to export the catalog for translators.
+    pub const ALL: [MessageId; 28] = [
+        MessageId::VersionNotInstalled,
+        MessageId::VersionSelected,
+        MessageId::VersionRenamed,
+        MessageId::VersionRemoved,
+        MessageId::RemoveInstallationFolderFailed,
+        MessageId::RemoveActivationScriptFailed,
+        MessageId::DownloadCancelled,
+        MessageId::ExtractionCancelled,
+        MessageId::CloneCancelled,
+        MessageId::ToolsInstallationCancelled,
+        MessageId::UnsupportedOs,
+        MessageId::UnsupportedPackageManager,
+        MessageId::NoPackageManager,
+        MessageId::PrerequisitesFailed,
+        MessageId::PackageManagerCannotInstall,
+        MessageId::PackageInstallFailed,
+        MessageId::NetworkRequired,
+        MessageId::HomebrewInstallFailed,
+        MessageId::XcodeToolsTimeout,
+        MessageId::Downloading,
+        MessageId::Installing,
+        MessageId::DriverAlreadyInstalled,
+        MessageId::FolderCreationFailed,
+        MessageId::ChecksumMismatch,
+        MessageId::DownloadFailed,
+        MessageId::NotADriverPackage,
+        MessageId::NoSignedCatalog,
+        MessageId::UnsupportedPlatform,
+    ];
+
+    /// The stable key of the message, used in translation files.
+    pub fn key(&self) -> &'static str {
+        match self {
+            MessageId::VersionNotInstalled => "version-not-installed",
+            MessageId::VersionSelected => "version-selected",
+            MessageId::VersionRenamed => "version-renamed",
+            MessageId::VersionRemoved => "version-removed",
+            MessageId::RemoveInstallationFolderFailed => "remove-installation-folder-failed",
+            MessageId::RemoveActivationScriptFailed => "remove-activation-script-failed",
+            MessageId::DownloadCancelled => "download-cancelled",
+            MessageId::ExtractionCancelled => "extraction-cancelled",
+            MessageId::CloneCancelled => "clone-cancelled",
+            MessageId::ToolsInstallationCancelled => "tools-installation-cancelled",
+            MessageId::UnsupportedOs => "unsupported-os",
+            MessageId::UnsupportedPackageManager => "unsupported-package-manager",
+            MessageId::NoPackageManager => "no-package-manager",
+            MessageId::PrerequisitesFailed => "prerequisites-failed",
+            MessageId::PackageManagerCannotInstall => "package-manager-cannot-install",
+            MessageId::PackageInstallFailed => "package-install-failed",
+            MessageId::NetworkRequired => "network-required",
+            MessageId::HomebrewInstallFailed => "homebrew-install-failed",
+            MessageId::XcodeToolsTimeout => "xcode-tools-timeout",
+            MessageId::Downloading => "downloading",
+            MessageId::Installing => "installing",
+            MessageId::DriverAlreadyInstalled => "driver-already-installed",
+            MessageId::FolderCreationFailed => "folder-creation-failed",
+            MessageId::ChecksumMismatch => "checksum-mismatch",
+            MessageId::DownloadFailed => "download-failed",
+            MessageId::NotADriverPackage => "not-a-driver-package",
+            MessageId::NoSignedCatalog => "no-signed-catalog",
+            MessageId::UnsupportedPlatform => "unsupported-platform",
+        }
+    }
+
+    /// The English template of the message, `{0}`, `{1}`, ... are replaced by the arguments.
+    pub fn english(&self) -> &'static str {
+        match self {
+            MessageId::VersionNotInstalled => "Version {0} not installed",
+            MessageId::VersionSelected => "Version {0} selected",
+            MessageId::VersionRenamed => "Version {0} renamed to {1}",
+            MessageId::VersionRemoved => "Version {0} removed",
+            MessageId::RemoveInstallationFolderFailed => {
+                "Failed to remove installation folder: {0}"
+            }
+            MessageId::RemoveActivationScriptFailed => "Failed to remove activation script: {0}",
+            MessageId::DownloadCancelled => "Download of {0} was cancelled",
+            MessageId::ExtractionCancelled => "Extraction of {0} was cancelled",
+            MessageId::CloneCancelled => "Cloning ESP-IDF was cancelled",
+            MessageId::ToolsInstallationCancelled => "Installation of the IDF tools was cancelled",
+            MessageId::UnsupportedOs => "Unsupported OS - {0}",
+            MessageId::UnsupportedPackageManager => "Unsupported package manager - {0}",
+            MessageId::NoPackageManager => "Unsupported package manager - none detected",
+            MessageId::PrerequisitesFailed => "Failed to install prerequisites: {0}",
+            MessageId::PackageManagerCannotInstall => {
+                "Unsupported package manager - {0} can not install packages"
+            }
+            MessageId::PackageInstallFailed => "Failed to install {0}: {1}",
+            MessageId::NetworkRequired => {
+                "{0} requires network access, which is disabled in offline mode"
+            }
+            MessageId::HomebrewInstallFailed => "Failed to install Homebrew: {0}",
+            MessageId::XcodeToolsTimeout => {
+                "Xcode Command Line Tools were not installed within {0} minutes"
+            }
+            MessageId::Downloading => "Downloading {0}",
+            MessageId::Installing => "Installing {0}",
+            MessageId::DriverAlreadyInstalled => "already installed ({0} {1})",
+            MessageId::FolderCreationFailed => "Failed to create the folder: {0}",
+            MessageId::ChecksumMismatch => "Checksum mismatch for {0}",
+            MessageId::DownloadFailed => "Failed to download {0}: {1}",
+            MessageId::NotADriverPackage => "{0} is not a driver package",
+            MessageId::NoSignedCatalog => "No signed catalog found in {0}",
+            MessageId::UnsupportedPlatform => "Unsupported platform: {0}",
+        }
+    }
+}
+
+/// Translates the messages of the library.
+///
+/// Frontends implement this on top of their translation files and register it with
+/// `set_localizer`. The returned template uses the same `{0}`, `{1}`, ... placeholders as
+/// `MessageId::english`; `None` falls back to English.
+pub trait Localizer: Send + Sync {
+    fn translate(&self, id: MessageId) -> Option<String>;
+}
+
+static LOCALIZER: RwLock<Option<Arc<dyn Localizer>>> = RwLock::new(None);
+
+/// Sets the localizer used for the user-facing messages, `None` restores English.
+pub fn set_localizer(localizer: Option<Arc<dyn Localizer>>) {
+    *LOCALIZER.write().unwrap() = localizer;
+}
+
+/// Replaces the `{0}`, `{1}`, ... placeholders of a template with the arguments, in a single pass
+/// so placeholders in the arguments are kept as they are. Placeholders without an argument are
+/// left in the message.
+fn fill_template(template: &str, args: &[&str]) -> String {
+    let mut message = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        message.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let placeholder = after
+            .find('}')
+            .and_then(|end| Some((end, after[..end].parse::<usize>().ok()?)))
+            .and_then(|(end, index)| Some((end, *args.get(index)?)));
+        match placeholder {
+            Some((end, arg)) => {
+                message.push_str(arg);
+                rest = &after[end + 1..];
+            }
+            None => {
+                message.push('{');
+                rest = after;
+            }
+        }
+    }
+    message.push_str(rest);
+    message
+}
+
+/// Builds a user-facing message in the language of the registered localizer.
+///
+/// # Parameters
+///
+/// * `id` - The message.
+/// * `args` - The values of the placeholders, in order.
+pub fn message(id: MessageId, args: &[&str]) -> String {
+    let translated = LOCALIZER
+        .read()
+        .unwrap()
+        .as_ref()
+        .and_then(|localizer| localizer.translate(id));
+    fill_template(translated.as_deref().unwrap_or(id.english()), args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fill_template() {
+        assert_eq!(
+            fill_template(MessageId::VersionRenamed.english(), &["v5.3", "stable"]),
+            "Version v5.3 renamed to stable"
+        );
+        assert_eq!(
+            fill_template("版本 {0} 已删除", &["v5.3"]),
+            "版本 v5.3 已删除"
+        );
+    }
+
+    #[test]
+    fn test_fill_template_keeps_placeholders_in_arguments() {
+        assert_eq!(
+            fill_template(MessageId::VersionRenamed.english(), &["{1}", "stable"]),
+            "Version {1} renamed to stable"
+        );
+        assert_eq!(fill_template("{0} {2} {x}", &["a"]), "a {2} {x}");
+    }
+
+    #[test]
+    fn test_message_keys_are_unique() {
+        let mut keys: Vec<&str> = MessageId::ALL.iter().map(|id| id.key()).collect();
+        keys.sort();
+        keys.dedup();
+        assert_eq!(keys.len(), MessageId::ALL.len());
+    }
+}
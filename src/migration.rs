@@ -0,0 +1,397 @@
+use anyhow::{anyhow, Result};
+use log::{debug, info, warn};
+use regex::Regex;
+use std::fs;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::idf_config::{IdfConfig, IdfInstallation};
+use crate::idf_tools::{get_tools_export_paths, read_and_parse_tools_file};
+use crate::utils::get_git_path;
+use crate::version_manager::{find_esp_idf_folders, get_installed_versions_from_config_file};
+
+/// An ESP-IDF installation created by the legacy `install.sh`/`install.ps1` scripts.
+#[derive(Debug, Clone)]
+pub struct LegacyInstallation {
+    pub idf_path: PathBuf,
+    pub idf_version: String,
+    pub tools_path: PathBuf,
+    pub python_env: Option<PathBuf>,
+}
+
+/// Outcome of a migration run.
+///
+/// `registered` contains the installations which were added to `eim_idf.json`,
+/// `skipped` contains the path of every installation which could not be reused
+/// together with a human readable reason.
+#[derive(Debug, Default)]
+pub struct MigrationReport {
+    pub registered: Vec<IdfInstallation>,
+    pub skipped: Vec<(String, String)>,
+}
+
+/// Returns the default location of the legacy tools directory (`~/.espressif`).
+///
+/// The `IDF_TOOLS_PATH` environment variable takes precedence, the same way it does
+/// for the legacy installation scripts.
+pub fn get_legacy_tools_path() -> Option<PathBuf> {
+    if let Ok(path) = std::env::var("IDF_TOOLS_PATH") {
+        return Some(PathBuf::from(path));
+    }
+    dirs::home_dir().map(|home| home.join(".espressif"))
+}
+
+/// Checks if the given directory looks like a tools directory populated by `install.sh`.
+///
+/// The legacy layout keeps the tools in `tools/` and the python virtual environments
+/// in `python_env/`, both directly under the tools directory.
+///
+/// # Parameters
+///
+/// * `espressif_dir` - A reference to a `Path` representing the legacy tools directory.
+///
+/// # Returns
+///
+/// * `bool` - `true` if both `tools` and `python_env` subdirectories are present.
+pub fn detect_legacy_layout(espressif_dir: &Path) -> bool {
+    espressif_dir.join("tools").is_dir() && espressif_dir.join("python_env").is_dir()
+}
+
+/// Parses the ESP-IDF version from the content of `tools/cmake/version.cmake`.
+///
+/// # Parameters
+///
+/// * `content` - A string slice holding the content of the `version.cmake` file.
+///
+/// # Returns
+///
+/// * `Option<(u32, u32, u32)>` - The major, minor and patch version, or `None` if the
+///   content does not contain all three components.
+pub fn parse_idf_version_cmake(content: &str) -> Option<(u32, u32, u32)> {
+    let get = |name: &str| -> Option<u32> {
+        let re = Regex::new(&format!(r"set\s*\(\s*{}\s+(\d+)\s*\)", name)).ok()?;
+        re.captures(content)?.get(1)?.as_str().parse().ok()
+    };
+    Some((
+        get("IDF_VERSION_MAJOR")?,
+        get("IDF_VERSION_MINOR")?,
+        get("IDF_VERSION_PATCH")?,
+    ))
+}
+
+/// Reads the version of the ESP-IDF repository located at `idf_path`.
+///
+/// # Returns
+///
+/// * `Option<(u32, u32, u32)>` - The major, minor and patch version, or `None` if the
+///   version file is missing or can not be parsed.
+pub fn read_idf_version(idf_path: &Path) -> Option<(u32, u32, u32)> {
+    let version_file = idf_path.join("tools").join("cmake").join("version.cmake");
+    let content = fs::read_to_string(version_file).ok()?;
+    parse_idf_version_cmake(&content)
+}
+
+/// Finds the legacy python virtual environment belonging to the given ESP-IDF version.
+///
+/// The legacy scripts name the environments `idf<major>.<minor>_py<python>_env`.
+/// If more than one environment matches, the last one in lexical order is returned.
+fn find_legacy_python_env(espressif_dir: &Path, major: u32, minor: u32) -> Option<PathBuf> {
+    let prefix = format!("idf{}.{}_py", major, minor);
+    let mut candidates: Vec<PathBuf> = fs::read_dir(espressif_dir.join("python_env"))
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.is_dir()
+                && path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .map(|name| name.starts_with(&prefix) && name.ends_with("_env"))
+                    .unwrap_or(false)
+        })
+        .collect();
+    candidates.sort();
+    candidates.pop()
+}
+
+/// Returns the path of the python executable inside a virtual environment.
+fn venv_python(venv: &Path) -> PathBuf {
+    match std::env::consts::OS {
+        "windows" => venv.join("Scripts").join("python.exe"),
+        _ => venv.join("bin").join("python3"),
+    }
+}
+
+/// Searches for ESP-IDF repositories which were set up against the legacy tools directory.
+///
+/// # Parameters
+///
+/// * `espressif_dir` - A reference to a `Path` representing the legacy tools directory.
+/// * `search_path` - A string slice with the directory to be searched for `esp-idf` folders
+///   (usually `~/esp`).
+///
+/// # Returns
+///
+/// * `Vec<LegacyInstallation>` - All valid ESP-IDF repositories found, paired with the
+///   python environment matching their version (if any).
+pub fn find_legacy_installations(
+    espressif_dir: &Path,
+    search_path: &str,
+) -> Vec<LegacyInstallation> {
+    let mut installations = vec![];
+    for idf_folder in find_esp_idf_folders(search_path) {
+        let idf_path = PathBuf::from(&idf_folder);
+        let (major, minor, patch) = match read_idf_version(&idf_path) {
+            Some(version) => version,
+            None => {
+                debug!("Unable to read version of {}", idf_folder);
+                continue;
+            }
+        };
+        installations.push(LegacyInstallation {
+            idf_version: format!("v{}.{}.{}", major, minor, patch),
+            python_env: find_legacy_python_env(espressif_dir, major, minor),
+            tools_path: espressif_dir.to_path_buf(),
+            idf_path,
+        });
+    }
+    installations
+}
+
+/// Returns the environment variables of the activation script of a legacy installation.
+///
+/// The python environment stays where the legacy scripts created it: `IDF_PYTHON_ENV_PATH` points
+/// to it instead of `<tools>/python`, so several versions can share the legacy tools directory
+/// and the directory itself is left untouched.
+fn legacy_env_vars(tools_path: &Path, idf_path: &Path, python_env: &Path) -> Vec<(String, String)> {
+    let mut env_vars =
+        crate::setup_environment_variables(&tools_path.to_path_buf(), &idf_path.to_path_buf())
+            .unwrap_or_default();
+    env_vars.retain(|(key, _)| key != "IDF_PYTHON_ENV_PATH");
+    env_vars.push((
+        "IDF_PYTHON_ENV_PATH".to_string(),
+        python_env.to_string_lossy().into_owned(),
+    ));
+    env_vars
+}
+
+/// Migrates installations created by the legacy installation scripts into the eim layout.
+///
+/// Every ESP-IDF repository found in `search_path` is checked for a matching python
+/// environment in the legacy tools directory. Compatible installations get an activation
+/// script generated and are registered in the `eim_idf.json` config file at `config_path`.
+/// Installations which are already registered, or which can not be reused, are reported in
+/// `MigrationReport::skipped`.
+///
+/// # Parameters
+///
+/// * `espressif_dir` - A reference to a `Path` representing the legacy tools directory.
+/// * `search_path` - A string slice with the directory to be searched for `esp-idf` folders.
+/// * `activation_scripts_path` - A string slice with the directory where the activation
+///   scripts should be created.
+/// * `config_path` - A reference to a `Path` representing the `eim_idf.json` file.
+///
+/// # Returns
+///
+/// * `Result<MigrationReport>` - On success, the report of the migration. An error is returned
+///   when the legacy layout is not detected or when the config file can not be written.
+pub fn migrate_legacy_installations(
+    espressif_dir: &Path,
+    search_path: &str,
+    activation_scripts_path: &str,
+    config_path: &Path,
+) -> Result<MigrationReport> {
+    if !detect_legacy_layout(espressif_dir) {
+        return Err(anyhow!(
+            "No legacy installation found in {}",
+            espressif_dir.display()
+        ));
+    }
+    let already_installed: Vec<String> =
+        get_installed_versions_from_config_file(&config_path.to_path_buf())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|install| install.path)
+            .collect();
+
+    let mut report = MigrationReport::default();
+    for legacy in find_legacy_installations(espressif_dir, search_path) {
+        let idf_path_str = legacy.idf_path.to_string_lossy().into_owned();
+        if already_installed.contains(&idf_path_str) {
+            report
+                .skipped
+                .push((idf_path_str, "Already registered".to_string()));
+            continue;
+        }
+        let python_env = match &legacy.python_env {
+            Some(env) if venv_python(env).exists() => env.clone(),
+            _ => {
+                report.skipped.push((
+                    idf_path_str,
+                    format!("No usable python environment for {}", legacy.idf_version),
+                ));
+                continue;
+            }
+        };
+        let tools_file = match read_and_parse_tools_file(
+            legacy
+                .idf_path
+                .join("tools")
+                .join("tools.json")
+                .to_str()
+                .unwrap(),
+        ) {
+            Ok(tools_file) => tools_file,
+            Err(e) => {
+                report
+                    .skipped
+                    .push((idf_path_str, format!("Unable to read tools.json: {}", e)));
+                continue;
+            }
+        };
+        let tools_path_str = legacy.tools_path.to_string_lossy().into_owned();
+        let export_paths = get_tools_export_paths(
+            tools_file,
+            vec!["all".to_string()],
+            legacy.tools_path.join("tools").to_str().unwrap(),
+        );
+        let env_vars = legacy_env_vars(&legacy.tools_path, &legacy.idf_path, &python_env);
+
+        let activation_script = match std::env::consts::OS {
+            "windows" => {
+                let profile_path = PathBuf::from(activation_scripts_path).join(&legacy.idf_version);
+                if let Err(e) = crate::create_desktop_shortcut(
+                    profile_path.to_str().unwrap(),
+                    &idf_path_str,
+                    &legacy.idf_version,
+                    &tools_path_str,
+                    export_paths,
+                    env_vars,
+                ) {
+                    report.skipped.push((
+                        idf_path_str,
+                        format!("Unable to create activation script: {}", e),
+                    ));
+                    continue;
+                }
+                profile_path.join("Microsoft.PowerShell_profile.ps1")
+            }
+            _ => {
+                if let Err(e) = crate::create_activation_scripts(
+                    activation_scripts_path,
+                    &idf_path_str,
+                    &tools_path_str,
+                    &legacy.idf_version,
+                    export_paths,
+                    env_vars,
+                ) {
+                    report.skipped.push((
+                        idf_path_str,
+                        format!("Unable to create activation script: {}", e),
+                    ));
+                    continue;
+                }
+                PathBuf::from(activation_scripts_path)
+                    .join(format!("activate_idf_{}.sh", legacy.idf_version))
+            }
+        };
+
+        let activation_scripts = match std::env::consts::OS {
+            "windows" => vec![],
+            _ => crate::find_activation_scripts(
+                Path::new(activation_scripts_path),
+                &legacy.idf_version,
+            )
+            .iter()
+            .map(|script| script.to_string_lossy().into_owned())
+            .collect(),
+        };
+
+        info!(
+            "Migrating ESP-IDF {} from {}",
+            legacy.idf_version, idf_path_str
+        );
+        report.registered.push(IdfInstallation {
+            activation_script: activation_script.to_string_lossy().into_owned(),
+            id: format!("esp-idf-{}", Uuid::new_v4().to_string().replace("-", "")),
+            idf_tools_path: tools_path_str,
+            name: legacy.idf_version.clone(),
+            path: idf_path_str,
+            python: venv_python(&python_env).to_string_lossy().into_owned(),
+            env_variables: vec![],
+            activation_scripts,
+            container_image: None,
+        });
+    }
+
+    if report.registered.is_empty() {
+        warn!("No legacy installation could be migrated");
+        return Ok(report);
+    }
+
+    // keep the user's selection if there already is one
+    let idf_selected_id = IdfConfig::from_file(config_path)
+        .ok()
+        .map(|config| config.idf_selected_id)
+        .filter(|id| !id.is_empty())
+        .unwrap_or_else(|| report.registered[0].id.clone());
+    let git_path = get_git_path().map_err(|e| anyhow!("Failed to get git path. {}", e))?;
+    let mut config = IdfConfig {
+        git_path,
+        idf_installed: report.registered.clone(),
+        idf_selected_id,
+    };
+    config.to_file(config_path, true)?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_idf_version_cmake() {
+        let content =
+            "set(IDF_VERSION_MAJOR 5)\nset(IDF_VERSION_MINOR 1)\nset(IDF_VERSION_PATCH 2)\n";
+        assert_eq!(parse_idf_version_cmake(content), Some((5, 1, 2)));
+    }
+
+    #[test]
+    fn test_parse_idf_version_cmake_incomplete() {
+        let content = "set(IDF_VERSION_MAJOR 5)\nset(IDF_VERSION_MINOR 1)\n";
+        assert_eq!(parse_idf_version_cmake(content), None);
+    }
+
+    #[test]
+    fn test_detect_legacy_layout() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(!detect_legacy_layout(dir.path()));
+        fs::create_dir_all(dir.path().join("tools")).unwrap();
+        fs::create_dir_all(dir.path().join("python_env")).unwrap();
+        assert!(detect_legacy_layout(dir.path()));
+    }
+
+    #[test]
+    fn test_find_legacy_python_env() {
+        let dir = tempfile::tempdir().unwrap();
+        let env = dir.path().join("python_env").join("idf5.1_py3.11_env");
+        fs::create_dir_all(&env).unwrap();
+        fs::create_dir_all(dir.path().join("python_env").join("idf4.4_py3.8_env")).unwrap();
+        assert_eq!(find_legacy_python_env(dir.path(), 5, 1), Some(env));
+        assert_eq!(find_legacy_python_env(dir.path(), 5, 2), None);
+    }
+
+    #[test]
+    fn test_legacy_env_vars_use_legacy_python_env() {
+        let dir = tempfile::tempdir().unwrap();
+        let env = dir.path().join("python_env").join("idf5.1_py3.11_env");
+        let env_vars = legacy_env_vars(dir.path(), &dir.path().join("esp-idf"), &env);
+        let python_env_paths: Vec<&String> = env_vars
+            .iter()
+            .filter(|(key, _)| key == "IDF_PYTHON_ENV_PATH")
+            .map(|(_, value)| value)
+            .collect();
+        assert_eq!(python_env_paths, vec![&env.to_string_lossy().into_owned()]);
+        assert!(!dir.path().join("python").exists());
+    }
+}
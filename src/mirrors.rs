@@ -0,0 +1,204 @@
+use log::debug;
+use std::time::{Duration, Instant};
+
+use crate::{get_idf_mirrors_list, get_idf_tools_mirrors_list};
+
+/// The file fetched from the ESP-IDF mirrors, the reference advertisement of the git repository,
+/// which every git host serves under the same path.
+const IDF_PROBE_PATH: &str = "/espressif/esp-idf.git/info/refs?service=git-upload-pack";
+
+/// The file fetched from the tools mirrors, a release asset of an ESP-IDF tool, which all the
+/// mirrors serve under the GitHub path.
+const TOOLS_PROBE_PATH: &str = "/espressif/binutils-gdb/releases/download/esp-gdb-v12.1_20231023/xtensa-esp-elf-gdb-12.1_20231023-x86_64-linux-gnu.tar.gz";
+
+/// At most this many bytes are read from a probe, enough to measure the throughput.
+const PROBE_BYTES: u64 = 512 * 1024;
+
+/// How long a single probe may take.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// What a mirror serves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MirrorKind {
+    /// The ESP-IDF repository, see `get_idf_mirrors_list`.
+    Idf,
+    /// The tool archives, see `get_idf_tools_mirrors_list`.
+    Tools,
+}
+
+/// The measured speed of a mirror.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MirrorBenchmark {
+    pub mirror: String,
+    pub kind: MirrorKind,
+    /// The time until the response headers arrived.
+    pub latency: Option<Duration>,
+    /// The download speed in bytes per second.
+    pub throughput: Option<f64>,
+    /// Why the mirror could not be measured, it is unusable then.
+    pub error: Option<String>,
+}
+
+impl MirrorBenchmark {
+    fn failed(mirror: &str, kind: MirrorKind, error: String) -> Self {
+        MirrorBenchmark {
+            mirror: mirror.to_string(),
+            kind,
+            latency: None,
+            throughput: None,
+            error: Some(error),
+        }
+    }
+
+    /// Whether the mirror answered the probe.
+    pub fn is_reachable(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Measures the latency and throughput of a mirror by downloading the start of a probe file.
+///
+/// # Parameters
+///
+/// * `mirror` - The mirror, e.g. `https://dl.espressif.com/github_assets`.
+/// * `kind` - What the mirror serves, which selects the probe file.
+pub async fn benchmark_mirror(mirror: &str, kind: MirrorKind) -> MirrorBenchmark {
+    let probe_path = match kind {
+        MirrorKind::Idf => IDF_PROBE_PATH,
+        MirrorKind::Tools => TOOLS_PROBE_PATH,
+    };
+    let url = format!("{}{}", mirror.trim_end_matches('/'), probe_path);
+    let client = match crate::http_client::client() {
+        Ok(client) => client,
+        Err(e) => return MirrorBenchmark::failed(mirror, kind, e.to_string()),
+    };
+
+    let start = Instant::now();
+    let mut response = match client
+        .get(&url)
+        .timeout(PROBE_TIMEOUT)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+    {
+        Ok(response) => response,
+        Err(e) => return MirrorBenchmark::failed(mirror, kind, e.to_string()),
+    };
+    let latency = start.elapsed();
+
+    let transfer_start = Instant::now();
+    let mut received: u64 = 0;
+    while received < PROBE_BYTES {
+        match response.chunk().await {
+            Ok(Some(chunk)) => received += chunk.len() as u64,
+            Ok(None) => break,
+            Err(e) => return MirrorBenchmark::failed(mirror, kind, e.to_string()),
+        }
+    }
+    let seconds = transfer_start.elapsed().as_secs_f64().max(0.001);
+    debug!(
+        "Mirror {} answered in {:?}, {} bytes in {:.3}s",
+        mirror, latency, received, seconds
+    );
+    MirrorBenchmark {
+        mirror: mirror.to_string(),
+        kind,
+        latency: Some(latency),
+        throughput: Some(received as f64 / seconds),
+        error: None,
+    }
+}
+
+/// Orders benchmarks from the fastest to the slowest mirror: the reachable ones by throughput,
+/// then by latency, followed by the unreachable ones.
+fn compare_speed(a: &MirrorBenchmark, b: &MirrorBenchmark) -> std::cmp::Ordering {
+    b.is_reachable()
+        .cmp(&a.is_reachable())
+        .then_with(|| {
+            b.throughput
+                .unwrap_or(0.0)
+                .partial_cmp(&a.throughput.unwrap_or(0.0))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        })
+        .then_with(|| a.latency.cmp(&b.latency))
+}
+
+/// Sorts benchmarks from the fastest to the slowest mirror, the unreachable ones last.
+pub fn rank_benchmarks(mut benchmarks: Vec<MirrorBenchmark>) -> Vec<MirrorBenchmark> {
+    benchmarks.sort_by(compare_speed);
+    benchmarks
+}
+
+/// Benchmarks every ESP-IDF and tools mirror, one after the other so they do not compete for the
+/// bandwidth.
+///
+/// # Returns
+///
+/// * `Vec<MirrorBenchmark>` - The ESP-IDF mirrors followed by the tools mirrors, each ranked from
+///   the fastest to the slowest, see `fastest_mirror`.
+pub async fn benchmark_mirrors() -> Vec<MirrorBenchmark> {
+    let mut results = vec![];
+    for (kind, mirrors) in [
+        (MirrorKind::Idf, get_idf_mirrors_list()),
+        (MirrorKind::Tools, get_idf_tools_mirrors_list()),
+    ] {
+        let mut benchmarks = vec![];
+        for mirror in mirrors {
+            benchmarks.push(benchmark_mirror(mirror, kind).await);
+        }
+        results.extend(rank_benchmarks(benchmarks));
+    }
+    results
+}
+
+/// Returns the fastest reachable mirror of a kind from the results of `benchmark_mirrors`.
+pub fn fastest_mirror(benchmarks: &[MirrorBenchmark], kind: MirrorKind) -> Option<&str> {
+    benchmarks
+        .iter()
+        .filter(|benchmark| benchmark.kind == kind && benchmark.is_reachable())
+        .min_by(|a, b| compare_speed(a, b))
+        .map(|benchmark| benchmark.mirror.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn benchmark(mirror: &str, throughput: Option<f64>, latency_ms: u64) -> MirrorBenchmark {
+        MirrorBenchmark {
+            mirror: mirror.to_string(),
+            kind: MirrorKind::Tools,
+            latency: throughput.map(|_| Duration::from_millis(latency_ms)),
+            throughput,
+            error: match throughput {
+                Some(_) => None,
+                None => Some("timed out".to_string()),
+            },
+        }
+    }
+
+    #[test]
+    fn test_rank_benchmarks() {
+        let ranked = rank_benchmarks(vec![
+            benchmark("unreachable", None, 0),
+            benchmark("slow", Some(1000.0), 20),
+            benchmark("fast", Some(5000.0), 200),
+        ]);
+        let order: Vec<&str> = ranked.iter().map(|b| b.mirror.as_str()).collect();
+        assert_eq!(order, vec!["fast", "slow", "unreachable"]);
+    }
+
+    #[test]
+    fn test_fastest_mirror() {
+        let benchmarks = vec![
+            benchmark("slow", Some(1000.0), 20),
+            benchmark("fast", Some(5000.0), 200),
+        ];
+        assert_eq!(fastest_mirror(&benchmarks, MirrorKind::Tools), Some("fast"));
+        assert_eq!(fastest_mirror(&benchmarks, MirrorKind::Idf), None);
+        assert_eq!(
+            fastest_mirror(&[benchmark("down", None, 0)], MirrorKind::Tools),
+            None
+        );
+    }
+}
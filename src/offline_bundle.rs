@@ -0,0 +1,740 @@
+use anyhow::{anyhow, Context, Result};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use crate::command_executor::{execute_checked, ExecOptions};
+use crate::drivers::{self, DriverInfo, DriverInstallOptions};
+use crate::dry_run::{self, ActionKind};
+use crate::idf_tools::{
+    get_platform_identification, get_tools_export_paths, read_and_parse_tools_file,
+};
+use crate::install_report::{InstallError, InstallReport, InstalledVersion};
+use crate::manifest::{get_idf_commit, get_manifest_tools, ManifestTool};
+use crate::progress::ProgressReporter;
+use crate::python_utils::get_python_version;
+use crate::settings::Settings;
+use crate::system_dependencies;
+use crate::utils::{copy_dir_all, now_timestamp};
+
+/// Name of the file describing a bundle, in its root folder.
+pub const BUNDLE_MANIFEST_FILE_NAME: &str = "eim_bundle.json";
+
+/// The version of the bundle layout, bumped on incompatible changes.
+pub const BUNDLE_FORMAT_VERSION: u32 = 1;
+
+/// A file of the bundle with its checksum, verified before anything is installed.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BundleFile {
+    /// The path relative to the bundle folder, with `/` separators.
+    pub path: String,
+    pub sha256: String,
+}
+
+/// An ESP-IDF version of the bundle.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BundleVersion {
+    pub version: String,
+    /// The commit of the bundled repository, checked when the bundle is verified.
+    #[serde(rename = "idfCommit")]
+    pub idf_commit: String,
+    /// The tool archives of every bundled platform.
+    pub tools: BTreeMap<String, Vec<ManifestTool>>,
+    /// The requirements pip has no wheels of, by platform. They have to be provided another way
+    /// before installing from the bundle on that platform.
+    #[serde(
+        rename = "missingWheels",
+        default,
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    pub missing_wheels: BTreeMap<String, Vec<String>>,
+}
+
+/// The content of an air-gapped bundle.
+///
+/// A bundle is a folder with:
+/// * `idf/<version>/esp-idf` - the ESP-IDF repositories with their submodules,
+/// * `tools/` - the tool archives of all versions and platforms,
+/// * `wheels/<platform>/` - the python packages of the ESP-IDF requirements,
+/// * `drivers/` - the Windows driver packages, when a Windows platform is bundled,
+/// * `eim_bundle.json` - this manifest.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BundleManifest {
+    #[serde(rename = "formatVersion")]
+    pub format_version: u32,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    /// The Python version the wheels were downloaded for, e.g. `3.11`.
+    #[serde(rename = "pythonVersion")]
+    pub python_version: String,
+    pub targets: Vec<String>,
+    pub platforms: Vec<String>,
+    pub versions: Vec<BundleVersion>,
+    #[serde(default)]
+    pub drivers: Vec<DriverInfo>,
+    pub files: Vec<BundleFile>,
+}
+
+impl BundleManifest {
+    /// Saves the manifest to the bundle folder.
+    pub fn to_file(&self, bundle_dir: &Path) -> Result<()> {
+        let path = bundle_dir.join(BUNDLE_MANIFEST_FILE_NAME);
+        fs::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| anyhow!("writing bundle manifest to {} failed", path.display()))
+    }
+
+    /// Reads the manifest of a bundle folder.
+    pub fn from_file(bundle_dir: &Path) -> Result<Self> {
+        let content = fs::read_to_string(bundle_dir.join(BUNDLE_MANIFEST_FILE_NAME))
+            .with_context(|| anyhow!("{} is not a bundle", bundle_dir.display()))?;
+        let manifest: BundleManifest = serde_json::from_str(&content)?;
+        Ok(manifest)
+    }
+}
+
+/// Returns the pip platform tag of the wheels for a platform, `None` if pip has none.
+pub fn pip_platform_tag(platform: &str) -> Option<&'static str> {
+    match platform {
+        "linux-amd64" => Some("manylinux2014_x86_64"),
+        "linux-arm64" => Some("manylinux2014_aarch64"),
+        "linux-armhf" | "linux-armel" => Some("linux_armv7l"),
+        "linux-i686" => Some("manylinux2014_i686"),
+        "macos" => Some("macosx_10_9_x86_64"),
+        "macos-arm64" => Some("macosx_11_0_arm64"),
+        "win64" => Some("win_amd64"),
+        "win32" => Some("win32"),
+        "win-arm64" => Some("win_arm64"),
+        _ => None,
+    }
+}
+
+/// Returns the requirements file of an ESP-IDF repository, which moved in ESP-IDF 5.0.
+fn requirements_file(idf_path: &Path) -> PathBuf {
+    let core = idf_path
+        .join("tools")
+        .join("requirements")
+        .join("requirements.core.txt");
+    if core.exists() {
+        core
+    } else {
+        idf_path.join("requirements.txt")
+    }
+}
+
+/// Returns the path as a string, or an error if it is not valid UTF-8.
+fn path_str(path: &Path) -> Result<&str> {
+    path.to_str()
+        .ok_or_else(|| anyhow!("{} is not a valid UTF-8 path", path.display()))
+}
+
+/// Reads the requirements of a requirements file, without the comments and pip options.
+fn read_requirements(requirements: &Path) -> Result<Vec<String>> {
+    let content = fs::read_to_string(requirements)
+        .with_context(|| anyhow!("Failed to read {}", requirements.display()))?;
+    Ok(content
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('-'))
+        .map(|line| line.to_string())
+        .collect())
+}
+
+/// Downloads the wheels of the requirements for a platform with pip.
+///
+/// pip aborts when a single requirement has no wheel, so when downloading all of them at once
+/// fails, they are downloaded one by one to find the ones without wheels.
+///
+/// # Returns
+///
+/// * `Result<Vec<String>, anyhow::Error>` - The requirements without wheels.
+fn download_wheels(
+    requirements: &Path,
+    tag: &str,
+    python_version: &str,
+    wheels_dir: &Path,
+) -> Result<Vec<String>> {
+    let pip_download = |requirement_args: &[&str]| {
+        let mut args = vec![
+            "-m",
+            "pip",
+            "download",
+            "--only-binary=:all:",
+            "--platform",
+            tag,
+            "--python-version",
+            python_version,
+            "-d",
+            path_str(wheels_dir)?,
+        ];
+        args.extend(requirement_args);
+        execute_checked("python3", &args, vec![], &ExecOptions::new())
+            .map(|_| ())
+            .map_err(|e| anyhow!("{}", e))
+    };
+    let Err(e) = pip_download(&["-r", path_str(requirements)?]) else {
+        return Ok(vec![]);
+    };
+    debug!("Downloading all wheels at once failed: {}", e);
+    let mut missing = vec![];
+    for requirement in read_requirements(requirements)? {
+        if let Err(e) = pip_download(&[&requirement]) {
+            warn!("No wheel of {} for {}: {}", requirement, tag, e);
+            missing.push(requirement);
+        }
+    }
+    Ok(missing)
+}
+
+/// Returns the path of a bundle file relative to the bundle folder, with `/` separators.
+fn relative_bundle_path(bundle_dir: &Path, path: &Path) -> String {
+    path.strip_prefix(bundle_dir)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+/// Lists the files of a folder recursively.
+fn list_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = vec![];
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                files.extend(list_files(&path));
+            } else {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+/// Creates an air-gapped bundle with everything needed to install ESP-IDF on machines without
+/// internet access: the repositories, the tool archives, the python wheels and the drivers.
+///
+/// The wheels are downloaded for the Python version running this function, the machines
+/// installing from the bundle need the same Python minor version. The requirements without a
+/// wheel for a platform do not abort the bundle creation, they are listed in `missing_wheels` of
+/// the bundled version.
+///
+/// # Parameters
+///
+/// * `versions` - The ESP-IDF versions to bundle.
+/// * `targets` - The targets whose tools are bundled, e.g. `["esp32", "esp32s3"]` or `["all"]`.
+/// * `platforms` - The platforms to bundle the tools and wheels for, e.g. `["linux-amd64", "win64"]`.
+/// * `out_dir` - The folder of the bundle, it is created if needed.
+/// * `progress_sender` - The reporter receiving the download progress, e.g. a `Sender<DownloadProgress>`.
+/// * `tx` - The reporter receiving the clone progress, e.g. a `Sender<ProgressMessage>`.
+///
+/// # Returns
+///
+/// * `Result<BundleManifest, anyhow::Error>` - The manifest written to the bundle.
+pub async fn create_offline_bundle(
+    versions: &[String],
+    targets: &[String],
+    platforms: &[String],
+    out_dir: &Path,
+    progress_sender: impl ProgressReporter,
+    tx: impl ProgressReporter,
+) -> Result<BundleManifest> {
+    let (major, minor, _) = get_python_version(None).map_err(|e| anyhow!(e))?;
+    let python_version = format!("{}.{}", major, minor);
+    let tools_dir = out_dir.join("tools");
+    crate::ensure_path(path_str(&tools_dir)?)?;
+    let mut files = vec![];
+    let mut bundle_versions = vec![];
+
+    for version in versions {
+        let idf_path = out_dir.join("idf").join(version).join("esp-idf");
+        if !idf_path.exists() {
+            info!("Cloning ESP-IDF {} into the bundle", version);
+            crate::get_esp_idf_by_version_and_mirror(
+                path_str(&idf_path)?,
+                version,
+                None,
+                &tx,
+                true,
+            )?;
+        }
+        let tools_json = idf_path.join("tools").join("tools.json");
+        let mut tools = BTreeMap::new();
+        let mut missing_wheels = BTreeMap::new();
+        for platform in platforms {
+            let tools_file = read_and_parse_tools_file(path_str(&tools_json)?)
+                .map_err(|e| anyhow!("Failed to read {}: {}", tools_json.display(), e))?;
+            let platform_tools = get_manifest_tools(tools_file, targets, platform);
+            for tool in &platform_tools {
+                let filename = Path::new(&tool.url)
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .ok_or_else(|| anyhow!("Invalid download url {}", tool.url))?;
+                let archive = tools_dir.join(filename);
+                if !crate::verify_file_checksum(&tool.sha256, path_str(&archive)?)? {
+                    debug!(
+                        "Downloading {} {} for {}",
+                        tool.name, tool.version, platform
+                    );
+                    crate::download_file(&tool.url, path_str(&tools_dir)?, &progress_sender)
+                        .await?;
+                    if !crate::verify_file_checksum(&tool.sha256, path_str(&archive)?)? {
+                        return Err(anyhow!("Checksum mismatch for {}", tool.name));
+                    }
+                }
+                let file = BundleFile {
+                    path: relative_bundle_path(out_dir, &archive),
+                    sha256: tool.sha256.clone(),
+                };
+                if !files.contains(&file) {
+                    files.push(file);
+                }
+            }
+            tools.insert(platform.clone(), platform_tools);
+
+            match pip_platform_tag(platform) {
+                Some(tag) => {
+                    let wheels_dir = out_dir.join("wheels").join(platform);
+                    info!(
+                        "Downloading the python wheels of {} for {}",
+                        version, platform
+                    );
+                    let missing = download_wheels(
+                        &requirements_file(&idf_path),
+                        tag,
+                        &python_version,
+                        &wheels_dir,
+                    )?;
+                    if !missing.is_empty() {
+                        missing_wheels.insert(platform.clone(), missing);
+                    }
+                }
+                None => warn!("No python wheels are available for {}", platform),
+            }
+        }
+        bundle_versions.push(BundleVersion {
+            version: version.clone(),
+            idf_commit: get_idf_commit(&idf_path)?,
+            tools,
+            missing_wheels,
+        });
+    }
+
+    for wheel in list_files(&out_dir.join("wheels")) {
+        files.push(BundleFile {
+            path: relative_bundle_path(out_dir, &wheel),
+            sha256: crate::compute_file_checksum(path_str(&wheel)?)?,
+        });
+    }
+
+    let mut bundled_drivers = vec![];
+    if platforms.iter().any(|platform| platform.starts_with("win")) {
+        let drivers_dir = out_dir.join("drivers");
+        let available = drivers::get_drivers().await;
+        for (name, result) in drivers::download_drivers(&available, &drivers_dir, ())
+            .await
+            .map_err(|e| anyhow!(e.to_string()))?
+        {
+            match result {
+                Ok(archive) => {
+                    files.push(BundleFile {
+                        path: relative_bundle_path(out_dir, &archive),
+                        sha256: crate::compute_file_checksum(path_str(&archive)?)?,
+                    });
+                    if let Some(driver) = available.iter().find(|driver| driver.name == name) {
+                        bundled_drivers.push(driver.clone());
+                    }
+                }
+                Err(e) => warn!("Driver {} is not bundled: {}", name, e),
+            }
+        }
+    }
+
+    let manifest = BundleManifest {
+        format_version: BUNDLE_FORMAT_VERSION,
+        created_at: now_timestamp(),
+        python_version,
+        targets: targets.to_vec(),
+        platforms: platforms.to_vec(),
+        versions: bundle_versions,
+        drivers: bundled_drivers,
+        files,
+    };
+    manifest.to_file(out_dir)?;
+    info!("Offline bundle written to {}", out_dir.display());
+    Ok(manifest)
+}
+
+/// Verifies a bundle: every file has to match its checksum and every repository its commit.
+///
+/// # Returns
+///
+/// * `Result<BundleManifest, anyhow::Error>` - The manifest, or an error naming the first
+///   missing or corrupted part.
+pub fn verify_bundle(bundle_dir: &Path) -> Result<BundleManifest> {
+    let manifest = BundleManifest::from_file(bundle_dir)?;
+    if manifest.format_version > BUNDLE_FORMAT_VERSION {
+        return Err(anyhow!(
+            "Bundle format {} is not supported, please update the installer",
+            manifest.format_version
+        ));
+    }
+    for file in &manifest.files {
+        let path = bundle_dir.join(&file.path);
+        if !crate::verify_file_checksum(&file.sha256, path_str(&path)?)? {
+            return Err(anyhow!("{} is missing or corrupted", file.path));
+        }
+    }
+    for version in &manifest.versions {
+        let idf_path = bundle_dir
+            .join("idf")
+            .join(&version.version)
+            .join("esp-idf");
+        let commit = get_idf_commit(&idf_path)
+            .map_err(|e| anyhow!("ESP-IDF {} is missing: {}", version.version, e))?;
+        if commit != version.idf_commit {
+            return Err(anyhow!(
+                "ESP-IDF {} is at {} instead of {}",
+                version.version,
+                commit,
+                version.idf_commit
+            ));
+        }
+    }
+    Ok(manifest)
+}
+
+/// Packs a bundle folder into a single `.tar.gz` archive, easier to carry to an air-gapped machine.
+///
+/// The bundle is verified first, so a corrupted bundle is never packed.
+///
+/// # Parameters
+///
+/// * `bundle_dir` - The folder of the bundle, as created by `create_offline_bundle`.
+/// * `archive_path` - The archive to create, e.g. `esp-idf-bundle.tar.gz`.
+pub fn pack_bundle(bundle_dir: &Path, archive_path: &Path) -> Result<PathBuf> {
+    verify_bundle(bundle_dir)?;
+    if let Some(parent) = archive_path.parent() {
+        crate::ensure_path(path_str(parent)?)?;
+    }
+    // the top-level entries are listed instead of `.`, so the paths in the archive have no `./`
+    let mut entries: Vec<String> = fs::read_dir(bundle_dir)?
+        .flatten()
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect();
+    entries.sort();
+    let mut args = vec!["-czf", path_str(archive_path)?, "-C", path_str(bundle_dir)?];
+    args.extend(entries.iter().map(|entry| entry.as_str()));
+    execute_checked("tar", &args, vec![], &ExecOptions::new())
+        .map_err(|e| anyhow!("Failed to pack the bundle: {}", e))?;
+    info!("Offline bundle packed into {}", archive_path.display());
+    Ok(archive_path.to_path_buf())
+}
+
+/// Extracts a bundle archive created by `pack_bundle` and verifies it.
+///
+/// # Parameters
+///
+/// * `archive_path` - The bundle archive.
+/// * `destination` - The folder to extract the bundle to.
+pub fn unpack_bundle(archive_path: &Path, destination: &Path) -> Result<BundleManifest> {
+    crate::decompress_archive(path_str(archive_path)?, path_str(destination)?)
+        .map_err(|e| anyhow!("Failed to extract {}: {}", archive_path.display(), e))?;
+    verify_bundle(destination)
+}
+
+/// Installs ESP-IDF from a bundle created by `create_offline_bundle`, without network access.
+///
+/// The bundle may be a folder or an archive created by `pack_bundle`, which is extracted to a
+/// temporary folder first. The bundle is verified first. Every bundled version, or only those of `idf_versions` of the
+/// settings when set, is installed into `path` of the settings, with the tools of this platform and
+/// a python environment built from the bundled wheels, and registered in `eim_idf.json`. On
+/// Windows the missing bundled drivers are installed as well.
+///
+/// The offline mode of the prerequisites subsystem is enabled for the duration of the
+/// installation, see `system_dependencies::set_offline_mode`.
+///
+/// The installation report is written next to `eim_idf.json` whether the installation succeeded
+/// or not. In dry-run mode it lists the planned actions.
+///
+/// # Parameters
+///
+/// * `bundle` - The folder or archive of the bundle.
+/// * `settings` - The settings of the installation.
+///
+/// # Returns
+///
+/// * `Result<InstallReport, InstallError>` - The report of the installation, listing the
+///   installed versions. On failure the error carries the report as well.
+pub async fn install_from_bundle(
+    bundle: &Path,
+    settings: &Settings,
+) -> Result<InstallReport, InstallError> {
+    let mut report = InstallReport::new();
+    let was_offline = system_dependencies::is_offline_mode();
+    system_dependencies::set_offline_mode(true);
+    let outcome = install_bundle_versions(bundle, settings, &mut report).await;
+    system_dependencies::set_offline_mode(was_offline);
+    report.complete(
+        outcome,
+        settings.esp_idf_json_path.as_deref().map(Path::new),
+    )
+}
+
+async fn install_bundle_versions(
+    bundle: &Path,
+    settings: &Settings,
+    report: &mut InstallReport,
+) -> Result<()> {
+    // kept alive until the installation finishes, it is removed when dropped
+    let mut unpacked = None;
+    let (bundle_dir, manifest) = report.time_phase("verify", || -> Result<_> {
+        if bundle.is_file() {
+            let dir = tempfile::tempdir()?;
+            let manifest = unpack_bundle(bundle, dir.path())?;
+            let bundle_dir = unpacked.insert(dir).path().to_path_buf();
+            Ok((bundle_dir, manifest))
+        } else {
+            Ok((bundle.to_path_buf(), verify_bundle(bundle)?))
+        }
+    })?;
+    let bundle_dir = bundle_dir.as_path();
+    let platform = get_platform_identification(None).map_err(|e| anyhow!(e))?;
+    if !manifest.platforms.contains(&platform) {
+        return Err(anyhow!(
+            "The bundle was created for {}, this system is {}",
+            manifest.platforms.join(", "),
+            platform
+        ));
+    }
+    let install_path = settings
+        .path
+        .clone()
+        .ok_or_else(|| anyhow!("No installation path is set"))?;
+    let tool_install_folder_name = settings
+        .tool_install_folder_name
+        .clone()
+        .unwrap_or("tools".to_string());
+    let wheels_dir = bundle_dir.join("wheels").join(&platform);
+    let selected: Vec<&BundleVersion> = manifest
+        .versions
+        .iter()
+        .filter(|version| match &settings.idf_versions {
+            Some(versions) => versions.contains(&version.version),
+            None => true,
+        })
+        .collect();
+
+    for bundled in &selected {
+        let version_path = install_path.join(&bundled.version);
+        let idf_path = version_path.join("esp-idf");
+        let tools_path = version_path.join(&tool_install_folder_name);
+        info!("Installing ESP-IDF {} from the bundle", bundled.version);
+        if let Some(missing) = bundled.missing_wheels.get(&platform) {
+            report.warn(&format!(
+                "The bundle has no wheels of {} for ESP-IDF {}, they have to be installed from another source",
+                missing.join(", "),
+                bundled.version
+            ));
+        }
+        let bundled_idf = bundle_dir
+            .join("idf")
+            .join(&bundled.version)
+            .join("esp-idf");
+        report.time_phase("idf", || {
+            if dry_run::plan(
+                ActionKind::WriteFile,
+                &format!("Copy {} to {}", bundled_idf.display(), idf_path.display()),
+                None,
+            ) {
+                return Ok(());
+            }
+            copy_dir_all(&bundled_idf, &idf_path)
+        })?;
+
+        report.time_phase("tools", || -> Result<()> {
+            for tool in bundled.tools.get(&platform).into_iter().flatten() {
+                let filename = Path::new(&tool.url)
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .ok_or_else(|| anyhow!("Invalid download url {}", tool.url))?;
+                let archive = bundle_dir.join("tools").join(filename);
+                let destination = tools_path
+                    .join("tools")
+                    .join(&tool.name)
+                    .join(&tool.version);
+                crate::decompress_archive(path_str(&archive)?, path_str(&destination)?)
+                    .map_err(|e| anyhow!("Failed to extract {}: {}", tool.name, e))?;
+            }
+            Ok(())
+        })?;
+
+        report.time_phase("python", || -> Result<()> {
+            let mut env_vars = crate::setup_environment_variables(&tools_path, &idf_path)
+                .map_err(|e| anyhow!("Failed to setup environment variables: {}", e))?;
+            // pip installs from the bundled wheels only, and idf_tools.py must not download the constraints
+            env_vars.push(("PIP_NO_INDEX".to_string(), "1".to_string()));
+            env_vars.push((
+                "PIP_FIND_LINKS".to_string(),
+                wheels_dir.to_string_lossy().into_owned(),
+            ));
+            env_vars.push(("IDF_PYTHON_CHECK_CONSTRAINTS".to_string(), "no".to_string()));
+            let idf_tools_py = idf_path.join("tools").join("idf_tools.py");
+            if dry_run::plan(
+                ActionKind::RunCommand,
+                &format!("Run {} install-python-env", idf_tools_py.display()),
+                None,
+            ) {
+                return Ok(());
+            }
+            crate::python_utils::run_python_script_from_file_with_options(
+                path_str(&idf_tools_py)?,
+                Some("install-python-env"),
+                None,
+                Some(&env_vars),
+                &ExecOptions::new().clean_env(),
+            )
+            .map_err(|e| anyhow!("Failed to create python environment: {}", e))?;
+            Ok(())
+        })?;
+
+        // read from the bundle, the copy does not exist in dry-run mode
+        let tools_file =
+            read_and_parse_tools_file(path_str(&bundled_idf.join("tools").join("tools.json"))?)
+                .map_err(|e| anyhow!("Failed to read tools.json: {}", e))?;
+        let export_paths = get_tools_export_paths(
+            tools_file,
+            manifest.targets.clone(),
+            path_str(&tools_path.join("tools"))?,
+        );
+        crate::single_version_post_install(
+            path_str(&version_path)?,
+            path_str(&idf_path)?,
+            &bundled.version,
+            path_str(&tools_path)?,
+            export_paths,
+        );
+        report.add_version(InstalledVersion {
+            version: bundled.version.clone(),
+            idf_path: idf_path.to_string_lossy().into_owned(),
+            tools_path: tools_path.to_string_lossy().into_owned(),
+            activation_script: None,
+        });
+    }
+
+    if manifest.drivers.is_empty() {
+        report.skip("drivers", "The bundle contains no drivers");
+    } else if !drivers::should_install_drivers() {
+        report.skip("drivers", "Drivers are not installed in this environment");
+    } else {
+        // the packages are extracted next to the archives, which may be on read-only media
+        let drivers_dir = install_path.join("drivers");
+        let started = Instant::now();
+        let result = async {
+            if !dry_run::plan(
+                ActionKind::WriteFile,
+                &format!("Copy the bundled drivers to {}", drivers_dir.display()),
+                None,
+            ) {
+                copy_dir_all(&bundle_dir.join("drivers"), &drivers_dir)?;
+            }
+            let installed_drivers = drivers::get_installed_drivers().unwrap_or_default();
+            let plan =
+                drivers::plan_driver_installation(&manifest.drivers, &[], &installed_drivers);
+            drivers::install_drivers(
+                &plan.install,
+                &drivers_dir,
+                &DriverInstallOptions::default(),
+                (),
+            )
+            .await
+            .map_err(|e| anyhow!(e.to_string()))
+        }
+        .await;
+        report.record_phase(
+            "drivers",
+            started.elapsed(),
+            result.as_ref().err().map(|e| e.to_string()),
+        );
+        result?;
+    }
+
+    let mut registered = settings.clone();
+    registered.path = Some(install_path);
+    registered.tool_install_folder_name = Some(tool_install_folder_name);
+    registered.idf_versions = Some(
+        selected
+            .iter()
+            .map(|version| version.version.clone())
+            .collect(),
+    );
+    registered.save_esp_ide_json("")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_bundle_detects_corruption() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("tools")).unwrap();
+        let archive = dir.path().join("tools").join("cmake.tar.gz");
+        fs::write(&archive, "cmake").unwrap();
+        let manifest = BundleManifest {
+            format_version: BUNDLE_FORMAT_VERSION,
+            created_at: now_timestamp(),
+            python_version: "3.11".to_string(),
+            targets: vec!["esp32".to_string()],
+            platforms: vec!["linux-amd64".to_string()],
+            versions: vec![],
+            drivers: vec![],
+            files: vec![BundleFile {
+                path: "tools/cmake.tar.gz".to_string(),
+                sha256: crate::compute_file_checksum(archive.to_str().unwrap()).unwrap(),
+            }],
+        };
+        manifest.to_file(dir.path()).unwrap();
+        assert!(verify_bundle(dir.path()).is_ok());
+
+        fs::write(&archive, "tampered").unwrap();
+        let error = verify_bundle(dir.path()).unwrap_err();
+        assert!(error.to_string().contains("tools/cmake.tar.gz"));
+    }
+
+    #[test]
+    fn test_pack_and_unpack_bundle() {
+        let dir = tempfile::tempdir().unwrap();
+        let bundle_dir = dir.path().join("bundle");
+        fs::create_dir_all(bundle_dir.join("tools")).unwrap();
+        let archive = bundle_dir.join("tools").join("ninja.zip");
+        fs::write(&archive, "ninja").unwrap();
+        let manifest = BundleManifest {
+            format_version: BUNDLE_FORMAT_VERSION,
+            created_at: now_timestamp(),
+            python_version: "3.11".to_string(),
+            targets: vec!["esp32".to_string()],
+            platforms: vec!["linux-amd64".to_string()],
+            versions: vec![],
+            drivers: vec![],
+            files: vec![BundleFile {
+                path: "tools/ninja.zip".to_string(),
+                sha256: crate::compute_file_checksum(archive.to_str().unwrap()).unwrap(),
+            }],
+        };
+        manifest.to_file(&bundle_dir).unwrap();
+
+        let packed = pack_bundle(&bundle_dir, &dir.path().join("bundle.tar.gz")).unwrap();
+        let unpacked = unpack_bundle(&packed, &dir.path().join("unpacked")).unwrap();
+        assert_eq!(unpacked, manifest);
+    }
+
+    #[test]
+    fn test_pip_platform_tag() {
+        assert_eq!(pip_platform_tag("win64"), Some("win_amd64"));
+        assert_eq!(pip_platform_tag("unknown"), None);
+    }
+}
@@ -0,0 +1,302 @@
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Hint shown to WSL users, which can not access USB devices without forwarding them from Windows.
+pub const WSL_USB_HINT: &str = "USB devices are not available inside WSL by default. Use usbipd-win (https://github.com/dorssel/usbipd-win) to attach the ESP board to WSL, USB drivers have to be installed on the Windows side.";
+
+/// Environment variables set by common CI providers, with the provider name.
+const CI_PROVIDERS: &[(&str, &str)] = &[
+    ("GITHUB_ACTIONS", "GitHub Actions"),
+    ("GITLAB_CI", "GitLab CI"),
+    ("JENKINS_URL", "Jenkins"),
+    ("TF_BUILD", "Azure Pipelines"),
+    ("CIRCLECI", "CircleCI"),
+    ("TRAVIS", "Travis CI"),
+    ("BUILDKITE", "Buildkite"),
+    ("APPVEYOR", "AppVeyor"),
+    ("TEAMCITY_VERSION", "TeamCity"),
+    ("BITBUCKET_BUILD_NUMBER", "Bitbucket Pipelines"),
+];
+
+/// Information about the platform the library is running on.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlatformInfo {
+    pub os: String,
+    /// The architecture the library was built for, see `native_arch` for the one of the machine.
+    pub arch: String,
+    /// The architecture of the machine, e.g. `aarch64` for an `x86_64` build emulated on Windows on ARM.
+    pub native_arch: String,
+    pub is_wsl: bool,
+    pub is_container: bool,
+    /// Name of the detected CI provider, `Some("unknown")` if only `CI` is set.
+    pub ci_provider: Option<String>,
+    /// Whether the process may create symbolic links. On Windows this needs Developer Mode or administrator rights.
+    pub can_symlink: bool,
+    /// Whether the Windows Developer Mode is enabled, always `false` on other systems.
+    pub developer_mode: bool,
+}
+
+impl PlatformInfo {
+    /// Returns `true` in containers and CI, where nobody can answer prompts and
+    /// desktop integration or drivers make no sense.
+    pub fn is_unattended(&self) -> bool {
+        self.is_container || self.ci_provider.is_some()
+    }
+
+    /// Returns `true` when the process runs emulated, e.g. an x86_64 build on Windows on ARM or under Rosetta.
+    pub fn is_emulated(&self) -> bool {
+        self.arch != self.native_arch
+    }
+}
+
+/// Checks if the kernel version string belongs to a Windows Subsystem for Linux kernel.
+///
+/// # Parameters
+///
+/// * `version` - A string slice with the content of `/proc/version` or `/proc/sys/kernel/osrelease`.
+pub fn is_wsl_kernel_version(version: &str) -> bool {
+    let version = version.to_lowercase();
+    version.contains("microsoft") || version.contains("wsl")
+}
+
+/// Detects if the library runs inside the Windows Subsystem for Linux.
+///
+/// # Returns
+///
+/// * `true` - If running on Linux and `/proc/version` reports a WSL kernel.
+pub fn is_wsl() -> bool {
+    if std::env::consts::OS != "linux" {
+        return false;
+    }
+    match fs::read_to_string("/proc/version") {
+        Ok(version) => is_wsl_kernel_version(&version),
+        Err(e) => {
+            debug!("Unable to read /proc/version: {}", e);
+            false
+        }
+    }
+}
+
+/// Checks if the path points to a Windows drive mounted into WSL, e.g. `/mnt/c/Users`.
+pub fn is_windows_mount_path(path: &Path) -> bool {
+    let mut components = path.components().map(|c| c.as_os_str().to_string_lossy());
+    components.next().as_deref() == Some("/")
+        && components.next().as_deref() == Some("mnt")
+        && components
+            .next()
+            .map(|drive| drive.len() == 1 && drive.chars().all(|c| c.is_ascii_alphabetic()))
+            .unwrap_or(false)
+}
+
+/// Checks if the cgroup hierarchy of a process belongs to a container runtime.
+///
+/// # Parameters
+///
+/// * `cgroup` - A string slice with the content of `/proc/1/cgroup`.
+pub fn is_container_cgroup(cgroup: &str) -> bool {
+    ["docker", "kubepods", "containerd", "lxc", "libpod"]
+        .iter()
+        .any(|runtime| cgroup.contains(runtime))
+}
+
+/// Detects if the library runs inside a container (Docker, Podman, Kubernetes, LXC).
+pub fn is_container() -> bool {
+    if std::env::consts::OS != "linux" {
+        return false;
+    }
+    if Path::new("/.dockerenv").exists()
+        || Path::new("/run/.containerenv").exists()
+        || std::env::var_os("container").is_some()
+    {
+        return true;
+    }
+    fs::read_to_string("/proc/1/cgroup")
+        .map(|cgroup| is_container_cgroup(&cgroup))
+        .unwrap_or(false)
+}
+
+/// Detects the CI provider from the environment variables.
+///
+/// # Returns
+///
+/// * `Some(String)` - The name of the CI provider, or `unknown` if only the generic `CI` variable is set.
+/// * `None` - If not running in CI.
+pub fn detect_ci_provider() -> Option<String> {
+    for (variable, provider) in CI_PROVIDERS {
+        if std::env::var_os(variable).is_some() {
+            return Some(provider.to_string());
+        }
+    }
+    match std::env::var("CI") {
+        Ok(value) if !value.is_empty() && value != "false" && value != "0" => {
+            Some("unknown".to_string())
+        }
+        _ => None,
+    }
+}
+
+/// Checks if the Windows Developer Mode is enabled, which allows unprivileged symlinks.
+pub fn is_developer_mode_enabled() -> bool {
+    #[cfg(windows)]
+    {
+        use winreg::enums::{HKEY_LOCAL_MACHINE, KEY_READ};
+        winreg::RegKey::predef(HKEY_LOCAL_MACHINE)
+            .open_subkey_with_flags(
+                r"SOFTWARE\Microsoft\Windows\CurrentVersion\AppModelUnlock",
+                KEY_READ,
+            )
+            .and_then(|key| key.get_value::<u32, _>("AllowDevelopmentWithoutDevLicense"))
+            .map(|value| value == 1)
+            .unwrap_or(false)
+    }
+    #[cfg(not(windows))]
+    {
+        false
+    }
+}
+
+fn probe_symlink() -> bool {
+    let dir = std::env::temp_dir().join(format!("eim-symlink-probe-{}", std::process::id()));
+    let target = dir.join("target");
+    let link = dir.join("link");
+    let created = fs::create_dir_all(&dir)
+        .and_then(|_| fs::write(&target, b""))
+        .and_then(|_| {
+            #[cfg(windows)]
+            {
+                std::os::windows::fs::symlink_file(&target, &link)
+            }
+            #[cfg(unix)]
+            {
+                std::os::unix::fs::symlink(&target, &link)
+            }
+        });
+    let _ = fs::remove_dir_all(&dir);
+    if let Err(e) = &created {
+        debug!("Unable to create symlinks: {}", e);
+    }
+    created.is_ok()
+}
+
+/// Checks if the process can create symbolic links, by creating one in the temporary directory.
+///
+/// On Windows, symlinks need Developer Mode or administrator rights, so code creating links
+/// should fall back to copies when this returns `false`. The result is cached.
+pub fn can_create_symlinks() -> bool {
+    static CAN_SYMLINK: OnceLock<bool> = OnceLock::new();
+    *CAN_SYMLINK.get_or_init(probe_symlink)
+}
+
+/// Maps the `PROCESSOR_ARCHITECTURE` values of Windows to the Rust architecture names.
+pub fn normalize_windows_arch(arch: &str) -> Option<&'static str> {
+    match arch.trim().to_uppercase().as_str() {
+        "ARM64" => Some("aarch64"),
+        "AMD64" | "X64" => Some("x86_64"),
+        "X86" => Some("x86"),
+        _ => None,
+    }
+}
+
+/// Returns the architecture of the machine, which differs from `std::env::consts::ARCH` when
+/// the process runs emulated (x86_64 on Windows on ARM, Rosetta on Apple Silicon).
+pub fn native_arch() -> String {
+    let arch = std::env::consts::ARCH;
+    match std::env::consts::OS {
+        // the environment of an emulated process reports the emulated architecture, the registry does not
+        "windows" => {
+            #[cfg(windows)]
+            {
+                use winreg::enums::{HKEY_LOCAL_MACHINE, KEY_READ};
+                let native = winreg::RegKey::predef(HKEY_LOCAL_MACHINE)
+                    .open_subkey_with_flags(
+                        r"SYSTEM\CurrentControlSet\Control\Session Manager\Environment",
+                        KEY_READ,
+                    )
+                    .and_then(|key| key.get_value::<String, _>("PROCESSOR_ARCHITECTURE"));
+                if let Some(native) = native.ok().as_deref().and_then(normalize_windows_arch) {
+                    return native.to_string();
+                }
+            }
+            arch.to_string()
+        }
+        "macos" => {
+            let arm64 =
+                crate::command_executor::execute_command("sysctl", &["-n", "hw.optional.arm64"])
+                    .map(|o| String::from_utf8_lossy(&o.stdout).trim() == "1")
+                    .unwrap_or(false);
+            if arm64 {
+                "aarch64".to_string()
+            } else {
+                arch.to_string()
+            }
+        }
+        _ => arch.to_string(),
+    }
+}
+
+/// Returns information about the current platform.
+pub fn platform_info() -> PlatformInfo {
+    PlatformInfo {
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        native_arch: native_arch(),
+        is_wsl: is_wsl(),
+        is_container: is_container(),
+        ci_provider: detect_ci_provider(),
+        can_symlink: can_create_symlinks(),
+        developer_mode: is_developer_mode_enabled(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_wsl_kernel_version() {
+        assert!(is_wsl_kernel_version(
+            "Linux version 5.15.153.1-microsoft-standard-WSL2 (root@941d701f84f1)"
+        ));
+        assert!(is_wsl_kernel_version(
+            "Linux version 4.4.0-19041-Microsoft (Microsoft@Microsoft.com)"
+        ));
+        assert!(!is_wsl_kernel_version(
+            "Linux version 6.5.0-35-generic (buildd@lcy02-amd64-079)"
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_can_create_symlinks() {
+        assert!(can_create_symlinks());
+    }
+
+    #[test]
+    fn test_normalize_windows_arch() {
+        assert_eq!(normalize_windows_arch("ARM64"), Some("aarch64"));
+        assert_eq!(normalize_windows_arch("AMD64\r\n"), Some("x86_64"));
+        assert_eq!(normalize_windows_arch("x86"), Some("x86"));
+        assert_eq!(normalize_windows_arch("IA64"), None);
+    }
+
+    #[test]
+    fn test_is_container_cgroup() {
+        assert!(is_container_cgroup(
+            "0::/system.slice/docker-3f1c2a.scope\n"
+        ));
+        assert!(is_container_cgroup(
+            "12:pids:/kubepods/besteffort/pod1234\n"
+        ));
+        assert!(!is_container_cgroup("0::/init.scope\n"));
+    }
+
+    #[test]
+    fn test_is_windows_mount_path() {
+        assert!(is_windows_mount_path(Path::new("/mnt/c/Users/esp")));
+        assert!(!is_windows_mount_path(Path::new("/mnt/data/esp")));
+        assert!(!is_windows_mount_path(Path::new("/home/esp/.espressif")));
+    }
+}
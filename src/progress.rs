@@ -0,0 +1,261 @@
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+
+use crate::download::TransferStats;
+use crate::events::{self, InstallEvent, InstallPhase, Severity};
+use crate::{DownloadProgress, ProgressMessage};
+
+/// What a progress update measures, beyond the percentage.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProgressDetail {
+    /// Work on the item started.
+    Started,
+    /// The bytes of a download.
+    Bytes { downloaded: u64, total: u64 },
+    /// The speed and remaining time of a download.
+    Transfer(TransferStats),
+    /// The git objects received by a clone or a submodule update.
+    Objects { received: u64, total: u64 },
+    /// The item is done.
+    Finished,
+    /// Work on the item failed, with the error message.
+    Failed(String),
+}
+
+/// A progress update of a download, clone or driver installation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProgressUpdate {
+    pub phase: InstallPhase,
+    /// What progressed, e.g. the URL of a download or the name of a driver.
+    pub item: String,
+    /// Between 0 and 100, `None` when the total is unknown.
+    pub percent: Option<u8>,
+    /// A message to show to the user.
+    pub message: Option<String>,
+    pub detail: Option<ProgressDetail>,
+}
+
+impl ProgressUpdate {
+    pub fn new(phase: InstallPhase, item: &str) -> Self {
+        ProgressUpdate {
+            phase,
+            item: item.to_string(),
+            percent: None,
+            message: None,
+            detail: None,
+        }
+    }
+
+    pub fn percent(mut self, percent: u8) -> Self {
+        self.percent = Some(percent.min(100));
+        self
+    }
+
+    pub fn message(mut self, message: &str) -> Self {
+        self.message = Some(message.to_string());
+        self
+    }
+
+    pub fn detail(mut self, detail: ProgressDetail) -> Self {
+        self.detail = Some(detail);
+        self
+    }
+
+    /// The `InstallEvent` published for the update, `None` for the transfer statistics and
+    /// updates without a percentage or message.
+    pub fn event(&self) -> Option<InstallEvent> {
+        let phase = self.phase;
+        let item = self.item.clone();
+        match (&self.detail, self.percent, &self.message) {
+            (Some(ProgressDetail::Started), _, _) => Some(InstallEvent::Started { phase, item }),
+            (Some(ProgressDetail::Finished), _, _) => Some(InstallEvent::Completed { phase, item }),
+            (Some(ProgressDetail::Failed(error)), _, _) => Some(InstallEvent::Failed {
+                phase,
+                item,
+                error: error.clone(),
+            }),
+            (Some(ProgressDetail::Transfer(_)), _, _) => None,
+            (_, Some(percent), _) => Some(InstallEvent::Progress {
+                phase,
+                item,
+                percent,
+            }),
+            (_, None, Some(message)) => Some(InstallEvent::Message {
+                phase,
+                severity: Severity::Info,
+                message: message.clone(),
+            }),
+            (_, None, None) => None,
+        }
+    }
+}
+
+/// Reports the update to the reporter of the operation and publishes it as an `InstallEvent` to
+/// the subscribers of `events`.
+///
+/// This is the way the operations report their progress, so the reporter of an operation and the
+/// installation-wide subscribers always see the same progress.
+pub fn report<R: ProgressReporter + ?Sized>(reporter: &R, update: &ProgressUpdate) {
+    reporter.report(update);
+    if let Some(event) = update.event() {
+        events::publish(event);
+    }
+}
+
+/// Receives the progress of a single operation, e.g. a download or a clone.
+///
+/// Closures taking a `&ProgressUpdate` are reporters, and so are the channel senders the
+/// operations took before, which receive the updates translated to their messages:
+/// `Sender<DownloadProgress>` the download updates and `Sender<ProgressMessage>` the clone updates.
+/// The operations report through `report`, which publishes the same updates to the subscribers
+/// of `events`.
+///
+/// `report` is called synchronously from the operation, possibly from a git or download thread.
+pub trait ProgressReporter: Send + Sync {
+    fn report(&self, update: &ProgressUpdate);
+}
+
+impl<F: Fn(&ProgressUpdate) + Send + Sync> ProgressReporter for F {
+    fn report(&self, update: &ProgressUpdate) {
+        self(update)
+    }
+}
+
+impl<R: ProgressReporter + ?Sized> ProgressReporter for &R {
+    fn report(&self, update: &ProgressUpdate) {
+        (**self).report(update)
+    }
+}
+
+impl<R: ProgressReporter + ?Sized> ProgressReporter for Arc<R> {
+    fn report(&self, update: &ProgressUpdate) {
+        (**self).report(update)
+    }
+}
+
+/// Ignores all updates, for callers not showing any progress.
+impl ProgressReporter for () {
+    fn report(&self, _update: &ProgressUpdate) {}
+}
+
+impl ProgressReporter for Sender<DownloadProgress> {
+    fn report(&self, update: &ProgressUpdate) {
+        let progress = match &update.detail {
+            Some(ProgressDetail::Bytes { downloaded, total }) => {
+                DownloadProgress::Progress(*downloaded, *total)
+            }
+            Some(ProgressDetail::Transfer(stats)) => DownloadProgress::Transfer(stats.clone()),
+            Some(ProgressDetail::Finished) => DownloadProgress::Complete,
+            Some(ProgressDetail::Failed(error)) => DownloadProgress::Error(error.clone()),
+            _ => return,
+        };
+        let _ = self.send(progress);
+    }
+}
+
+impl ProgressReporter for Sender<ProgressMessage> {
+    fn report(&self, update: &ProgressUpdate) {
+        match (&update.detail, update.percent) {
+            (Some(ProgressDetail::Finished), _) => {
+                let _ = self.send(ProgressMessage::Finish);
+            }
+            (Some(ProgressDetail::Objects { .. }), Some(percent)) => {
+                let _ = self.send(ProgressMessage::Update(percent as u64));
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::channel;
+
+    #[test]
+    fn test_download_sender_receives_bytes() {
+        let (sender, receiver) = channel();
+        let reporter: &dyn ProgressReporter = &sender;
+        reporter.report(
+            &ProgressUpdate::new(InstallPhase::Download, "cmake.tar.gz")
+                .percent(50)
+                .detail(ProgressDetail::Bytes {
+                    downloaded: 50,
+                    total: 100,
+                }),
+        );
+        reporter.report(&ProgressUpdate::new(InstallPhase::Download, "cmake.tar.gz"));
+        reporter.report(
+            &ProgressUpdate::new(InstallPhase::Download, "cmake.tar.gz")
+                .detail(ProgressDetail::Finished),
+        );
+        let received: Vec<DownloadProgress> = receiver.try_iter().collect();
+        assert_eq!(received.len(), 2);
+        assert!(matches!(received[0], DownloadProgress::Progress(50, 100)));
+        assert!(matches!(received[1], DownloadProgress::Complete));
+    }
+
+    #[test]
+    fn test_update_events() {
+        let update = ProgressUpdate::new(InstallPhase::Download, "cmake.tar.gz");
+        assert_eq!(
+            update.clone().detail(ProgressDetail::Started).event(),
+            Some(InstallEvent::Started {
+                phase: InstallPhase::Download,
+                item: "cmake.tar.gz".to_string(),
+            })
+        );
+        assert_eq!(
+            update
+                .clone()
+                .percent(40)
+                .detail(ProgressDetail::Bytes {
+                    downloaded: 40,
+                    total: 100,
+                })
+                .event(),
+            Some(InstallEvent::Progress {
+                phase: InstallPhase::Download,
+                item: "cmake.tar.gz".to_string(),
+                percent: 40,
+            })
+        );
+        assert_eq!(
+            update
+                .clone()
+                .percent(40)
+                .detail(ProgressDetail::Transfer(TransferStats {
+                    file_name: "cmake.tar.gz".to_string(),
+                    downloaded: 40,
+                    total: 100,
+                    speed: 0.0,
+                    average_speed: 0.0,
+                    eta: None,
+                }))
+                .event(),
+            None
+        );
+        assert_eq!(
+            update
+                .detail(ProgressDetail::Failed("offline".to_string()))
+                .event()
+                .map(|event| event.severity()),
+            Some(Severity::Error)
+        );
+    }
+
+    #[test]
+    fn test_clone_sender_receives_percent() {
+        let (sender, receiver) = channel();
+        sender.report(
+            &ProgressUpdate::new(InstallPhase::Clone, "esp-idf")
+                .percent(30)
+                .detail(ProgressDetail::Objects {
+                    received: 3,
+                    total: 10,
+                }),
+        );
+        let received: Vec<ProgressMessage> = receiver.try_iter().collect();
+        assert!(matches!(received[..], [ProgressMessage::Update(30)]));
+    }
+}
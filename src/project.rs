@@ -0,0 +1,227 @@
+use anyhow::{anyhow, Result};
+use log::{debug, info};
+use regex::Regex;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::command_executor::{execute_checked, ExecOptions};
+use crate::dry_run::{self, ActionKind};
+use crate::environment::{find_installation, get_activation_environment, variables_with_path};
+use crate::idf_config::IdfInstallation;
+use crate::utils::copy_dir_all;
+
+/// Name of the file recording the ESP-IDF installation a project was created with.
+pub const PROJECT_VERSION_FILE_NAME: &str = ".eim_idf_version";
+
+/// What a new project is created from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProjectTemplate {
+    /// An example of the ESP-IDF installation, relative to its `examples` folder,
+    /// e.g. `get-started/hello_world`.
+    Example(String),
+    /// An example of a component of the ESP Component Registry, e.g.
+    /// `espressif/esp_insights^1.0.0:minimal_diagnostics`.
+    Registry(String),
+}
+
+/// Lists the examples of an installation, relative to its `examples` folder.
+///
+/// A folder counts as example when its `CMakeLists.txt` declares a project.
+pub fn list_examples(installation: &IdfInstallation) -> Vec<String> {
+    fn collect(root: &Path, dir: &Path, examples: &mut Vec<String>) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        let mut subdirs: Vec<PathBuf> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir())
+            .collect();
+        subdirs.sort();
+        for subdir in subdirs {
+            let is_project = fs::read_to_string(subdir.join("CMakeLists.txt"))
+                .map(|content| content.contains("project.cmake"))
+                .unwrap_or(false);
+            if is_project {
+                let relative = subdir.strip_prefix(root).unwrap_or(&subdir);
+                examples.push(relative.to_string_lossy().replace('\\', "/"));
+            } else {
+                collect(root, &subdir, examples);
+            }
+        }
+    }
+
+    let root = Path::new(&installation.path).join("examples");
+    let mut examples = vec![];
+    collect(&root, &root, &mut examples);
+    examples
+}
+
+/// Replaces the name in the `project()` call of a top-level `CMakeLists.txt`.
+pub fn rename_cmake_project(cmake_lists: &str, name: &str) -> String {
+    let project = Regex::new(r"(?m)^(\s*project\s*\(\s*)[^\s)]+").unwrap();
+    project
+        .replace(cmake_lists, |caps: &regex::Captures| {
+            format!("{}{}", &caps[1], name)
+        })
+        .into_owned()
+}
+
+/// Runs idf.py of an installation in a directory.
+fn run_idf_py(installation: &IdfInstallation, dir: &Path, args: &[&str]) -> Result<()> {
+    let idf_py = Path::new(&installation.path).join("tools").join("idf.py");
+    let mut full_args = vec![idf_py.to_str().unwrap()];
+    full_args.extend(args);
+    if dry_run::plan(
+        ActionKind::RunCommand,
+        &format!("Run idf.py {} in {}", args.join(" "), dir.display()),
+        None,
+    ) {
+        return Ok(());
+    }
+    let environment = get_activation_environment(installation)?;
+    let variables = variables_with_path(&environment, std::env::var_os("PATH"));
+    let env: Vec<(&str, &str)> = variables
+        .iter()
+        .map(|(key, value)| (key.as_str(), value.as_str()))
+        .collect();
+    execute_checked(
+        &installation.python,
+        &full_args,
+        env,
+        &ExecOptions::new().current_dir(dir),
+    )
+    .map_err(|e| anyhow!("idf.py {} failed: {}", args.join(" "), e))?;
+    Ok(())
+}
+
+/// Writes the version marker of a project, recording the installation it belongs to.
+pub fn write_version_marker(project_dir: &Path, installation: &IdfInstallation) -> Result<()> {
+    let marker = project_dir.join(PROJECT_VERSION_FILE_NAME);
+    if dry_run::plan(
+        ActionKind::WriteFile,
+        &format!("Write {}", marker.display()),
+        None,
+    ) {
+        return Ok(());
+    }
+    fs::write(&marker, format!("{}\n", installation.name))?;
+    Ok(())
+}
+
+/// Reads the version marker of a project, the name of the installation it was created with.
+pub fn read_version_marker(project_dir: &Path) -> Option<String> {
+    fs::read_to_string(project_dir.join(PROJECT_VERSION_FILE_NAME))
+        .ok()
+        .map(|content| content.trim().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+/// Creates a new project from a template with a registered installation.
+///
+/// The template is copied to `project_dir` and renamed after its folder, the target is set if
+/// given and the version marker records the installation, so tools can activate the right one.
+///
+/// # Parameters
+///
+/// * `identifier` - The id or name of the installation.
+/// * `template` - The example or registry template to start from.
+/// * `project_dir` - The folder of the new project, it must not exist.
+/// * `target` - The chip to set, e.g. `esp32s3`, or `None` to keep the default.
+///
+/// # Returns
+///
+/// * `Result<PathBuf, anyhow::Error>` - The folder of the created project.
+pub fn create_project(
+    identifier: &str,
+    template: &ProjectTemplate,
+    project_dir: &Path,
+    target: Option<&str>,
+) -> Result<PathBuf> {
+    let installation = find_installation(identifier)?;
+    if project_dir.exists() {
+        return Err(anyhow!("{} already exists", project_dir.display()));
+    }
+    let name = project_dir
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| anyhow!("Invalid project folder {}", project_dir.display()))?;
+    let parent = project_dir
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+
+    match template {
+        ProjectTemplate::Example(example) => {
+            let source = Path::new(&installation.path).join("examples").join(example);
+            if !source.is_dir() {
+                return Err(anyhow!(
+                    "Example {} not found in ESP-IDF {}",
+                    example,
+                    installation.name
+                ));
+            }
+            if !dry_run::plan(
+                ActionKind::WriteFile,
+                &format!("Copy {} to {}", source.display(), project_dir.display()),
+                None,
+            ) {
+                copy_dir_all(&source, project_dir)?;
+            }
+        }
+        ProjectTemplate::Registry(reference) => {
+            crate::ensure_path(parent.to_str().unwrap())?;
+            run_idf_py(
+                &installation,
+                parent,
+                &[
+                    "create-project-from-example",
+                    "-p",
+                    project_dir.to_str().unwrap(),
+                    reference,
+                ],
+            )?;
+        }
+    }
+
+    let cmake_lists = project_dir.join("CMakeLists.txt");
+    if let Ok(content) = fs::read_to_string(&cmake_lists) {
+        fs::write(&cmake_lists, rename_cmake_project(&content, name))?;
+        debug!(
+            "Renamed the project in {} to {}",
+            cmake_lists.display(),
+            name
+        );
+    }
+    if let Some(target) = target {
+        run_idf_py(&installation, project_dir, &["set-target", target])?;
+    }
+    write_version_marker(project_dir, &installation)?;
+    info!(
+        "Created project {} with ESP-IDF {}",
+        project_dir.display(),
+        installation.name
+    );
+    Ok(project_dir.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rename_cmake_project() {
+        let cmake = "cmake_minimum_required(VERSION 3.16)\n\ninclude($ENV{IDF_PATH}/tools/cmake/project.cmake)\nproject(hello_world)\n";
+        let renamed = rename_cmake_project(cmake, "blinky");
+        assert!(renamed.contains("project(blinky)\n"));
+        assert!(renamed.contains("include($ENV{IDF_PATH}/tools/cmake/project.cmake)"));
+    }
+
+    #[test]
+    fn test_version_marker() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(read_version_marker(dir.path()), None);
+        fs::write(dir.path().join(PROJECT_VERSION_FILE_NAME), "v5.3\n").unwrap();
+        assert_eq!(read_version_marker(dir.path()), Some("v5.3".to_string()));
+    }
+}
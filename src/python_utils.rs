@@ -8,7 +8,67 @@ use std::process::ExitCode;
 #[cfg(feature = "userustpython")]
 use vm::{builtins::PyStrRef, Interpreter};
 
-use crate::{command_executor, replace_unescaped_spaces_posix, replace_unescaped_spaces_win};
+use std::path::Path;
+use std::sync::mpsc::Sender;
+
+use crate::command_executor::{self, quote_posix, quote_powershell};
+use crate::command_executor::{CapturedOutput, ExecOptions, OutputLine};
+use crate::events::InstallPhase;
+use crate::progress::{self, ProgressDetail, ProgressUpdate};
+use crate::CancellationToken;
+
+/// Builds the command running a Python script file, returning the program and its arguments.
+fn python_script_command(
+    path: &str,
+    args: Option<&str>,
+    python: Option<&str>,
+) -> (String, Vec<String>) {
+    match std::env::consts::OS {
+        "windows" => (
+            "powershell".to_string(),
+            vec![
+                "-Command".to_string(),
+                format!(
+                    "& {} {} {}",
+                    quote_powershell(python.unwrap_or("python3.exe")),
+                    quote_powershell(path),
+                    args.unwrap_or("")
+                )
+                .trim_end()
+                .to_string(),
+            ],
+        ),
+        _ => {
+            let callable = format!(
+                "{} {} {}",
+                quote_posix(python.unwrap_or("python3")),
+                quote_posix(path),
+                args.unwrap_or("")
+            )
+            .trim_end()
+            .to_string();
+            (
+                command_executor::get_script_shell(),
+                vec!["-c".to_string(), callable],
+            )
+        }
+    }
+}
+
+/// On failure, the error contains the interleaved stdout and stderr, which keeps pip and idf_tools.py
+/// errors next to the step which caused them.
+fn captured_to_result(output: std::io::Result<CapturedOutput>) -> Result<String, String> {
+    match output {
+        Ok(out) => {
+            if out.success() {
+                Ok(out.stdout())
+            } else {
+                Err(out.report())
+            }
+        }
+        Err(e) => Err(e.to_string()),
+    }
+}
 
 /// Runs a Python script from a specified file with optional arguments and environment variables.
 /// todo: check documentation
@@ -22,73 +82,111 @@ use crate::{command_executor, replace_unescaped_spaces_posix, replace_unescaped_
 /// # Returns
 ///
 /// * `Result<String, String>` - On success, returns a `Result` containing the standard output of the Python script as a string.
-///   On error, returns a `Result` containing the standard error of the Python script as a string.
+///   On error, returns a `Result` containing the interleaved stdout and stderr of the Python script, each line tagged with its stream.
 pub fn run_python_script_from_file(
     path: &str,
     args: Option<&str>,
     python: Option<&str>,
     envs: Option<&Vec<(String, String)>>,
 ) -> Result<String, String> {
-    let callable = if let Some(args) = args {
-        format!("{} {} {}", python.unwrap_or("python3"), path, args)
-    } else {
-        format!("{} {}", python.unwrap_or("python3"), path)
-    };
-    let executor = command_executor::get_executor();
+    run_python_script_from_file_with_options(path, args, python, envs, &ExecOptions::default())
+}
 
-    let output = match envs {
-        Some(envs) => {
-            let envs_str = envs
-                .iter()
+/// Runs a Python script from a file like `run_python_script_from_file`, with the given execution options,
+/// e.g. the working directory or a clean environment.
+///
+/// # Parameters
+///
+/// * `path` - The path to the script file.
+/// * `args` - Optional arguments passed to the script.
+/// * `python` - Optional python executable, defaults to `python3` (or `python` on Windows).
+/// * `envs` - Optional additional environment variables.
+/// * `options` - The options of the python invocation.
+///
+/// # Returns
+///
+/// * `Result<String, String>` - The stdout of the script on success, its tagged, interleaved stdout and stderr otherwise.
+pub fn run_python_script_from_file_with_options(
+    path: &str,
+    args: Option<&str>,
+    python: Option<&str>,
+    envs: Option<&Vec<(String, String)>>,
+    options: &ExecOptions,
+) -> Result<String, String> {
+    let (program, program_args) = python_script_command(path, args, python);
+    let program_args: Vec<&str> = program_args.iter().map(|a| a.as_str()).collect();
+    let envs_str = envs
+        .map(|envs| {
+            envs.iter()
                 .map(|(k, v)| (k.as_str(), v.as_str()))
-                .collect::<Vec<(&str, &str)>>();
-
-            match std::env::consts::OS {
-                "windows" => executor.execute_with_env(
-                    "powershell",
-                    &vec![
-                        "-Command",
-                        python.unwrap_or("python3.exe"),
-                        path,
-                        args.unwrap_or(""),
-                    ],
-                    envs_str,
-                ),
-                _ => executor.execute_with_env("bash", &vec!["-c", &callable], envs_str),
-            }
-        }
-        None => match std::env::consts::OS {
-            "windows" => executor.execute(
-                "powershell",
-                &vec![
-                    "-Command",
-                    python.unwrap_or("python3.exe"),
-                    path,
-                    args.unwrap_or(""),
-                ],
-            ),
-            _ => executor.execute("bash", &vec!["-c", &callable]),
-        },
-    };
+                .collect::<Vec<(&str, &str)>>()
+        })
+        .unwrap_or_default();
 
-    match output {
-        Ok(out) => {
-            if out.status.success() {
-                Ok(std::str::from_utf8(&out.stdout).unwrap().to_string())
-            } else {
-                Err(std::str::from_utf8(&out.stderr).unwrap().to_string())
-            }
-        }
-        Err(e) => Err(e.to_string()),
-    }
+    let output =
+        command_executor::execute_command_captured(&program, &program_args, envs_str, options);
+
+    captured_to_result(output)
+}
+
+/// Runs a Python script from a file like `run_python_script_from_file`, streaming its output while it runs.
+///
+/// # Parameters
+///
+/// * `path` - A reference to a string representing the path to the Python script file.
+/// * `args` - An optional reference to a string representing the arguments to be passed to the Python script.
+/// * `python` - An optional reference to a string representing the Python interpreter to be used.
+/// * `envs` - An optional reference to a vector of tuples representing environment variables to be set for the Python script.
+/// * `sender` - A channel sender receiving every line of the output.
+///
+/// # Returns
+///
+/// * `Result<String, String>` - On success, returns the standard output of the Python script as a string.
+///   On error, returns the interleaved stdout and stderr of the Python script, each line tagged with its stream.
+pub fn run_python_script_from_file_streaming(
+    path: &str,
+    args: Option<&str>,
+    python: Option<&str>,
+    envs: Option<&Vec<(String, String)>>,
+    sender: Sender<OutputLine>,
+) -> Result<String, String> {
+    stream_python_script(path, args, python, envs, &ExecOptions::default(), sender)
+}
+
+fn stream_python_script(
+    path: &str,
+    args: Option<&str>,
+    python: Option<&str>,
+    envs: Option<&Vec<(String, String)>>,
+    options: &ExecOptions,
+    sender: Sender<OutputLine>,
+) -> Result<String, String> {
+    let (program, program_args) = python_script_command(path, args, python);
+    let program_args: Vec<&str> = program_args.iter().map(|a| a.as_str()).collect();
+    let envs_str = envs
+        .map(|envs| {
+            envs.iter()
+                .map(|(k, v)| (k.as_str(), v.as_str()))
+                .collect::<Vec<(&str, &str)>>()
+        })
+        .unwrap_or_default();
+    let mut lines = vec![];
+    let output = command_executor::get_executor()
+        .execute_streaming_with_options(&program, &program_args, envs_str, options, &mut |line| {
+            lines.push(line.clone());
+            let _ = sender.send(line);
+        })
+        .map(|out| CapturedOutput {
+            status: out.status,
+            lines,
+        });
+    captured_to_result(output)
 }
 
 /// Runs the IDF tools Python installation script.
 ///
-/// This function prepares the environment to run a Python installation script for
-/// IDF tools by ensuring that the path is properly escaped based on the operating
-/// system. It then executes the installation script followed by the Python environment
-/// setup script.
+/// This function runs the IDF tools installation script, with the path quoted for the
+/// shell of the operating system, followed by the Python environment setup script.
 ///
 /// # Parameters
 ///
@@ -118,24 +216,148 @@ pub fn run_idf_tools_py(
     idf_tools_path: &str,
     environment_variables: &Vec<(String, String)>,
 ) -> Result<String, String> {
-    let escaped_path = if std::env::consts::OS == "windows" {
-        replace_unescaped_spaces_win(&idf_tools_path)
-    } else {
-        replace_unescaped_spaces_posix(&idf_tools_path)
-    };
-    run_install_script(&escaped_path, environment_variables)?;
-    run_install_python_env_script(&escaped_path, environment_variables)
+    if plan_idf_tools_py(idf_tools_path) {
+        return Ok(String::new());
+    }
+    let options = idf_tools_options(idf_tools_path);
+    publish_phase(InstallPhase::Tools, idf_tools_path, || {
+        run_install_script(idf_tools_path, environment_variables, &options)
+    })?;
+    publish_phase(InstallPhase::PythonEnvironment, idf_tools_path, || {
+        run_install_python_env_script(idf_tools_path, environment_variables, &options)
+    })
+}
+
+/// Runs the IDF tools installation like `run_idf_tools_py`, killing idf_tools.py once the token
+/// is cancelled.
+///
+/// On cancellation the partially created python environment (`IDF_PYTHON_ENV_PATH` of
+/// `environment_variables`) is removed, so the next attempt does not reuse a broken one.
+pub fn run_idf_tools_py_cancellable(
+    idf_tools_path: &str,
+    environment_variables: &Vec<(String, String)>,
+    cancellation: &CancellationToken,
+) -> Result<String, String> {
+    if plan_idf_tools_py(idf_tools_path) {
+        return Ok(String::new());
+    }
+    let options = idf_tools_options(idf_tools_path).cancellation(cancellation.clone());
+    let result = publish_phase(InstallPhase::Tools, idf_tools_path, || {
+        run_install_script(idf_tools_path, environment_variables, &options)
+    })
+    .and_then(|_| {
+        publish_phase(InstallPhase::PythonEnvironment, idf_tools_path, || {
+            run_install_python_env_script(idf_tools_path, environment_variables, &options)
+        })
+    });
+    if cancellation.is_cancelled() {
+        if let Some((_, python_env)) = environment_variables
+            .iter()
+            .find(|(key, _)| key == "IDF_PYTHON_ENV_PATH")
+        {
+            let _ = crate::utils::remove_directory_all(python_env);
+        }
+        return Err(crate::messages::message(
+            crate::messages::MessageId::ToolsInstallationCancelled,
+            &[],
+        ));
+    }
+    result
+}
+
+/// Runs a step of the tools installation, publishing its start and outcome to the event subscribers.
+fn publish_phase(
+    phase: InstallPhase,
+    item: &str,
+    step: impl FnOnce() -> Result<String, String>,
+) -> Result<String, String> {
+    let update = ProgressUpdate::new(phase, item);
+    progress::report(&(), &update.clone().detail(ProgressDetail::Started));
+    let result = step();
+    progress::report(
+        &(),
+        &update.detail(match &result {
+            Ok(_) => ProgressDetail::Finished,
+            Err(e) => ProgressDetail::Failed(e.clone()),
+        }),
+    );
+    result
+}
+
+/// Records the tools installation and the python environment creation in dry-run mode.
+///
+/// Returns `true` if they must be skipped.
+fn plan_idf_tools_py(idf_tools_path: &str) -> bool {
+    crate::dry_run::plan(
+        crate::dry_run::ActionKind::RunCommand,
+        &format!("Run {} install and install-python-env", idf_tools_path),
+        None,
+    )
+}
+
+/// The options for running idf_tools.py: from the root of the IDF, in a clean environment,
+/// so an activated conda or virtual environment or a stale `IDF_PATH` of the user's shell do not leak in.
+fn idf_tools_options(idf_tools_path: &str) -> ExecOptions {
+    let options = ExecOptions::new().clean_env();
+    // idf_tools.py lives in <idf_path>/tools
+    match Path::new(idf_tools_path).parent().and_then(Path::parent) {
+        Some(idf_dir) => options.current_dir(idf_dir),
+        None => options,
+    }
+}
+
+/// Runs the IDF tools Python installation script like `run_idf_tools_py`, streaming the output of both steps.
+///
+/// # Parameters
+///
+/// - `idf_tools_path`: A string slice that represents the path to the IDF tools.
+/// - `environment_variables`: A vector of tuples containing environment variable names
+///   and their corresponding values, which will be passed to the installation scripts.
+/// - `sender`: A channel sender receiving every line of the output, so frontends can show live logs.
+///
+/// # Returns
+///
+/// This function returns a `Result<String, String>`. On success, it returns an `Ok`
+/// containing the output of the Python environment setup script. On failure, it returns
+/// an `Err` containing an error message.
+pub fn run_idf_tools_py_streaming(
+    idf_tools_path: &str,
+    environment_variables: &Vec<(String, String)>,
+    sender: Sender<OutputLine>,
+) -> Result<String, String> {
+    if plan_idf_tools_py(idf_tools_path) {
+        return Ok(String::new());
+    }
+    let options = idf_tools_options(idf_tools_path);
+    stream_python_script(
+        idf_tools_path,
+        Some("install"),
+        None,
+        Some(environment_variables),
+        &options,
+        sender.clone(),
+    )?;
+    stream_python_script(
+        idf_tools_path,
+        Some("install-python-env"),
+        None,
+        Some(environment_variables),
+        &options,
+        sender,
+    )
 }
 
 fn run_install_script(
     idf_tools_path: &str,
     environment_variables: &Vec<(String, String)>,
+    options: &ExecOptions,
 ) -> Result<String, String> {
-    let output = run_python_script_from_file(
+    let output = run_python_script_from_file_with_options(
         idf_tools_path,
         Some("install"),
         None,
         Some(environment_variables),
+        options,
     );
 
     trace!("idf_tools.py install output:\n{:?}", output);
@@ -146,12 +368,14 @@ fn run_install_script(
 fn run_install_python_env_script(
     idf_tools_path: &str,
     environment_variables: &Vec<(String, String)>,
+    options: &ExecOptions,
 ) -> Result<String, String> {
-    let output = run_python_script_from_file(
+    let output = run_python_script_from_file_with_options(
         idf_tools_path,
         Some("install-python-env"),
         None,
         Some(environment_variables),
+        options,
     );
 
     trace!("idf_tools.py install-python-env output:\n{:?}", output);
@@ -209,6 +433,62 @@ pub fn get_python_platform_definition(python: Option<&str>) -> String {
     }
 }
 
+/// The oldest Python version supported by ESP-IDF.
+pub const MINIMUM_PYTHON_VERSION: (u32, u32) = (3, 8);
+
+/// Parses the output of `python --version`, e.g. `Python 3.11.4`.
+///
+/// # Returns
+///
+/// * `Some((u32, u32, u32))` - The major, minor and patch version. A missing patch is treated as 0.
+/// * `None` - If the output can not be parsed.
+pub fn parse_python_version(output: &str) -> Option<(u32, u32, u32)> {
+    let version = output.trim().strip_prefix("Python ")?;
+    let mut parts = version.split('.').map(|part| {
+        part.chars()
+            .take_while(|c| c.is_ascii_digit())
+            .collect::<String>()
+            .parse::<u32>()
+    });
+    let major = parts.next()?.ok()?;
+    let minor = parts.next()?.ok()?;
+    let patch = parts.next().and_then(|p| p.ok()).unwrap_or(0);
+    Some((major, minor, patch))
+}
+
+/// Retrieves the version of the Python interpreter.
+///
+/// # Parameters
+///
+/// * `python` - An optional reference to a string representing the Python interpreter to be used.
+///   If `None`, the function will default to using "python3".
+///
+/// # Returns
+///
+/// * `Result<(u32, u32, u32), String>` - On success, returns the major, minor and patch version.
+///   On error, returns the error message.
+pub fn get_python_version(python: Option<&str>) -> Result<(u32, u32, u32), String> {
+    let output = command_executor::execute_command(python.unwrap_or("python3"), &["--version"])
+        .map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).to_string());
+    }
+    // python 2 printed the version to stderr
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let text = if stdout.trim().is_empty() {
+        stderr
+    } else {
+        stdout
+    };
+    parse_python_version(&text).ok_or(format!("Unable to parse python version from {}", text))
+}
+
+/// Checks if the given Python version satisfies `MINIMUM_PYTHON_VERSION`.
+pub fn is_python_version_supported(version: (u32, u32, u32)) -> bool {
+    (version.0, version.1) >= MINIMUM_PYTHON_VERSION
+}
+
 /// Performs a series of sanity checks for the Python interpreter.
 ///
 /// This function executes various Python scripts and checks for the availability of essential Python modules,
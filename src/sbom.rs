@@ -0,0 +1,322 @@
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+
+use crate::environment::find_installation;
+use crate::idf_config::IdfInstallation;
+use crate::idf_tools::{
+    get_download_for_platform, get_platform_identification, read_and_parse_tools_file,
+};
+use crate::manifest::{get_idf_commit, get_python_packages};
+
+/// The format of a software bill of materials, see `generate_sbom`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SbomFormat {
+    /// CycloneDX 1.5 JSON.
+    CycloneDx,
+    /// SPDX 2.3 JSON.
+    Spdx,
+}
+
+impl SbomFormat {
+    /// Parses the name of a format: `cyclonedx` or `spdx`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "cyclonedx" | "cdx" => Some(SbomFormat::CycloneDx),
+            "spdx" => Some(SbomFormat::Spdx),
+            _ => None,
+        }
+    }
+}
+
+/// What a component of an installation is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComponentKind {
+    /// The ESP-IDF repository itself.
+    Framework,
+    /// A tool from `tools.json`, e.g. a compiler or OpenOCD.
+    Tool,
+    /// A package of the python environment.
+    PythonPackage,
+}
+
+/// A component of an installation listed in its SBOM.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SbomComponent {
+    pub kind: ComponentKind,
+    pub name: String,
+    pub version: String,
+    /// SPDX license expression, as declared in `tools.json`.
+    pub license: Option<String>,
+    /// Checksum of the downloaded archive.
+    pub sha256: Option<String>,
+    pub download_url: Option<String>,
+    /// Package URL identifying the component, e.g. `pkg:pypi/esptool@4.7.0`.
+    pub purl: Option<String>,
+}
+
+/// Collects the ESP-IDF commit, the installed tools and the python packages of an installation.
+///
+/// Only the tool versions present in the tools directory are listed, with the checksum and the
+/// download URL of this platform.
+///
+/// # Parameters
+///
+/// * `installation` - The installation to inspect.
+///
+/// # Returns
+///
+/// * `Result<Vec<SbomComponent>, anyhow::Error>` - The components, ESP-IDF first.
+pub fn collect_sbom_components(installation: &IdfInstallation) -> Result<Vec<SbomComponent>> {
+    let idf_path = PathBuf::from(&installation.path);
+    let commit = get_idf_commit(&idf_path).ok();
+    let mut components = vec![SbomComponent {
+        kind: ComponentKind::Framework,
+        name: "esp-idf".to_string(),
+        version: installation.name.clone(),
+        license: Some("Apache-2.0".to_string()),
+        sha256: None,
+        download_url: Some("https://github.com/espressif/esp-idf".to_string()),
+        purl: Some(format!(
+            "pkg:github/espressif/esp-idf@{}",
+            commit.as_deref().unwrap_or(&installation.name)
+        )),
+    }];
+
+    let tools_file =
+        read_and_parse_tools_file(idf_path.join("tools").join("tools.json").to_str().unwrap())
+            .map_err(|e| anyhow!("Failed to read tools.json: {}", e))?;
+    let platform = get_platform_identification(None).map_err(|e| anyhow!(e))?;
+    let tools_dir = Path::new(&installation.idf_tools_path).join("tools");
+    for tool in tools_file.tools {
+        for version in &tool.versions {
+            if !tools_dir.join(&tool.name).join(&version.name).is_dir() {
+                continue;
+            }
+            let download = get_download_for_platform(&version.downloads, &platform);
+            components.push(SbomComponent {
+                kind: ComponentKind::Tool,
+                name: tool.name.clone(),
+                version: version.name.clone(),
+                license: tool.license.clone(),
+                sha256: download.map(|download| download.sha256.clone()),
+                download_url: download.map(|download| download.url.clone()),
+                purl: None,
+            });
+        }
+    }
+
+    for package in get_python_packages(&installation.python)? {
+        // editable installs and direct references have no version to report
+        let Some((name, version)) = package.split_once("==") else {
+            continue;
+        };
+        components.push(SbomComponent {
+            kind: ComponentKind::PythonPackage,
+            name: name.to_string(),
+            version: version.to_string(),
+            license: None,
+            sha256: None,
+            download_url: None,
+            purl: Some(format!("pkg:pypi/{}@{}", name.to_lowercase(), version)),
+        });
+    }
+    Ok(components)
+}
+
+/// Renders a CycloneDX 1.5 document.
+fn render_cyclonedx(
+    installation_name: &str,
+    components: &[SbomComponent],
+    timestamp: &str,
+) -> Value {
+    let components: Vec<Value> = components
+        .iter()
+        .map(|component| {
+            let mut entry = json!({
+                "type": match component.kind {
+                    ComponentKind::Framework => "framework",
+                    ComponentKind::Tool => "application",
+                    ComponentKind::PythonPackage => "library",
+                },
+                "name": component.name,
+                "version": component.version,
+            });
+            if let Some(purl) = &component.purl {
+                entry["purl"] = json!(purl);
+            }
+            if let Some(license) = &component.license {
+                entry["licenses"] = json!([{ "expression": license }]);
+            }
+            if let Some(sha256) = &component.sha256 {
+                entry["hashes"] = json!([{ "alg": "SHA-256", "content": sha256 }]);
+            }
+            if let Some(url) = &component.download_url {
+                entry["externalReferences"] = json!([{ "type": "distribution", "url": url }]);
+            }
+            entry
+        })
+        .collect();
+    json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "serialNumber": format!("urn:uuid:{}", uuid::Uuid::new_v4()),
+        "version": 1,
+        "metadata": {
+            "timestamp": timestamp,
+            "tools": [{ "name": env!("CARGO_PKG_NAME"), "version": env!("CARGO_PKG_VERSION") }],
+            "component": { "type": "framework", "name": "esp-idf", "version": installation_name },
+        },
+        "components": components,
+    })
+}
+
+/// Renders an SPDX 2.3 document.
+fn render_spdx(installation_name: &str, components: &[SbomComponent], timestamp: &str) -> Value {
+    let packages: Vec<Value> = components
+        .iter()
+        .enumerate()
+        .map(|(index, component)| {
+            let mut package = json!({
+                "SPDXID": format!("SPDXRef-Package-{}", index),
+                "name": component.name,
+                "versionInfo": component.version,
+                "downloadLocation": component.download_url.as_deref().unwrap_or("NOASSERTION"),
+                "licenseConcluded": "NOASSERTION",
+                "licenseDeclared": component.license.as_deref().unwrap_or("NOASSERTION"),
+                "filesAnalyzed": false,
+            });
+            if let Some(sha256) = &component.sha256 {
+                package["checksums"] = json!([{ "algorithm": "SHA256", "checksumValue": sha256 }]);
+            }
+            if let Some(purl) = &component.purl {
+                package["externalRefs"] = json!([{
+                    "referenceCategory": "PACKAGE-MANAGER",
+                    "referenceType": "purl",
+                    "referenceLocator": purl,
+                }]);
+            }
+            package
+        })
+        .collect();
+    let relationships: Vec<Value> = (0..components.len())
+        .map(|index| {
+            json!({
+                "spdxElementId": "SPDXRef-DOCUMENT",
+                "relationshipType": "DESCRIBES",
+                "relatedSpdxElement": format!("SPDXRef-Package-{}", index),
+            })
+        })
+        .collect();
+    json!({
+        "spdxVersion": "SPDX-2.3",
+        "dataLicense": "CC0-1.0",
+        "SPDXID": "SPDXRef-DOCUMENT",
+        "name": format!("ESP-IDF {}", installation_name),
+        "documentNamespace": format!(
+            "https://spdx.espressif.com/eim/{}-{}",
+            installation_name,
+            uuid::Uuid::new_v4()
+        ),
+        "creationInfo": {
+            "created": timestamp,
+            "creators": [format!("Tool: {}-{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))],
+        },
+        "packages": packages,
+        "relationships": relationships,
+    })
+}
+
+/// Renders the components of an installation as an SBOM document in the given format.
+///
+/// # Parameters
+///
+/// * `installation_name` - The name of the installation, used as the version of the document subject.
+/// * `components` - The components, as returned by `collect_sbom_components`.
+/// * `format` - The format to render.
+pub fn render_sbom(
+    installation_name: &str,
+    components: &[SbomComponent],
+    format: SbomFormat,
+) -> String {
+    let timestamp = crate::utils::now_timestamp();
+    let document = match format {
+        SbomFormat::CycloneDx => render_cyclonedx(installation_name, components, &timestamp),
+        SbomFormat::Spdx => render_spdx(installation_name, components, &timestamp),
+    };
+    serde_json::to_string_pretty(&document).unwrap_or_default()
+}
+
+/// Generates a software bill of materials of an installation, covering the ESP-IDF commit, every
+/// installed tool and the python packages.
+///
+/// # Parameters
+///
+/// * `identifier` - The id or name of the installation.
+/// * `format` - The format of the document.
+///
+/// # Returns
+///
+/// * `Result<String, anyhow::Error>` - The JSON document.
+pub fn generate_sbom(identifier: &str, format: SbomFormat) -> Result<String> {
+    let installation = find_installation(identifier)?;
+    let components = collect_sbom_components(&installation)?;
+    Ok(render_sbom(&installation.name, &components, format))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn components() -> Vec<SbomComponent> {
+        vec![
+            SbomComponent {
+                kind: ComponentKind::Tool,
+                name: "cmake".to_string(),
+                version: "3.24.0".to_string(),
+                license: Some("BSD-3-Clause".to_string()),
+                sha256: Some("abc123".to_string()),
+                download_url: Some("https://example.com/cmake.tar.gz".to_string()),
+                purl: None,
+            },
+            SbomComponent {
+                kind: ComponentKind::PythonPackage,
+                name: "esptool".to_string(),
+                version: "4.7.0".to_string(),
+                license: None,
+                sha256: None,
+                download_url: None,
+                purl: Some("pkg:pypi/esptool@4.7.0".to_string()),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_render_cyclonedx() {
+        let sbom = render_sbom("v5.3", &components(), SbomFormat::CycloneDx);
+        let parsed: Value = serde_json::from_str(&sbom).unwrap();
+
+        assert_eq!(parsed["bomFormat"], "CycloneDX");
+        assert_eq!(parsed["components"][0]["hashes"][0]["content"], "abc123");
+        assert_eq!(
+            parsed["components"][0]["licenses"][0]["expression"],
+            "BSD-3-Clause"
+        );
+        assert_eq!(parsed["components"][1]["type"], "library");
+        assert_eq!(parsed["components"][1]["purl"], "pkg:pypi/esptool@4.7.0");
+    }
+
+    #[test]
+    fn test_render_spdx() {
+        let sbom = render_sbom("v5.3", &components(), SbomFormat::Spdx);
+        let parsed: Value = serde_json::from_str(&sbom).unwrap();
+
+        assert_eq!(parsed["spdxVersion"], "SPDX-2.3");
+        assert_eq!(
+            parsed["packages"][0]["checksums"][0]["checksumValue"],
+            "abc123"
+        );
+        assert_eq!(parsed["packages"][1]["downloadLocation"], "NOASSERTION");
+        assert_eq!(parsed["relationships"].as_array().unwrap().len(), 2);
+    }
+}
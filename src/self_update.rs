@@ -0,0 +1,211 @@
+use anyhow::{anyhow, Result};
+use log::debug;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+use crate::idf_versions::parse_version_numbers;
+use crate::progress::ProgressReporter;
+
+/// The latest stable release of the installer on GitHub, drafts and pre-releases are excluded.
+const EIM_LATEST_RELEASE_URL: &str =
+    "https://api.github.com/repos/espressif/idf-im-ui/releases/latest";
+
+/// A downloadable file of an installer release.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct ReleaseAsset {
+    pub name: String,
+    pub browser_download_url: String,
+    pub size: u64,
+    /// The checksum computed by GitHub, e.g. `sha256:4f2a...`.
+    #[serde(default)]
+    pub digest: Option<String>,
+}
+
+/// An installer release as returned by the GitHub releases API.
+#[derive(Debug, Deserialize, Clone)]
+pub struct EimRelease {
+    pub tag_name: String,
+    pub html_url: String,
+    /// The release notes in markdown.
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub assets: Vec<ReleaseAsset>,
+}
+
+/// A newer installer version the frontends can offer to the user.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelfUpdateInfo {
+    pub current_version: String,
+    pub available_version: String,
+    /// The page of the release, for the users to download it themselves.
+    pub release_url: String,
+    pub changelog: String,
+    /// The asset for this platform, if the release has one.
+    pub asset: Option<ReleaseAsset>,
+}
+
+/// Parses a version into a comparable `(major, minor, patch)`, missing components count as 0.
+fn version_key(version: &str) -> Option<(u32, u32, u32)> {
+    let (major, minor, patch) = parse_version_numbers(version)?;
+    Some((major, minor.unwrap_or(0), patch.unwrap_or(0)))
+}
+
+/// Picks the asset of a release built for the running platform.
+///
+/// # Parameters
+///
+/// * `assets` - The assets of the release.
+/// * `name_filter` - A part of the asset name telling the products of a release apart, e.g. `eim-cli`.
+pub fn select_release_asset(assets: &[ReleaseAsset], name_filter: &str) -> Option<ReleaseAsset> {
+    let os_names: &[&str] = match std::env::consts::OS {
+        "macos" => &["macos", "darwin"],
+        os => &[os],
+    };
+    let arch_names: &[&str] = match std::env::consts::ARCH {
+        "x86_64" => &["x64", "x86_64", "amd64"],
+        "aarch64" => &["aarch64", "arm64"],
+        arch => &[arch],
+    };
+    assets
+        .iter()
+        .filter(|asset| asset.name.contains(name_filter))
+        .filter(|asset| !asset.name.ends_with(".sha256"))
+        .find(|asset| {
+            let name = asset.name.to_lowercase();
+            os_names.iter().any(|os| name.contains(os))
+                && arch_names.iter().any(|arch| name.contains(arch))
+        })
+        .cloned()
+}
+
+/// Compares a release with the running version.
+///
+/// # Returns
+///
+/// * `Option<SelfUpdateInfo>` - The update, `None` if the release is not newer or a version can not be parsed.
+pub fn find_self_update(
+    current_version: &str,
+    release: &EimRelease,
+    name_filter: &str,
+) -> Option<SelfUpdateInfo> {
+    if version_key(&release.tag_name)? <= version_key(current_version)? {
+        return None;
+    }
+    Some(SelfUpdateInfo {
+        current_version: current_version.to_string(),
+        available_version: release.tag_name.clone(),
+        release_url: release.html_url.clone(),
+        changelog: release.body.clone().unwrap_or_default(),
+        asset: select_release_asset(&release.assets, name_filter),
+    })
+}
+
+/// Checks whether a newer version of the installer is released.
+///
+/// # Parameters
+///
+/// * `current_version` - The version of the running application, e.g. `env!("CARGO_PKG_VERSION")` of the frontend.
+/// * `name_filter` - A part of the asset name of the frontend, e.g. `eim-cli`.
+///
+/// # Returns
+///
+/// * `Result<Option<SelfUpdateInfo>, anyhow::Error>` - The update, `None` when up to date, or an
+///   error if the releases feed can not be fetched.
+pub async fn check_for_self_update(
+    current_version: &str,
+    name_filter: &str,
+) -> Result<Option<SelfUpdateInfo>> {
+    let client = crate::http_client::client()?;
+    let response = client
+        .get(EIM_LATEST_RELEASE_URL)
+        .header("Accept", "application/vnd.github+json")
+        .send()
+        .await?
+        .error_for_status()?;
+    let release: EimRelease = serde_json::from_str(&response.text().await?)?;
+    debug!("Latest installer release is {}", release.tag_name);
+    Ok(find_self_update(current_version, &release, name_filter))
+}
+
+/// Downloads the asset of an update and verifies it against the checksum published by GitHub.
+///
+/// A file that does not match is removed.
+///
+/// # Parameters
+///
+/// * `update` - The update returned by `check_for_self_update`.
+/// * `destination` - The directory to download the asset to.
+/// * `progress_sender` - The reporter receiving the download progress, e.g. a `Sender<DownloadProgress>`.
+///
+/// # Returns
+///
+/// * `Result<PathBuf, anyhow::Error>` - The path of the verified file.
+pub async fn download_self_update(
+    update: &SelfUpdateInfo,
+    destination: &Path,
+    progress_sender: impl ProgressReporter,
+) -> Result<PathBuf> {
+    let asset = update.asset.as_ref().ok_or_else(|| {
+        anyhow!(
+            "Release {} has no asset for this platform",
+            update.available_version
+        )
+    })?;
+    let expected = asset
+        .digest
+        .as_deref()
+        .and_then(|digest| digest.strip_prefix("sha256:"))
+        .ok_or_else(|| anyhow!("No checksum is published for {}", asset.name))?;
+    crate::ensure_path(destination.to_str().unwrap())?;
+    crate::download_file(
+        &asset.browser_download_url,
+        destination.to_str().unwrap(),
+        progress_sender,
+    )
+    .await?;
+    let file = destination.join(&asset.name);
+    if !crate::verify_file_checksum(expected, file.to_str().unwrap())? {
+        let _ = std::fs::remove_file(&file);
+        return Err(anyhow!("Checksum mismatch for {}", asset.name));
+    }
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn asset(name: &str) -> ReleaseAsset {
+        ReleaseAsset {
+            name: name.to_string(),
+            browser_download_url: format!("https://example.com/{}", name),
+            size: 1,
+            digest: Some("sha256:abc".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_find_self_update() {
+        let os = std::env::consts::OS;
+        let arch = match std::env::consts::ARCH {
+            "x86_64" => "x64",
+            arch => arch,
+        };
+        let native = format!("eim-cli-{}-{}.zip", os, arch);
+        let release = EimRelease {
+            tag_name: "v0.2.0".to_string(),
+            html_url: "https://github.com/espressif/idf-im-ui/releases/tag/v0.2.0".to_string(),
+            body: Some("Fixes".to_string()),
+            assets: vec![asset("eim-gui-other-os.zip"), asset(&native)],
+        };
+
+        let update = find_self_update("0.1.5", &release, "eim-cli").unwrap();
+        assert_eq!(update.available_version, "v0.2.0");
+        assert_eq!(update.changelog, "Fixes");
+        assert_eq!(update.asset.unwrap().name, native);
+
+        assert!(find_self_update("v0.2.0", &release, "eim-cli").is_none());
+        assert!(find_self_update("0.3", &release, "eim-cli").is_none());
+    }
+}
@@ -3,10 +3,14 @@ use config::{Config, ConfigError, File};
 use serde::{Deserialize, Serialize};
 use std::fs::{self, OpenOptions};
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use uuid::Uuid;
 
 use crate::idf_config::{IdfConfig, IdfInstallation};
+use crate::idf_versions::{
+    resolve_version_specifier, validate_targets_for_version, Releases, TargetValidationError,
+};
 use crate::utils::get_git_path;
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -29,6 +33,22 @@ pub struct Settings {
     pub idf_mirror: Option<String>,
     pub recurse_submodules: Option<bool>,
     pub install_all_prerequisites: Option<bool>,
+    pub idf_versions_url: Option<String>,
+    pub package_manager: Option<String>,
+    pub command_timeout: Option<u64>, // in seconds
+    pub script_shell: Option<String>,
+    pub drivers_manifest_url: Option<String>,
+    pub extra_drivers: Option<Vec<crate::drivers::DriverInfo>>,
+    pub env_scope: Option<String>, // "user" or "machine", Windows only
+    pub activation_shells: Option<Vec<String>>, // e.g. ["fish"], the login shell when unset
+    pub shell_integration: Option<bool>, // prompt prefix and idf.py completions in activation scripts
+    pub dry_run: Option<bool>,           // only report what the installation would change
+    pub download_cache: Option<bool>, // reuse tool archives downloaded for other versions from the download cache
+    pub proxy: Option<String>, // proxy of all downloads, the proxy environment variables are used when not set
+    pub ca_certificate: Option<String>, // PEM file with certificate authorities trusted in addition to the system ones
+    #[serde(skip_serializing)]
+    // never written by `save` or into the manifests, only read from the user's config file
+    pub mirror_credentials: Option<Vec<crate::http_client::MirrorCredentials>>, // for mirrors behind authentication
 }
 
 impl Default for Settings {
@@ -74,6 +94,20 @@ impl Default for Settings {
             idf_mirror: Some(crate::get_idf_mirrors_list().first().unwrap().to_string()),
             recurse_submodules: Some(false),
             install_all_prerequisites: Some(false),
+            idf_versions_url: None,
+            package_manager: None,
+            command_timeout: None,
+            script_shell: None,
+            drivers_manifest_url: None,
+            extra_drivers: None,
+            env_scope: None,
+            activation_shells: None,
+            shell_integration: Some(false),
+            dry_run: Some(false),
+            download_cache: Some(true),
+            proxy: None,
+            ca_certificate: None,
+            mirror_credentials: None,
         }
     }
 }
@@ -122,14 +156,52 @@ impl Settings {
             .write(true)
             .truncate(true)
             .create(true)
-            .open(save_path)
+            .open(&save_path)
             .map_err(|e| ConfigError::Message(e.to_string()))?;
+        // only the user may read the file, the proxy url may contain a password
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&save_path, fs::Permissions::from_mode(0o600))
+                .map_err(|e| ConfigError::Message(e.to_string()))?;
+        }
         file.write_all(toml_value.as_bytes())
             .map_err(|e| ConfigError::Message(e.to_string()))?;
 
         Ok(())
     }
 
+    /// Configures the library for these settings.
+    ///
+    /// The settings changing the behavior of the whole library, like the proxy, the dry-run mode or
+    /// the shells of the activation scripts, are kept by the modules they belong to. This passes
+    /// them all to their setters, so frontends call it once the settings are loaded instead of
+    /// calling every setter themselves. Unset fields restore the defaults.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - An error if the CA certificate can not be read, nothing is changed then.
+    pub fn apply(&self) -> Result<()> {
+        crate::http_client::set_ca_certificate(self.ca_certificate.as_deref().map(Path::new))
+            .map_err(|e| anyhow!("Failed to read the CA certificate: {}", e))?;
+        crate::http_client::set_proxy(self.proxy.clone());
+        crate::http_client::set_mirror_credentials(
+            self.mirror_credentials.clone().unwrap_or_default(),
+        );
+        crate::idf_versions::set_versions_url(self.idf_versions_url.clone());
+        crate::command_executor::set_default_timeout(self.command_timeout.map(Duration::from_secs));
+        crate::command_executor::set_script_shell(self.script_shell.clone());
+        crate::system_dependencies::set_preferred_package_manager(self.package_manager.clone());
+        crate::cache::set_cache_enabled(self.download_cache.unwrap_or(true));
+        crate::dry_run::set_dry_run(self.dry_run.unwrap_or(false));
+        crate::drivers::set_drivers_manifest_url(self.drivers_manifest_url.clone());
+        crate::drivers::set_extra_drivers(self.extra_drivers.clone());
+        crate::win_tools::set_env_scope(self.env_scope.clone());
+        crate::set_activation_shells(self.activation_shells.clone());
+        crate::set_shell_integration(self.shell_integration.unwrap_or(false));
+        Ok(())
+    }
+
     pub fn is_default(&self, field: &str) -> bool {
         let default_settings = Settings::default();
         match field {
@@ -153,12 +225,92 @@ impl Settings {
             "install_all_prerequisites" => {
                 self.install_all_prerequisites == default_settings.install_all_prerequisites
             }
+            "idf_versions_url" => self.idf_versions_url == default_settings.idf_versions_url,
+            "package_manager" => self.package_manager == default_settings.package_manager,
+            "command_timeout" => self.command_timeout == default_settings.command_timeout,
+            "script_shell" => self.script_shell == default_settings.script_shell,
+            "drivers_manifest_url" => {
+                self.drivers_manifest_url == default_settings.drivers_manifest_url
+            }
+            "extra_drivers" => self.extra_drivers == default_settings.extra_drivers,
+            "env_scope" => self.env_scope == default_settings.env_scope,
+            "activation_shells" => self.activation_shells == default_settings.activation_shells,
+            "shell_integration" => self.shell_integration == default_settings.shell_integration,
+            "dry_run" => self.dry_run == default_settings.dry_run,
+            "download_cache" => self.download_cache == default_settings.download_cache,
+            "proxy" => self.proxy == default_settings.proxy,
+            "ca_certificate" => self.ca_certificate == default_settings.ca_certificate,
+            "mirror_credentials" => self.mirror_credentials == default_settings.mirror_credentials,
             "mirror" => self.mirror == default_settings.mirror,
             "idf_mirror" => self.idf_mirror == default_settings.idf_mirror,
             _ => false,
         }
     }
 
+    /// Resolves the requested `idf_versions` to concrete versions from the manifest.
+    ///
+    /// Partial and symbolic specifiers like `latest`, `5.3` or `release/v5.3` are replaced
+    /// by the newest matching version, so the install pipeline always works with real tags.
+    ///
+    /// # Parameters
+    ///
+    /// * `releases` - A reference to the `Releases` parsed from the versions manifest.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Ok if every version could be resolved, otherwise an error listing
+    ///   the specifiers which do not match any version. The settings are left untouched on error.
+    pub fn resolve_idf_versions(&mut self, releases: &Releases) -> Result<()> {
+        if let Some(versions) = &self.idf_versions {
+            let mut resolved = vec![];
+            let mut unresolved = vec![];
+            for spec in versions {
+                match resolve_version_specifier(releases, spec) {
+                    Some(version) => {
+                        if !resolved.contains(&version) {
+                            resolved.push(version);
+                        }
+                    }
+                    None => unresolved.push(spec.clone()),
+                }
+            }
+            if !unresolved.is_empty() {
+                return Err(anyhow!(
+                    "Unknown ESP-IDF version(s): {}",
+                    unresolved.join(", ")
+                ));
+            }
+            self.idf_versions = Some(resolved);
+        }
+        Ok(())
+    }
+
+    /// Validates the selected `target`s against every selected ESP-IDF version.
+    ///
+    /// # Parameters
+    ///
+    /// * `releases` - A reference to the `Releases` parsed from the versions manifest.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Vec<TargetValidationError>>` - Ok if all targets are supported by all versions,
+    ///   otherwise one error for every unsupported combination of target and version.
+    pub fn validate_targets(&self, releases: &Releases) -> Result<(), Vec<TargetValidationError>> {
+        let targets = self.target.clone().unwrap_or_default();
+        let mut errors = vec![];
+        for version in self.idf_versions.clone().unwrap_or_default() {
+            if let Err(version_errors) = validate_targets_for_version(releases, &version, &targets)
+            {
+                errors.extend(version_errors);
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
     /// Saves ESP-IDF configuration to a JSON file.
     ///
     /// This function generates and saves a JSON configuration file for ESP-IDF installations.
@@ -198,6 +350,14 @@ impl Settings {
                     _ => base_path.join(format!("activate_idf_{}.sh", version)),
                 };
 
+                let activation_scripts = match std::env::consts::OS {
+                    "windows" => vec![],
+                    _ => crate::find_activation_scripts(base_path, version)
+                        .iter()
+                        .map(|script| script.to_string_lossy().into_owned())
+                        .collect(),
+                };
+
                 let installation = IdfInstallation {
                     id,
                     name: version.to_string(),
@@ -205,6 +365,9 @@ impl Settings {
                     python: python_path.to_string_lossy().into_owned(),
                     idf_tools_path: tools_path.to_string_lossy().into_owned(),
                     activation_script: activation_script.to_string_lossy().into_owned(),
+                    env_variables: vec![],
+                    activation_scripts,
+                    container_image: None,
                 };
 
                 idf_installations.push(installation);
@@ -1,38 +1,790 @@
 use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
 
-use log::{debug, trace, warn};
+use log::{debug, error, info, trace, warn};
+use serde::{Deserialize, Serialize};
 
-use crate::command_executor;
+use crate::command_executor::{self, quote_posix, quote_powershell, ExecOptions, RetryPolicy};
+use crate::events::InstallPhase;
+use crate::messages::{message, MessageId};
+use crate::progress::{self, ProgressDetail, ProgressReporter, ProgressUpdate};
 
-/// Determines the package manager installed on the system.
+/// A system package manager used to check and install the prerequisites.
 ///
-/// This function attempts to identify the package manager by executing each
-/// listed package manager's version command and checking if the command
-/// execution is successful.
+/// Backends only describe which commands should be run, the execution itself is shared
+/// by the provided methods. This keeps the backends trivially testable and makes adding
+/// a new package manager a matter of implementing a few small functions.
+pub trait PackageManager {
+    /// The name of the package manager, as used in logs and error messages.
+    fn name(&self) -> &'static str;
+
+    /// The command used to detect if the package manager is present on the system.
+    fn detect_command(&self) -> (String, Vec<String>) {
+        (self.name().to_string(), vec!["--version".to_string()])
+    }
+
+    /// The command which succeeds if the given package is installed.
+    fn query_command(&self, package: &str) -> (String, Vec<String>);
+
+    /// The command used instead of `query_command` in offline mode. Must not touch the network.
+    fn offline_query_command(&self, package: &str) -> (String, Vec<String>) {
+        self.query_command(package)
+    }
+
+    /// The command which installs the given package.
+    fn install_command(&self, package: &str) -> Option<(String, Vec<String>)>;
+
+    /// Whether the package manager is able to install packages, or only to query them.
+    fn can_install(&self) -> bool {
+        true
+    }
+
+    /// Whether installing packages requires root privileges (the install command is run with `sudo`).
+    fn requires_root(&self) -> bool {
+        false
+    }
+
+    /// Builds a ready-to-copy command the user can run to install the given prerequisites manually.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(String)` - The command installing all the packages at once.
+    /// * `None` - If the list is empty or the package manager can not install packages.
+    fn manual_install_command(&self, tools: &[String]) -> Option<String> {
+        let packages: Vec<String> = tools.iter().map(|t| self.package_name(t)).collect();
+        let (command, mut args) = self.install_command(packages.first()?)?;
+        args.extend(packages.iter().skip(1).cloned());
+        Some(format!("{} {}", command, args.join(" ")))
+    }
+
+    /// Maps the name of a prerequisite to the name of the package providing it.
+    fn package_name(&self, tool: &str) -> String {
+        tool.to_string()
+    }
+
+    /// Checks if the package manager is present on the system.
+    fn is_available(&self) -> bool {
+        let (command, args) = self.detect_command();
+        let args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+        match command_executor::execute_command(&command, &args) {
+            Ok(output) => output.status.success(),
+            Err(_) => false,
+        }
+    }
+
+    /// Checks if the package providing the given prerequisite is installed.
+    fn is_installed(&self, tool: &str) -> bool {
+        let package = self.package_name(tool);
+        let (command, args) = if is_offline_mode() {
+            self.offline_query_command(&package)
+        } else {
+            self.query_command(&package)
+        };
+        let args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+        match command_executor::execute_command(&command, &args) {
+            Ok(o) => {
+                if o.status.success() {
+                    debug!("{} is already installed: {:?}", tool, o);
+                    true
+                } else {
+                    debug!("check for {} failed: {:?}", tool, o);
+                    false
+                }
+            }
+            Err(_e) => false,
+        }
+    }
+
+    /// Installs the package providing the given prerequisite.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the package was installed.
+    /// * `Err(String)` - If the installation failed or the package manager can not install packages.
+    fn install(&self, tool: &str) -> Result<(), String> {
+        let package = self.package_name(tool);
+        let (mut command, mut args) = match self.install_command(&package) {
+            Some(cmd) => cmd,
+            None => {
+                return Err(message(
+                    MessageId::PackageManagerCannotInstall,
+                    &[self.name()],
+                ))
+            }
+        };
+        if command == "sudo" && is_root() {
+            // sudo is often missing in containers, and is not needed anyway
+            command = args.remove(0);
+        } else if command == "sudo" && crate::platform::platform_info().is_unattended() {
+            // nobody can type a password in CI, fail instead of waiting forever
+            args.insert(0, "-n".to_string());
+        }
+        let args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+        // mirrors and package locks fail transiently, try again before giving up
+        let options = ExecOptions::new().retry(RetryPolicy::default());
+        match command_executor::execute_checked(&command, &args, vec![], &options) {
+            Ok(_) => {
+                debug!("Successfully installed {}", package);
+                Ok(())
+            }
+            Err(e) => Err(message(
+                MessageId::PackageInstallFailed,
+                &[&package, &e.to_string()],
+            )),
+        }
+    }
+}
+
+/// Checks if the current process is running with root privileges.
 ///
-/// This should be only executed on Linux systems, as package managers on other operating systems
-/// are not supported.
+/// # Returns
+///
+/// * `true` - If the effective user id is 0. Always `false` on Windows.
+pub fn is_root() -> bool {
+    match std::env::consts::OS {
+        "windows" => false,
+        _ => match command_executor::execute_command("id", &["-u"]) {
+            Ok(o) => o.status.success() && String::from_utf8_lossy(&o.stdout).trim() == "0",
+            Err(_) => false,
+        },
+    }
+}
+
+fn to_args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|a| a.to_string()).collect()
+}
+
+// The prerequisites are named after their Debian packages, these tables translate them
+// for the other distributions. Names missing from a table are used unchanged.
+const DEBIAN_PACKAGE_NAMES: &[(&str, &str)] = &[("ninja", "ninja-build")];
+
+const FEDORA_PACKAGE_NAMES: &[(&str, &str)] = &[
+    ("ninja", "ninja-build"),
+    ("python3-venv", "python3"),
+    ("libffi-dev", "libffi-devel"),
+    ("libssl-dev", "openssl-devel"),
+    ("libusb-1.0-0", "libusb1"),
+];
+
+const ARCH_PACKAGE_NAMES: &[(&str, &str)] = &[
+    ("python3", "python"),
+    ("python3-pip", "python-pip"),
+    ("python3-venv", "python"),
+    ("libffi-dev", "libffi"),
+    ("libssl-dev", "openssl"),
+    ("libusb-1.0-0", "libusb"),
+];
+
+const OPENSUSE_PACKAGE_NAMES: &[(&str, &str)] = &[
+    ("python3-venv", "python3"),
+    ("libffi-dev", "libffi-devel"),
+    ("libssl-dev", "libopenssl-devel"),
+    ("libusb-1.0-0", "libusb-1_0-0"),
+];
+
+const ALPINE_PACKAGE_NAMES: &[(&str, &str)] = &[
+    ("python3-pip", "py3-pip"),
+    ("python3-venv", "python3"),
+    ("libssl-dev", "openssl-dev"),
+    ("libusb-1.0-0", "libusb"),
+];
+
+const VOID_PACKAGE_NAMES: &[(&str, &str)] = &[
+    ("python3-venv", "python3"),
+    ("libffi-dev", "libffi-devel"),
+    ("libssl-dev", "openssl-devel"),
+    ("libusb-1.0-0", "libusb"),
+];
+
+// Homebrew python comes with pip and venv
+const BREW_PACKAGE_NAMES: &[(&str, &str)] = &[
+    ("python3", "python"),
+    ("python3-pip", "python"),
+    ("python3-venv", "python"),
+];
+
+const TERMUX_PACKAGE_NAMES: &[(&str, &str)] = &[
+    ("python3", "python"),
+    ("python3-pip", "python-pip"),
+    ("python3-venv", "python"),
+    ("libffi-dev", "libffi"),
+    ("libssl-dev", "openssl"),
+    ("libusb-1.0-0", "libusb"),
+];
+
+/// Looks up the name of a prerequisite in a package name table.
+///
+/// # Parameters
+///
+/// * `table` - A slice of `(prerequisite, package)` pairs.
+/// * `tool` - A string slice representing the name of the prerequisite.
 ///
 /// # Returns
 ///
-/// * `Some(&'static str)` - If a package manager is found, returns the name of the package manager.
-/// * `None` - If no package manager is found, returns None.
-fn determine_package_manager() -> Option<&'static str> {
-    let package_managers = vec!["apt", "dpkg", "dnf", "pacman", "zypper"];
+/// * `String` - The mapped package name, or the prerequisite name if it is not in the table.
+pub fn map_package_name(table: &[(&str, &str)], tool: &str) -> String {
+    table
+        .iter()
+        .find(|(from, _)| *from == tool)
+        .map(|(_, to)| to.to_string())
+        .unwrap_or_else(|| tool.to_string())
+}
+
+pub struct Apt;
+
+impl PackageManager for Apt {
+    fn name(&self) -> &'static str {
+        "apt"
+    }
+    fn requires_root(&self) -> bool {
+        true
+    }
+    fn package_name(&self, tool: &str) -> String {
+        map_package_name(DEBIAN_PACKAGE_NAMES, tool)
+    }
+    fn query_command(&self, package: &str) -> (String, Vec<String>) {
+        (
+            "sh".to_string(),
+            to_args(&[
+                "-c",
+                &format!("apt list --installed | grep {}", quote_posix(package)),
+            ]),
+        )
+    }
+    fn install_command(&self, package: &str) -> Option<(String, Vec<String>)> {
+        Some((
+            "sudo".to_string(),
+            to_args(&["apt", "install", "-y", package]),
+        ))
+    }
+}
+
+/// Debian based systems without `apt`. Only checking is supported.
+pub struct Dpkg;
+
+impl PackageManager for Dpkg {
+    fn name(&self) -> &'static str {
+        "dpkg"
+    }
+    fn package_name(&self, tool: &str) -> String {
+        map_package_name(DEBIAN_PACKAGE_NAMES, tool)
+    }
+    fn query_command(&self, package: &str) -> (String, Vec<String>) {
+        (
+            "sh".to_string(),
+            to_args(&["-c", &format!("dpkg -l | grep {}", quote_posix(package))]),
+        )
+    }
+    fn install_command(&self, _package: &str) -> Option<(String, Vec<String>)> {
+        None
+    }
+    fn can_install(&self) -> bool {
+        false
+    }
+}
+
+pub struct Dnf;
+
+impl PackageManager for Dnf {
+    fn name(&self) -> &'static str {
+        "dnf"
+    }
+    // may refresh the repository metadata, rpm only reads the local database
+    fn offline_query_command(&self, package: &str) -> (String, Vec<String>) {
+        ("rpm".to_string(), to_args(&["-q", package]))
+    }
+    fn requires_root(&self) -> bool {
+        true
+    }
+    fn package_name(&self, tool: &str) -> String {
+        map_package_name(FEDORA_PACKAGE_NAMES, tool)
+    }
+    fn query_command(&self, package: &str) -> (String, Vec<String>) {
+        (
+            "sh".to_string(),
+            to_args(&[
+                "-c",
+                &format!("dnf list installed | grep {}", quote_posix(package)),
+            ]),
+        )
+    }
+    fn install_command(&self, package: &str) -> Option<(String, Vec<String>)> {
+        Some((
+            "sudo".to_string(),
+            to_args(&["dnf", "install", "-y", package]),
+        ))
+    }
+}
+
+pub struct Pacman;
+
+impl PackageManager for Pacman {
+    fn name(&self) -> &'static str {
+        "pacman"
+    }
+    fn requires_root(&self) -> bool {
+        true
+    }
+    fn package_name(&self, tool: &str) -> String {
+        map_package_name(ARCH_PACKAGE_NAMES, tool)
+    }
+    fn query_command(&self, package: &str) -> (String, Vec<String>) {
+        (
+            "sh".to_string(),
+            to_args(&["-c", &format!("pacman -Qs | grep {}", quote_posix(package))]),
+        )
+    }
+    fn install_command(&self, package: &str) -> Option<(String, Vec<String>)> {
+        Some((
+            "sudo".to_string(),
+            to_args(&["pacman", "-S", "--noconfirm", package]),
+        ))
+    }
+}
+
+pub struct Zypper;
+
+impl PackageManager for Zypper {
+    fn name(&self) -> &'static str {
+        "zypper"
+    }
+    // may refresh the repository metadata, rpm only reads the local database
+    fn offline_query_command(&self, package: &str) -> (String, Vec<String>) {
+        ("rpm".to_string(), to_args(&["-q", package]))
+    }
+    fn requires_root(&self) -> bool {
+        true
+    }
+    fn package_name(&self, tool: &str) -> String {
+        map_package_name(OPENSUSE_PACKAGE_NAMES, tool)
+    }
+    fn query_command(&self, package: &str) -> (String, Vec<String>) {
+        (
+            "sh".to_string(),
+            to_args(&[
+                "-c",
+                &format!("zypper se --installed-only {}", quote_posix(package)),
+            ]),
+        )
+    }
+    fn install_command(&self, package: &str) -> Option<(String, Vec<String>)> {
+        Some((
+            "sudo".to_string(),
+            to_args(&["zypper", "install", "-y", package]),
+        ))
+    }
+}
+
+pub struct Apk;
+
+impl PackageManager for Apk {
+    fn name(&self) -> &'static str {
+        "apk"
+    }
+    fn requires_root(&self) -> bool {
+        true
+    }
+    fn package_name(&self, tool: &str) -> String {
+        map_package_name(ALPINE_PACKAGE_NAMES, tool)
+    }
+    fn query_command(&self, package: &str) -> (String, Vec<String>) {
+        ("apk".to_string(), to_args(&["info", "-e", package]))
+    }
+    fn install_command(&self, package: &str) -> Option<(String, Vec<String>)> {
+        Some((
+            "sudo".to_string(),
+            to_args(&["apk", "add", "--no-interactive", package]),
+        ))
+    }
+}
+
+pub struct Xbps;
+
+impl PackageManager for Xbps {
+    fn name(&self) -> &'static str {
+        "xbps"
+    }
+    fn requires_root(&self) -> bool {
+        true
+    }
+    fn package_name(&self, tool: &str) -> String {
+        map_package_name(VOID_PACKAGE_NAMES, tool)
+    }
+    fn detect_command(&self) -> (String, Vec<String>) {
+        ("xbps-install".to_string(), to_args(&["--version"]))
+    }
+    fn query_command(&self, package: &str) -> (String, Vec<String>) {
+        ("xbps-query".to_string(), to_args(&[package]))
+    }
+    fn install_command(&self, package: &str) -> Option<(String, Vec<String>)> {
+        Some((
+            "sudo".to_string(),
+            to_args(&["xbps-install", "-y", package]),
+        ))
+    }
+}
+
+/// The `pkg` wrapper of Termux on Android. Packages are installed into the app prefix, so no root is needed.
+pub struct TermuxPkg;
+
+impl PackageManager for TermuxPkg {
+    fn name(&self) -> &'static str {
+        "pkg"
+    }
+    fn package_name(&self, tool: &str) -> String {
+        map_package_name(TERMUX_PACKAGE_NAMES, tool)
+    }
+    // `pkg` is also the name of the FreeBSD package manager, so Termux is detected by its environment
+    fn is_available(&self) -> bool {
+        env::var_os("TERMUX_VERSION").is_some()
+            || env::var("PREFIX")
+                .map(|prefix| prefix.contains("com.termux"))
+                .unwrap_or(false)
+    }
+    fn query_command(&self, package: &str) -> (String, Vec<String>) {
+        ("dpkg".to_string(), to_args(&["-s", package]))
+    }
+    fn install_command(&self, package: &str) -> Option<(String, Vec<String>)> {
+        Some(("pkg".to_string(), to_args(&["install", "-y", package])))
+    }
+}
+
+pub struct Brew;
+
+impl PackageManager for Brew {
+    fn name(&self) -> &'static str {
+        "brew"
+    }
+    fn package_name(&self, tool: &str) -> String {
+        map_package_name(BREW_PACKAGE_NAMES, tool)
+    }
+    fn query_command(&self, package: &str) -> (String, Vec<String>) {
+        (
+            "zsh".to_string(),
+            to_args(&["-c", &format!("brew list | grep {}", quote_posix(package))]),
+        )
+    }
+    fn install_command(&self, package: &str) -> Option<(String, Vec<String>)> {
+        Some(("brew".to_string(), to_args(&["install", package])))
+    }
+}
+
+const WINGET_PACKAGE_NAMES: &[(&str, &str)] = &[
+    ("git", "Git.Git"),
+    ("cmake", "Kitware.CMake"),
+    ("ninja", "Ninja-build.Ninja"),
+];
+
+/// Returns the architecture to request from the Windows package managers, `Some("arm64")` on
+/// Windows on ARM so native builds are installed instead of whatever x64 or x86 build comes first.
+fn windows_package_arch() -> Option<&'static str> {
+    (std::env::consts::OS == "windows" && crate::platform::native_arch() == "aarch64")
+        .then_some("arm64")
+}
+
+/// Checks for a tool on Windows by running it, regardless of which package manager installed it.
+fn windows_tool_installed(tool: &str) -> bool {
+    match command_executor::execute_command(
+        "powershell",
+        &[
+            "-Command",
+            &format!("& {} --version", quote_powershell(tool)),
+        ],
+    ) {
+        Ok(o) => {
+            if o.status.success() {
+                debug!("{} is already installed: {:?}", tool, o);
+                true
+            } else {
+                debug!("check for {} failed: {:?}", tool, o);
+                false
+            }
+        }
+        Err(_e) => false,
+    }
+}
+
+/// The Windows Package Manager, preinstalled on Windows 11 and recent Windows 10 builds.
+pub struct Winget;
+
+impl PackageManager for Winget {
+    fn name(&self) -> &'static str {
+        "winget"
+    }
+    fn package_name(&self, tool: &str) -> String {
+        map_package_name(WINGET_PACKAGE_NAMES, tool)
+    }
+    fn query_command(&self, package: &str) -> (String, Vec<String>) {
+        (
+            "winget".to_string(),
+            to_args(&["list", "--id", package, "-e"]),
+        )
+    }
+    fn install_command(&self, package: &str) -> Option<(String, Vec<String>)> {
+        let mut args = to_args(&[
+            "install",
+            "--id",
+            package,
+            "-e",
+            "--silent",
+            "--accept-source-agreements",
+            "--accept-package-agreements",
+        ]);
+        if let Some(arch) = windows_package_arch() {
+            args.extend(to_args(&["--architecture", arch]));
+        }
+        Some(("winget".to_string(), args))
+    }
+    fn is_installed(&self, tool: &str) -> bool {
+        windows_tool_installed(tool)
+    }
+}
+
+/// Returns the major version of Chocolatey, `None` if it can not be run.
+fn choco_major_version() -> Option<u32> {
+    let output = command_executor::execute_command("choco", &["--version"]).ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_tool_version(&String::from_utf8_lossy(&output.stdout))?
+        .split('.')
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// The arguments of `choco list` querying an installed package. Chocolatey 2 only lists the
+/// installed packages and removed `--local-only`, older versions search the sources without it.
+fn choco_list_args(major_version: u32, package: &str) -> Vec<String> {
+    let mut args = to_args(&["list", "--exact", "--limit-output"]);
+    if major_version < 2 {
+        args.push("--local-only".to_string());
+    }
+    args.push(package.to_string());
+    args
+}
+
+pub struct Choco;
+
+impl PackageManager for Choco {
+    fn name(&self) -> &'static str {
+        "choco"
+    }
+    fn query_command(&self, package: &str) -> (String, Vec<String>) {
+        (
+            "choco".to_string(),
+            choco_list_args(choco_major_version().unwrap_or(2), package),
+        )
+    }
+    fn install_command(&self, package: &str) -> Option<(String, Vec<String>)> {
+        Some(("choco".to_string(), to_args(&["install", "-y", package])))
+    }
+    fn is_installed(&self, tool: &str) -> bool {
+        if windows_tool_installed(tool) {
+            return true;
+        }
+        // `choco list` succeeds without output when the package is not installed
+        let (command, args) = self.query_command(&self.package_name(tool));
+        let args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+        match command_executor::execute_command(&command, &args) {
+            Ok(o) => o.status.success() && !String::from_utf8_lossy(&o.stdout).trim().is_empty(),
+            Err(_e) => false,
+        }
+    }
+}
 
-    for manager in package_managers {
-        let output = command_executor::execute_command(manager, &["--version"]);
+pub struct Scoop;
+
+impl PackageManager for Scoop {
+    fn name(&self) -> &'static str {
+        "scoop"
+    }
+    fn detect_command(&self) -> (String, Vec<String>) {
+        (
+            "powershell".to_string(),
+            to_args(&["-Command", "scoop", "--version"]),
+        )
+    }
+    fn query_command(&self, package: &str) -> (String, Vec<String>) {
+        (
+            "powershell".to_string(),
+            to_args(&[
+                "-Command",
+                &format!("& {} --version", quote_powershell(package)),
+            ]),
+        )
+    }
+    fn install_command(&self, package: &str) -> Option<(String, Vec<String>)> {
+        let mut args = to_args(&["-ExecutionPolicy", "Bypass", "-Command", "scoop", "install"]);
+        if let Some(arch) = windows_package_arch() {
+            args.extend(to_args(&["--arch", arch]));
+        }
+        args.push(package.to_string());
+        Some(("powershell".to_string(), args))
+    }
+
+    fn install(&self, tool: &str) -> Result<(), String> {
+        let package = self.package_name(tool);
+        let path_with_scoop = match get_scoop_path() {
+            Some(s) => s,
+            None => {
+                debug!("Could not get scoop path");
+                return Err(String::from("Could not get scoop path"));
+            }
+        };
+        debug!("Installing {} with scoop: {}", package, path_with_scoop);
+        let (mut main_command, args) = self.install_command(&package).unwrap();
+
+        let test_for_pwsh = command_executor::execute_command("pwsh", &["--version"]);
+        match test_for_pwsh {
+            // this needs to be used in powershell 7
+            Ok(_) => {
+                debug!("Found powershell core");
+                main_command = "pwsh".to_string();
+            }
+            Err(_) => {
+                debug!("Powershell core not found, using powershell");
+            }
+        }
+
+        let args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+        let output = command_executor::execute_checked(
+            &main_command,
+            &args,
+            vec![("PATH", &add_to_path(&path_with_scoop).unwrap())],
+            &ExecOptions::new().retry(RetryPolicy::default()),
+        );
         match output {
-            Ok(output) => {
-                if output.status.success() {
-                    return Some(manager);
-                }
+            Ok(o) => {
+                trace!("{}", String::from_utf8_lossy(&o.stdout));
+                debug!("Successfully installed {:?}", package);
+                Ok(())
+            }
+            Err(e) => {
+                debug!("Output: {}", e.stdout);
+                Err(message(
+                    MessageId::PackageInstallFailed,
+                    &[&package, &e.to_string()],
+                ))
             }
-            Err(_) => continue,
         }
     }
+}
+
+static OFFLINE_MODE: AtomicBool = AtomicBool::new(false);
+
+/// Restricts the prerequisites subsystem to local queries.
+///
+/// In offline mode no package manager is bootstrapped and no package is installed, the checks
+/// only query the local package databases. Missing prerequisites are listed in
+/// `PrerequisitesReport::offline_media` so they can be provided from offline media.
+pub fn set_offline_mode(offline: bool) {
+    OFFLINE_MODE.store(offline, Ordering::Relaxed);
+}
+
+/// Returns `true` if the offline mode is enabled, see `set_offline_mode`.
+pub fn is_offline_mode() -> bool {
+    OFFLINE_MODE.load(Ordering::Relaxed)
+}
+
+fn ensure_online(action: &str) -> Result<(), String> {
+    if is_offline_mode() {
+        Err(message(MessageId::NetworkRequired, &[action]))
+    } else {
+        Ok(())
+    }
+}
+
+static PREFERRED_PACKAGE_MANAGER: RwLock<Option<String>> = RwLock::new(None);
 
-    None
+/// Forces a specific package manager to be used for the prerequisites.
+/// Passing `None` (or `"auto"`) restores the automatic detection.
+pub fn set_preferred_package_manager(name: Option<String>) {
+    *PREFERRED_PACKAGE_MANAGER.write().unwrap() = name.filter(|n| n != "auto");
+}
+
+/// Returns the backend of the package manager with the given name.
+///
+/// # Parameters
+///
+/// * `name` - A string slice representing the name of the package manager, e.g. `apt` or `winget`.
+///
+/// # Returns
+///
+/// * `Some(Box<dyn PackageManager>)` - If the name is known.
+/// * `None` - If there is no backend with such name.
+pub fn get_package_manager_by_name(name: &str) -> Option<Box<dyn PackageManager>> {
+    match name {
+        "apt" => Some(Box::new(Apt)),
+        "dpkg" => Some(Box::new(Dpkg)),
+        "dnf" => Some(Box::new(Dnf)),
+        "pacman" => Some(Box::new(Pacman)),
+        "zypper" => Some(Box::new(Zypper)),
+        "apk" => Some(Box::new(Apk)),
+        "xbps" => Some(Box::new(Xbps)),
+        "pkg" => Some(Box::new(TermuxPkg)),
+        "brew" => Some(Box::new(Brew)),
+        "scoop" => Some(Box::new(Scoop)),
+        "winget" => Some(Box::new(Winget)),
+        "choco" => Some(Box::new(Choco)),
+        _ => None,
+    }
+}
+
+/// Determines the package manager installed on the system.
+///
+/// On Linux (and Android with Termux), this function attempts to identify the package manager by executing each
+/// known package manager's version command and checking if the command
+/// execution is successful. On macOS Homebrew is used. On Windows an already installed Scoop is
+/// preferred, followed by winget and Chocolatey; if none of them is present Scoop will be bootstrapped.
+/// A package manager set by `set_preferred_package_manager` overrides the detection.
+///
+/// # Returns
+///
+/// * `Some(Box<dyn PackageManager>)` - If a package manager is found, returns its backend.
+/// * `None` - If no package manager is found, returns None.
+pub fn get_package_manager() -> Option<Box<dyn PackageManager>> {
+    if let Some(name) = PREFERRED_PACKAGE_MANAGER.read().unwrap().as_deref() {
+        match get_package_manager_by_name(name) {
+            Some(manager) => return Some(manager),
+            None => warn!("Unknown package manager {}, using autodetection", name),
+        }
+    }
+    match std::env::consts::OS {
+        "linux" | "android" => {
+            // Termux ships apt and dpkg as well, so it has to be detected first
+            let package_managers: Vec<Box<dyn PackageManager>> = vec![
+                Box::new(TermuxPkg),
+                Box::new(Apt),
+                Box::new(Dpkg),
+                Box::new(Dnf),
+                Box::new(Pacman),
+                Box::new(Zypper),
+                Box::new(Apk),
+                Box::new(Xbps),
+            ];
+            package_managers
+                .into_iter()
+                .find(|manager| manager.is_available())
+        }
+        "macos" => Some(Box::new(Brew)),
+        "windows" => {
+            if let Some(scoop_path) = get_scoop_path() {
+                let _ = add_to_path(&scoop_path);
+            }
+            let package_managers: Vec<Box<dyn PackageManager>> =
+                vec![Box::new(Scoop), Box::new(Winget), Box::new(Choco)];
+            Some(
+                package_managers
+                    .into_iter()
+                    .find(|manager| manager.is_available())
+                    .unwrap_or(Box::new(Scoop)),
+            )
+        }
+        _ => None,
+    }
 }
 
 /// Returns a hardcoded vector of required tools based on the operating system.
@@ -42,7 +794,7 @@ fn determine_package_manager() -> Option<&'static str> {
 /// * `Vec<&'static str>` - A vector of required tools for the current operating system.
 pub fn get_prequisites() -> Vec<&'static str> {
     match std::env::consts::OS {
-        "linux" => vec![
+        "linux" | "android" => vec![
             "git",
             "cmake",
             "ninja",
@@ -55,196 +807,230 @@ pub fn get_prequisites() -> Vec<&'static str> {
             "libssl-dev",
             "dfu-util",
             "libusb-1.0-0",
+            "python3",
+            "python3-pip",
+            "python3-venv",
         ],
         "windows" => vec!["git", "cmake", "ninja"], // temporary added cmake back before solving why it does not install from tools.json
-        "macos" => vec!["dfu-util", "cmake", "ninja"],
+        "macos" => vec!["xcode-clt", "dfu-util", "cmake", "ninja", "python3"],
         _ => vec![],
     }
 }
 
-/// Checks the system for the required tools and returns a list of unsatisfied tools.
+/// State of a single prerequisite.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RequirementStatus {
+    Satisfied,
+    Missing,
+    Outdated,
+}
+
+/// The result of checking a single prerequisite.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RequirementCheck {
+    /// Name of the prerequisite, as returned by `get_prequisites`.
+    pub name: String,
+    pub status: RequirementStatus,
+    /// Version reported by the tool itself, if it is an executable and is present.
+    pub detected_version: Option<String>,
+    /// Name of the package providing the prerequisite for the backend.
+    pub package: String,
+    /// Name of the package manager which performed the check.
+    pub backend: String,
+}
+
+/// Structured result of the prerequisites check, suitable for a summary screen.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PrerequisitesReport {
+    pub package_manager: String,
+    pub requirements: Vec<RequirementCheck>,
+    /// Platform specific environment checks (Windows, WSL, locale).
+    pub preflight: Vec<PreflightCheck>,
+    /// Packages which have to be installed from offline media, only filled in offline mode.
+    pub offline_media: Vec<String>,
+}
+
+impl PrerequisitesReport {
+    /// Returns the names of the prerequisites which are missing or outdated.
+    pub fn unsatisfied(&self) -> Vec<&str> {
+        self.requirements
+            .iter()
+            .filter(|r| r.status != RequirementStatus::Satisfied)
+            .map(|r| r.name.as_str())
+            .collect()
+    }
+
+    /// Returns `true` if all prerequisites are satisfied and no preflight check failed.
+    pub fn is_satisfied(&self) -> bool {
+        self.unsatisfied().is_empty()
+            && self
+                .preflight
+                .iter()
+                .all(|check| check.status != CheckStatus::Failed)
+    }
+}
+
+/// Extracts the first version-like token (e.g. `3.24.0`) from the output of `tool --version`.
+pub fn parse_tool_version(output: &str) -> Option<String> {
+    let re = regex::Regex::new(r"\d+(\.\d+)+").unwrap();
+    re.find(output.lines().next()?)
+        .map(|m| m.as_str().to_string())
+}
+
+/// Asks an executable prerequisite for its version. Libraries and python modules are skipped.
+fn detect_tool_version(tool: &str) -> Option<String> {
+    if tool.starts_with("lib") || tool.starts_with("python3-") {
+        return None;
+    }
+    let output = command_executor::execute_command(tool, &["--version"]).ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_tool_version(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Checks the system for the required tools and returns a detailed report.
 ///
-/// This function determines the operating system and package manager, then checks if each required tool is installed.
-/// If a tool is not found, it is added to the `unsatisfied` vector and returned.
-/// The prerequsites are met when empty vector is returned.
+/// Besides the state of every prerequisite, the report contains the Windows and WSL preflight
+/// checks for the given installation path.
+///
+/// # Parameters
+///
+/// * `install_path` - A reference to a `Path` representing the folder ESP-IDF will be installed into.
 ///
 /// # Returns
 ///
-/// * `Ok(Vec<&'static str>)` - If the function completes successfully, returns a vector of unsatisfied tools.
-/// * `Err(String)` - If an error occurs, returns an error message.
-pub fn check_prerequisites() -> Result<Vec<&'static str>, String> {
+/// * `Ok(PrerequisitesReport)` - If the function completes successfully.
+/// * `Err(String)` - If no usable package manager was found.
+pub fn check_prerequisites_report(install_path: &Path) -> Result<PrerequisitesReport, String> {
     let list_of_required_tools = get_prequisites();
     debug!("Checking for prerequisites...");
     debug!("will be checking for : {:?}", list_of_required_tools);
-    let mut unsatisfied = vec![];
-    match std::env::consts::OS {
-        "linux" => {
-            let package_manager = determine_package_manager();
-            debug!("Detected package manager: {:?}", package_manager);
-            match package_manager {
-                Some("apt") => {
-                    for tool in list_of_required_tools {
-                        let output = command_executor::execute_command(
-                            "sh",
-                            &["-c", &format!("apt list --installed | grep {}", tool)],
-                        );
-                        match output {
-                            Ok(o) => {
-                                if o.status.success() {
-                                    debug!("{} is already installed: {:?}", tool, o);
-                                } else {
-                                    debug!("check for {} failed: {:?}", tool, o);
-                                    unsatisfied.push(tool);
-                                }
-                            }
-                            Err(_e) => {
-                                unsatisfied.push(tool);
-                            }
-                        }
-                    }
-                }
-                Some("dpkg") => {
-                    for tool in list_of_required_tools {
-                        let output = command_executor::execute_command(
-                            "sh",
-                            &["-c", &format!("dpkg -l | grep {}", tool)],
-                        );
-                        match output {
-                            Ok(o) => {
-                                if o.status.success() {
-                                    debug!("{} is already installed: {:?}", tool, o);
-                                } else {
-                                    debug!("check for {} failed: {:?}", tool, o);
-                                    unsatisfied.push(tool);
-                                }
-                            }
-                            Err(_e) => {
-                                unsatisfied.push(tool);
-                            }
-                        }
-                    }
-                }
-                Some("dnf") => {
-                    for tool in list_of_required_tools {
-                        let output = command_executor::execute_command(
-                            "sh",
-                            &["-c", &format!("dnf list installed | grep {}", tool)],
-                        );
-                        match output {
-                            Ok(o) => {
-                                if o.status.success() {
-                                    debug!("{} is already installed: {:?}", tool, o);
-                                } else {
-                                    unsatisfied.push(tool);
-                                }
-                            }
-                            Err(_e) => {
-                                unsatisfied.push(tool);
-                            }
-                        }
-                    }
-                }
-                Some("pacman") => {
-                    for tool in list_of_required_tools {
-                        let output = command_executor::execute_command(
-                            "sh",
-                            &["-c", &format!("pacman -Qs | grep {}", tool)],
-                        );
-                        match output {
-                            Ok(o) => {
-                                if o.status.success() {
-                                    debug!("{} is already installed: {:?}", tool, o);
-                                } else {
-                                    unsatisfied.push(tool);
-                                }
-                            }
-                            Err(_e) => {
-                                unsatisfied.push(tool);
-                            }
-                        }
-                    }
-                }
-                Some("zypper") => {
-                    for tool in list_of_required_tools {
-                        let output = command_executor::execute_command(
-                            "sh",
-                            &["-c", &format!("zypper se --installed-only {}", tool)],
-                        );
-                        match output {
-                            Ok(o) => {
-                                if o.status.success() {
-                                    debug!("{} is already installed: {:?}", tool, o);
-                                } else {
-                                    unsatisfied.push(tool);
-                                }
-                            }
-                            Err(_e) => {
-                                unsatisfied.push(tool);
-                            }
-                        }
-                    }
-                }
+    let package_manager = get_package_manager().ok_or_else(|| match std::env::consts::OS {
+        "linux" => message(MessageId::NoPackageManager, &[]),
+        os => message(MessageId::UnsupportedOs, &[os]),
+    })?;
+    debug!("Detected package manager: {}", package_manager.name());
+    if crate::platform::is_wsl() {
+        warn!("{}", crate::platform::WSL_USB_HINT);
+    }
+    let requirements: Vec<RequirementCheck> = list_of_required_tools
+        .into_iter()
+        .map(|tool| {
+            let (status, detected_version) = match check_python_prerequisite(tool) {
+                Some(result) => result,
+                None if tool == XCODE_CLT => match is_xcode_clt_installed() {
+                    true => (RequirementStatus::Satisfied, None),
+                    false => (RequirementStatus::Missing, None),
+                },
                 None => {
-                    return Err(format!(
-                        "Unsupported package manager - {}",
-                        package_manager.unwrap()
-                    ));
-                }
-                _ => {
-                    return Err(format!(
-                        "Unsupported package manager - {}",
-                        package_manager.unwrap()
-                    ));
+                    if package_manager.is_installed(tool) {
+                        (RequirementStatus::Satisfied, detect_tool_version(tool))
+                    } else {
+                        (RequirementStatus::Missing, None)
+                    }
                 }
+            };
+            RequirementCheck {
+                name: tool.to_string(),
+                status,
+                detected_version,
+                package: package_manager.package_name(tool),
+                backend: package_manager.name().to_string(),
             }
-        }
-        "macos" => {
-            for tool in list_of_required_tools {
-                let output = command_executor::execute_command(
-                    "zsh",
-                    &["-c", &format!("brew list | grep {}", tool)],
-                );
-                match output {
-                    Ok(o) => {
-                        if o.status.success() {
-                            debug!("{} is already installed: {:?}", tool, o);
-                        } else {
-                            debug!("check for {} failed: {:?}", tool, o);
-                            unsatisfied.push(tool);
-                        }
-                    }
-                    Err(_e) => {
-                        unsatisfied.push(tool);
+        })
+        .collect();
+    let mut preflight = windows_preflight_checks(install_path);
+    preflight.extend(wsl_preflight_checks(install_path));
+    preflight.extend(locale_preflight_checks());
+    let offline_media = match is_offline_mode() {
+        true => requirements
+            .iter()
+            .filter(|r| r.status != RequirementStatus::Satisfied)
+            .map(|r| r.package.clone())
+            .collect(),
+        false => vec![],
+    };
+    Ok(PrerequisitesReport {
+        package_manager: package_manager.name().to_string(),
+        requirements,
+        preflight,
+        offline_media,
+    })
+}
+
+/// Checks the system for the required tools and returns a list of unsatisfied tools.
+///
+/// This function determines the package manager of the system, then checks if each required tool is installed.
+/// If a tool is not found, it is added to the `unsatisfied` vector and returned.
+/// The prerequsites are met when empty vector is returned.
+/// Use `check_prerequisites_report` to get the details of every check.
+///
+/// # Returns
+///
+/// * `Ok(Vec<&'static str>)` - If the function completes successfully, returns a vector of unsatisfied tools.
+/// * `Err(String)` - If an error occurs, returns an error message.
+pub fn check_prerequisites() -> Result<Vec<&'static str>, String> {
+    let install_path = dirs::home_dir().unwrap_or_default();
+    let report = check_prerequisites_report(&install_path)?;
+    let unsatisfied = report.unsatisfied();
+    Ok(get_prequisites()
+        .into_iter()
+        .filter(|tool| unsatisfied.contains(tool))
+        .collect())
+}
+
+/// Checks the python related prerequisites by using the interpreter instead of querying packages.
+///
+/// Python is packaged very differently across systems (split pip and venv packages, versioned
+/// Homebrew formulae, ...), so checking for what actually works is more reliable.
+///
+/// # Parameters
+///
+/// * `tool` - A string slice representing the name of the prerequisite.
+///
+/// # Returns
+///
+/// * `Some((RequirementStatus, Option<String>))` - The state of the python prerequisite and the detected python version.
+/// * `None` - If the prerequisite is not python related.
+fn check_python_prerequisite(tool: &str) -> Option<(RequirementStatus, Option<String>)> {
+    let args: &[&str] = match tool {
+        "python3" => {
+            return Some(match crate::python_utils::get_python_version(None) {
+                Ok(version) => {
+                    let detected = Some(format!("{}.{}.{}", version.0, version.1, version.2));
+                    if crate::python_utils::is_python_version_supported(version) {
+                        (RequirementStatus::Satisfied, detected)
+                    } else {
+                        warn!(
+                            "Python {}.{}.{} is too old, at least {}.{} is required",
+                            version.0,
+                            version.1,
+                            version.2,
+                            crate::python_utils::MINIMUM_PYTHON_VERSION.0,
+                            crate::python_utils::MINIMUM_PYTHON_VERSION.1
+                        );
+                        (RequirementStatus::Outdated, detected)
                     }
                 }
-            }
-        }
-        "windows" => {
-            for tool in list_of_required_tools {
-                let output = command_executor::execute_command(
-                    "powershell",
-                    &["-Command", &format!("{} --version", tool)],
-                );
-                match output {
-                    Ok(o) => {
-                        if o.status.success() {
-                            debug!("{} is already installed: {:?}", tool, o);
-                        } else {
-                            debug!("check for {} failed: {:?}", tool, o);
-                            unsatisfied.push(tool);
-                        }
-                    }
-                    Err(_e) => {
-                        unsatisfied.push(tool);
-                    }
+                Err(e) => {
+                    debug!("Python check failed: {}", e);
+                    (RequirementStatus::Missing, None)
                 }
-            }
-        }
-        _ => {
-            return Err(format!("Unsupported OS - {}", std::env::consts::OS));
+            })
         }
-    }
-    Ok(unsatisfied)
+        "python3-pip" => &["-m", "pip", "--version"],
+        // on Debian based systems the venv module is present, but ensurepip is packaged separately
+        "python3-venv" => &["-c", "import venv, ensurepip"],
+        _ => return None,
+    };
+    let satisfied = command_executor::execute_command("python3", args)
+        .map(|o| o.status.success())
+        .unwrap_or(false);
+    Some(match satisfied {
+        true => (RequirementStatus::Satisfied, None),
+        false => (RequirementStatus::Missing, None),
+    })
 }
 
 /// Returns the path to the Scoop shims directory.
@@ -282,6 +1068,7 @@ pub fn get_scoop_path() -> Option<String> {
 /// * `Ok(())` - If the Scoop package manager is successfully installed.
 /// * `Err(String)` - If an error occurs during the installation process.
 fn install_scoop_package_manager() -> Result<(), String> {
+    ensure_online("Installing Scoop")?;
     match std::env::consts::OS {
         "windows" => {
             let path_with_scoop = match get_scoop_path() {
@@ -308,7 +1095,7 @@ fn install_scoop_package_manager() -> Result<(), String> {
         _ => {
             // this function should not be called on non-windows platforms
             debug!("Scoop package manager is only supported on Windows. Skipping installation.");
-            Err(format!("Unsupported OS - {}", std::env::consts::OS))
+            Err(message(MessageId::UnsupportedOs, &[std::env::consts::OS]))
         }
     }
 }
@@ -357,162 +1144,699 @@ pub fn ensure_scoop_package_manager() -> Result<(), String> {
         _ => {
             // this function should not be called on non-windows platforms
             debug!("Scoop package manager is only supported on Windows. Skipping installation.");
-            Err(format!("Unsupported OS - {}", std::env::consts::OS))
+            Err(message(MessageId::UnsupportedOs, &[std::env::consts::OS]))
         }
     }
 }
 
-/// Installs the required packages based on the operating system.
-/// This function actually panics if the required packages install fail.
-/// This is to ensure that user actually sees the error and realize which package failed to install.
+/// Returns the path to the Homebrew binaries directory.
+/// This function is only relevant for macOS systems.
 ///
-/// # Parameters
+/// # Returns
 ///
-/// * `packages_list` - A vector of strings representing the names of the packages to be installed.
-/// this can be obtained by calling the check_prerequisites() function.
+/// * `Some(String)` - If the function is executed on macOS, returns `/opt/homebrew/bin` on Apple Silicon
+///   and `/usr/local/bin` on Intel machines.
+/// * `None` - If the function is executed on a non-macOS system.
+pub fn get_homebrew_path() -> Option<String> {
+    if std::env::consts::OS == "macos" {
+        match std::env::consts::ARCH {
+            "aarch64" => Some(String::from("/opt/homebrew/bin")),
+            _ => Some(String::from("/usr/local/bin")),
+        }
+    } else {
+        None
+    }
+}
+
+/// Installs the Homebrew package manager on macOS.
+///
+/// The official installer script is downloaded and executed in non-interactive mode.
 ///
 /// # Returns
 ///
-/// * `Ok(())` - If the packages are successfully installed.
+/// * `Ok(())` - If the Homebrew package manager is successfully installed.
 /// * `Err(String)` - If an error occurs during the installation process.
-pub fn install_prerequisites(packages_list: Vec<String>) -> Result<(), String> {
+fn install_homebrew_package_manager() -> Result<(), String> {
+    ensure_online("Installing Homebrew")?;
     match std::env::consts::OS {
-        "linux" => {
-            let package_manager = determine_package_manager();
-            match package_manager {
-                Some("apt") => {
-                    for package in packages_list {
-                        let output = command_executor::execute_command(
-                            "sudo",
-                            &["apt", "install", "-y", &package],
-                        );
-                        match output {
-                            Ok(_) => {
-                                debug!("Successfully installed {}", package);
-                            }
-                            Err(e) => panic!("Failed to install {}: {}", package, e),
-                        }
-                    }
-                }
-                Some("dnf") => {
-                    for package in packages_list {
-                        let output = command_executor::execute_command(
-                            "sudo",
-                            &["dnf", "install", "-y", &package],
-                        );
-                        match output {
-                            Ok(_) => {
-                                debug!("Successfully installed {}", package);
-                            }
-                            Err(e) => panic!("Failed to install {}: {}", package, e),
-                        }
-                    }
-                }
-                Some("pacman") => {
-                    for package in packages_list {
-                        let output = command_executor::execute_command(
-                            "sudo",
-                            &["pacman", "-S", "--noconfirm", &package],
-                        );
-                        match output {
-                            Ok(_) => {
-                                debug!("Successfully installed {}", package);
-                            }
-                            Err(e) => panic!("Failed to install {}: {}", package, e),
-                        }
-                    }
+        "macos" => {
+            let path_with_brew = match get_homebrew_path() {
+                Some(s) => s,
+                None => {
+                    debug!("Could not get homebrew path");
+                    return Err(String::from("Could not get homebrew path"));
                 }
-                Some("zypper") => {
-                    for package in packages_list {
-                        let output = command_executor::execute_command(
-                            "sudo",
-                            &["zypper", "install", "-y", &package],
-                        );
-                        match output {
-                            Ok(_) => {
-                                debug!("Successfully installed {}", package);
-                            }
-                            Err(e) => panic!("Failed to install {}: {}", package, e),
-                        }
+            };
+            let output = command_executor::execute_command_with_env(
+                "/bin/bash",
+                &vec![
+                    "-c",
+                    "$(curl -fsSL https://raw.githubusercontent.com/Homebrew/install/HEAD/install.sh)",
+                ],
+                vec![("NONINTERACTIVE", "1")],
+            );
+            match output {
+                Ok(o) => {
+                    if o.status.success() {
+                        trace!("output: {}", String::from_utf8_lossy(&o.stdout));
+                        debug!("Successfully installed Homebrew package manager. Adding to PATH");
+                        add_to_path(&path_with_brew).map_err(|e| e.to_string())?;
+                        Ok(())
+                    } else {
+                        Err(message(
+                            MessageId::HomebrewInstallFailed,
+                            &[&String::from_utf8_lossy(&o.stderr)],
+                        ))
                     }
                 }
-                _ => {
-                    return Err(format!(
-                        "Unsupported package manager - {}",
-                        package_manager.unwrap()
-                    ));
-                }
+                Err(e) => Err(e.to_string()),
             }
         }
+        _ => {
+            // this function should not be called on non-macos platforms
+            debug!("Homebrew package manager is only installed on macOS. Skipping installation.");
+            Err(message(MessageId::UnsupportedOs, &[std::env::consts::OS]))
+        }
+    }
+}
+
+/// Ensures that the Homebrew package manager is installed on macOS.
+///
+/// This function adds the Homebrew binaries directory to the PATH and checks if `brew` can be executed.
+/// If it can not, Homebrew is installed with its official non-interactive installer.
+///
+/// # Returns
+///
+/// * `Ok(())` - If the Homebrew package manager is available.
+/// * `Err(String)` - If an error occurs during the installation process.
+pub fn ensure_homebrew_package_manager() -> Result<(), String> {
+    match std::env::consts::OS {
         "macos" => {
-            for package in packages_list {
-                let output = command_executor::execute_command("brew", &["install", &package]);
-                match output {
-                    Ok(_) => {
-                        debug!("Successfully installed {}", package);
-                    }
-                    Err(e) => panic!("Failed to install {}: {}", package, e),
+            let path_with_brew = match get_homebrew_path() {
+                Some(s) => s,
+                None => {
+                    debug!("Could not get homebrew path");
+                    return Err(String::from("Could not get homebrew path"));
                 }
+            };
+            add_to_path(&path_with_brew).map_err(|e| e.to_string())?;
+            if Brew.is_available() {
+                debug!("Homebrew package manager is already installed");
+                Ok(())
+            } else {
+                debug!("Installing Homebrew package manager");
+                install_homebrew_package_manager()
             }
         }
-        "windows" => {
-            ensure_scoop_package_manager()?;
-            for package in packages_list {
-                let path_with_scoop = match get_scoop_path() {
-                    Some(s) => s,
-                    None => {
-                        debug!("Could not get scoop path");
-                        return Err(String::from("Could not get scoop path"));
-                    }
-                };
-                debug!("Installing {} with scoop: {}", package, path_with_scoop);
-                let mut main_command = "powershell";
-
-                let test_for_pwsh = command_executor::execute_command("pwsh", &["--version"]);
-                match test_for_pwsh {
-                    // this needs to be used in powershell 7
-                    Ok(_) => {
-                        debug!("Found powershell core");
-                        main_command = "pwsh";
-                    }
-                    Err(_) => {
-                        debug!("Powershell core not found, using powershell");
-                    }
-                }
+        _ => {
+            // this function should not be called on non-macos platforms
+            debug!("Homebrew package manager is only installed on macOS. Skipping installation.");
+            Err(message(MessageId::UnsupportedOs, &[std::env::consts::OS]))
+        }
+    }
+}
 
-                let output = command_executor::execute_command_with_env(
-                    main_command,
-                    &vec![
-                        "-ExecutionPolicy",
-                        "Bypass",
-                        "-Command",
-                        "scoop",
-                        "install",
-                        &package,
-                    ],
-                    vec![("PATH", &add_to_path(&path_with_scoop).unwrap())],
-                );
-                match output {
-                    Ok(o) => {
-                        if o.status.success() {
-                            trace!("{}", String::from_utf8(o.stdout).unwrap());
-                            debug!("Successfully installed {:?}", package);
-                        } else {
-                            let output = String::from_utf8(o.stdout).unwrap();
-                            let error_message = String::from_utf8(o.stderr).unwrap();
-                            debug!("Failed to install {}: {}", package, error_message);
-                            debug!("Output: {}", output);
-                        }
-                    }
-                    Err(e) => panic!("Failed to install {}: {}", package, e),
-                }
+/// Installs the required packages based on the operating system.
+///
+/// Every package is attempted, even if some of the previous failed. Use
+/// `install_prerequisites_with_progress` to follow the installation of the individual packages.
+///
+/// # Parameters
+///
+/// * `packages_list` - A vector of strings representing the names of the packages to be installed.
+/// this can be obtained by calling the check_prerequisites() function.
+///
+/// # Returns
+///
+/// * `Ok(())` - If the packages are successfully installed.
+/// * `Err(String)` - If an error occurs during the installation process, or any package failed to install.
+pub fn install_prerequisites(packages_list: Vec<String>) -> Result<(), String> {
+    install_prerequisites_with_progress(packages_list, ())
+}
+
+/// Installs the required packages, reporting the progress of every package as updates of the
+/// `Prerequisites` phase with the package name as item.
+///
+/// # Parameters
+///
+/// * `packages_list` - A vector of strings representing the names of the packages to be installed.
+/// this can be obtained by calling the check_prerequisites() function.
+/// * `progress_sender` - The reporter receiving the progress of the packages.
+///
+/// # Returns
+///
+/// * `Ok(())` - If the packages are successfully installed.
+/// * `Err(String)` - If the package manager is not usable, or lists the packages which failed to install.
+pub fn install_prerequisites_with_progress(
+    packages_list: Vec<String>,
+    progress_sender: impl ProgressReporter,
+) -> Result<(), String> {
+    let package_manager = get_package_manager().ok_or_else(|| match std::env::consts::OS {
+        "linux" => message(MessageId::NoPackageManager, &[]),
+        os => message(MessageId::UnsupportedOs, &[os]),
+    })?;
+    if !package_manager.can_install() {
+        return Err(message(
+            MessageId::UnsupportedPackageManager,
+            &[package_manager.name()],
+        ));
+    }
+    if plan_prerequisites(package_manager.as_ref(), &packages_list) {
+        return Ok(());
+    }
+    match package_manager.name() {
+        "scoop" => ensure_scoop_package_manager()?,
+        "brew" => ensure_homebrew_package_manager()?,
+        _ => {}
+    }
+    let mut failed = vec![];
+    for package in packages_list {
+        info!("Installing {} with {}", package, package_manager.name());
+        let update = ProgressUpdate::new(InstallPhase::Prerequisites, &package);
+        progress::report(
+            &progress_sender,
+            &update.clone().detail(ProgressDetail::Started),
+        );
+        match install_prerequisite(package_manager.as_ref(), &package) {
+            Ok(()) => {
+                info!("Installed {}", package);
+                progress::report(&progress_sender, &update.detail(ProgressDetail::Finished));
+            }
+            Err(e) => {
+                error!("{}", e);
+                progress::report(&progress_sender, &update.detail(ProgressDetail::Failed(e)));
+                failed.push(package);
             }
         }
-        _ => {
-            return Err(format!("Unsupported OS - {}", std::env::consts::OS));
+    }
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(message(
+            MessageId::PrerequisitesFailed,
+            &[&failed.join(", ")],
+        ))
+    }
+}
+
+/// Installs the required packages without ever escalating privileges.
+///
+/// Packages which can be installed by the current user (Homebrew, Scoop, or any package manager
+/// when already running as root) are installed as usual. For the rest no installation is attempted,
+/// instead a ready-to-copy command is returned, so the caller can show it to the user and continue
+/// with the rest of the installation.
+/// Failed installations are reported the same way.
+///
+/// # Parameters
+///
+/// * `packages_list` - A vector of strings representing the names of the packages to be installed.
+/// this can be obtained by calling the check_prerequisites() function.
+///
+/// # Returns
+///
+/// * `Ok(Vec<String>)` - The commands the user needs to run to install the missing packages. Empty if everything was installed.
+/// * `Err(String)` - If no usable package manager was found.
+pub fn install_prerequisites_without_sudo(
+    packages_list: Vec<String>,
+) -> Result<Vec<String>, String> {
+    let package_manager = get_package_manager().ok_or_else(|| match std::env::consts::OS {
+        "linux" => message(MessageId::NoPackageManager, &[]),
+        os => message(MessageId::UnsupportedOs, &[os]),
+    })?;
+    if packages_list.is_empty() {
+        return Ok(vec![]);
+    }
+    if !package_manager.can_install() || (package_manager.requires_root() && !is_root()) {
+        warn!(
+            "Not allowed to install {:?} with {} without sudo",
+            packages_list,
+            package_manager.name()
+        );
+        return Ok(package_manager
+            .manual_install_command(&packages_list)
+            .into_iter()
+            .collect());
+    }
+    if plan_prerequisites(package_manager.as_ref(), &packages_list) {
+        return Ok(vec![]);
+    }
+    match package_manager.name() {
+        "scoop" => ensure_scoop_package_manager()?,
+        "brew" => ensure_homebrew_package_manager()?,
+        _ => {}
+    }
+    let mut failed = vec![];
+    let mut commands = vec![];
+    for package in packages_list {
+        if let Err(e) = install_prerequisite(package_manager.as_ref(), &package) {
+            warn!("{}", e);
+            if package == XCODE_CLT {
+                commands.push("xcode-select --install".to_string());
+            } else {
+                failed.push(package);
+            }
+        }
+    }
+    commands.extend(package_manager.manual_install_command(&failed));
+    Ok(commands)
+}
+
+/// Name of the prerequisite representing the Xcode Command Line Tools on macOS.
+pub const XCODE_CLT: &str = "xcode-clt";
+
+/// How long to wait for the user to finish the Xcode Command Line Tools installation.
+pub const XCODE_CLT_INSTALL_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30 * 60);
+
+/// Checks if the Xcode Command Line Tools, which provide git and the C compiler on macOS, are installed.
+pub fn is_xcode_clt_installed() -> bool {
+    match command_executor::execute_command("xcode-select", &["-p"]) {
+        Ok(o) => o.status.success(),
+        Err(_) => false,
+    }
+}
+
+/// Triggers the installation of the Xcode Command Line Tools and waits for it to finish.
+///
+/// `xcode-select --install` only opens the system installer dialog, so the function polls
+/// `xcode-select -p` until the tools appear or the timeout expires.
+///
+/// # Parameters
+///
+/// * `timeout` - How long to wait for the installation, see `XCODE_CLT_INSTALL_TIMEOUT`.
+///
+/// # Returns
+///
+/// * `Ok(())` - If the Command Line Tools are installed.
+/// * `Err(String)` - If the installer could not be started or did not finish in time.
+pub fn install_xcode_clt(timeout: std::time::Duration) -> Result<(), String> {
+    if std::env::consts::OS != "macos" {
+        return Err(message(MessageId::UnsupportedOs, &[std::env::consts::OS]));
+    }
+    if is_xcode_clt_installed() {
+        debug!("Xcode Command Line Tools are already installed");
+        return Ok(());
+    }
+    command_executor::execute_command("xcode-select", &["--install"]).map_err(|e| {
+        format!(
+            "Failed to start Xcode Command Line Tools installation: {}",
+            e
+        )
+    })?;
+    info!("Waiting for the Xcode Command Line Tools installation to finish, please follow the system dialog");
+    let poll_interval = std::time::Duration::from_secs(5);
+    let start = std::time::Instant::now();
+    while start.elapsed() < timeout {
+        std::thread::sleep(poll_interval);
+        if is_xcode_clt_installed() {
+            info!("Xcode Command Line Tools installed");
+            return Ok(());
+        }
+        trace!(
+            "Still waiting for Xcode Command Line Tools ({}s)",
+            start.elapsed().as_secs()
+        );
+    }
+    Err(message(
+        MessageId::XcodeToolsTimeout,
+        &[&(timeout.as_secs() / 60).to_string()],
+    ))
+}
+
+/// Records the installation of the packages in dry-run mode.
+///
+/// Returns `true` if the installation must be skipped.
+fn plan_prerequisites(package_manager: &dyn PackageManager, packages: &[String]) -> bool {
+    if !crate::dry_run::is_dry_run() {
+        return false;
+    }
+    for package in packages {
+        crate::dry_run::plan(
+            crate::dry_run::ActionKind::InstallPackage,
+            &format!(
+                "Install {} with {}",
+                package_manager.package_name(package),
+                package_manager.name()
+            ),
+            None,
+        );
+    }
+    true
+}
+
+/// Installs a single prerequisite, handling the ones not provided by the package manager.
+fn install_prerequisite(package_manager: &dyn PackageManager, package: &str) -> Result<(), String> {
+    ensure_online(&message(MessageId::Installing, &[package]))?;
+    match package {
+        XCODE_CLT => install_xcode_clt(XCODE_CLT_INSTALL_TIMEOUT),
+        _ => package_manager.install(package),
+    }
+}
+
+/// Free space recommended on the installation drive for a single ESP-IDF version with its tools.
+pub const RECOMMENDED_FREE_SPACE_BYTES: u64 = 5 * 1024 * 1024 * 1024;
+
+/// The outcome of a single environment check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CheckStatus {
+    Passed,
+    Warning,
+    Failed,
+}
+
+/// Result of a single environment check, with a human readable explanation or remediation hint.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PreflightCheck {
+    pub name: String,
+    pub status: CheckStatus,
+    pub message: String,
+}
+
+impl PreflightCheck {
+    fn new(name: &str, status: CheckStatus, message: String) -> Self {
+        PreflightCheck {
+            name: name.to_string(),
+            status,
+            message,
+        }
+    }
+}
+
+/// Runs a PowerShell command and returns its trimmed output, if it succeeded.
+fn powershell_query(command: &str) -> Option<String> {
+    match command_executor::execute_command(
+        "powershell",
+        &["-NoProfile", "-NonInteractive", "-Command", command],
+    ) {
+        Ok(o) if o.status.success() => Some(String::from_utf8_lossy(&o.stdout).trim().to_string()),
+        Ok(o) => {
+            debug!(
+                "PowerShell query {} failed: {}",
+                command,
+                String::from_utf8_lossy(&o.stderr)
+            );
+            None
+        }
+        Err(e) => {
+            debug!("PowerShell query {} failed: {}", command, e);
+            None
         }
     }
-    Ok(())
+}
+
+fn check_long_paths() -> PreflightCheck {
+    let policy = powershell_query(
+        "(Get-ItemProperty -Path 'HKLM:\\SYSTEM\\CurrentControlSet\\Policies' -Name LongPathsEnabled -ErrorAction SilentlyContinue).LongPathsEnabled",
+    );
+    let enabled = powershell_query(
+        "(Get-ItemProperty -Path 'HKLM:\\SYSTEM\\CurrentControlSet\\Control\\FileSystem' -Name LongPathsEnabled).LongPathsEnabled",
+    );
+    match (policy.as_deref(), enabled.as_deref()) {
+        (Some("0"), _) => PreflightCheck::new(
+            "long_paths",
+            CheckStatus::Warning,
+            "Long path support is disabled by a group policy, deeply nested ESP-IDF files may fail to install. Ask your administrator to enable \"Enable Win32 long paths\"".to_string(),
+        ),
+        (Some("1"), _) | (_, Some("1")) => PreflightCheck::new(
+            "long_paths",
+            CheckStatus::Passed,
+            "Long path support is enabled".to_string(),
+        ),
+        _ => PreflightCheck::new(
+            "long_paths",
+            CheckStatus::Warning,
+            "Long path support is disabled, deeply nested ESP-IDF files may fail to install. Let the installer enable it, or enable it with (as administrator): Set-ItemProperty -Path 'HKLM:\\SYSTEM\\CurrentControlSet\\Control\\FileSystem' -Name LongPathsEnabled -Value 1".to_string(),
+        ),
+    }
+}
+
+fn check_powershell_version() -> PreflightCheck {
+    match command_executor::get_powershell_version() {
+        Ok(version) if version >= 5 => PreflightCheck::new(
+            "powershell",
+            CheckStatus::Passed,
+            format!("PowerShell {} found", version),
+        ),
+        Ok(version) => PreflightCheck::new(
+            "powershell",
+            CheckStatus::Failed,
+            format!(
+                "PowerShell {} is too old, at least version 5 is required",
+                version
+            ),
+        ),
+        Err(e) => PreflightCheck::new(
+            "powershell",
+            CheckStatus::Failed,
+            format!("PowerShell is not available: {}", e),
+        ),
+    }
+}
+
+fn check_defender_exclusion(install_path: &Path) -> PreflightCheck {
+    let path = install_path.to_string_lossy().to_lowercase();
+    // reading the exclusions requires administrator rights, so an empty answer is not conclusive
+    let excluded = powershell_query("(Get-MpPreference).ExclusionPath")
+        .map(|exclusions| {
+            exclusions.lines().any(|line| {
+                path.starts_with(&line.trim().to_lowercase()) && !line.trim().is_empty()
+            })
+        })
+        .unwrap_or(false);
+    if excluded {
+        PreflightCheck::new(
+            "defender_exclusion",
+            CheckStatus::Passed,
+            format!(
+                "{} is excluded from Windows Defender scanning",
+                install_path.display()
+            ),
+        )
+    } else {
+        PreflightCheck::new(
+            "defender_exclusion",
+            CheckStatus::Warning,
+            format!(
+                "Windows Defender scanning can slow down the installation and builds considerably. Consider excluding the installation folder (as administrator): Add-MpPreference -ExclusionPath '{}'",
+                install_path.display()
+            ),
+        )
+    }
+}
+
+fn check_vc_runtime() -> PreflightCheck {
+    let system_root = env::var("SystemRoot").unwrap_or("C:\\Windows".to_string());
+    let runtime = PathBuf::from(system_root)
+        .join("System32")
+        .join("vcruntime140.dll");
+    if runtime.exists() {
+        PreflightCheck::new(
+            "vc_runtime",
+            CheckStatus::Passed,
+            "Visual C++ runtime is installed".to_string(),
+        )
+    } else {
+        PreflightCheck::new(
+            "vc_runtime",
+            CheckStatus::Warning,
+            "Visual C++ runtime was not found, some tools may fail to start. Install it from https://aka.ms/vs/17/release/vc_redist.x64.exe".to_string(),
+        )
+    }
+}
+
+fn check_free_space(install_path: &Path) -> PreflightCheck {
+    let drive = install_path
+        .to_string_lossy()
+        .chars()
+        .next()
+        .filter(|c| c.is_ascii_alphabetic())
+        .unwrap_or('C');
+    let free = powershell_query(&format!("(Get-PSDrive {}).Free", drive))
+        .and_then(|free| free.parse::<u64>().ok());
+    match free {
+        Some(free) if free >= RECOMMENDED_FREE_SPACE_BYTES => PreflightCheck::new(
+            "disk_space",
+            CheckStatus::Passed,
+            format!("{} MB free on drive {}:", free / 1024 / 1024, drive),
+        ),
+        Some(free) => PreflightCheck::new(
+            "disk_space",
+            CheckStatus::Failed,
+            format!(
+                "Only {} MB free on drive {}:, at least {} MB are recommended",
+                free / 1024 / 1024,
+                drive,
+                RECOMMENDED_FREE_SPACE_BYTES / 1024 / 1024
+            ),
+        ),
+        None => PreflightCheck::new(
+            "disk_space",
+            CheckStatus::Warning,
+            format!("Could not determine free space on drive {}:", drive),
+        ),
+    }
+}
+
+/// Runs the Windows specific environment checks.
+///
+/// Checks long path support, the PowerShell version, Windows Defender exclusions,
+/// presence of the Visual C++ runtime and free space on the drive of the installation path.
+///
+/// # Parameters
+///
+/// * `install_path` - A reference to a `Path` representing the folder ESP-IDF will be installed into.
+///
+/// # Returns
+///
+/// * `Vec<PreflightCheck>` - The results of the checks, empty on other operating systems.
+pub fn windows_preflight_checks(install_path: &Path) -> Vec<PreflightCheck> {
+    if std::env::consts::OS != "windows" {
+        return vec![];
+    }
+    vec![
+        check_long_paths(),
+        check_powershell_version(),
+        check_defender_exclusion(install_path),
+        check_vc_runtime(),
+        check_free_space(install_path),
+    ]
+}
+
+/// Runs the checks specific to the Windows Subsystem for Linux.
+///
+/// Inside WSL the installation behaves like on Linux, but USB devices have to be forwarded from Windows
+/// and installing onto the mounted Windows drives is very slow and mixes in Windows paths.
+///
+/// # Parameters
+///
+/// * `install_path` - A reference to a `Path` representing the folder ESP-IDF will be installed into.
+///
+/// # Returns
+///
+/// * `Vec<PreflightCheck>` - The results of the checks, empty when not running inside WSL.
+pub fn wsl_preflight_checks(install_path: &Path) -> Vec<PreflightCheck> {
+    if !crate::platform::is_wsl() {
+        return vec![];
+    }
+    let mut checks = vec![PreflightCheck::new(
+        "wsl_usb",
+        CheckStatus::Warning,
+        crate::platform::WSL_USB_HINT.to_string(),
+    )];
+    if crate::platform::is_windows_mount_path(install_path) {
+        checks.push(PreflightCheck::new(
+            "wsl_install_path",
+            CheckStatus::Warning,
+            format!(
+                "{} is on a Windows drive, which is very slow from WSL. Install into the Linux filesystem instead, e.g. ~/.espressif",
+                install_path.display()
+            ),
+        ));
+    }
+    checks
+}
+
+/// Checks if a locale name (e.g. `en_US.UTF-8`) uses the UTF-8 encoding.
+pub fn is_utf8_locale(locale: &str) -> bool {
+    let locale = locale.to_lowercase();
+    locale.contains("utf-8") || locale.contains("utf8")
+}
+
+/// Returns the locale used for character encoding, following the POSIX precedence of
+/// `LC_ALL`, `LC_CTYPE` and `LANG`.
+pub fn get_effective_locale() -> Option<String> {
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|variable| env::var(variable).ok())
+        .find(|value| !value.is_empty())
+}
+
+/// Returns the active console code page on Windows, e.g. `65001` for UTF-8.
+fn get_windows_code_page() -> Option<u32> {
+    let output = command_executor::execute_command("cmd", &["/C", "chcp"]).ok()?;
+    String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .rsplit(|c: char| c == ' ' || c == ':')
+        .next()
+        .and_then(|code_page| code_page.trim_end_matches('.').parse().ok())
+}
+
+/// Checks that the environment uses an UTF-8 capable locale, which the ESP-IDF python tooling relies on.
+///
+/// On Linux and macOS the `LC_ALL`, `LC_CTYPE` and `LANG` variables are checked, on Windows the console
+/// code page. The fix can be applied to the activation scripts with `get_locale_remediation_env`.
+///
+/// # Returns
+///
+/// * `Vec<PreflightCheck>` - The result of the locale check.
+pub fn locale_preflight_checks() -> Vec<PreflightCheck> {
+    let check = match std::env::consts::OS {
+        "windows" => match get_windows_code_page() {
+            Some(65001) => PreflightCheck::new(
+                "locale",
+                CheckStatus::Passed,
+                "Console uses the UTF-8 code page".to_string(),
+            ),
+            code_page => PreflightCheck::new(
+                "locale",
+                CheckStatus::Warning,
+                format!(
+                    "Console code page {} is not UTF-8, python tools may fail on non-ASCII output. PYTHONUTF8=1 will be set in the ESP-IDF environment",
+                    code_page.map(|c| c.to_string()).unwrap_or("unknown".to_string())
+                ),
+            ),
+        },
+        _ => match get_effective_locale() {
+            Some(locale) if is_utf8_locale(&locale) => PreflightCheck::new(
+                "locale",
+                CheckStatus::Passed,
+                format!("Locale {} uses UTF-8", locale),
+            ),
+            locale => PreflightCheck::new(
+                "locale",
+                CheckStatus::Warning,
+                format!(
+                    "Locale {} is not UTF-8, ESP-IDF python tools may fail. Set LC_ALL and LANG to an UTF-8 locale, e.g. C.UTF-8",
+                    locale.unwrap_or("(unset)".to_string())
+                ),
+            ),
+        },
+    };
+    vec![check]
+}
+
+/// Returns the environment variables fixing a non UTF-8 locale.
+///
+/// The pairs are meant to be appended to the `env_var_pairs` passed to `create_activation_shell_script`
+/// or `create_desktop_shortcut`, so the fix only applies to the ESP-IDF environment.
+///
+/// # Returns
+///
+/// * `Vec<(String, String)>` - The variables to set, empty if the locale is already UTF-8.
+pub fn get_locale_remediation_env() -> Vec<(String, String)> {
+    match std::env::consts::OS {
+        "windows" => match get_windows_code_page() {
+            Some(65001) => vec![],
+            _ => vec![
+                ("PYTHONUTF8".to_string(), "1".to_string()),
+                ("PYTHONIOENCODING".to_string(), "utf-8".to_string()),
+            ],
+        },
+        os => match get_effective_locale() {
+            Some(locale) if is_utf8_locale(&locale) => vec![],
+            _ => {
+                // older macOS releases do not provide C.UTF-8
+                let locale = if os == "macos" {
+                    "en_US.UTF-8"
+                } else {
+                    "C.UTF-8"
+                };
+                vec![
+                    ("LC_ALL".to_string(), locale.to_string()),
+                    ("LANG".to_string(), locale.to_string()),
+                ]
+            }
+        },
+    }
 }
 
 /// Adds a new directory to the system's PATH environment variable.
@@ -573,3 +1897,97 @@ fn add_to_path(new_path: &str) -> Result<String, std::io::Error> {
 
     Ok(new_path_string)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_install_commands() {
+        let (cmd, args) = Apt.install_command("cmake").unwrap();
+        assert_eq!(cmd, "sudo");
+        assert_eq!(args, vec!["apt", "install", "-y", "cmake"]);
+        let (cmd, args) = Brew.install_command("ninja").unwrap();
+        assert_eq!(cmd, "brew");
+        assert_eq!(args, vec!["install", "ninja"]);
+        assert!(Dpkg.install_command("git").is_none());
+        assert!(!Dpkg.can_install());
+    }
+
+    #[test]
+    fn test_manual_install_command() {
+        let packages = vec!["ninja".to_string(), "libffi-dev".to_string()];
+        assert_eq!(
+            Dnf.manual_install_command(&packages).unwrap(),
+            "sudo dnf install -y ninja-build libffi-devel"
+        );
+        assert_eq!(
+            Brew.manual_install_command(&["cmake".to_string()]).unwrap(),
+            "brew install cmake"
+        );
+        assert!(Apt.manual_install_command(&[]).is_none());
+        assert!(Dpkg.manual_install_command(&packages).is_none());
+    }
+
+    #[test]
+    fn test_query_commands() {
+        let (cmd, args) = Pacman.query_command("git");
+        assert_eq!(cmd, "sh");
+        assert_eq!(args, vec!["-c", "pacman -Qs | grep git"]);
+        let (_, args) = Zypper.query_command("flex");
+        assert_eq!(args, vec!["-c", "zypper se --installed-only flex"]);
+    }
+
+    #[test]
+    fn test_choco_list_args() {
+        assert_eq!(
+            choco_list_args(2, "git"),
+            vec!["list", "--exact", "--limit-output", "git"]
+        );
+        assert_eq!(
+            choco_list_args(1, "git"),
+            vec!["list", "--exact", "--limit-output", "--local-only", "git"]
+        );
+    }
+
+    #[test]
+    fn test_parse_tool_version() {
+        assert_eq!(
+            parse_tool_version("cmake version 3.24.0\n\nCMake suite maintained"),
+            Some("3.24.0".to_string())
+        );
+        assert_eq!(
+            parse_tool_version("git version 2.43.0.windows.1"),
+            Some("2.43.0".to_string())
+        );
+        assert_eq!(parse_tool_version("no version here"), None);
+    }
+
+    #[test]
+    fn test_is_utf8_locale() {
+        assert!(is_utf8_locale("en_US.UTF-8"));
+        assert!(is_utf8_locale("C.utf8"));
+        assert!(!is_utf8_locale("C"));
+        assert!(!is_utf8_locale("POSIX"));
+        assert!(!is_utf8_locale("de_DE.ISO-8859-1"));
+    }
+
+    #[test]
+    fn test_package_name_mapping() {
+        assert_eq!(Apt.package_name("ninja"), "ninja-build");
+        assert_eq!(Apt.package_name("libffi-dev"), "libffi-dev");
+        assert_eq!(Dnf.package_name("libffi-dev"), "libffi-devel");
+        assert_eq!(Dnf.package_name("libssl-dev"), "openssl-devel");
+        assert_eq!(Pacman.package_name("libusb-1.0-0"), "libusb");
+        assert_eq!(Zypper.package_name("libusb-1.0-0"), "libusb-1_0-0");
+        assert_eq!(Brew.package_name("dfu-util"), "dfu-util");
+        assert_eq!(Apk.package_name("libssl-dev"), "openssl-dev");
+        assert_eq!(Xbps.package_name("libffi-dev"), "libffi-devel");
+        assert_eq!(TermuxPkg.package_name("libusb-1.0-0"), "libusb");
+        assert_eq!(Winget.package_name("cmake"), "Kitware.CMake");
+        assert_eq!(Pacman.package_name("python3-pip"), "python-pip");
+        assert_eq!(Apk.package_name("python3-pip"), "py3-pip");
+        assert_eq!(Brew.package_name("python3"), "python");
+        assert_eq!(Choco.package_name("ninja"), "ninja");
+    }
+}
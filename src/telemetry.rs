@@ -0,0 +1,110 @@
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// A lifecycle event of the installer.
+///
+/// The events are anonymized: they carry version names, durations and the names of failed
+/// phases, never paths, user names or error messages.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LifecycleEvent {
+    InstallStarted,
+    InstallSucceeded {
+        versions: Vec<String>,
+        duration: Duration,
+    },
+    InstallFailed {
+        versions: Vec<String>,
+        duration: Duration,
+        /// The name of the first phase that failed, e.g. `tools`.
+        failed_phase: Option<String>,
+    },
+    VersionSelected {
+        version: String,
+    },
+    VersionRemoved {
+        version: String,
+    },
+}
+
+/// A lifecycle event together with the platform it happened on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TelemetryEvent {
+    pub event: LifecycleEvent,
+    pub os: &'static str,
+    pub arch: &'static str,
+    pub library_version: &'static str,
+}
+
+/// Receives the lifecycle events of the library.
+///
+/// Applications with opt-in telemetry implement this to forward the events to their backend and
+/// register it with `set_event_sink` once the user agreed. `record` is called synchronously from
+/// the installation code, so it should hand the event off instead of sending it right away.
+pub trait EventSink: Send + Sync {
+    fn record(&self, event: &TelemetryEvent);
+}
+
+/// The default sink, which drops every event.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopEventSink;
+
+impl EventSink for NoopEventSink {
+    fn record(&self, _event: &TelemetryEvent) {}
+}
+
+static EVENT_SINK: RwLock<Option<Arc<dyn EventSink>>> = RwLock::new(None);
+
+/// Sets the sink receiving the lifecycle events, `None` restores the no-op default.
+pub fn set_event_sink(sink: Option<Arc<dyn EventSink>>) {
+    *EVENT_SINK.write().unwrap() = sink;
+}
+
+/// Delivers an event to the registered sink, if any.
+pub fn emit(event: LifecycleEvent) {
+    let sink = EVENT_SINK.read().unwrap().clone();
+    if let Some(sink) = sink {
+        sink.record(&TelemetryEvent {
+            event,
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+            library_version: env!("CARGO_PKG_VERSION"),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct RecordingSink(Mutex<Vec<TelemetryEvent>>);
+
+    impl EventSink for RecordingSink {
+        fn record(&self, event: &TelemetryEvent) {
+            self.0.lock().unwrap().push(event.clone());
+        }
+    }
+
+    #[test]
+    fn test_emit_reaches_the_sink() {
+        let sink = Arc::new(RecordingSink::default());
+        set_event_sink(Some(sink.clone()));
+        emit(LifecycleEvent::VersionSelected {
+            version: "v5.3".to_string(),
+        });
+        set_event_sink(None);
+        emit(LifecycleEvent::VersionRemoved {
+            version: "v5.3".to_string(),
+        });
+
+        let events = sink.0.lock().unwrap();
+        assert!(events.iter().any(|event| event.event
+            == LifecycleEvent::VersionSelected {
+                version: "v5.3".to_string()
+            }));
+        assert!(!events
+            .iter()
+            .any(|event| matches!(event.event, LifecycleEvent::VersionRemoved { .. })));
+    }
+}
@@ -1,4 +1,7 @@
-use crate::{command_executor::execute_command, idf_tools::read_and_parse_tools_file};
+use crate::{
+    command_executor::{execute_checked, ExecOptions},
+    idf_tools::read_and_parse_tools_file,
+};
 use rust_search::SearchBuilder;
 #[cfg(not(windows))]
 use std::os::unix::fs::MetadataExt;
@@ -28,14 +31,9 @@ pub fn get_git_path() -> Result<String, String> {
         _ => "which",
     };
 
-    let output = execute_command(cmd, &vec!["git"]).expect("failed to execute process");
-
-    if output.status.success() {
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        Ok(stdout.trim().to_string())
-    } else {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        Err(stderr.trim().to_string())
+    match execute_checked(cmd, &["git"], vec![], &ExecOptions::new()) {
+        Ok(output) => Ok(String::from_utf8_lossy(&output.stdout).trim().to_string()),
+        Err(e) => Err(e.to_string()),
     }
 }
 // Finds all directories in the specified path that match the given name.
@@ -226,3 +224,129 @@ pub fn remove_directory_all<P: AsRef<Path>>(path: P) -> io::Result<()> {
 
     Ok(())
 }
+
+/// Calculates the total size of all files in a directory and its subdirectories.
+///
+/// Symbolic links are not followed and unreadable entries are skipped.
+///
+/// # Parameters
+///
+/// - `path`: A reference to the directory to measure.
+///
+/// # Return Value
+///
+/// - `u64`: The size in bytes, `0` if the directory does not exist.
+pub fn get_directory_size(path: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.file_type() {
+            Ok(t) if t.is_dir() => get_directory_size(&entry.path()),
+            Ok(t) if t.is_file() => entry.metadata().map(|m| m.len()).unwrap_or(0),
+            _ => 0,
+        })
+        .sum()
+}
+
+/// How `link_or_copy_dir` made the directory available.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkKind {
+    Symlink,
+    Copy,
+}
+
+/// Recursively copies a directory with all its content.
+pub fn copy_dir_all(source: &Path, destination: &Path) -> io::Result<()> {
+    fs::create_dir_all(destination)?;
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        let target = destination.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir_all(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Makes the `source` directory available at `destination`, as a symlink when the platform allows it
+/// and as a copy otherwise (e.g. on Windows without Developer Mode or administrator rights).
+///
+/// A python virtual environment is never copied: its scripts and `pyvenv.cfg` refer to the absolute
+/// path it was created at, so the copy would not work. An error is returned instead, the environment
+/// has to be created again at `destination`.
+///
+/// # Parameters
+///
+/// - `source`: The existing directory.
+/// - `destination`: The path where the link or copy is created, it must not exist.
+///
+/// # Return Value
+///
+/// - `io::Result<LinkKind>`: Whether a symlink or a copy was created.
+pub fn link_or_copy_dir(source: &Path, destination: &Path) -> io::Result<LinkKind> {
+    if crate::platform::can_create_symlinks() {
+        #[cfg(unix)]
+        let linked = std::os::unix::fs::symlink(source, destination);
+        #[cfg(windows)]
+        let linked = std::os::windows::fs::symlink_dir(source, destination);
+        match linked {
+            Ok(()) => return Ok(LinkKind::Symlink),
+            Err(e) => log::debug!("Symlinking {} failed, copying it: {}", source.display(), e),
+        }
+    }
+    if source.join("pyvenv.cfg").exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!(
+                "Unable to link the python environment {} to {}: symlinks are not available and a \
+                 copy would not work, enable Developer Mode or create the environment again",
+                source.display(),
+                destination.display()
+            ),
+        ));
+    }
+    copy_dir_all(source, destination)?;
+    Ok(LinkKind::Copy)
+}
+
+/// Formats seconds since the Unix epoch as an RFC 3339 UTC timestamp, e.g. `2024-05-01T12:30:00Z`.
+pub fn format_timestamp(secs: u64) -> String {
+    // civil date from the number of days since 1970-01-01, after Howard Hinnant's algorithm
+    let days = (secs / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    let secs_of_day = secs % 86400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60
+    )
+}
+
+/// Returns the current time as an RFC 3339 UTC timestamp.
+pub fn now_timestamp() -> String {
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    format_timestamp(secs)
+}
@@ -6,6 +6,9 @@ use std::path::PathBuf;
 
 use log::warn;
 
+use crate::idf_versions::{get_idf_versions, parse_version_numbers, Releases, VersionFilter};
+use crate::messages::{message, MessageId};
+use crate::telemetry::LifecycleEvent;
 use crate::utils::remove_directory_all;
 use crate::{
     idf_config::{IdfConfig, IdfInstallation},
@@ -121,10 +124,18 @@ pub fn select_idf_version(identifier: &str) -> Result<String> {
     let config_path = get_default_config_path();
     let mut ide_config = IdfConfig::from_file(&config_path)?;
     if ide_config.select_installation(identifier) {
+        if let Some(installation) = ide_config.get_selected_installation() {
+            crate::telemetry::emit(LifecycleEvent::VersionSelected {
+                version: installation.name.clone(),
+            });
+        }
         ide_config.to_file(config_path, true)?;
-        return Ok(format!("Version {} selected", identifier));
+        return Ok(message(MessageId::VersionSelected, &[identifier]));
     }
-    Err(anyhow!("Version {} not installed", identifier))
+    Err(anyhow!(message(
+        MessageId::VersionNotInstalled,
+        &[identifier]
+    )))
 }
 
 /// Renames the specified ESP-IDF version in the configuration file.
@@ -151,9 +162,12 @@ pub fn rename_idf_version(identifier: &str, new_name: String) -> Result<String>
     let res = ide_config.update_installation_name(identifier, new_name.to_string());
     if res {
         ide_config.to_file(config_path, true)?;
-        Ok(format!("Version {} renamed to {}", identifier, new_name))
+        Ok(message(MessageId::VersionRenamed, &[identifier, &new_name]))
     } else {
-        Err(anyhow!("Version {} not installed", identifier))
+        Err(anyhow!(message(
+            MessageId::VersionNotInstalled,
+            &[identifier]
+        )))
     }
 }
 
@@ -174,7 +188,6 @@ pub fn rename_idf_version(identifier: &str, new_name: String) -> Result<String>
 /// * `Result<String, anyhow::Error>` - On success, returns a `Result` containing a string message indicating
 ///   that the version has been removed. On error, returns an `anyhow::Error` with a description of the error.
 pub fn remove_single_idf_version(identifier: &str) -> Result<String> {
-    //TODO: remove also from path
     let config_path = get_default_config_path();
     let mut ide_config = IdfConfig::from_file(&config_path)?;
     if let Some(installation) = ide_config
@@ -184,27 +197,60 @@ pub fn remove_single_idf_version(identifier: &str) -> Result<String> {
     {
         let installation_folder_path = PathBuf::from(installation.path.clone());
         let installation_folder = installation_folder_path.parent().unwrap();
+        #[cfg(windows)]
+        if let Err(e) = crate::win_tools::revert_installation_environment(
+            installation,
+            installation_folder.to_str().unwrap(),
+        ) {
+            warn!("Failed to revert the environment of {}: {}", identifier, e);
+        }
+        #[cfg(windows)]
+        if let Err(e) = crate::win_tools::unregister_installation(installation) {
+            warn!(
+                "Failed to remove {} from Add/Remove Programs: {}",
+                identifier, e
+            );
+        }
         match remove_directory_all(&installation_folder) {
             Ok(_) => {}
             Err(e) => {
-                return Err(anyhow!("Failed to remove installation folder: {}", e));
+                return Err(anyhow!(message(
+                    MessageId::RemoveInstallationFolderFailed,
+                    &[&e.to_string()]
+                )));
             }
         }
+        if let Err(e) = crate::remove_start_menu_shortcut(&installation.name) {
+            warn!("Failed to remove the Start Menu shortcut: {}", e);
+        }
         match remove_directory_all(installation.clone().activation_script) {
             Ok(_) => {}
             Err(e) => {
-                return Err(anyhow!("Failed to remove activation script: {}", e));
+                return Err(anyhow!(message(
+                    MessageId::RemoveActivationScriptFailed,
+                    &[&e.to_string()]
+                )));
+            }
+        }
+        for script in &installation.activation_scripts {
+            if let Err(e) = remove_directory_all(script) {
+                warn!("Failed to remove activation script {}: {}", script, e);
             }
         }
+        let version = installation.name.clone();
         if ide_config.remove_installation(identifier) {
             debug!("Removed installation from config file");
+            crate::telemetry::emit(LifecycleEvent::VersionRemoved { version });
         } else {
             return Err(anyhow!("Failed to remove installation from config file"));
         }
         ide_config.to_file(config_path, true)?;
-        Ok(format!("Version {} removed", identifier))
+        Ok(message(MessageId::VersionRemoved, &[identifier]))
     } else {
-        Err(anyhow!("Version {} not installed", identifier))
+        Err(anyhow!(message(
+            MessageId::VersionNotInstalled,
+            &[identifier]
+        )))
     }
 }
 
@@ -233,3 +279,903 @@ pub fn find_esp_idf_folders(path: &str) -> Vec<String> {
         .cloned()
         .collect()
 }
+
+/// Problems which can be found by the integrity check of an installation.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InstallationIssue {
+    /// The ESP-IDF repository is missing or is not a valid ESP-IDF directory.
+    InvalidIdfPath,
+    /// The tools directory does not exist.
+    MissingToolsPath,
+    /// A tool which is always installed by ESP-IDF is missing from the tools directory.
+    MissingTool(String),
+    /// An installed tool version was changed since it was installed, see `ToolIntegrity::Modified`.
+    ModifiedTool {
+        name: String,
+        version: String,
+        reason: String,
+    },
+    /// The python executable of the virtual environment does not exist.
+    MissingPythonEnv,
+    /// The activation script (or PowerShell profile on Windows) does not exist.
+    MissingActivationScript,
+    /// The activation script exists but is not executable.
+    ActivationScriptNotExecutable,
+}
+
+/// Checks the integrity of a single ESP-IDF installation.
+///
+/// This function verifies that the ESP-IDF repository, the tools directory, the python virtual
+/// environment and the activation script of the installation are present. The tools are verified
+/// like `verify_installation` does, so tools which are missing or were modified are reported too.
+///
+/// # Parameters
+///
+/// * `installation` - A reference to the `IdfInstallation` to be checked.
+///
+/// # Returns
+///
+/// * `Vec<InstallationIssue>` - A vector of all issues found. An empty vector means the
+///   installation is healthy.
+pub fn check_installation_integrity(installation: &IdfInstallation) -> Vec<InstallationIssue> {
+    let mut issues = vec![];
+    let idf_path = PathBuf::from(&installation.path);
+    let tools_path = PathBuf::from(&installation.idf_tools_path);
+
+    if !crate::utils::is_valid_idf_directory(&installation.path) {
+        issues.push(InstallationIssue::InvalidIdfPath);
+    }
+    if !tools_path.is_dir() {
+        issues.push(InstallationIssue::MissingToolsPath);
+    } else if let Ok(tools_file) = crate::idf_tools::read_and_parse_tools_file(
+        idf_path.join("tools").join("tools.json").to_str().unwrap(),
+    ) {
+        // without a known platform the archives are not hashed, the tools are still looked up
+        let platform = installation_platform(installation).unwrap_or_default();
+        for tool in verify_installation_tools(installation, tools_file, &platform) {
+            match tool.integrity {
+                ToolIntegrity::Intact => {}
+                ToolIntegrity::Missing => issues.push(InstallationIssue::MissingTool(tool.name)),
+                ToolIntegrity::Modified(reason) => issues.push(InstallationIssue::ModifiedTool {
+                    name: tool.name,
+                    version: tool.version,
+                    reason,
+                }),
+            }
+        }
+    }
+    if !Path::new(&installation.python).exists() {
+        issues.push(InstallationIssue::MissingPythonEnv);
+    }
+
+    let activation_script = Path::new(&installation.activation_script);
+    if !activation_script.exists() {
+        issues.push(InstallationIssue::MissingActivationScript);
+    } else if !is_executable(activation_script) {
+        issues.push(InstallationIssue::ActivationScriptNotExecutable);
+    }
+    issues
+}
+
+/// The state of an installed tool, as found by `verify_installation`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ToolIntegrity {
+    /// The archive matches `tools.json` and the executable is present.
+    Intact,
+    /// The tool was changed since it was installed, with what was found.
+    Modified(String),
+    /// The tool is not installed.
+    Missing,
+}
+
+/// The result of `verify_installation` for a single tool version.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolVerification {
+    pub name: String,
+    pub version: String,
+    pub integrity: ToolIntegrity,
+}
+
+/// The result of `verify_installation`, one entry per tool.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstallationVerification {
+    pub tools: Vec<ToolVerification>,
+}
+
+impl InstallationVerification {
+    /// Whether every tool is intact, so the installation needs no repair.
+    pub fn is_intact(&self) -> bool {
+        self.tools
+            .iter()
+            .all(|tool| tool.integrity == ToolIntegrity::Intact)
+    }
+
+    /// The tools which are modified or missing and have to be reinstalled.
+    pub fn damaged(&self) -> Vec<&ToolVerification> {
+        self.tools
+            .iter()
+            .filter(|tool| tool.integrity != ToolIntegrity::Intact)
+            .collect()
+    }
+}
+
+/// Verifies the tools of `tools.json` against their installation in `tools_path`.
+///
+/// Every installed version is checked, and the `always` installed tools without any installed
+/// version are reported missing. The archives are hashed when they are still in one of the
+/// `archive_dirs`. `tools.json` has no checksums of the extracted
+/// files, so of those only the presence of the executable of `version_cmd` is checked.
+fn verify_tools(
+    tools_file: crate::idf_tools::ToolsFile,
+    platform: &str,
+    tools_path: &Path,
+    archive_dirs: &[PathBuf],
+) -> Vec<ToolVerification> {
+    let mut results = vec![];
+    for tool in tools_file.tools {
+        let tool_dir = tools_path.join("tools").join(&tool.name);
+        let mut installed_any = false;
+        for version in &tool.versions {
+            let version_dir = tool_dir.join(&version.name);
+            if !version_dir.is_dir() {
+                continue;
+            }
+            installed_any = true;
+            let download =
+                crate::idf_tools::get_download_for_platform(&version.downloads, platform);
+            results.push(ToolVerification {
+                name: tool.name.clone(),
+                version: version.name.clone(),
+                integrity: verify_tool_version(&tool, &version_dir, download, archive_dirs),
+            });
+        }
+        if !installed_any && tool.install == "always" {
+            let recommended = tool
+                .versions
+                .iter()
+                .find(|version| version.status == "recommended")
+                .or(tool.versions.first());
+            if let Some(version) = recommended {
+                results.push(ToolVerification {
+                    name: tool.name.clone(),
+                    version: version.name.clone(),
+                    integrity: ToolIntegrity::Missing,
+                });
+            }
+        }
+    }
+    results
+}
+
+/// Verifies a single installed tool version, see `verify_tools`.
+fn verify_tool_version(
+    tool: &crate::idf_tools::Tool,
+    version_dir: &Path,
+    download: Option<&crate::idf_tools::Download>,
+    archive_dirs: &[PathBuf],
+) -> ToolIntegrity {
+    let is_empty = std::fs::read_dir(version_dir)
+        .map(|mut entries| entries.next().is_none())
+        .unwrap_or(true);
+    if is_empty {
+        return ToolIntegrity::Missing;
+    }
+
+    if let Some(download) = download {
+        let file_name = Path::new(&download.url)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default();
+        let archive = archive_dirs
+            .iter()
+            .map(|dir| dir.join(file_name))
+            .find(|archive| archive.is_file());
+        if let Some(archive) = archive {
+            match crate::verify_file_checksum(&download.sha256, archive.to_str().unwrap()) {
+                Ok(true) => {}
+                Ok(false) => {
+                    return ToolIntegrity::Modified(format!(
+                        "{} does not match its checksum",
+                        archive.display()
+                    ))
+                }
+                Err(e) => {
+                    return ToolIntegrity::Modified(format!(
+                        "{} can not be read: {}",
+                        archive.display(),
+                        e
+                    ))
+                }
+            }
+        } else {
+            debug!("No archive of {} left to verify", tool.name);
+        }
+    }
+
+    let Some(executable) = tool.version_cmd.first() else {
+        return ToolIntegrity::Intact;
+    };
+    let found = tool.export_paths.iter().any(|levels| {
+        let mut dir = version_dir.to_path_buf();
+        dir.extend(levels);
+        dir.join(executable).is_file() || dir.join(format!("{}.exe", executable)).is_file()
+    });
+    if found || tool.export_paths.is_empty() {
+        ToolIntegrity::Intact
+    } else {
+        ToolIntegrity::Modified(format!("{} is missing", executable))
+    }
+}
+
+/// Verifies the installed tools of an installation against the metadata in its `tools.json`.
+///
+/// The tool archives still in the download folder are re-hashed and the executables of the
+/// tools are looked up, so a frontend can offer to repair the installation, see
+/// `repair_installation`.
+///
+/// # Parameters
+///
+/// * `installation` - A reference to the `IdfInstallation` to be verified.
+///
+/// # Returns
+///
+/// * `Result<InstallationVerification, anyhow::Error>` - Which tools are intact, modified or
+///   missing. On error, returns an `anyhow::Error` if `tools.json` or the platform can not be read.
+pub fn verify_installation(installation: &IdfInstallation) -> Result<InstallationVerification> {
+    let tools_json = Path::new(&installation.path)
+        .join("tools")
+        .join("tools.json");
+    let tools_file = crate::idf_tools::read_and_parse_tools_file(tools_json.to_str().unwrap())
+        .map_err(|e| anyhow!("Failed to read {}: {}", tools_json.display(), e))?;
+    let platform = installation_platform(installation).map_err(|e| anyhow!(e))?;
+    let tools = verify_installation_tools(installation, tools_file, &platform);
+    debug!("Verified the tools of {}: {:?}", installation.name, tools);
+    Ok(InstallationVerification { tools })
+}
+
+/// The platform of an installation, as seen by its python or else by the system one.
+fn installation_platform(installation: &IdfInstallation) -> Result<String, String> {
+    crate::idf_tools::get_platform_identification(Some(&installation.python))
+        .or_else(|_| crate::idf_tools::get_platform_identification(None))
+}
+
+/// Verifies the tools of an installation, looking for the archives in its download folders.
+fn verify_installation_tools(
+    installation: &IdfInstallation,
+    tools_file: crate::idf_tools::ToolsFile,
+    platform: &str,
+) -> Vec<ToolVerification> {
+    let tools_path = PathBuf::from(&installation.idf_tools_path);
+    // the archives are downloaded next to the tools folder by eim and into it by idf_tools.py
+    let download_folder_name = Settings::default()
+        .tool_download_folder_name
+        .unwrap_or("dist".to_string());
+    let mut archive_dirs = vec![tools_path.join(&download_folder_name)];
+    if let Some(version_path) = tools_path.parent() {
+        archive_dirs.push(version_path.join(&download_folder_name));
+    }
+    verify_tools(tools_file, platform, &tools_path, &archive_dirs)
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(path)
+        .map(|metadata| metadata.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(_path: &Path) -> bool {
+    true
+}
+
+/// Sets the executable bit on the given file, if it is not already set.
+#[cfg(unix)]
+fn make_executable(path: &Path) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let mut permissions = std::fs::metadata(path)?.permissions();
+    if permissions.mode() & 0o111 == 0 {
+        permissions.set_mode(permissions.mode() | 0o755);
+        std::fs::set_permissions(path, permissions)?;
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// Repairs a single ESP-IDF installation based on the results of the integrity check.
+///
+/// This function runs `check_installation_integrity` on the installation with the given
+/// identifier and then:
+/// - removes the modified tool versions, reinstalls them and the missing tools and recreates the
+///   python environment using `idf_tools.py`,
+/// - restores executable permissions of the tool binaries and the activation script,
+/// - re-runs the post install steps, which regenerates the activation script
+///   (or the PowerShell profile and desktop shortcut on Windows).
+///
+/// An installation whose ESP-IDF repository is missing or broken can not be repaired and has
+/// to be reinstalled.
+///
+/// # Parameters
+///
+/// * `identifier` - A reference to a string representing the identifier of the ESP-IDF version to repair.
+///   The identifier can be either the version number or the name of the installation.
+///
+/// # Returns
+///
+/// * `Result<Vec<InstallationIssue>, anyhow::Error>` - On success, returns the issues which are still
+///   present after the repair (empty if the repair was complete). On error, returns an `anyhow::Error`
+///   with a description of the error.
+pub fn repair_installation(identifier: &str) -> Result<Vec<InstallationIssue>> {
+    let config_path = get_default_config_path();
+    let ide_config = IdfConfig::from_file(&config_path)?;
+    let installation = match ide_config
+        .idf_installed
+        .iter()
+        .find(|install| install.id == identifier || install.name == identifier)
+    {
+        Some(installation) => installation.clone(),
+        None => {
+            return Err(anyhow!(message(
+                MessageId::VersionNotInstalled,
+                &[identifier]
+            )))
+        }
+    };
+    repair(&installation)
+}
+
+/// Repairs an installation, see `repair_installation`.
+fn repair(installation: &IdfInstallation) -> Result<Vec<InstallationIssue>> {
+    let issues = check_installation_integrity(installation);
+    debug!(
+        "Integrity check of {} found: {:?}",
+        installation.name, issues
+    );
+    if issues.contains(&InstallationIssue::InvalidIdfPath) {
+        return Err(anyhow!(
+            "ESP-IDF in {} is missing or broken, please reinstall version {}",
+            installation.path,
+            installation.name
+        ));
+    }
+
+    let idf_path = PathBuf::from(&installation.path);
+    let tools_path = PathBuf::from(&installation.idf_tools_path);
+    let env_vars = crate::setup_environment_variables(&tools_path, &idf_path)
+        .map_err(|e| anyhow!("Failed to setup environment variables: {}", e))?;
+
+    let needs_tools = issues.iter().any(|issue| {
+        matches!(
+            issue,
+            InstallationIssue::MissingToolsPath
+                | InstallationIssue::MissingTool(_)
+                | InstallationIssue::ModifiedTool { .. }
+                | InstallationIssue::MissingPythonEnv
+        )
+    });
+    if needs_tools {
+        // idf_tools.py keeps a tool version whose folder exists, so the modified ones are removed
+        remove_modified_tools(&tools_path, &issues)?;
+        crate::ensure_path(tools_path.to_str().unwrap())?;
+        let idf_tools_py = idf_path.join("tools").join("idf_tools.py");
+        crate::python_utils::run_idf_tools_py(idf_tools_py.to_str().unwrap(), &env_vars)
+            .map_err(|e| anyhow!("Failed to reinstall tools: {}", e))?;
+    }
+
+    let tools_file = crate::idf_tools::read_and_parse_tools_file(
+        idf_path.join("tools").join("tools.json").to_str().unwrap(),
+    )
+    .map_err(|e| anyhow!("Failed to read tools.json: {}", e))?;
+    let tools_install_path = tools_path.join("tools");
+    for bin_dir in crate::idf_tools::find_bin_directories(&tools_install_path) {
+        if let Ok(entries) = std::fs::read_dir(&bin_dir) {
+            for entry in entries.flatten() {
+                if entry.path().is_file() {
+                    if let Err(e) = make_executable(&entry.path()) {
+                        warn!(
+                            "Failed to fix permissions of {}: {}",
+                            entry.path().display(),
+                            e
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    // re-run the post install steps into the same location the activation script is registered at
+    let activation_script = PathBuf::from(&installation.activation_script);
+    let script_dir = activation_script.parent().unwrap();
+    let (version_installation_path, idf_version) = match std::env::consts::OS {
+        "windows" => (script_dir.to_path_buf(), installation.name.clone()),
+        _ => {
+            let version = activation_script
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.strip_prefix("activate_idf_"))
+                .unwrap_or(&installation.name)
+                .to_string();
+            (script_dir.join(&version), version)
+        }
+    };
+    let export_paths = crate::idf_tools::get_tools_export_paths(
+        tools_file,
+        vec!["all".to_string()],
+        tools_install_path.to_str().unwrap(),
+    );
+    crate::single_version_post_install(
+        version_installation_path.to_str().unwrap(),
+        &installation.path,
+        &idf_version,
+        &installation.idf_tools_path,
+        export_paths,
+    );
+    if activation_script.exists() {
+        make_executable(&activation_script)?;
+    }
+
+    Ok(check_installation_integrity(installation))
+}
+
+/// Removes the folders of the tool versions reported as modified by the integrity check.
+fn remove_modified_tools(tools_path: &Path, issues: &[InstallationIssue]) -> Result<()> {
+    for issue in issues {
+        if let InstallationIssue::ModifiedTool { name, version, .. } = issue {
+            let version_dir = tools_path.join("tools").join(name).join(version);
+            if crate::dry_run::plan(
+                crate::dry_run::ActionKind::WriteFile,
+                &format!("Remove {}", version_dir.display()),
+                None,
+            ) {
+                continue;
+            }
+            debug!("Removing modified tool {}", version_dir.display());
+            std::fs::remove_dir_all(&version_dir)
+                .map_err(|e| anyhow!("Failed to remove {}: {}", version_dir.display(), e))?;
+        }
+    }
+    Ok(())
+}
+
+/// Maps a path inside `old_root` to the same location inside `new_root`, other paths are kept.
+fn relocate_path(path: &str, old_root: &Path, new_root: &Path) -> String {
+    match Path::new(path).strip_prefix(old_root) {
+        Ok(relative) => new_root.join(relative).to_string_lossy().into_owned(),
+        Err(_) => path.to_string(),
+    }
+}
+
+/// Regenerates the activation scripts of an installation whose folder was moved or renamed.
+///
+/// The recorded ESP-IDF, tools and python paths are mapped into `new_root`, the activation scripts
+/// (PowerShell profile and shortcuts on Windows) are rendered again with the new paths, the scripts
+/// left at the old location are removed and the configuration file is updated. On Windows, the
+/// persistent environment variables set for the installation are rewritten as well.
+///
+/// # Parameters
+///
+/// * `identifier` - The id or name of the installation.
+/// * `new_root` - The new location of the installation folder, the one holding `esp-idf` and the tools.
+///
+/// # Returns
+///
+/// * `Result<IdfInstallation, anyhow::Error>` - The updated installation record.
+pub fn regenerate_activation_scripts(identifier: &str, new_root: &Path) -> Result<IdfInstallation> {
+    regenerate_activation_scripts_in(&get_default_config_path(), identifier, new_root)
+}
+
+fn regenerate_activation_scripts_in(
+    config_path: &Path,
+    identifier: &str,
+    new_root: &Path,
+) -> Result<IdfInstallation> {
+    let mut ide_config = IdfConfig::from_file(config_path)?;
+    let installation = match ide_config
+        .idf_installed
+        .iter_mut()
+        .find(|install| install.id == identifier || install.name == identifier)
+    {
+        Some(installation) => installation,
+        None => {
+            return Err(anyhow!(message(
+                MessageId::VersionNotInstalled,
+                &[identifier]
+            )))
+        }
+    };
+    let old_root = Path::new(&installation.path)
+        .parent()
+        .ok_or_else(|| anyhow!("Invalid ESP-IDF path {}", installation.path))?
+        .to_path_buf();
+    let new_idf_path = relocate_path(&installation.path, &old_root, new_root);
+    if !Path::new(&new_idf_path)
+        .join("tools")
+        .join("idf.py")
+        .exists()
+    {
+        return Err(anyhow!(
+            "ESP-IDF not found in {}, is {} the new location of {}?",
+            new_idf_path,
+            new_root.display(),
+            identifier
+        ));
+    }
+
+    let old_scripts: Vec<PathBuf> = std::iter::once(&installation.activation_script)
+        .chain(installation.activation_scripts.iter())
+        .map(PathBuf::from)
+        .collect();
+    let old_script = PathBuf::from(&installation.activation_script);
+    let idf_version = match std::env::consts::OS {
+        "windows" => installation.name.clone(),
+        _ => old_script
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.strip_prefix("activate_idf_"))
+            .unwrap_or(&installation.name)
+            .to_string(),
+    };
+
+    installation.path = new_idf_path;
+    installation.idf_tools_path = relocate_path(&installation.idf_tools_path, &old_root, new_root);
+    installation.python = relocate_path(&installation.python, &old_root, new_root);
+    let idf_path = PathBuf::from(&installation.path);
+    let tools_path = PathBuf::from(&installation.idf_tools_path);
+
+    let tools_file = crate::idf_tools::read_and_parse_tools_file(
+        idf_path.join("tools").join("tools.json").to_str().unwrap(),
+    )
+    .map_err(|e| anyhow!("Failed to read tools.json: {}", e))?;
+    let export_paths = crate::idf_tools::get_tools_export_paths(
+        tools_file,
+        vec!["all".to_string()],
+        tools_path.join("tools").to_str().unwrap(),
+    );
+    // the scripts are laid out as by the installation: the PowerShell profile inside the
+    // installation folder, the shell scripts next to it
+    crate::single_version_post_install(
+        new_root.to_str().unwrap(),
+        &installation.path,
+        &idf_version,
+        &installation.idf_tools_path,
+        export_paths,
+    );
+    let scripts_dir = match std::env::consts::OS {
+        "windows" => new_root.to_path_buf(),
+        _ => new_root
+            .parent()
+            .ok_or_else(|| anyhow!("Invalid installation folder {}", new_root.display()))?
+            .to_path_buf(),
+    };
+    let new_script = scripts_dir.join(old_script.file_name().unwrap_or_default());
+    if !new_script.exists() {
+        return Err(anyhow!(
+            "Failed to regenerate the activation script {}",
+            new_script.display()
+        ));
+    }
+    installation.activation_script = new_script.to_string_lossy().into_owned();
+    installation.activation_scripts = match std::env::consts::OS {
+        "windows" => vec![],
+        _ => crate::find_activation_scripts(&scripts_dir, &idf_version)
+            .iter()
+            .map(|script| script.to_string_lossy().into_owned())
+            .collect(),
+    };
+    for script in old_scripts {
+        let kept = script == new_script
+            || installation
+                .activation_scripts
+                .contains(&script.to_string_lossy().into_owned());
+        if !kept && script.exists() {
+            if let Err(e) = remove_directory_all(&script) {
+                warn!("Failed to remove {}: {}", script.display(), e);
+            }
+        }
+    }
+
+    #[cfg(windows)]
+    for key in &installation.env_variables {
+        let scope = crate::win_tools::get_env_scope();
+        if let Ok(Some(value)) = crate::win_tools::get_env_variable(key, scope) {
+            let relocated = relocate_path(&value, &old_root, new_root);
+            if relocated != value {
+                if let Err(e) = crate::win_tools::set_env_variable_in_scope(key, &relocated, scope)
+                {
+                    warn!("Failed to update {}: {}", key, e);
+                }
+            }
+        }
+    }
+
+    let installation = installation.clone();
+    ide_config.replace_file(config_path, true)?;
+    debug!(
+        "Regenerated the activation scripts of {} in {}",
+        identifier,
+        new_root.display()
+    );
+    Ok(installation)
+}
+
+/// A newer patch release available for an installed ESP-IDF version.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpdateInfo {
+    pub installation_id: String,
+    pub installation_name: String,
+    pub installed_version: String,
+    pub available_version: String,
+}
+
+/// Finds newer patch releases in the same minor line for each of the given installations.
+///
+/// The installed version is read from the ESP-IDF repository of the installation; if that is not
+/// possible, the installation name is used. Only stable, supported versions are offered.
+///
+/// # Parameters
+///
+/// * `installed` - A slice of the installations to check.
+/// * `releases` - A reference to the `Releases` parsed from the versions manifest.
+///
+/// # Returns
+///
+/// * `Vec<UpdateInfo>` - One entry for every installation which has a newer patch release available.
+pub fn find_updates(installed: &[IdfInstallation], releases: &Releases) -> Vec<UpdateInfo> {
+    let mut updates = vec![];
+    for installation in installed {
+        let current = match crate::migration::read_idf_version(Path::new(&installation.path)) {
+            Some(version) => version,
+            None => match parse_version_numbers(&installation.name) {
+                Some((major, Some(minor), patch)) => (major, minor, patch.unwrap_or(0)),
+                _ => continue,
+            },
+        };
+        let newest = releases
+            .VERSIONS
+            .iter()
+            .filter(|v| VersionFilter::default().matches(v))
+            .filter_map(|v| match parse_version_numbers(&v.name) {
+                Some((major, Some(minor), patch))
+                    if major == current.0
+                        && minor == current.1
+                        && patch.unwrap_or(0) > current.2 =>
+                {
+                    Some((patch.unwrap_or(0), v.name.clone()))
+                }
+                _ => None,
+            })
+            .max_by_key(|(patch, _)| *patch);
+        if let Some((_, available_version)) = newest {
+            updates.push(UpdateInfo {
+                installation_id: installation.id.clone(),
+                installation_name: installation.name.clone(),
+                installed_version: format!("v{}.{}.{}", current.0, current.1, current.2),
+                available_version,
+            });
+        }
+    }
+    updates
+}
+
+/// Checks all installed ESP-IDF versions for newer patch releases (e.g. v5.2.1 -> v5.2.3).
+///
+/// # Returns
+///
+/// * `Result<Vec<UpdateInfo>, anyhow::Error>` - On success, returns the available updates (empty if everything
+///   is up to date). On error, returns an `anyhow::Error` if the config file or the versions manifest can not be read.
+pub async fn check_for_updates() -> Result<Vec<UpdateInfo>> {
+    let installed = list_installed_versions()?;
+    let releases = get_idf_versions().await.map_err(|e| anyhow!(e))?;
+    Ok(find_updates(&installed, &releases))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tools_file(sha256: &str) -> crate::idf_tools::ToolsFile {
+        serde_json::from_str(&tools_json(sha256)).unwrap()
+    }
+
+    fn tools_json(sha256: &str) -> String {
+        format!(
+            r#"{{"version": 2, "tools": [
+                {{"name": "ninja", "description": "", "info_url": "", "install": "always",
+                  "export_paths": [[""]], "export_vars": {{}}, "version_cmd": ["ninja", "--version"],
+                  "version_regex": "", "versions": [{{"name": "1.11.1", "status": "recommended",
+                  "linux-amd64": {{"sha256": "{}", "size": 5, "url": "https://example.com/ninja.zip"}}}}]}},
+                {{"name": "cmake", "description": "", "info_url": "", "install": "always",
+                  "export_paths": [["bin"]], "export_vars": {{}}, "version_cmd": ["cmake", "--version"],
+                  "version_regex": "", "versions": [{{"name": "3.24.0", "status": "recommended"}}]}}
+            ]}}"#,
+            sha256
+        )
+    }
+
+    /// An installation in `root` with the tools of `tools_json` and a python environment, whose
+    /// activation script is not executable and whose ninja is installed but cmake is not.
+    fn test_installation(root: &Path) -> IdfInstallation {
+        let idf_path = root.join("esp-idf");
+        std::fs::create_dir_all(idf_path.join("tools")).unwrap();
+        std::fs::write(idf_path.join("tools").join("idf.py"), "").unwrap();
+        std::fs::write(idf_path.join("tools").join("tools.json"), tools_json("")).unwrap();
+        let ninja_dir = root
+            .join("tools")
+            .join("tools")
+            .join("ninja")
+            .join("1.11.1");
+        std::fs::create_dir_all(&ninja_dir).unwrap();
+        std::fs::write(ninja_dir.join("ninja"), "").unwrap();
+        let python = root.join("python");
+        std::fs::write(&python, "").unwrap();
+        let activation_script = root.join("activate_idf_v5.3.sh");
+        std::fs::write(&activation_script, "").unwrap();
+        IdfInstallation {
+            activation_script: activation_script.to_string_lossy().into_owned(),
+            id: "esp-idf-1".to_string(),
+            idf_tools_path: root.join("tools").to_string_lossy().into_owned(),
+            name: "v5.3".to_string(),
+            path: idf_path.to_string_lossy().into_owned(),
+            python: python.to_string_lossy().into_owned(),
+            env_variables: vec![],
+            activation_scripts: vec![],
+            container_image: None,
+        }
+    }
+
+    #[test]
+    fn test_check_installation_integrity() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut installation = test_installation(dir.path());
+        let issues = check_installation_integrity(&installation);
+        assert!(issues.contains(&InstallationIssue::MissingTool("cmake".to_string())));
+        assert!(!issues.contains(&InstallationIssue::MissingTool("ninja".to_string())));
+        #[cfg(unix)]
+        assert!(issues.contains(&InstallationIssue::ActivationScriptNotExecutable));
+
+        let ninja_dir = dir
+            .path()
+            .join("tools")
+            .join("tools")
+            .join("ninja")
+            .join("1.11.1");
+        std::fs::remove_file(ninja_dir.join("ninja")).unwrap();
+        std::fs::write(ninja_dir.join("README"), "").unwrap();
+        installation.python = dir.path().join("missing").to_string_lossy().into_owned();
+        let issues = check_installation_integrity(&installation);
+        assert!(issues.contains(&InstallationIssue::MissingPythonEnv));
+        assert!(issues.iter().any(|issue| matches!(
+            issue,
+            InstallationIssue::ModifiedTool { name, version, .. }
+                if name == "ninja" && version == "1.11.1"
+        )));
+
+        installation.path = dir.path().join("missing").to_string_lossy().into_owned();
+        assert!(check_installation_integrity(&installation)
+            .contains(&InstallationIssue::InvalidIdfPath));
+        assert!(repair(&installation).is_err());
+    }
+
+    #[test]
+    fn test_remove_modified_tools() {
+        let dir = tempfile::tempdir().unwrap();
+        let installation = test_installation(dir.path());
+        let tools_path = Path::new(&installation.idf_tools_path);
+        remove_modified_tools(
+            tools_path,
+            &[
+                InstallationIssue::MissingTool("cmake".to_string()),
+                InstallationIssue::ModifiedTool {
+                    name: "ninja".to_string(),
+                    version: "1.11.1".to_string(),
+                    reason: "ninja is missing".to_string(),
+                },
+            ],
+        )
+        .unwrap();
+        assert!(!tools_path
+            .join("tools")
+            .join("ninja")
+            .join("1.11.1")
+            .exists());
+        assert!(tools_path.join("tools").join("ninja").exists());
+    }
+
+    #[test]
+    fn test_verify_tools() {
+        let dir = tempfile::tempdir().unwrap();
+        let tools_path = dir.path().join("tools");
+        let dist = dir.path().join("dist");
+        std::fs::create_dir_all(tools_path.join("tools").join("ninja").join("1.11.1")).unwrap();
+        std::fs::write(
+            tools_path
+                .join("tools")
+                .join("ninja")
+                .join("1.11.1")
+                .join("ninja"),
+            "",
+        )
+        .unwrap();
+        std::fs::create_dir_all(&dist).unwrap();
+        std::fs::write(dist.join("ninja.zip"), "ninja").unwrap();
+        let sha256 =
+            crate::compute_file_checksum(dist.join("ninja.zip").to_str().unwrap()).unwrap();
+
+        let results = verify_tools(
+            tools_file(&sha256),
+            "linux-amd64",
+            &tools_path,
+            &[dist.clone()],
+        );
+        assert_eq!(results[0].integrity, ToolIntegrity::Intact);
+        assert_eq!(results[1].name, "cmake");
+        assert_eq!(results[1].integrity, ToolIntegrity::Missing);
+
+        std::fs::write(dist.join("ninja.zip"), "tampered").unwrap();
+        let results = verify_tools(tools_file(&sha256), "linux-amd64", &tools_path, &[dist]);
+        assert!(matches!(results[0].integrity, ToolIntegrity::Modified(_)));
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_regenerate_activation_scripts() {
+        let dir = tempfile::tempdir().unwrap();
+        let old_root = dir.path().join("old").join("v5.3");
+        let new_root = dir.path().join("new").join("v5.3");
+        let new_idf_path = new_root.join("esp-idf");
+        std::fs::create_dir_all(new_idf_path.join("tools")).unwrap();
+        std::fs::write(new_idf_path.join("tools").join("idf.py"), "").unwrap();
+        std::fs::write(
+            new_idf_path.join("tools").join("tools.json"),
+            r#"{"version": 2, "tools": []}"#,
+        )
+        .unwrap();
+
+        let installation = |id: &str, root: &Path| IdfInstallation {
+            activation_script: root
+                .parent()
+                .unwrap()
+                .join("activate_idf_v5.3.sh")
+                .to_string_lossy()
+                .into_owned(),
+            id: id.to_string(),
+            idf_tools_path: root.join("tools").to_string_lossy().into_owned(),
+            name: "v5.3".to_string(),
+            path: root.join("esp-idf").to_string_lossy().into_owned(),
+            python: root.join("python").to_string_lossy().into_owned(),
+            env_variables: vec![],
+            activation_scripts: vec![],
+            container_image: None,
+        };
+        let config_path = dir.path().join("eim_idf.json");
+        IdfConfig {
+            git_path: String::new(),
+            idf_installed: vec![
+                installation("esp-idf-1", &old_root),
+                installation("esp-idf-2", &dir.path().join("other")),
+            ],
+            idf_selected_id: "esp-idf-1".to_string(),
+        }
+        .replace_file(&config_path, true)
+        .unwrap();
+
+        let updated =
+            regenerate_activation_scripts_in(&config_path, "esp-idf-1", &new_root).unwrap();
+        assert_eq!(updated.path, new_idf_path.to_string_lossy());
+        assert!(Path::new(&updated.activation_script).exists());
+
+        let config = IdfConfig::from_file(&config_path).unwrap();
+        let ids: Vec<&str> = config
+            .idf_installed
+            .iter()
+            .map(|install| install.id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["esp-idf-1", "esp-idf-2"]);
+        assert_eq!(config.idf_installed[0].path, updated.path);
+    }
+}
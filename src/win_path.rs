@@ -0,0 +1,284 @@
+/// Length above which the PATH gets truncated by parts of Windows (e.g. Explorer and the
+/// environment variables dialog), silently dropping the entries at the end.
+pub const MAX_WIN_PATH_LENGTH: usize = 2047;
+
+/// What `win_tools::add_to_win_path` did with the directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathUpdate {
+    Added,
+    AlreadyPresent,
+    /// The directory was added after removing duplicate and empty entries to make room for it.
+    AddedAfterConsolidation {
+        removed: usize,
+    },
+    /// Adding the directory would make the PATH too long even after consolidation, so the PATH was
+    /// left untouched. The tools are only available through the activation script.
+    TooLong {
+        length: usize,
+    },
+}
+
+/// Removes empty entries and duplicates (ignoring case and trailing backslashes) from a PATH string,
+/// keeping the first occurrence of every entry.
+///
+/// # Returns
+///
+/// * `(String, usize)` - The consolidated PATH and the number of removed entries.
+pub fn consolidate_path(path: &str) -> (String, usize) {
+    let mut seen = std::collections::HashSet::new();
+    let entries: Vec<&str> = path.split(';').collect();
+    let kept: Vec<&str> = entries
+        .iter()
+        .copied()
+        .filter(|entry| {
+            let normalized = entry.trim().trim_end_matches('\\').to_lowercase();
+            !normalized.is_empty() && seen.insert(normalized)
+        })
+        .collect();
+    let removed = entries.len() - kept.len();
+    (kept.join(";"), removed)
+}
+
+/// Computes the PATH with the directory prepended, consolidating it when it would get too long.
+///
+/// # Returns
+///
+/// * `(Option<String>, PathUpdate)` - The new PATH to store, `None` when it stays as it is.
+pub fn plan_path_update(current: &str, directory: &str) -> (Option<String>, PathUpdate) {
+    let directory = directory.trim_end_matches('\\');
+    if current
+        .split(';')
+        .any(|entry| entry.trim_end_matches('\\').eq_ignore_ascii_case(directory))
+    {
+        return (None, PathUpdate::AlreadyPresent);
+    }
+    let prepend = |path: &str| {
+        if path.is_empty() {
+            directory.to_string()
+        } else {
+            format!("{};{}", directory, path)
+        }
+    };
+    let new_path = prepend(current);
+    if new_path.len() <= MAX_WIN_PATH_LENGTH {
+        return (Some(new_path), PathUpdate::Added);
+    }
+    let (consolidated, removed) = consolidate_path(current);
+    let new_path = prepend(&consolidated);
+    if new_path.len() <= MAX_WIN_PATH_LENGTH {
+        (
+            Some(new_path),
+            PathUpdate::AddedAfterConsolidation { removed },
+        )
+    } else {
+        (
+            None,
+            PathUpdate::TooLong {
+                length: new_path.len(),
+            },
+        )
+    }
+}
+
+/// Checks if a path is the folder or below it, comparing whole components and ignoring case,
+/// so `C:\Espressif2` is not in `C:\Espressif`. Both `\` and `/` are accepted as separators.
+pub fn is_in_folder(path: &str, folder: &str) -> bool {
+    let components = |path: &str| -> Vec<String> {
+        path.trim()
+            .split(['\\', '/'])
+            .filter(|component| !component.is_empty())
+            .map(|component| component.to_lowercase())
+            .collect()
+    };
+    let folder = components(folder);
+    !folder.is_empty() && components(path).starts_with(&folder)
+}
+
+/// Removes the entries in the `prefix` folder from a PATH string, see `is_in_folder`.
+/// Empty entries left over by doubled or trailing semicolons are dropped as well.
+///
+/// # Returns
+///
+/// * `(String, usize)` - The new PATH and the number of removed entries.
+pub fn remove_path_entries(path: &str, prefix: &str) -> (String, usize) {
+    let mut removed = 0;
+    let kept: Vec<&str> = path
+        .split(';')
+        .filter(|entry| !entry.trim().is_empty())
+        .filter(|entry| {
+            let matches = is_in_folder(entry, prefix);
+            if matches {
+                removed += 1;
+            }
+            !matches
+        })
+        .collect();
+    (kept.join(";"), removed)
+}
+
+/// A single change of the persistent environment, applied together with others by
+/// `win_tools::apply_env_changes`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EnvChange {
+    Set(String, String),
+    Unset(String),
+    AddToPath(String),
+    RemoveFromPath(String),
+}
+
+/// Computes the final value of every variable touched by the changes, applied in order.
+///
+/// # Parameters
+///
+/// * `changes` - The changes to apply.
+/// * `current` - Returns the current value of a variable, `None` when it is not set.
+///
+/// # Returns
+///
+/// * `(Vec<(String, Option<String>)>, Vec<PathUpdate>)` - The new value of every touched variable
+///   (`None` to delete it) and the outcome of every `EnvChange::AddToPath`.
+pub fn resolve_env_changes<F>(
+    changes: &[EnvChange],
+    current: F,
+) -> (Vec<(String, Option<String>)>, Vec<PathUpdate>)
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut values: Vec<(String, Option<String>)> = vec![];
+    let mut path_updates = vec![];
+    let value_of = |key: &str, values: &mut Vec<(String, Option<String>)>| -> usize {
+        match values.iter().position(|(k, _)| k.eq_ignore_ascii_case(key)) {
+            Some(index) => index,
+            None => {
+                values.push((key.to_string(), current(key)));
+                values.len() - 1
+            }
+        }
+    };
+    for change in changes {
+        match change {
+            EnvChange::Set(key, value) => {
+                let index = value_of(key, &mut values);
+                values[index].1 = Some(value.clone());
+            }
+            EnvChange::Unset(key) => {
+                let index = value_of(key, &mut values);
+                values[index].1 = None;
+            }
+            EnvChange::AddToPath(directory) => {
+                let index = value_of("Path", &mut values);
+                let path = values[index].1.clone().unwrap_or_default();
+                let (new_path, update) = plan_path_update(&path, directory);
+                if let Some(new_path) = new_path {
+                    values[index].1 = Some(new_path);
+                }
+                path_updates.push(update);
+            }
+            EnvChange::RemoveFromPath(prefix) => {
+                let index = value_of("Path", &mut values);
+                if let Some(path) = &values[index].1 {
+                    values[index].1 = Some(remove_path_entries(path, prefix).0);
+                }
+            }
+        }
+    }
+    (values, path_updates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_consolidate_path() {
+        let (path, removed) =
+            consolidate_path(r"C:\Windows;;C:\Tools\;c:\tools;C:\Windows;%SystemRoot%");
+        assert_eq!(path, r"C:\Windows;C:\Tools\;%SystemRoot%");
+        assert_eq!(removed, 3);
+    }
+
+    #[test]
+    fn test_remove_path_entries() {
+        let path = r"C:\Espressif\tools\cmake\bin;%SystemRoot%\system32;;c:\espressif\python\;C:\EspressifOther\bin;";
+        let (new_path, removed) = remove_path_entries(path, r"C:\Espressif\");
+        assert_eq!(removed, 2);
+        assert_eq!(new_path, r"%SystemRoot%\system32;C:\EspressifOther\bin");
+
+        let (unchanged, removed) = remove_path_entries(r"C:\Windows", r"C:\Espressif");
+        assert_eq!((unchanged.as_str(), removed), (r"C:\Windows", 0));
+    }
+
+    #[test]
+    fn test_is_in_folder() {
+        assert!(is_in_folder(r"C:\Espressif", r"C:\Espressif\"));
+        assert!(is_in_folder(r"c:\espressif\v5.3\esp-idf", r"C:\Espressif"));
+        assert!(is_in_folder("C:/Espressif/tools", r"C:\Espressif"));
+        assert!(!is_in_folder(r"C:\Espressif2\esp-idf", r"C:\Espressif"));
+        assert!(!is_in_folder(r"C:\Esp", r"C:\Espressif"));
+        assert!(!is_in_folder(r"C:\Espressif", ""));
+    }
+
+    #[test]
+    fn test_plan_path_update() {
+        assert_eq!(
+            plan_path_update(r"C:\Windows;C:\Tools\", r"c:\tools"),
+            (None, PathUpdate::AlreadyPresent)
+        );
+        assert_eq!(
+            plan_path_update(r"C:\Windows", r"C:\Espressif\bin"),
+            (
+                Some(r"C:\Espressif\bin;C:\Windows".to_string()),
+                PathUpdate::Added
+            )
+        );
+
+        let entry = format!(r"C:\{}", "x".repeat(100));
+        let duplicated = vec![entry.as_str(); 25].join(";");
+        let (new_path, update) = plan_path_update(&duplicated, r"C:\Espressif\bin");
+        assert_eq!(update, PathUpdate::AddedAfterConsolidation { removed: 24 });
+        assert_eq!(new_path, Some(format!(r"C:\Espressif\bin;{}", entry)));
+
+        let unique = (0..25)
+            .map(|i| format!(r"C:\{}{}", i, "x".repeat(100)))
+            .collect::<Vec<_>>()
+            .join(";");
+        let (new_path, update) = plan_path_update(&unique, r"C:\Espressif\bin");
+        assert_eq!(new_path, None);
+        assert!(matches!(update, PathUpdate::TooLong { length } if length > MAX_WIN_PATH_LENGTH));
+    }
+
+    #[test]
+    fn test_resolve_env_changes() {
+        let current = |key: &str| match key {
+            "Path" => Some(r"C:\Windows;C:\Espressif\old\bin".to_string()),
+            "IDF_PATH" => Some(r"C:\Espressif\old".to_string()),
+            _ => None,
+        };
+        let changes = vec![
+            EnvChange::RemoveFromPath(r"C:\Espressif\old".to_string()),
+            EnvChange::AddToPath(r"C:\Espressif\v5.3\bin".to_string()),
+            EnvChange::Set(
+                "IDF_TOOLS_PATH".to_string(),
+                r"C:\Espressif\tools".to_string(),
+            ),
+            EnvChange::Unset("IDF_PATH".to_string()),
+            EnvChange::AddToPath(r"C:\Windows".to_string()),
+        ];
+        let (values, updates) = resolve_env_changes(&changes, current);
+        assert_eq!(
+            values,
+            vec![
+                (
+                    "Path".to_string(),
+                    Some(r"C:\Espressif\v5.3\bin;C:\Windows".to_string())
+                ),
+                (
+                    "IDF_TOOLS_PATH".to_string(),
+                    Some(r"C:\Espressif\tools".to_string())
+                ),
+                ("IDF_PATH".to_string(), None),
+            ]
+        );
+        assert_eq!(updates, vec![PathUpdate::Added, PathUpdate::AlreadyPresent]);
+    }
+}
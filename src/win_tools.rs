@@ -0,0 +1,861 @@
+use std::io;
+use std::path::Path;
+use std::sync::RwLock;
+
+use log::{debug, warn};
+use winreg::enums::{HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE, KEY_READ, KEY_WRITE, REG_EXPAND_SZ};
+use winreg::{RegKey, RegValue};
+
+use crate::command_executor;
+use crate::idf_config::IdfInstallation;
+pub use crate::win_path::{
+    consolidate_path, is_in_folder, plan_path_update, remove_path_entries, resolve_env_changes,
+    EnvChange, PathUpdate, MAX_WIN_PATH_LENGTH,
+};
+
+const USER_ENVIRONMENT_KEY: &str = "Environment";
+const MACHINE_ENVIRONMENT_KEY: &str =
+    r"SYSTEM\CurrentControlSet\Control\Session Manager\Environment";
+
+/// Where persistent environment variables are stored.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EnvScope {
+    /// `HKEY_CURRENT_USER`, visible to the current user only.
+    #[default]
+    User,
+    /// `HKEY_LOCAL_MACHINE`, visible to all users. Writing it requires administrator rights.
+    Machine,
+}
+
+impl EnvScope {
+    fn registry_location(&self) -> (RegKey, &'static str) {
+        match self {
+            EnvScope::User => (RegKey::predef(HKEY_CURRENT_USER), USER_ENVIRONMENT_KEY),
+            EnvScope::Machine => (RegKey::predef(HKEY_LOCAL_MACHINE), MACHINE_ENVIRONMENT_KEY),
+        }
+    }
+
+    fn registry_drive_path(&self) -> String {
+        match self {
+            EnvScope::User => format!("HKCU:\\{}", USER_ENVIRONMENT_KEY),
+            EnvScope::Machine => format!("HKLM:\\{}", MACHINE_ENVIRONMENT_KEY),
+        }
+    }
+}
+
+static ENV_SCOPE: RwLock<EnvScope> = RwLock::new(EnvScope::User);
+
+/// Selects where `add_to_win_path` and `set_env_variable` store the variables.
+/// `"machine"` makes the IDF tools available to all users of the computer, anything else
+/// (including `None`) keeps them in the environment of the current user.
+pub fn set_env_scope(scope: Option<String>) {
+    let scope = match scope.as_deref() {
+        Some("machine") => EnvScope::Machine,
+        Some("user") | None => EnvScope::User,
+        Some(other) => {
+            warn!("Unknown environment scope {}, using user", other);
+            EnvScope::User
+        }
+    };
+    *ENV_SCOPE.write().unwrap() = scope;
+}
+
+/// Returns the scope selected by `set_env_scope`.
+pub fn get_env_scope() -> EnvScope {
+    *ENV_SCOPE.read().unwrap()
+}
+
+/// How long a single broadcast waits for each top-level window to process the change.
+const BROADCAST_TIMEOUT_MS: u32 = 2000;
+/// How many times a timed out or failed broadcast is attempted before giving up.
+const BROADCAST_ATTEMPTS: u32 = 3;
+/// Pause between two broadcast attempts.
+const BROADCAST_RETRY_DELAY: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// The outcome of a `WM_SETTINGCHANGE` broadcast.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BroadcastStatus {
+    /// Every responsive window, Explorer included, processed the change.
+    Delivered,
+    /// At least one window did not answer in time on the last attempt.
+    TimedOut,
+    /// The broadcast could not be sent, with the Win32 error code.
+    Failed(u32),
+}
+
+/// Describes whether an environment change is visible to the shells the user opens next.
+///
+/// Shells that are already running never see registry changes, whatever the outcome of the broadcast.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BroadcastReport {
+    pub status: BroadcastStatus,
+    pub attempts: u32,
+}
+
+impl BroadcastReport {
+    /// Returns true when terminals started from Explorer from now on get the new variables.
+    pub fn new_shells_updated(&self) -> bool {
+        self.status == BroadcastStatus::Delivered
+    }
+
+    /// A short instruction for the user on how to pick up the new environment.
+    pub fn user_hint(&self) -> &'static str {
+        if self.new_shells_updated() {
+            "Open a new terminal to use the updated environment."
+        } else {
+            "Sign out and back in (or restart Explorer) to use the updated environment."
+        }
+    }
+}
+
+fn send_environment_change(timeout_ms: u32) -> BroadcastStatus {
+    use windows_sys::Win32::Foundation::{GetLastError, ERROR_TIMEOUT};
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        SendMessageTimeoutW, HWND_BROADCAST, SMTO_ABORTIFHUNG, WM_SETTINGCHANGE,
+    };
+    let environment: Vec<u16> = "Environment\0".encode_utf16().collect();
+    let mut result = 0;
+    let sent = unsafe {
+        SendMessageTimeoutW(
+            HWND_BROADCAST,
+            WM_SETTINGCHANGE,
+            0,
+            environment.as_ptr() as isize,
+            SMTO_ABORTIFHUNG,
+            timeout_ms,
+            &mut result,
+        )
+    };
+    if sent != 0 {
+        return BroadcastStatus::Delivered;
+    }
+    match unsafe { GetLastError() } {
+        ERROR_TIMEOUT => BroadcastStatus::TimedOut,
+        code => BroadcastStatus::Failed(code),
+    }
+}
+
+/// Notifies the running applications (Explorer in particular) that the environment has changed,
+/// so newly started terminals see the new values without logging out.
+///
+/// This blocks while hung applications time out and retries a few times, prefer
+/// `spawn_environment_broadcast` on threads that must stay responsive.
+///
+/// # Returns
+///
+/// * `BroadcastReport` - The status of the last attempt and how many attempts were made.
+pub fn broadcast_environment_change() -> BroadcastReport {
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        let status = send_environment_change(BROADCAST_TIMEOUT_MS);
+        if status == BroadcastStatus::Delivered || attempts >= BROADCAST_ATTEMPTS {
+            let report = BroadcastReport { status, attempts };
+            match status {
+                BroadcastStatus::Delivered => {
+                    debug!("Environment change broadcast after {} attempt(s)", attempts)
+                }
+                _ => warn!(
+                    "Environment change broadcast failed ({:?}). {}",
+                    status,
+                    report.user_hint()
+                ),
+            }
+            return report;
+        }
+        debug!(
+            "Environment change broadcast attempt {} returned {:?}, retrying",
+            attempts, status
+        );
+        std::thread::sleep(BROADCAST_RETRY_DELAY);
+    }
+}
+
+/// Runs `broadcast_environment_change` on a background thread.
+///
+/// # Returns
+///
+/// * `JoinHandle<BroadcastReport>` - Join it to learn whether new shells will see the change;
+///   dropping it lets the broadcast finish on its own.
+pub fn spawn_environment_broadcast() -> std::thread::JoinHandle<BroadcastReport> {
+    std::thread::spawn(broadcast_environment_change)
+}
+
+fn decode_reg_string(value: &RegValue) -> String {
+    let wide: Vec<u16> = value
+        .bytes
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .collect();
+    String::from_utf16_lossy(&wide)
+        .trim_end_matches('\0')
+        .to_string()
+}
+
+fn encode_reg_string(value: &str, vtype: winreg::enums::RegType) -> RegValue {
+    RegValue {
+        bytes: value
+            .encode_utf16()
+            .chain(std::iter::once(0))
+            .flat_map(|c| c.to_le_bytes())
+            .collect(),
+        vtype,
+    }
+}
+
+/// Reads a persistent environment variable, `None` when it is not set.
+pub fn get_env_variable(key: &str, scope: EnvScope) -> io::Result<Option<String>> {
+    let (root, path) = scope.registry_location();
+    let environment = root.open_subkey_with_flags(path, KEY_READ)?;
+    match environment.get_raw_value(key) {
+        Ok(value) => Ok(Some(decode_reg_string(&value))),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Writes the value through an elevated PowerShell, for the machine scope without administrator rights.
+fn set_value_elevated(key: &str, value: &str, scope: EnvScope, expand: bool) -> io::Result<()> {
+    let script = format!(
+        "New-ItemProperty -Path {} -Name {} -Value {} -PropertyType {} -Force | Out-Null",
+        command_executor::quote_powershell(&scope.registry_drive_path()),
+        command_executor::quote_powershell(key),
+        command_executor::quote_powershell(value),
+        if expand { "ExpandString" } else { "String" }
+    );
+    let output = command_executor::execute_command_elevated(
+        "powershell",
+        &["-NoProfile", "-NonInteractive", "-Command", &script],
+        &command_executor::ExecOptions::new(),
+    )?;
+    if !output.status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!(
+                "Failed to set {}: exit code {:?}",
+                key,
+                output.status.code()
+            ),
+        ));
+    }
+    Ok(())
+}
+
+fn set_value(key: &str, value: &str, scope: EnvScope, expand: bool) -> io::Result<()> {
+    let (root, path) = scope.registry_location();
+    let vtype = if expand {
+        REG_EXPAND_SZ
+    } else {
+        winreg::enums::REG_SZ
+    };
+    let written = root
+        .open_subkey_with_flags(path, KEY_READ | KEY_WRITE)
+        .and_then(|environment| environment.set_raw_value(key, &encode_reg_string(value, vtype)));
+    match written {
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied && scope == EnvScope::Machine => {
+            debug!("Requesting elevation to set {} for all users", key);
+            set_value_elevated(key, value, scope, expand)?;
+        }
+        result => result?,
+    }
+    spawn_environment_broadcast();
+    Ok(())
+}
+
+/// Persistently sets an environment variable in the given scope and notifies the running applications.
+///
+/// Values containing `%` are stored as expandable strings, so references like `%USERPROFILE%` keep working.
+/// In the machine scope, elevation is requested through UAC when the process is not elevated.
+///
+/// # Parameters
+///
+/// * `key` - The name of the variable.
+/// * `value` - The value of the variable.
+/// * `scope` - Whether the variable is set for the current user or for all users.
+pub fn set_env_variable_in_scope(key: &str, value: &str, scope: EnvScope) -> io::Result<()> {
+    if crate::dry_run::plan(
+        crate::dry_run::ActionKind::Environment,
+        &format!("Set {}={} for {:?}", key, value, scope),
+        None,
+    ) {
+        return Ok(());
+    }
+    set_value(key, value, scope, value.contains('%'))?;
+    debug!("Set {} for {:?}", key, scope);
+    Ok(())
+}
+
+/// Persistently sets an environment variable in the scope selected by `set_env_scope`.
+pub fn set_env_variable(key: &str, value: &str) -> io::Result<()> {
+    set_env_variable_in_scope(key, value, get_env_scope())
+}
+
+/// Prepends a directory to the persistent PATH of the given scope, unless it is already present.
+///
+/// The PATH is kept as an expandable string, so entries like `%SystemRoot%\system32` are preserved.
+/// When the PATH would exceed `MAX_WIN_PATH_LENGTH`, duplicate and empty entries are removed first;
+/// if that is not enough, the PATH is not changed and `PathUpdate::TooLong` is returned, so the caller
+/// can tell the user to use the activation script instead.
+/// In the machine scope, elevation is requested through UAC when the process is not elevated.
+///
+/// # Parameters
+///
+/// * `directory` - The directory to add.
+/// * `scope` - Whether the PATH of the current user or the system PATH is changed.
+///
+/// # Returns
+///
+/// * `io::Result<PathUpdate>` - What was done with the directory.
+pub fn add_to_win_path_in_scope(directory: &str, scope: EnvScope) -> io::Result<PathUpdate> {
+    let current = get_env_variable("Path", scope)?.unwrap_or_default();
+    let (new_path, update) = plan_path_update(&current, directory);
+    match &update {
+        PathUpdate::AlreadyPresent => {
+            debug!("{} is already in the {:?} PATH", directory, scope)
+        }
+        PathUpdate::TooLong { length } => warn!(
+            "Not adding {} to the {:?} PATH, it would be {} characters long (limit {})",
+            directory, scope, length, MAX_WIN_PATH_LENGTH
+        ),
+        PathUpdate::AddedAfterConsolidation { removed } => warn!(
+            "Removed {} duplicate entries from the {:?} PATH to make room for {}",
+            removed, scope, directory
+        ),
+        PathUpdate::Added => {}
+    }
+    if new_path.is_some()
+        && crate::dry_run::plan(
+            crate::dry_run::ActionKind::Environment,
+            &format!("Add {} to the {:?} PATH", directory, scope),
+            None,
+        )
+    {
+        return Ok(update);
+    }
+    if let Some(new_path) = new_path {
+        set_value("Path", &new_path, scope, true)?;
+        debug!("Added {} to the {:?} PATH", directory, scope);
+    }
+    Ok(update)
+}
+
+/// Prepends a directory to the persistent PATH of the scope selected by `set_env_scope`.
+pub fn add_to_win_path(directory: &str) -> io::Result<PathUpdate> {
+    add_to_win_path_in_scope(directory, get_env_scope())
+}
+
+fn is_path_key(key: &str) -> bool {
+    key.eq_ignore_ascii_case("path")
+}
+
+/// Applies all values through a single elevated PowerShell, so the user is asked only once.
+fn apply_values_elevated(values: &[(String, Option<String>)], scope: EnvScope) -> io::Result<()> {
+    let location = command_executor::quote_powershell(&scope.registry_drive_path());
+    let mut script = vec!["$ErrorActionPreference = 'Stop'".to_string()];
+    for (key, value) in values {
+        script.push(match value {
+            Some(value) => format!(
+                "New-ItemProperty -Path {} -Name {} -Value {} -PropertyType {} -Force | Out-Null",
+                location,
+                command_executor::quote_powershell(key),
+                command_executor::quote_powershell(value),
+                if is_path_key(key) || value.contains('%') {
+                    "ExpandString"
+                } else {
+                    "String"
+                }
+            ),
+            None => format!(
+                "Remove-ItemProperty -Path {} -Name {} -ErrorAction SilentlyContinue",
+                location,
+                command_executor::quote_powershell(key)
+            ),
+        });
+    }
+    let output = command_executor::execute_command_elevated(
+        "powershell",
+        &[
+            "-NoProfile",
+            "-NonInteractive",
+            "-Command",
+            &script.join("; "),
+        ],
+        &command_executor::ExecOptions::new(),
+    )?;
+    if !output.status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!(
+                "Failed to update the environment: exit code {:?}",
+                output.status.code()
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Applies several environment changes at once and notifies the running applications a single time.
+///
+/// When writing any of the variables fails, the already written ones are restored to their previous
+/// values, so the environment is never left half updated. In the machine scope without administrator
+/// rights, all changes are made by one elevated PowerShell, asking the user only once.
+///
+/// # Parameters
+///
+/// * `changes` - The changes to apply, in order.
+/// * `scope` - Whether the environment of the current user or of all users is changed.
+///
+/// # Returns
+///
+/// * `io::Result<Vec<PathUpdate>>` - The outcome of every `EnvChange::AddToPath`, in order.
+pub fn apply_env_changes_in_scope(
+    changes: &[EnvChange],
+    scope: EnvScope,
+) -> io::Result<Vec<PathUpdate>> {
+    // read up front, so an unreadable variable fails instead of being treated as unset, which
+    // would replace the whole PATH by the added directory
+    let mut current: Vec<(&str, Option<String>)> = vec![];
+    for change in changes {
+        let key = match change {
+            EnvChange::Set(key, _) | EnvChange::Unset(key) => key.as_str(),
+            EnvChange::AddToPath(_) | EnvChange::RemoveFromPath(_) => "Path",
+        };
+        if !current.iter().any(|(k, _)| k.eq_ignore_ascii_case(key)) {
+            current.push((key, get_env_variable(key, scope)?));
+        }
+    }
+    let (values, path_updates) = resolve_env_changes(changes, |key| {
+        current
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .and_then(|(_, value)| value.clone())
+    });
+    if crate::dry_run::is_dry_run() {
+        for (key, value) in &values {
+            let description = match value {
+                Some(value) => format!("Set {}={} for {:?}", key, value, scope),
+                None => format!("Remove {} for {:?}", key, scope),
+            };
+            crate::dry_run::plan(crate::dry_run::ActionKind::Environment, &description, None);
+        }
+        return Ok(path_updates);
+    }
+    let (root, path) = scope.registry_location();
+    let environment = match root.open_subkey_with_flags(path, KEY_READ | KEY_WRITE) {
+        Ok(environment) => environment,
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied && scope == EnvScope::Machine => {
+            debug!("Requesting elevation to update the environment of all users");
+            apply_values_elevated(&values, scope)?;
+            spawn_environment_broadcast();
+            return Ok(path_updates);
+        }
+        Err(e) => return Err(e),
+    };
+    let mut applied: Vec<(&str, Option<RegValue>)> = vec![];
+    for (key, value) in &values {
+        let previous = environment.get_raw_value(key).ok();
+        let result = match value {
+            Some(value) => {
+                let vtype = if is_path_key(key) || value.contains('%') {
+                    REG_EXPAND_SZ
+                } else {
+                    winreg::enums::REG_SZ
+                };
+                environment.set_raw_value(key, &encode_reg_string(value, vtype))
+            }
+            None => match environment.delete_value(key) {
+                Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+                result => result,
+            },
+        };
+        if let Err(e) = result {
+            warn!("Failed to update {}, reverting the environment: {}", key, e);
+            for (key, previous) in applied.iter().rev() {
+                let _ = match previous {
+                    Some(previous) => environment.set_raw_value(key, previous),
+                    None => environment.delete_value(key),
+                };
+            }
+            return Err(e);
+        }
+        applied.push((key, previous));
+    }
+    spawn_environment_broadcast();
+    debug!(
+        "Applied {} environment changes for {:?}",
+        changes.len(),
+        scope
+    );
+    Ok(path_updates)
+}
+
+/// Applies several environment changes at once in the scope selected by `set_env_scope`.
+pub fn apply_env_changes(changes: &[EnvChange]) -> io::Result<Vec<PathUpdate>> {
+    apply_env_changes_in_scope(changes, get_env_scope())
+}
+
+/// Sets a persistent environment variable for an installation and records its name in the installation,
+/// so `revert_installation_environment` can remove it again.
+pub fn set_installation_env_variable(
+    installation: &mut IdfInstallation,
+    key: &str,
+    value: &str,
+) -> io::Result<()> {
+    set_env_variable(key, value)?;
+    if !installation.env_variables.iter().any(|k| k == key) {
+        installation.env_variables.push(key.to_string());
+    }
+    Ok(())
+}
+
+/// Deletes a persistent environment variable from the given scope and notifies the running applications.
+///
+/// The variable is removed from the environment of the running process as well.
+/// In the machine scope, elevation is requested through UAC when the process is not elevated.
+///
+/// # Returns
+///
+/// * `io::Result<bool>` - Whether the variable was set before.
+pub fn unset_env_variable_in_scope(key: &str, scope: EnvScope) -> io::Result<bool> {
+    if crate::dry_run::plan(
+        crate::dry_run::ActionKind::Environment,
+        &format!("Remove {} for {:?}", key, scope),
+        None,
+    ) {
+        return Ok(get_env_variable(key, scope)?.is_some());
+    }
+    std::env::remove_var(key);
+    let (root, path) = scope.registry_location();
+    let deleted = root
+        .open_subkey_with_flags(path, KEY_READ | KEY_WRITE)
+        .and_then(|environment| environment.delete_value(key));
+    match deleted {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied && scope == EnvScope::Machine => {
+            if get_env_variable(key, scope)?.is_none() {
+                return Ok(false);
+            }
+            debug!("Requesting elevation to remove {} for all users", key);
+            let script = format!(
+                "Remove-ItemProperty -Path {} -Name {}",
+                command_executor::quote_powershell(&scope.registry_drive_path()),
+                command_executor::quote_powershell(key)
+            );
+            let output = command_executor::execute_command_elevated(
+                "powershell",
+                &["-NoProfile", "-NonInteractive", "-Command", &script],
+                &command_executor::ExecOptions::new(),
+            )?;
+            if !output.status.success() {
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    format!(
+                        "Failed to remove {}: exit code {:?}",
+                        key,
+                        output.status.code()
+                    ),
+                ));
+            }
+        }
+        Err(e) => return Err(e),
+    }
+    spawn_environment_broadcast();
+    debug!("Removed {} for {:?}", key, scope);
+    Ok(true)
+}
+
+/// Deletes a persistent environment variable from the scope selected by `set_env_scope`.
+pub fn unset_env_variable(key: &str) -> io::Result<bool> {
+    unset_env_variable_in_scope(key, get_env_scope())
+}
+
+/// Reverts the persistent environment changes made for an installation.
+///
+/// The PATH entries below `installation_folder` are removed, and so are the recorded variables,
+/// unless they were changed to point somewhere else in the meantime (e.g. by another installation).
+///
+/// # Parameters
+///
+/// * `installation` - The installation being removed.
+/// * `installation_folder` - The folder containing everything of the installation.
+pub fn revert_installation_environment(
+    installation: &IdfInstallation,
+    installation_folder: &str,
+) -> io::Result<()> {
+    let scope = get_env_scope();
+    remove_from_win_path_in_scope(installation_folder, scope)?;
+    for key in &installation.env_variables {
+        match get_env_variable(key, scope)? {
+            Some(value) if is_in_folder(&value, installation_folder) => {
+                unset_env_variable_in_scope(key, scope)?;
+            }
+            Some(_) => debug!("{} points outside {}, keeping it", key, installation_folder),
+            None => {}
+        }
+    }
+    Ok(())
+}
+
+const FILESYSTEM_KEY: &str = r"SYSTEM\CurrentControlSet\Control\FileSystem";
+/// Where the "Enable Win32 long paths" group policy is stored, it takes precedence over `FILESYSTEM_KEY`.
+const FILESYSTEM_POLICY_KEY: &str = r"SYSTEM\CurrentControlSet\Policies";
+const LONG_PATHS_VALUE: &str = "LongPathsEnabled";
+
+/// Whether Windows allows paths longer than 260 characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LongPathsStatus {
+    Enabled,
+    Disabled,
+    /// A group policy disables long paths, it can only be changed by the administrator of the domain.
+    DisabledByPolicy,
+}
+
+fn read_dword(path: &str, name: &str) -> Option<u32> {
+    RegKey::predef(HKEY_LOCAL_MACHINE)
+        .open_subkey_with_flags(path, KEY_READ)
+        .and_then(|key| key.get_value::<u32, _>(name))
+        .ok()
+}
+
+/// Checks if long paths are enabled, taking the group policy into account.
+pub fn get_long_paths_status() -> LongPathsStatus {
+    match read_dword(FILESYSTEM_POLICY_KEY, LONG_PATHS_VALUE) {
+        Some(0) => LongPathsStatus::DisabledByPolicy,
+        Some(_) => LongPathsStatus::Enabled,
+        None => match read_dword(FILESYSTEM_KEY, LONG_PATHS_VALUE) {
+            Some(1) => LongPathsStatus::Enabled,
+            _ => LongPathsStatus::Disabled,
+        },
+    }
+}
+
+/// Enables support for paths longer than 260 characters, requesting elevation through UAC when needed.
+///
+/// This changes a system wide setting, so it must only be done after the user agreed to it.
+/// Applications started afterwards (including new terminals) pick up the change.
+///
+/// # Parameters
+///
+/// * `user_consent` - Whether the user explicitly agreed to enable long paths.
+///
+/// # Returns
+///
+/// * `Ok(LongPathsStatus::Enabled)` - If long paths were enabled already or are now.
+/// * `Err(io::Error)` - Of kind `PermissionDenied` without consent or when the elevation was declined,
+///   of kind `Other` when a group policy prevents the change.
+pub fn enable_long_paths(user_consent: bool) -> io::Result<LongPathsStatus> {
+    match get_long_paths_status() {
+        LongPathsStatus::Enabled => return Ok(LongPathsStatus::Enabled),
+        LongPathsStatus::DisabledByPolicy => {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Long paths are disabled by a group policy, ask your administrator to enable \"Enable Win32 long paths\"",
+            ))
+        }
+        LongPathsStatus::Disabled => {}
+    }
+    if !user_consent {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "Enabling long paths requires the consent of the user",
+        ));
+    }
+    let written = RegKey::predef(HKEY_LOCAL_MACHINE)
+        .open_subkey_with_flags(FILESYSTEM_KEY, KEY_READ | KEY_WRITE)
+        .and_then(|key| key.set_value(LONG_PATHS_VALUE, &1u32));
+    match written {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+            debug!("Requesting elevation to enable long paths");
+            let script = format!(
+                "Set-ItemProperty -Path 'HKLM:\\{}' -Name {} -Value 1 -Type DWord",
+                FILESYSTEM_KEY, LONG_PATHS_VALUE
+            );
+            let output = command_executor::execute_command_elevated(
+                "powershell",
+                &["-NoProfile", "-NonInteractive", "-Command", &script],
+                &command_executor::ExecOptions::new(),
+            )?;
+            if !output.status.success() {
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    format!(
+                        "Failed to enable long paths: exit code {:?}",
+                        output.status.code()
+                    ),
+                ));
+            }
+        }
+        Err(e) => return Err(e),
+    }
+    debug!("Long paths enabled");
+    Ok(get_long_paths_status())
+}
+
+const UNINSTALL_KEY: &str = r"Software\Microsoft\Windows\CurrentVersion\Uninstall";
+
+fn uninstall_key_name(installation: &IdfInstallation) -> String {
+    format!("eim-{}", installation.id)
+}
+
+/// Registers an installation in Add/Remove Programs (Apps & Features) of the current user.
+///
+/// # Parameters
+///
+/// * `installation` - The installation to register, its `id` identifies the entry.
+/// * `installation_folder` - The folder containing everything of the installation, used for the estimated size.
+/// * `uninstall_command` - The command removing the installation, e.g. `"C:\eim\eim.exe" uninstall v5.3.1`.
+pub fn register_installation(
+    installation: &IdfInstallation,
+    installation_folder: &str,
+    uninstall_command: &str,
+) -> io::Result<()> {
+    if crate::dry_run::plan(
+        crate::dry_run::ActionKind::Registry,
+        &format!(
+            "Register ESP-IDF {} in Add/Remove Programs",
+            installation.name
+        ),
+        None,
+    ) {
+        return Ok(());
+    }
+    let (key, _) = RegKey::predef(HKEY_CURRENT_USER).create_subkey(format!(
+        "{}\\{}",
+        UNINSTALL_KEY,
+        uninstall_key_name(installation)
+    ))?;
+    let size_kb =
+        crate::utils::get_directory_size(std::path::Path::new(installation_folder)) / 1024;
+    key.set_value("DisplayName", &format!("ESP-IDF {}", installation.name))?;
+    key.set_value(
+        "DisplayVersion",
+        &installation.name.trim_start_matches('v').to_string(),
+    )?;
+    key.set_value("Publisher", &"Espressif Systems")?;
+    key.set_value("InstallLocation", &installation_folder)?;
+    key.set_value("UninstallString", &uninstall_command)?;
+    key.set_value("EstimatedSize", &(size_kb.min(u32::MAX as u64) as u32))?;
+    key.set_value("NoModify", &1u32)?;
+    key.set_value("NoRepair", &1u32)?;
+    let icon = dirs::home_dir().map(|h| h.join("Icons").join("eim.ico"));
+    if let Some(icon) = icon.filter(|i| i.exists()) {
+        key.set_value("DisplayIcon", &icon.to_string_lossy().to_string())?;
+    }
+    debug!("Registered {} in Add/Remove Programs", installation.name);
+    Ok(())
+}
+
+/// Removes the Add/Remove Programs entry of an installation, if it exists.
+pub fn unregister_installation(installation: &IdfInstallation) -> io::Result<()> {
+    if crate::dry_run::plan(
+        crate::dry_run::ActionKind::Registry,
+        &format!("Remove {} from Add/Remove Programs", installation.name),
+        None,
+    ) {
+        return Ok(());
+    }
+    let uninstall = RegKey::predef(HKEY_CURRENT_USER)
+        .open_subkey_with_flags(UNINSTALL_KEY, KEY_READ | KEY_WRITE)?;
+    match uninstall.delete_subkey_all(uninstall_key_name(installation)) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+/// Removes the directory and everything below it from the persistent PATH of the given scope.
+///
+/// This is the inverse of `add_to_win_path_in_scope`, used when an installation is removed.
+/// The PATH of the running process is updated as well.
+///
+/// # Parameters
+///
+/// * `prefix` - The directory whose entries are removed, e.g. the installation folder of a version.
+/// * `scope` - Whether the PATH of the current user or the system PATH is changed.
+///
+/// # Returns
+///
+/// * `io::Result<usize>` - The number of entries removed from the persistent PATH.
+pub fn remove_from_win_path_in_scope(prefix: &str, scope: EnvScope) -> io::Result<usize> {
+    let Some(current) = get_env_variable("Path", scope)? else {
+        return Ok(0);
+    };
+    let (new_path, removed) = remove_path_entries(&current, prefix);
+    if crate::dry_run::is_dry_run() {
+        if removed > 0 {
+            crate::dry_run::plan(
+                crate::dry_run::ActionKind::Environment,
+                &format!("Remove {} from the {:?} PATH", prefix, scope),
+                None,
+            );
+        }
+        return Ok(removed);
+    }
+    if let Ok(process_path) = std::env::var("PATH") {
+        let (process_path, _) = remove_path_entries(&process_path, prefix);
+        std::env::set_var("PATH", process_path);
+    }
+    if removed > 0 {
+        set_value("Path", &new_path, scope, true)?;
+        debug!(
+            "Removed {} entries of {} from the {:?} PATH",
+            removed, prefix, scope
+        );
+    }
+    Ok(removed)
+}
+
+/// Removes the directory and everything below it from the persistent PATH of the scope selected by `set_env_scope`.
+pub fn remove_from_win_path(prefix: &str) -> io::Result<usize> {
+    remove_from_win_path_in_scope(prefix, get_env_scope())
+}
+
+/// Creates a `.lnk` shortcut through the `IShellLink` COM interface.
+///
+/// Unlike the PowerShell `WScript.Shell` scripts, this keeps working when PowerShell runs in
+/// constrained language mode or scripts are blocked by AppLocker.
+///
+/// # Parameters
+///
+/// * `link_path` - Where the shortcut is written, including the `.lnk` extension.
+/// * `target` - The program started by the shortcut.
+/// * `arguments` - The command line arguments passed to the program.
+/// * `working_dir` - The directory the program starts in.
+/// * `icon` - The icon file displayed for the shortcut.
+pub fn create_shortcut(
+    link_path: &Path,
+    target: &str,
+    arguments: &str,
+    working_dir: &Path,
+    icon: &Path,
+) -> io::Result<()> {
+    use windows::core::{Interface, HSTRING};
+    use windows::Win32::Foundation::TRUE;
+    use windows::Win32::System::Com::{
+        CoCreateInstance, CoInitializeEx, CoUninitialize, IPersistFile, CLSCTX_INPROC_SERVER,
+        COINIT_APARTMENTTHREADED,
+    };
+    use windows::Win32::UI::Shell::{IShellLinkW, ShellLink};
+
+    let to_io = |e: windows::core::Error| io::Error::new(io::ErrorKind::Other, e.to_string());
+    unsafe {
+        // S_FALSE means COM was already initialized on this thread, which still has to be balanced.
+        let initialized = CoInitializeEx(None, COINIT_APARTMENTTHREADED).is_ok();
+        let result = (|| -> windows::core::Result<()> {
+            let link: IShellLinkW = CoCreateInstance(&ShellLink, None, CLSCTX_INPROC_SERVER)?;
+            link.SetPath(&HSTRING::from(target))?;
+            link.SetArguments(&HSTRING::from(arguments))?;
+            link.SetWorkingDirectory(&HSTRING::from(working_dir.as_os_str()))?;
+            link.SetIconLocation(&HSTRING::from(icon.as_os_str()), 0)?;
+            let file: IPersistFile = link.cast()?;
+            file.Save(&HSTRING::from(link_path.as_os_str()), TRUE)
+        })();
+        if initialized {
+            CoUninitialize();
+        }
+        result.map_err(to_io)?;
+    }
+    debug!("Created shortcut {}", link_path.display());
+    Ok(())
+}